@@ -0,0 +1,567 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::meta::{META_PARAM_TOKEN, META_TOKEN};
+
+/// Byte range within the comment, or sidecar entry, an annotation was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An argument value, e.g., the `arr` in `length(arr)` or the `"abc"` in `default("abc")`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Value {
+    Ident(String),
+    Str(String),
+    Number(String),
+}
+impl Value {
+    /// The value as written, minus the quotes of a string
+    pub fn as_str(&self) -> &str {
+        match self {
+            Value::Ident(s) | Value::Str(s) | Value::Number(s) => s,
+        }
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Ident(s) | Value::Number(s) => write!(f, "{}", s),
+            Value::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// A single argument of an annotation, either positional or `key=value`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Argument {
+    pub key: Option<String>,
+    pub value: Value,
+    pub span: Span,
+}
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            Some(k) => write!(f, "{}={}", k, self.value),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// A single `;` terminated entry of a meta line, e.g., `nullable`, `length(arr)` or `rename=getToken`.
+///
+/// The `name=value` form is shorthand for `name(value)`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub span: Span,
+}
+impl Annotation {
+    /// An annotation without arguments that was not parsed from any text, e.g., one that was inferred
+    pub fn flag(name: &str) -> Self {
+        Annotation {
+            name: name.to_owned(),
+            args: vec![],
+            span: Span { start: 0, end: 0 },
+        }
+    }
+}
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.args.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.name, args.join(", "))
+    }
+}
+
+/// What the annotations of a line apply to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum AnnotationTarget {
+    /// `#meta:`, the item the comment belongs to
+    Item,
+    /// `#meta_param: name;`, a single parameter of the function the comment belongs to
+    Param { name: String, span: Span },
+}
+
+/// A parsed `#meta:` or `#meta_param:` line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationLine {
+    pub target: AnnotationTarget,
+    pub annotations: Vec<Annotation>,
+    /// Span of the `#meta:`/`#meta_param:` token
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// All meta lines of a single comment
+#[derive(Debug, Clone, Default)]
+pub struct CommentAnnotations {
+    pub lines: Vec<AnnotationLine>,
+    /// Entries that could not be parsed. They are skipped, the rest of the line is still used
+    pub errors: Vec<AnnotationError>,
+}
+
+impl CommentAnnotations {
+    /// Annotations of the `#meta:` lines, applying to the item the comment belongs to
+    pub fn for_item(&self) -> Vec<Annotation> {
+        self.lines
+            .iter()
+            .filter(|l| l.target == AnnotationTarget::Item)
+            .flat_map(|l| l.annotations.iter().cloned())
+            .collect()
+    }
+
+    /// Annotations of the `#meta_param:` lines naming exactly `param_name`
+    pub fn for_param(&self, param_name: &str) -> Vec<Annotation> {
+        self.lines
+            .iter()
+            .filter(
+                |l| matches!(&l.target, AnnotationTarget::Param { name, .. } if name == param_name),
+            )
+            .flat_map(|l| l.annotations.iter().cloned())
+            .collect()
+    }
+}
+
+/// Annotations of the `#meta:` lines of `comment`
+pub fn item_annotations(comment: &Option<String>) -> Vec<Annotation> {
+    comment
+        .as_deref()
+        .map_or_else(Vec::new, |c| parse_comment(c).for_item())
+}
+
+/// Annotations of the `#meta_param:` lines of `comment` naming exactly `param_name`
+pub fn param_annotations(comment: &Option<String>, param_name: &str) -> Vec<Annotation> {
+    comment
+        .as_deref()
+        .map_or_else(Vec::new, |c| parse_comment(c).for_param(param_name))
+}
+
+/// Parses every meta line of a doc comment.
+///
+/// ```text
+/// line     = "#meta:" { entry ";" } | "#meta_param:" ident ";" { entry ";" }
+/// entry    = ident [ "(" [ argument { "," argument } ] ")" | "=" value ]
+/// argument = [ ident "=" ] value
+/// value    = ident | number | "quoted string"
+/// ```
+pub fn parse_comment(comment: &str) -> CommentAnnotations {
+    let mut result = CommentAnnotations::default();
+    let mut line_start = 0;
+    for line in comment.split('\n') {
+        let token = match (line.find(META_PARAM_TOKEN), line.find(META_TOKEN)) {
+            (Some(i), _) => Some((i, META_PARAM_TOKEN)),
+            (None, Some(i)) => Some((i, META_TOKEN)),
+            (None, None) => None,
+        };
+        if let Some((i, token)) = token {
+            let mut parser = Parser {
+                text: comment,
+                pos: line_start + i + token.len(),
+                end: line_start + line.len(),
+            };
+            let span = Span {
+                start: line_start + i,
+                end: line_start + i + token.len(),
+            };
+            if let Some(parsed) = parser.line(token == META_PARAM_TOKEN, span, &mut result.errors) {
+                result.lines.push(parsed);
+            }
+        }
+        line_start += line.len() + 1;
+    }
+    result
+}
+
+/// Parses a single annotation outside of a comment, e.g., `length(arr)`. A trailing `;` is optional
+pub fn parse_annotation(text: &str) -> Result<Annotation, AnnotationError> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        end: text.len(),
+    };
+    let annotation = parser.entry()?;
+    parser.skip_whitespace();
+    parser.eat(';');
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(annotation),
+        Some(c) => Err(parser.error(
+            parser.pos,
+            format!("unexpected `{}` after `{}`", c, annotation),
+        )),
+    }
+}
+
+/// Recursive descent parser over a single line of a comment. Positions are byte offsets into the whole comment
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn line(
+        &mut self,
+        is_param: bool,
+        span: Span,
+        errors: &mut Vec<AnnotationError>,
+    ) -> Option<AnnotationLine> {
+        let target = if is_param {
+            let (name, name_span) = match self.ident().and_then(|n| self.expect(';').map(|_| n)) {
+                Ok(n) => n,
+                Err(e) => {
+                    errors.push(e);
+                    return None;
+                }
+            };
+            AnnotationTarget::Param {
+                name,
+                span: name_span,
+            }
+        } else {
+            AnnotationTarget::Item
+        };
+
+        let mut annotations = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.at_end() {
+                break;
+            }
+            match self.entry().and_then(|a| self.expect(';').map(|_| a)) {
+                Ok(a) => annotations.push(a),
+                Err(e) => {
+                    errors.push(e);
+                    self.recover();
+                }
+            }
+        }
+
+        Some(AnnotationLine {
+            target,
+            annotations,
+            span,
+        })
+    }
+
+    fn entry(&mut self) -> Result<Annotation, AnnotationError> {
+        let start = self.pos;
+        let (name, _) = self.ident()?;
+        let mut args = vec![];
+        self.skip_whitespace();
+        if self.eat('(') {
+            self.skip_whitespace();
+            if !self.eat(')') {
+                loop {
+                    args.push(self.argument()?);
+                    self.skip_whitespace();
+                    if self.eat(')') {
+                        break;
+                    }
+                    self.expect(',')?;
+                }
+            }
+        } else if self.eat('=') {
+            let value_start = self.pos;
+            let value = self.value()?;
+            args.push(Argument {
+                key: None,
+                value,
+                span: self.span_from(value_start),
+            });
+        }
+        Ok(Annotation {
+            name,
+            args,
+            span: self.span_from(start),
+        })
+    }
+
+    fn argument(&mut self) -> Result<Argument, AnnotationError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let first = self.value()?;
+        self.skip_whitespace();
+        let (key, value) = match first {
+            Value::Ident(key) if self.eat('=') => (Some(key), self.value()?),
+            value => (None, value),
+        };
+        Ok(Argument {
+            key,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    fn value(&mut self) -> Result<Value, AnnotationError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string(),
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let start = self.pos;
+                self.pos += c.len_utf8();
+                self.take_while(|c| c.is_ascii_digit() || c == '.');
+                Ok(Value::Number(self.text[start..self.pos].to_owned()))
+            }
+            _ => self.ident().map(|(i, _)| Value::Ident(i)),
+        }
+    }
+
+    fn string(&mut self) -> Result<Value, AnnotationError> {
+        let start = self.pos;
+        self.pos += 1; // opening quote
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(Value::Str(s)),
+                '\\' => match self.peek() {
+                    Some(escaped) => {
+                        self.pos += escaped.len_utf8();
+                        s.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            other => other,
+                        });
+                    }
+                    None => break,
+                },
+                c => s.push(c),
+            }
+        }
+        Err(self.error(start, "unterminated string".to_owned()))
+    }
+
+    fn ident(&mut self) -> Result<(String, Span), AnnotationError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        self.take_while(|c| c.is_alphanumeric() || c == '_');
+        if start == self.pos {
+            let found = match self.peek() {
+                Some(c) => format!("`{}`", c),
+                None => "end of line".to_owned(),
+            };
+            return Err(self.error(start, format!("expected a name, found {}", found)));
+        }
+        Ok((self.text[start..self.pos].to_owned(), self.span_from(start)))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), AnnotationError> {
+        self.skip_whitespace();
+        if self.eat(c) {
+            return Ok(());
+        }
+        let found = match self.peek() {
+            Some(f) => format!("`{}`", f),
+            None => "end of line".to_owned(),
+        };
+        Err(self.error(self.pos, format!("expected `{}`, found {}", c, found)))
+    }
+
+    /// Skips past the next `;`, so that parsing can continue with the following entry
+    fn recover(&mut self) {
+        self.take_while(|c| c != ';');
+        self.eat(';');
+    }
+
+    /// The end of a line is either its actual end, or the end of a single line comment
+    fn at_end(&self) -> bool {
+        self.pos >= self.end || self.text[self.pos..self.end].trim() == "*/"
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) {
+        while let Some(c) = self.peek() {
+            if !f(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn peek(&self) -> Option<char> {
+        if self.pos >= self.end {
+            return None;
+        }
+        self.text[self.pos..self.end].chars().next()
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.pos,
+        }
+    }
+
+    fn error(&self, start: usize, message: String) -> AnnotationError {
+        AnnotationError {
+            message,
+            span: Span {
+                start,
+                end: self.pos.max(start + 1).min(self.end.max(start)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::MetaValue;
+
+    fn ident(s: &str) -> Value {
+        Value::Ident(s.to_owned())
+    }
+
+    #[test]
+    fn parses_flags_and_arguments() {
+        let parsed = parse_comment("/**\n * #meta: nullable;length(arr);rename=getToken;\n */");
+        assert!(parsed.errors.is_empty());
+        let names: Vec<String> = parsed.for_item().iter().map(|a| a.to_string()).collect();
+        assert_eq!(names, ["nullable", "length(arr)", "rename(getToken)"]);
+    }
+
+    #[test]
+    fn parses_values_and_keys() {
+        let a = parse_comment(r#"#meta: default(-1.5, "a\"b", key=value);"#)
+            .for_item()
+            .remove(0);
+        assert_eq!(a.name, "default");
+        let args: Vec<(Option<&str>, &Value)> = a
+            .args
+            .iter()
+            .map(|a| (a.key.as_deref(), &a.value))
+            .collect();
+        assert_eq!(
+            args,
+            [
+                (None, &Value::Number("-1.5".to_owned())),
+                (None, &Value::Str("a\"b".to_owned())),
+                (Some("key"), &ident("value")),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_point_into_the_comment() {
+        let comment = "/**\n * #meta_param: out;output;free_with(lib_free);\n */";
+        let parsed = parse_comment(comment);
+        let line = &parsed.lines[0];
+        let at = |s: Span| &comment[s.start..s.end];
+        assert_eq!(at(line.span), "#meta_param:");
+        match &line.target {
+            AnnotationTarget::Param { name, span } => {
+                assert_eq!(name, "out");
+                assert_eq!(at(*span), "out");
+            }
+            AnnotationTarget::Item => panic!("expected a parameter line"),
+        }
+        let free_with = &line.annotations[1];
+        assert_eq!(at(free_with.span), "free_with(lib_free)");
+        assert_eq!(at(free_with.args[0].span), "lib_free");
+    }
+
+    #[test]
+    fn separates_item_and_param_lines() {
+        let parsed =
+            parse_comment("#meta: throws;\n#meta_param: out;output;\n#meta_param: outer;nullable;");
+        let item: Vec<String> = parsed.for_item().iter().map(|a| a.to_string()).collect();
+        assert_eq!(item, ["throws"]);
+        let out: Vec<String> = parsed
+            .for_param("out")
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(out, ["output"]);
+        assert!(parsed.for_param("ou").is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_end_of_a_single_line_comment() {
+        let parsed = parse_comment("/** #meta: nullable; */");
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.for_item()[0].name, "nullable");
+    }
+
+    #[test]
+    fn skips_malformed_entries_and_keeps_the_rest() {
+        let comment = "#meta: length(arr;nullable;";
+        let parsed = parse_comment(comment);
+        assert_eq!(parsed.errors.len(), 1);
+        let error = &parsed.errors[0];
+        assert_eq!(error.message, "expected `,`, found `;`");
+        assert_eq!(error.span.start, comment.find(';').unwrap());
+        let names: Vec<String> = parsed.for_item().iter().map(|a| a.to_string()).collect();
+        assert_eq!(names, ["nullable"]);
+    }
+
+    #[test]
+    fn drops_param_lines_without_a_name() {
+        let parsed = parse_comment("#meta_param: out");
+        assert!(parsed.lines.is_empty());
+        assert_eq!(parsed.errors[0].message, "expected `;`, found end of line");
+    }
+
+    #[test]
+    fn reports_malformed_entries() {
+        let error = |comment: &str| parse_comment(comment).errors.remove(0);
+        assert_eq!(error("#meta: (x);").message, "expected a name, found `(`");
+        assert_eq!(
+            error("#meta: length(,);").message,
+            "expected a name, found `,`"
+        );
+        assert_eq!(
+            error("#meta: nullable extra;").message,
+            "expected `;`, found `e`"
+        );
+        let unterminated = error(r#"#meta: default("abc"#);
+        assert_eq!(unterminated.message, "unterminated string");
+        assert_eq!(unterminated.span, Span { start: 15, end: 19 });
+    }
+
+    #[test]
+    fn leaves_unknown_keywords_to_the_meta_value() {
+        let entry = |comment: &str| parse_comment(comment).for_item().remove(0);
+        assert_eq!(
+            MetaValue::new().apply(&entry("#meta: frobnicate;")),
+            Err("unknown meta keyword `frobnicate`".to_owned())
+        );
+        assert_eq!(
+            MetaValue::new().apply(&entry("#meta: nullable(x);")),
+            Err("`nullable` does not take any arguments".to_owned())
+        );
+        assert_eq!(MetaValue::new().apply(&entry("#meta: nullable;")), Ok(()));
+    }
+
+    #[test]
+    fn parses_a_single_annotation() {
+        let a = parse_annotation("length(arr);").unwrap();
+        assert_eq!(a.to_string(), "length(arr)");
+        let error = |text: &str| parse_annotation(text).unwrap_err().message;
+        assert_eq!(error("nullable extra"), "unexpected `e` after `nullable`");
+        assert_eq!(error(""), "expected a name, found end of line");
+    }
+}
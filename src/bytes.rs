@@ -0,0 +1,60 @@
+use crate::lexer::{CType, CVariableDeclaration};
+
+/// A buffer of raw bytes, which C sees as a `uint8_t *` along with its size.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta_param: data;bytes;
+///  * #meta_param: data_len;length(data);
+///  */
+/// uint32_t Engine_sign(const struct Engine *this_, const uint8_t *data, uintptr_t data_len, char **err);
+/// ```
+///
+/// An `output` buffer is a `uint8_t **`, whose size the library writes to a `uintptr_t *` that is not marked `output` itself.
+/// The same annotations describe a buffer stored in the fields of a struct
+pub struct Bytes<'a> {
+    /// The `uint8_t *` marked `bytes`, which names the buffer in the generated code
+    pub data: &'a CVariableDeclaration,
+    /// Size of the buffer, marked `length(data)`
+    pub length: Option<&'a CVariableDeclaration>,
+}
+
+impl<'a> Bytes<'a> {
+    /// Every buffer among `declarations`, the parameters of a function or the fields of a struct
+    pub fn all(declarations: &'a [CVariableDeclaration]) -> Vec<Self> {
+        declarations
+            .iter()
+            .filter(|d| d.meta.as_ref().is_some_and(|m| m.is_bytes))
+            .map(|data| Bytes {
+                data,
+                length: declarations.iter().find(|d| {
+                    d.meta
+                        .as_ref()
+                        .is_some_and(|m| m.length_for.as_ref() == Some(&data.label))
+                }),
+            })
+            .collect()
+    }
+
+    /// Whether `d` is the length of one of the buffers among `declarations`.
+    /// It is marshalled along with the buffer, and is left out of generated signatures
+    pub fn is_part(declarations: &[CVariableDeclaration], d: &CVariableDeclaration) -> bool {
+        Bytes::all(declarations)
+            .iter()
+            .any(|b| b.length.is_some_and(|l| l.label == d.label))
+    }
+
+    /// Whether the library hands this buffer back, rather than reading it
+    pub fn is_output(&self) -> bool {
+        self.data.meta.as_ref().is_some_and(|m| m.is_output)
+    }
+
+    /// Whether `d` is a pointer to single bytes, the only thing a buffer can be made of
+    pub fn is_byte_pointer(d: &CVariableDeclaration) -> bool {
+        matches!(
+            d.variable_type.kind,
+            CType::UInt8T(_) | CType::Int8T(_) | CType::Char(_)
+        ) && d.variable_type.pointer_count > 0
+    }
+}
@@ -0,0 +1,125 @@
+use std::mem::discriminant;
+
+use crate::lexer::{CFunction, CType, CVariableDeclaration, CVariableType, HeaderFile};
+
+/// An `async` function that can be cancelled while it runs, by handing its `cancel_handle` to another function.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: for_struct;throws;async;cancellable(OAuth2PKCE_cancel);
+///  * #meta_param: this_;this;
+///  * #meta_param: token_output;output;
+///  * #meta_param: cancel;cancel_handle;
+///  * #meta_param: err_ptr;error;
+///  */
+/// uint32_t OAuth2PKCE_start_web_server_for_callback(struct OAuth2PKCE *this_,
+///                                                   struct TokenResponse **token_output,
+///                                                   uint64_t cancel,
+///                                                   char **err_ptr);
+///
+/// void OAuth2PKCE_cancel(uint64_t cancel);
+/// ```
+///
+/// The bindings make up a new handle for each call, and hand the same handle to the cancel function
+/// when the caller gives up on the call. The library is expected to make the call fail soon after
+pub struct Cancellation<'a> {
+    /// The function marked `cancellable(cancel)`
+    pub function: &'a CFunction,
+    /// The function that cancels `function`
+    pub cancel: &'a CFunction,
+    /// The parameter of `function` marked `cancel_handle`
+    pub handle: &'a CVariableDeclaration,
+}
+
+impl<'a> Cancellation<'a> {
+    /// Every cancellable function of `header`, leaving out those that `Cancellation::of` rejects
+    pub fn all(header: &'a HeaderFile) -> Vec<Self> {
+        header
+            .functions
+            .iter()
+            .filter_map(|f| Cancellation::of(f, header).ok().flatten())
+            .collect()
+    }
+
+    /// How `f` is cancelled, if it is marked `cancellable`, or what is wrong with it
+    pub fn of(f: &'a CFunction, header: &'a HeaderFile) -> Result<Option<Self>, String> {
+        let meta = match &f.meta {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let cancel_label = match &meta.cancel_with {
+            Some(cancel) => cancel,
+            None => return Ok(None),
+        };
+        if !meta.is_async {
+            return Err(format!(
+                "function `{}` is cancellable, but is not async",
+                f.label
+            ));
+        }
+        let cancel = header
+            .functions
+            .iter()
+            .find(|c| c.label == *cancel_label)
+            .ok_or_else(|| {
+                format!(
+                    "function `{}` is cancelled with `{}`, which is not a function of the header",
+                    f.label, cancel_label
+                )
+            })?;
+        let mut handles = f
+            .parameters
+            .iter()
+            .filter(|p| p.meta.as_ref().is_some_and(|m| m.is_cancel_handle));
+        let handle = match (handles.next(), handles.next()) {
+            (Some(handle), None) if is_integer(&handle.variable_type) => handle,
+            _ => {
+                return Err(format!(
+                    "function `{}` is cancellable, so exactly one of its parameters must be an integer marked cancel_handle",
+                    f.label
+                ))
+            }
+        };
+        match cancel.parameters.as_slice() {
+            [p] if is_integer(&p.variable_type)
+                && discriminant(&p.variable_type.kind)
+                    == discriminant(&handle.variable_type.kind) => {}
+            _ => {
+                return Err(format!(
+                    "`{}` cancels `{}`, so it must take its cancel handle `{}`, and nothing else",
+                    cancel_label, f.label, handle.label
+                ))
+            }
+        }
+        Ok(Some(Cancellation {
+            function: f,
+            cancel,
+            handle,
+        }))
+    }
+}
+
+/// Whether a cancel handle can be of type `t`: a plain integer, which the bindings count up
+fn is_integer(t: &CVariableType) -> bool {
+    t.pointer_count == 0
+        && matches!(
+            t.kind,
+            CType::SignedShort(_)
+                | CType::UnsignedShort(_)
+                | CType::SignedInteger(_)
+                | CType::UnsignedInteger(_)
+                | CType::SignedLong(_)
+                | CType::UnsignedLong(_)
+                | CType::Int64T(_)
+                | CType::IntPtrT(_)
+                | CType::UIntPtrT(_)
+                | CType::Int8T(_)
+                | CType::Int16T(_)
+                | CType::Int32T(_)
+                | CType::UInt8T(_)
+                | CType::UInt16T(_)
+                | CType::UInt32T(_)
+                | CType::UInt64T(_)
+        )
+}
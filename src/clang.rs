@@ -0,0 +1,162 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Header path that makes clang2src read the header from stdin instead
+pub const STDIN_HEADER: &str = "-";
+
+/// How to invoke clang: which executable, and any extra arguments to hand it
+#[derive(Debug, Clone)]
+pub struct Clang {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl Default for Clang {
+    fn default() -> Self {
+        Clang {
+            program: PathBuf::from("clang"),
+            args: vec![],
+        }
+    }
+}
+
+impl Clang {
+    /// Runs clang over the header and returns its raw token dump.
+    ///
+    /// A header of `-` is read from stdin and piped through to clang
+    pub fn dump_clang_tokes(&self, p: &Path) -> Result<String, String> {
+        let mut cmd = Command::new(&self.program);
+        cmd.arg("-fsyntax-only")
+            .arg("-Xclang")
+            .arg("-dump-raw-tokens")
+            .args(&self.args);
+
+        let output = if is_stdin(p) {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|e| format!("Failed to read header from stdin: {}", e))?;
+            cmd.args(["-x", "c", "-"]);
+            self.run(cmd, Some(&source))?
+        } else {
+            if !p.exists() {
+                return Err(format!("No file found: {}", p.display()));
+            }
+            cmd.arg(p);
+            self.run(cmd, None)?
+        };
+
+        /* FYI(nf): Clang dumps output to stderr for some reason */
+        // let stdout = String::from_utf8(output.stdout).unwrap();
+        String::from_utf8(output.stderr).map_err(|e| format!("clang produced invalid UTF-8: {}", e))
+    }
+
+    /// Every header pulled in by `p`, including `p` itself, as reported by `clang -MM`.
+    ///
+    /// System headers are left out. Returns just `p` if clang fails
+    pub fn included_headers(&self, p: &Path) -> Vec<PathBuf> {
+        let mut cmd = Command::new(&self.program);
+        cmd.arg("-MM").args(&self.args).arg(p);
+        match self.run(cmd, None) {
+            Ok(o) if o.status.success() => {
+                parse_make_dependencies(&String::from_utf8_lossy(&o.stdout))
+            }
+            _ => vec![p.to_owned()],
+        }
+    }
+
+    fn run(&self, mut cmd: Command, stdin: Option<&str>) -> Result<std::process::Output, String> {
+        let failed = |e: std::io::Error| format!("Failed to run {}: {}", self.program.display(), e);
+        let input = match stdin {
+            None => return cmd.output().map_err(failed),
+            Some(input) => input,
+        };
+
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        /* Write from another thread, clang starts dumping tokens before it has read all of its input */
+        let mut pipe = child.stdin.take().unwrap();
+        let input = input.to_owned();
+        let writer = std::thread::spawn(move || pipe.write_all(input.as_bytes()));
+        let output = child.wait_with_output().map_err(failed)?;
+        if let Ok(Err(e)) = writer.join() {
+            return Err(format!("Failed to pipe header to clang: {}", e));
+        }
+        Ok(output)
+    }
+}
+
+/// Whether `p` stands for stdin rather than an actual file
+pub fn is_stdin(p: &Path) -> bool {
+    p.as_os_str() == STDIN_HEADER
+}
+
+/// Parses a make rule of the form `header.o: header.h other.h \` into its prerequisites
+fn parse_make_dependencies(rule: &str) -> Vec<PathBuf> {
+    let prerequisites = match rule.split_once(": ") {
+        Some((_, p)) => p,
+        None => return vec![],
+    };
+
+    let mut paths = vec![];
+    let mut current = String::new();
+    let mut chars = prerequisites.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // line continuation
+                Some('\n') | Some('\r') => {}
+                Some(escaped) => current.push(escaped),
+                None => {}
+            },
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    paths.push(PathBuf::from(std::mem::take(&mut current)));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        paths.push(PathBuf::from(current));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_make_dependencies() {
+        let rule = "lib.o: include/lib.h include/types.h \\\n  include/with\\ space.h\n";
+        assert_eq!(
+            parse_make_dependencies(rule),
+            [
+                PathBuf::from("include/lib.h"),
+                PathBuf::from("include/types.h"),
+                PathBuf::from("include/with space.h"),
+            ]
+        );
+        assert!(parse_make_dependencies("no rule here").is_empty());
+    }
+
+    #[test]
+    fn reads_stdin_for_a_dash() {
+        assert!(is_stdin(Path::new(STDIN_HEADER)));
+        assert!(!is_stdin(Path::new("./-")));
+    }
+
+    #[test]
+    fn reports_missing_headers() {
+        let error = Clang::default()
+            .dump_clang_tokes(Path::new("does/not/exist.h"))
+            .unwrap_err();
+        assert_eq!(error, "No file found: does/not/exist.h");
+    }
+}
@@ -0,0 +1,239 @@
+use crate::cursor::destructor_of;
+use crate::lexer::{CFunction, CType, CVariableDeclaration, HeaderFile};
+use crate::list::Element;
+
+/// A function that returns at once, and hands its result to a completion callback once the work is done,
+/// which the bindings turn into the language's own kind of future, without holding up a thread while it runs.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: for_struct;async_callback(on_done);
+///  * #meta_param: this_;this;
+///  * #meta_param: url;string;
+///  * #meta_param: token;free_with(TokenResponse_free);
+///  * #meta_param: err;error;
+///  */
+/// void OAuth2PKCE_fetch(struct OAuth2PKCE *this_,
+///                       const char *url,
+///                       void (*on_done)(void *user_data, struct TokenResponse *token, char *err),
+///                       void *user_data);
+/// ```
+///
+/// The callback is called exactly once, from any thread, with the `user_data` the call was handed.
+/// It takes that `void *` first, then the result, if there is one, then the string marked `error`, if there is one,
+/// which is null unless the work failed. The result is a number, an enum, a string or a pointer to a struct.
+///
+/// Both are handed over to the bindings, as an `output` and an `error` are: they are copied and then released,
+/// with their `free_with`, the `destructor` of the struct, or the language's own `free`,
+/// unless the result is a `persistent` struct, which the caller takes over
+pub struct Completion<'a> {
+    /// The function marked `async_callback(callback)`
+    pub function: &'a CFunction,
+    /// The function pointer parameter of `function`
+    pub callback: &'a CVariableDeclaration,
+    /// What `callback` points to
+    pub signature: &'a CFunction,
+    /// The `void *` parameter of `function`, handed back to `callback`
+    pub user_data: &'a CVariableDeclaration,
+    /// The `void *` parameter of `callback`
+    pub callback_user_data: &'a CVariableDeclaration,
+    /// The parameter of `callback` the result is handed in
+    pub result: Option<&'a CVariableDeclaration>,
+    /// What `result` is, see `Completion::of`
+    pub result_element: Option<Element<'a>>,
+    /// The parameter of `callback` marked `error`
+    pub error: Option<&'a CVariableDeclaration>,
+    /// The method of the result struct marked `destructor`
+    result_destructor: Option<&'a CFunction>,
+}
+
+impl<'a> Completion<'a> {
+    /// Every function of `header` that hands its result to a callback, leaving out those that `Completion::of` rejects
+    pub fn all(header: &'a HeaderFile) -> Vec<Self> {
+        header
+            .functions
+            .iter()
+            .filter_map(|f| Completion::of(f, header).ok().flatten())
+            .collect()
+    }
+
+    /// How `f` hands over its result, if it is marked `async_callback`, or what is wrong with it
+    pub fn of(f: &'a CFunction, header: &'a HeaderFile) -> Result<Option<Self>, String> {
+        let meta = match &f.meta {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let callback_label = match &meta.completes_with {
+            Some(callback) => callback,
+            None => return Ok(None),
+        };
+        if meta.is_async {
+            return Err(format!(
+                "function `{}` hands its result to `{}`, so it must not also be async, which blocks a thread until it is done",
+                f.label, callback_label
+            ));
+        }
+        let (callback, signature) = f
+            .parameters
+            .iter()
+            .find_map(|p| match &p.variable_type.kind {
+                CType::Function(signature) if p.label == *callback_label => Some((p, signature)),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                format!(
+                    "function `{}` hands its result to `{}`, which is not a function pointer parameter of it",
+                    f.label, callback_label
+                )
+            })?;
+        if !matches!(f.return_type.kind, CType::Void) || f.return_type.pointer_count != 0 {
+            return Err(format!(
+                "function `{}` hands its result to `{}`, so it must return void",
+                f.label, callback_label
+            ));
+        }
+        if f.parameters
+            .iter()
+            .any(|p| p.meta.as_ref().is_some_and(|m| m.is_output || m.is_error))
+        {
+            return Err(format!(
+                "function `{}` hands its result and error to `{}`, so none of its own parameters can be an output or an error",
+                f.label, callback_label
+            ));
+        }
+        let user_data = match f
+            .parameters
+            .iter()
+            .filter(|p| is_user_data(p))
+            .collect::<Vec<_>>()[..]
+        {
+            [user_data] => user_data,
+            _ => return Err(format!(
+                "function `{}` must take exactly one `void *`, the user data it hands back to `{}`",
+                f.label, callback_label
+            )),
+        };
+
+        let malformed = || {
+            format!(
+                "`{}` must take a `void *` for the user data, then at most a result and a string marked error, and return void",
+                callback_label
+            )
+        };
+        if !matches!(signature.return_type.kind, CType::Void)
+            || signature.return_type.pointer_count != 0
+        {
+            return Err(malformed());
+        }
+        let (callback_user_data, rest) = match signature.parameters.split_first() {
+            Some((first, rest)) if is_user_data(first) => (first, rest),
+            _ => return Err(malformed()),
+        };
+        let is_error = |p: &CVariableDeclaration| {
+            p.meta.as_ref().is_some_and(|m| m.is_error)
+                && matches!(p.variable_type.kind, CType::Char(_))
+                && p.variable_type.pointer_count == 1
+        };
+        let (result, error) = match rest {
+            [] => (None, None),
+            [error] if is_error(error) => (None, Some(error)),
+            [result] if !is_error(result) => (Some(result), None),
+            [result, error] if is_error(error) => (Some(result), Some(error)),
+            _ => return Err(malformed()),
+        };
+
+        let result_element = match result {
+            Some(r) => Some(element_of(r, header).ok_or_else(|| {
+                format!(
+                    "the result `{}` of `{}` must be a number, an enum, a string, or a pointer to a struct of the header",
+                    r.label, callback_label
+                )
+            })?),
+            None => None,
+        };
+        let result_destructor = match &result_element {
+            Some(Element::StructPointer(s)) => destructor_of(&s.identifier.label, header),
+            _ => None,
+        };
+        Ok(Some(Completion {
+            function: f,
+            callback,
+            signature,
+            user_data,
+            callback_user_data,
+            result,
+            result_element,
+            error,
+            result_destructor,
+        }))
+    }
+
+    /// Whether the result is a handle the caller takes over, rather than a value that is copied
+    pub fn is_result_persistent(&self) -> bool {
+        matches!(&self.result_element, Some(Element::StructPointer(s)) if s.meta.as_ref().is_some_and(|m| m.is_persistent))
+    }
+
+    /// The function releasing the result once it is copied: its `free_with`, or else the `destructor` of its struct.
+    ///
+    /// Returns `None` for numbers and enums, for `persistent` structs, and for results left to the language's own `free`
+    pub fn result_release(&self) -> Option<&'a str> {
+        let result = self.result?;
+        if self.is_result_persistent()
+            || matches!(
+                self.result_element,
+                Some(Element::Primitive(_) | Element::Enum(_))
+            )
+        {
+            return None;
+        }
+        match result.meta.as_ref().and_then(|m| m.free_with.as_ref()) {
+            Some(free_with) => Some(free_with),
+            None => self.result_destructor.map(|f| f.label.as_str()),
+        }
+    }
+
+    /// Whether the result must be left alone once copied, as it is `borrowed` from the library
+    pub fn is_result_borrowed(&self) -> bool {
+        self.result
+            .is_some_and(|r| r.meta.as_ref().is_some_and(|m| m.is_borrowed))
+    }
+
+    /// Whether `p` is handed to the library as the callback or the user data, rather than by the caller.
+    /// It is left out of generated signatures
+    pub fn is_part(&self, p: &CVariableDeclaration) -> bool {
+        p.label == self.callback.label || p.label == self.user_data.label
+    }
+}
+
+/// Whether `p` is a `void *`, which carries the user data
+fn is_user_data(p: &CVariableDeclaration) -> bool {
+    matches!(p.variable_type.kind, CType::Void) && p.variable_type.pointer_count == 1
+}
+
+/// What the result `p` is, with a struct filled in with its declaration in `header`
+fn element_of<'a>(p: &'a CVariableDeclaration, header: &'a HeaderFile) -> Option<Element<'a>> {
+    match (&p.variable_type.kind, p.variable_type.pointer_count) {
+        (CType::Char(_), 1) => Some(Element::String),
+        (CType::Struct(s), 1) => header
+            .structs
+            .iter()
+            .find(|h| h.identifier.label == s.identifier.label)
+            .map(Element::StructPointer),
+        (CType::Enum(e), 0) => Some(Element::Enum(e)),
+        (
+            CType::Char(_)
+            | CType::Struct(_)
+            | CType::Enum(_)
+            | CType::Include(_)
+            | CType::Function(_)
+            | CType::Define(_, _)
+            | CType::VoidStar
+            | CType::Void
+            | CType::UNINITIALIZED,
+            _,
+        ) => None,
+        (kind, 0) => Some(Element::Primitive(kind)),
+        _ => None,
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::Deserialize;
+
+/// File name looked up in the working directory when `clang2src` is invoked without a target
+pub const DEFAULT_CONFIG_FILE: &str = "clang2src.toml";
+
+/// Project configuration, usually read from `clang2src.toml`
+///
+/// e.g.,
+/// ```toml
+/// header = "include/liboauthtool.h"
+/// clang = "toolchain/bin/clang"
+/// clang_args = ["-std=c11"]
+/// annotations = "include/annotations.toml"
+///
+/// [target.dart]
+/// library_path = "libs"
+/// library_name = "liboauthtool"
+/// output = "dart/lib/src/bindings.dart"
+///
+/// [target.go]
+/// package_name = "oauthtool"
+/// ld_flags = "-L${SRCDIR}/libs -loauthtool"
+/// header_file_location = "liboauthtool.h"
+/// output = "go/bindings.go"
+/// ```
+///
+/// Each target may also be given as an array of tables (`[[target.dart]]`) to generate it more than once.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Path to the C header file to parse, relative to the config file
+    pub header: PathBuf,
+
+    /// clang executable to use instead of the one on the `PATH`
+    #[serde(default)]
+    pub clang: Option<PathBuf>,
+
+    /// Additional arguments handed to clang when dumping the header tokens
+    #[serde(default)]
+    pub clang_args: Vec<String>,
+
+    /// Sidecar file with annotations for the header, see [crate::sidecar::Sidecar]
+    #[serde(default)]
+    pub annotations: Option<PathBuf>,
+
+    /// Languages to generate bindings for
+    #[serde(default)]
+    pub target: TargetTables,
+}
+
+impl Config {
+    /// Reads and parses the config file at `path`.
+    ///
+    /// All relative paths inside of the config are resolved against the directory of the config file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        config.header = base.join(&config.header);
+        config.annotations = config.annotations.map(|a| base.join(a));
+        /* A bare program name is looked up on the PATH, anything else is a path relative to the config */
+        if let Some(clang) = &mut config.clang {
+            if clang.components().count() > 1 {
+                *clang = base.join(&*clang);
+            }
+        }
+        let tables = &mut config.target;
+        let dart = tables.dart.iter_mut().flat_map(|d| d.iter_mut());
+        let go = tables.go.iter_mut().flat_map(|g| g.iter_mut());
+        let csharp = tables.csharp.iter_mut().flat_map(|c| c.iter_mut());
+        let outputs = dart
+            .map(|o| &mut o.output)
+            .chain(go.map(|o| &mut o.output))
+            .chain(csharp.map(|o| &mut o.output));
+        for output in outputs.flatten() {
+            *output = base.join(&*output);
+        }
+
+        Ok(config)
+    }
+
+    /// All configured targets, in the order Dart, Go, C#
+    pub fn targets(&self) -> Vec<Target> {
+        self.target.iter().collect()
+    }
+}
+
+/// The `[target.*]` tables of the config file
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TargetTables {
+    #[serde(default)]
+    dart: Option<OneOrMany<DartOptions>>,
+    #[serde(default)]
+    go: Option<OneOrMany<GoOptions>>,
+    #[serde(default)]
+    csharp: Option<OneOrMany<CSharpOptions>>,
+}
+
+impl TargetTables {
+    fn iter(&self) -> impl Iterator<Item = Target> + '_ {
+        let dart = self.dart.iter().flat_map(|d| d.iter()).cloned();
+        let go = self.go.iter().flat_map(|g| g.iter()).cloned();
+        let csharp = self.csharp.iter().flat_map(|c| c.iter()).cloned();
+        dart.map(Target::Dart)
+            .chain(go.map(Target::Go))
+            .chain(csharp.map(Target::CSharp))
+    }
+}
+
+/// Either a single table `[target.x]` or an array of tables `[[target.x]]`
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn iter(&self) -> std::slice::Iter<'_, T> {
+        match self {
+            OneOrMany::One(item) => std::slice::from_ref(item).iter(),
+            OneOrMany::Many(items) => items.iter(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        match self {
+            OneOrMany::One(item) => std::slice::from_mut(item).iter_mut(),
+            OneOrMany::Many(items) => items.iter_mut(),
+        }
+    }
+}
+
+/// A single language to generate bindings for, along with its options
+#[derive(Debug, Clone)]
+pub enum Target {
+    Dart(DartOptions),
+    Go(GoOptions),
+    CSharp(CSharpOptions),
+}
+
+impl Target {
+    /// Human readable name of the target language
+    pub fn language(&self) -> &'static str {
+        match self {
+            Target::Dart(_) => "dart",
+            Target::Go(_) => "go",
+            Target::CSharp(_) => "csharp",
+        }
+    }
+
+    /// Where the generated bindings should be written. `None` means stdout
+    pub fn output(&self) -> Option<&Path> {
+        match self {
+            Target::Dart(o) => o.output.as_deref(),
+            Target::Go(o) => o.output.as_deref(),
+            Target::CSharp(o) => o.output.as_deref(),
+        }
+    }
+}
+
+#[derive(Args, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CSharpOptions {
+    /// C# Namespace of the generated file to use
+    ///
+    /// e.g., `namespace FFI {
+    ///     public struct OAuthManager{}
+    ///  [...]
+    /// }`
+    pub namespace: String,
+
+    /// Location, relative to the runtime location of the final libary, where the dll
+    /// will be loaded from. Should include the extension, e.g.,
+    ///
+    /// `some/relative/folder/liboauthtool.dll`
+    pub dll_location: String,
+
+    /// File to write the generated bindings to. Prints to stdout if not set
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GoOptions {
+    /// Name of the Go Package to place the generated bindings under
+    pub package_name: String,
+    /// Where to load the lib files relative to the go binary
+    pub ld_flags: String,
+    /// header file location relative to the building location of the go binary
+    pub header_file_location: String,
+
+    /// File to write the generated bindings to. Prints to stdout if not set
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DartOptions {
+    /// Location of the folder containing DLLs/SO files
+    pub library_path: String,
+    /// Name of the lib file, without the extension
+    pub library_name: String,
+
+    /// File to write the generated bindings to. Prints to stdout if not set
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str) -> Result<(tempfile::TempDir, Config), String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_CONFIG_FILE);
+        std::fs::write(&path, contents).unwrap();
+        let config = Config::load(&path)?;
+        Ok((dir, config))
+    }
+
+    #[test]
+    fn resolves_paths_against_the_config_directory() {
+        let (dir, config) = load(
+            r#"
+header = "include/lib.h"
+clang = "toolchain/bin/clang"
+annotations = "annotations.toml"
+
+[target.go]
+package_name = "lib"
+ld_flags = "-llib"
+header_file_location = "lib.h"
+output = "go/bindings.go"
+"#,
+        )
+        .unwrap();
+        let base = dir.path();
+        assert_eq!(config.header, base.join("include/lib.h"));
+        assert_eq!(config.clang, Some(base.join("toolchain/bin/clang")));
+        assert_eq!(config.annotations, Some(base.join("annotations.toml")));
+        let targets = config.targets();
+        assert_eq!(targets[0].output(), Some(&*base.join("go/bindings.go")));
+    }
+
+    #[test]
+    fn looks_up_bare_clang_names_on_the_path() {
+        let (_dir, config) = load("header = \"lib.h\"\nclang = \"clang-17\"\n").unwrap();
+        assert_eq!(config.clang, Some(PathBuf::from("clang-17")));
+        assert!(config.targets().is_empty());
+    }
+
+    #[test]
+    fn lists_targets_in_order_and_repeated_tables() {
+        let (_dir, config) = load(
+            r#"
+header = "lib.h"
+
+[target.csharp]
+namespace = "Lib"
+dll_location = "lib.dll"
+
+[[target.dart]]
+library_path = "libs"
+library_name = "lib"
+
+[[target.dart]]
+library_path = "other"
+library_name = "lib"
+output = "other.dart"
+"#,
+        )
+        .unwrap();
+        let targets = config.targets();
+        let languages: Vec<&str> = targets.iter().map(|t| t.language()).collect();
+        assert_eq!(languages, ["dart", "dart", "csharp"]);
+        assert_eq!(targets[0].output(), None);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let error = load("header = \"lib.h\"\nheaders = \"other.h\"\n").unwrap_err();
+        assert!(error.starts_with("Invalid config file"), "{}", error);
+        assert!(error.contains("unknown field `headers`"), "{}", error);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::lexer::{CFunction, CStruct, CType, CVariableDeclaration, HeaderFile};
+
+/// A `persistent` struct that hands out the items of a paginated API one at a time,
+/// which the bindings turn into the language's own kind of iterator.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: persistent;iterator(TokenResponse);
+///  */
+/// typedef struct TokenCursor TokenCursor;
+///
+/// /**
+///  * #meta: for_struct;
+///  * #meta_param: this_;this;
+///  * #meta_param: out;output;free_with(TokenResponse_free);
+///  */
+/// uint8_t TokenCursor_next(struct TokenCursor *this_, struct TokenResponse **out);
+/// ```
+///
+/// `{cursor}_next` writes the next item to its one `output` and returns non-zero, or returns `0` once there are none left,
+/// at which point the cursor is released with its `destructor`, if it has one.
+/// A `persistent` item is handed over to the caller, any other item is copied and then released, see `item_release`
+pub struct Cursor<'a> {
+    /// The struct marked `iterator(item)`
+    pub owner: &'a CStruct,
+    /// The struct handed out
+    pub item: &'a CStruct,
+    /// `{owner}_next`
+    pub next: &'a CFunction,
+    /// The parameter of `next` the item is written to
+    pub output: &'a CVariableDeclaration,
+    /// The method of `owner` marked `destructor`, which releases the cursor once it runs out
+    pub destructor: Option<&'a CFunction>,
+    /// The method of `item` marked `destructor`
+    item_destructor: Option<&'a CFunction>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Every cursor of `header`, leaving out those that `Cursor::of` rejects
+    pub fn all(header: &'a HeaderFile) -> Vec<Self> {
+        header
+            .structs
+            .iter()
+            .filter_map(|s| Cursor::of(s, header).ok().flatten())
+            .collect()
+    }
+
+    /// The cursor `s` describes, if it is marked `iterator`, or what is wrong with it
+    pub fn of(s: &'a CStruct, header: &'a HeaderFile) -> Result<Option<Self>, String> {
+        let item_name = match s.meta.as_ref().and_then(|m| m.iterator_of.as_ref()) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let owner = &s.identifier.label;
+        if !s.meta.as_ref().is_some_and(|m| m.is_persistent) {
+            return Err(format!(
+                "struct `{}` is an iterator, but is not persistent",
+                owner
+            ));
+        }
+        let item = header
+            .structs
+            .iter()
+            .find(|s| s.identifier.label == *item_name)
+            .ok_or_else(|| {
+                format!(
+                    "struct `{}` is an iterator of `{}`, which is not a struct of the header",
+                    owner, item_name
+                )
+            })?;
+        let next_label = format!("{}_next", owner);
+        let next = header
+            .functions
+            .iter()
+            .find(|f| f.label == next_label)
+            .ok_or_else(|| {
+                format!(
+                    "struct `{}` is an iterator, but the header has no `{}` to hand out its items",
+                    owner, next_label
+                )
+            })?;
+        let is_this = |p: &CVariableDeclaration| p.meta.as_ref().is_some_and(|m| m.is_this);
+        let is_item = |p: &CVariableDeclaration| {
+            p.meta.as_ref().is_some_and(|m| m.is_output)
+                && p.variable_type.pointer_count == 2
+                && matches!(&p.variable_type.kind, CType::Struct(s) if s.identifier.label == *item_name)
+        };
+        let output = match next.parameters.as_slice() {
+            [this, output] if is_this(this) && is_item(output) => output,
+            _ => {
+                return Err(format!(
+                    "`{}` must take the cursor as `this`, and a `struct {} **` marked output, and nothing else",
+                    next_label, item_name
+                ))
+            }
+        };
+        if next.return_type.pointer_count != 0
+            || matches!(
+                next.return_type.kind,
+                CType::Void
+                    | CType::VoidStar
+                    | CType::Struct(_)
+                    | CType::Float(_)
+                    | CType::Double(_)
+            )
+        {
+            return Err(format!(
+                "`{}` must return whether it handed out an item, as a number that is 0 once there are none left",
+                next_label
+            ));
+        }
+        Ok(Some(Cursor {
+            owner: s,
+            item,
+            next,
+            output,
+            destructor: destructor_of(owner, header),
+            item_destructor: destructor_of(item_name, header),
+        }))
+    }
+
+    /// Whether the items are handles the caller takes over, rather than values that are copied
+    pub fn is_item_persistent(&self) -> bool {
+        self.item.meta.as_ref().is_some_and(|m| m.is_persistent)
+    }
+
+    /// The function releasing each item once it is copied: the `free_with` of the output,
+    /// or else the `destructor` of the item.
+    ///
+    /// Returns `None` for `persistent` items, which the caller takes over, and for items left to the language's own `free`
+    pub fn item_release(&self) -> Option<&'a str> {
+        if self.is_item_persistent() {
+            return None;
+        }
+        match self.output.meta.as_ref().and_then(|m| m.free_with.as_ref()) {
+            Some(free_with) => Some(free_with),
+            None => self.item_destructor.map(|f| f.label.as_str()),
+        }
+    }
+
+    /// Whether `f` is the `next` function of one of the cursors of `header`.
+    /// It is wrapped by the iterator, rather than generated as a method of its own
+    pub fn is_next(header: &HeaderFile, f: &CFunction) -> bool {
+        Cursor::all(header).iter().any(|c| c.next.label == f.label)
+    }
+}
+
+/// The method of the struct `owner` marked `destructor`
+pub fn destructor_of<'a>(owner: &str, header: &'a HeaderFile) -> Option<&'a CFunction> {
+    header.functions.iter().find(|f| {
+        f.meta
+            .as_ref()
+            .is_some_and(|m| m.for_struct && m.is_destructor)
+            && f.label.split('_').next() == Some(owner)
+    })
+}
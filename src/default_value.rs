@@ -0,0 +1,183 @@
+use crate::annotation::Value;
+use crate::lexer::{CFunction, CType, CVariableDeclaration};
+use crate::property::Property;
+
+/// The value a parameter takes when the caller leaves it out, which the bindings turn into
+/// the language's own kind of optional parameter.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: for_struct;constructor;throws;
+///  * #meta_param: this_;output;this;
+///  * #meta_param: client_id;string;
+///  * #meta_param: scopes;string;default("openid");
+///  * #meta_param: extra_parameters;string;nullable;default(null);
+///  * #meta_param: timeout_in_milliseconds;duration;default(30000);
+///  * #meta_param: err_ptr;error;
+///  */
+/// uint32_t OAuth2Authorization_new(struct OAuth2Authorization **this_,
+///                                  const char *client_id,
+///                                  const char *scopes,
+///                                  const char *extra_parameters,
+///                                  uint64_t timeout_in_milliseconds,
+///                                  char **err_ptr);
+/// ```
+///
+/// A string goes with a `string`, a number with a plain number, or a `duration` in milliseconds,
+/// and `null` with a `nullable` parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefaultValue<'a> {
+    Null,
+    Str(&'a str),
+    /// As written, e.g., `30000` or `1.5`
+    Number(&'a str),
+}
+
+impl<'a> DefaultValue<'a> {
+    /// The default of the parameter `p` of `f`, if it has one, or why it cannot have that one
+    pub fn of(p: &'a CVariableDeclaration, f: &CFunction) -> Result<Option<Self>, String> {
+        let meta = match &p.meta {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let value = match &meta.default_value {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let what = format!("parameter `{}` of function `{}`", p.label, f.label);
+        let is_handed_in = !(meta.is_this
+            || meta.is_output
+            || meta.is_error
+            || meta.is_cancel_handle
+            || meta.is_list
+            || meta.is_bytes
+            || meta.is_hashmap
+            || meta.length_for.is_some()
+            || meta.capacity_for.is_some()
+            || meta.values_for.is_some());
+        if !is_handed_in {
+            return Err(format!(
+                "{} has a default, but is not a plain value handed in by the caller",
+                what
+            ));
+        }
+        if Property::of(f).is_some() {
+            return Err(format!(
+                "{} has a default, but the function is the getter or setter of a property",
+                what
+            ));
+        }
+
+        let pointer_count = p.variable_type.pointer_count;
+        match value {
+            Value::Ident(null) if null == "null" => {
+                if !meta.is_nullable {
+                    return Err(format!("{} defaults to null, but is not nullable", what));
+                }
+                Ok(Some(DefaultValue::Null))
+            }
+            Value::Str(s) => {
+                if !(meta.is_string
+                    && matches!(p.variable_type.kind, CType::Char(_))
+                    && pointer_count == 1)
+                {
+                    return Err(format!(
+                        "{} defaults to a string, but is not a `char *` marked string",
+                        what
+                    ));
+                }
+                Ok(Some(DefaultValue::Str(s)))
+            }
+            Value::Number(n) => {
+                let is_float = matches!(
+                    p.variable_type.kind,
+                    CType::Float(_) | CType::Double(_) | CType::DoubleDouble(_)
+                );
+                let is_number = pointer_count == 0
+                    && !meta.is_datetime
+                    && !meta.is_timestamp
+                    && matches!(
+                        p.variable_type.kind,
+                        CType::SignedShort(_)
+                            | CType::UnsignedShort(_)
+                            | CType::SignedInteger(_)
+                            | CType::UnsignedInteger(_)
+                            | CType::SignedLong(_)
+                            | CType::UnsignedLong(_)
+                            | CType::Int64T(_)
+                            | CType::Float(_)
+                            | CType::Double(_)
+                            | CType::DoubleDouble(_)
+                            | CType::IntPtrT(_)
+                            | CType::UIntPtrT(_)
+                            | CType::Int8T(_)
+                            | CType::Int16T(_)
+                            | CType::Int32T(_)
+                            | CType::UInt8T(_)
+                            | CType::UInt16T(_)
+                            | CType::UInt32T(_)
+                            | CType::UInt64T(_)
+                    );
+                if !is_number {
+                    return Err(format!(
+                        "{} defaults to a number, but is not a number",
+                        what
+                    ));
+                }
+                if n.contains('.') && !is_float {
+                    return Err(format!(
+                        "{} defaults to `{}`, but only takes whole numbers",
+                        what, n
+                    ));
+                }
+                if n.starts_with('-') && is_unsigned(&p.variable_type.kind) {
+                    return Err(format!("{} defaults to `{}`, but is unsigned", what, n));
+                }
+                Ok(Some(DefaultValue::Number(n)))
+            }
+            Value::Ident(_) => Err(format!(
+                "{} defaults to `{}`, but a default must be a string, a number or null",
+                what, value
+            )),
+        }
+    }
+
+    /// Whether `p` of `f` has a default, which makes it optional in the bindings
+    pub fn is_optional(p: &CVariableDeclaration, f: &CFunction) -> bool {
+        matches!(DefaultValue::of(p, f), Ok(Some(_)))
+    }
+}
+
+/// Whether a number of the type `kind` cannot be negative
+fn is_unsigned(kind: &CType) -> bool {
+    matches!(
+        kind,
+        CType::UnsignedShort(_)
+            | CType::UnsignedInteger(_)
+            | CType::UnsignedLong(_)
+            | CType::UIntPtrT(_)
+            | CType::UInt8T(_)
+            | CType::UInt16T(_)
+            | CType::UInt32T(_)
+            | CType::UInt64T(_)
+    )
+}
+
+/// `s` as a string literal in the C family of languages, e.g., `"hello \"world\""`
+pub fn string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' | '"' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
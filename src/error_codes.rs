@@ -0,0 +1,110 @@
+use serde::Serialize;
+
+use crate::lexer::{CType, CVariableDeclaration, HeaderFile};
+
+/// A failure code returned by the library, from a group of `#define`s or an enum marked `error_codes`
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorCode {
+    /// C name of the code
+    pub label: String,
+    /// `label` in PascalCase, what the generated error types are named after
+    pub name: String,
+    pub value: i32,
+    pub comment: Option<String>,
+}
+
+/// Collects every failure code of the header.
+///
+/// A `#define` marked `#meta: error_codes;` starts a group, which continues with each directly following `#define`
+/// sharing its prefix up to the first underscore, e.g., `OAUTHTOOL_PASS`, `OAUTHTOOL_FAIL`, `OAUTHTOOL_FAIL_NULL_POINTER`.
+/// An enum marked `#meta: error_codes;` contributes all of its members, with the values `CEnum::values` gives them.
+///
+/// Codes of `0` mean success and are left out, as are codes whose value was already taken by an earlier one
+pub fn error_codes(header: &HeaderFile) -> Vec<ErrorCode> {
+    let mut codes: Vec<ErrorCode> = vec![];
+    for (label, value, comment) in all_codes(header) {
+        if value != 0 && !codes.iter().any(|c| c.value == value) {
+            codes.push(ErrorCode {
+                label: label.to_owned(),
+                name: pascal_case(label),
+                value,
+                comment: comment.to_owned(),
+            });
+        }
+    }
+    codes
+}
+
+/// C name of the first code of `0`, which the library returns on success, e.g., `OAUTHTOOL_PASS`
+pub fn success_code(header: &HeaderFile) -> Option<String> {
+    all_codes(header)
+        .into_iter()
+        .find(|(_, value, _)| *value == 0)
+        .map(|(label, _, _)| label.to_owned())
+}
+
+/// Every code of the header, successes included, as its C name, value and comment.
+/// Enums with an initializer that is not an integer are left out, see `validate_error_codes`
+fn all_codes(header: &HeaderFile) -> Vec<(&str, i32, &Option<String>)> {
+    let mut codes = vec![];
+    for group in define_groups(&header.defines) {
+        for d in group {
+            if let CType::SignedInteger(value) = d.variable_type.kind {
+                codes.push((d.label.as_str(), value, &d.comment));
+            }
+        }
+    }
+
+    for e in header
+        .enums
+        .iter()
+        .filter(|e| e.meta.as_ref().is_some_and(|m| m.is_error_codes))
+    {
+        let Ok(values) = e.values() else {
+            continue;
+        };
+        for (member, value) in e.declarations.iter().zip(values) {
+            codes.push((member.label.as_str(), value as i32, &member.comment));
+        }
+    }
+    codes
+}
+
+/// The groups of `#define`s that make up error codes, each starting with the one marked `error_codes`
+pub fn define_groups(defines: &[CVariableDeclaration]) -> Vec<&[CVariableDeclaration]> {
+    let prefix = |label: &str| label.split('_').next().unwrap_or_default().to_owned();
+    let mut groups = vec![];
+    for (start, d) in defines.iter().enumerate() {
+        if !d.meta.as_ref().is_some_and(|m| m.is_error_codes) {
+            continue;
+        }
+        let group_prefix = prefix(&d.label);
+        let len = defines[start..]
+            .iter()
+            .enumerate()
+            .take_while(|(i, next)| {
+                *i == 0
+                    || (prefix(&next.label) == group_prefix
+                        && !next.meta.as_ref().is_some_and(|m| m.is_error_codes))
+            })
+            .count();
+        groups.push(&defines[start..start + len]);
+    }
+    groups
+}
+
+/// `OAUTHTOOL_FAIL_NULL_POINTER` as `OauthtoolFailNullPointer`. Parts that are not all uppercase keep their casing
+fn pascal_case(label: &str) -> String {
+    label
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let rest = if part.chars().any(|c| c.is_ascii_lowercase()) {
+                part[1..].to_owned()
+            } else {
+                part[1..].to_ascii_lowercase()
+            };
+            format!("{}{}", part[..1].to_ascii_uppercase(), rest)
+        })
+        .collect()
+}
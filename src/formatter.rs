@@ -0,0 +1,285 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Target;
+
+/// How long a formatter may run before it is killed and the bindings are left unformatted
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A post-generation step that pretty-prints generated bindings using the language's own tooling
+pub trait Formatter {
+    /// Executable that has to be on the `PATH` for this formatter to run
+    fn program(&self) -> &'static str;
+
+    /// Formats `code`, returning the formatted source
+    fn format(&self, program: &Path, code: &str) -> Result<String, String>;
+}
+
+/// `gofmt`, fed through stdin
+pub struct GoFmt;
+
+impl Formatter for GoFmt {
+    fn program(&self) -> &'static str {
+        "gofmt"
+    }
+
+    fn format(&self, program: &Path, code: &str) -> Result<String, String> {
+        run_with_timeout(Command::new(program), Some(code))
+    }
+}
+
+/// `dart format`, run over a copy of the bindings in a temporary directory
+pub struct DartFormat;
+
+impl Formatter for DartFormat {
+    fn program(&self) -> &'static str {
+        "dart"
+    }
+
+    fn format(&self, program: &Path, code: &str) -> Result<String, String> {
+        let (_dir, file) = write_temporary_file("bindings.dart", code)?;
+        let mut cmd = Command::new(program);
+        cmd.args(["format", "--fix"]).arg(&file);
+        run_with_timeout(cmd, None)?;
+        read_temporary_file(&file)
+    }
+}
+
+/// `dotnet format whitespace`, run over a temporary folder holding just the bindings
+pub struct DotnetFormat;
+
+impl Formatter for DotnetFormat {
+    fn program(&self) -> &'static str {
+        "dotnet"
+    }
+
+    fn format(&self, program: &Path, code: &str) -> Result<String, String> {
+        let (dir, file) = write_temporary_file("Bindings.cs", code)?;
+        let mut cmd = Command::new(program);
+        cmd.args(["format", "whitespace", "--folder"])
+            .arg(dir.path());
+        run_with_timeout(cmd, None)?;
+        read_temporary_file(&file)
+    }
+}
+
+/// The formatter used for the given target's language
+pub fn for_target(target: &Target) -> Box<dyn Formatter> {
+    match target {
+        Target::Dart(_) => Box::new(DartFormat),
+        Target::Go(_) => Box::new(GoFmt),
+        Target::CSharp(_) => Box::new(DotnetFormat),
+    }
+}
+
+/// Runs `formatter` over `code`. If the formatter is unavailable or fails, a warning is printed
+/// and `code` is returned unchanged
+pub fn format_or_warn(formatter: &dyn Formatter, code: String) -> String {
+    let program = match find_program(formatter.program()) {
+        Some(p) => p,
+        None => {
+            eprintln!(
+                "warning: {} not found on PATH, output is left unformatted",
+                formatter.program()
+            );
+            return code;
+        }
+    };
+    match formatter.format(&program, &code) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!(
+                "warning: {} failed, output is left unformatted: {}",
+                formatter.program(),
+                e
+            );
+            code
+        }
+    }
+}
+
+/// Searches the `PATH` for an executable called `program_name`, honouring `PATHEXT` on Windows
+pub fn find_program(program_name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let extensions: Vec<String> = if cfg!(windows) {
+        std::env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_owned())
+            .split(';')
+            .map(|e| e.to_owned())
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+
+    std::env::split_paths(&path)
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{}{}", program_name, ext)))
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(p: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(p) {
+        Ok(m) => m.is_file() && m.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_executable(p: &Path) -> bool {
+    p.is_file()
+}
+
+/// Writes `contents` to a new file called `name` inside a fresh temporary directory.
+///
+/// The directory is removed once the returned handle is dropped
+fn write_temporary_file(
+    name: &str,
+    contents: &str,
+) -> Result<(tempfile::TempDir, PathBuf), String> {
+    let dir = tempfile::Builder::new()
+        .prefix("clang2src")
+        .tempdir()
+        .map_err(|e| format!("Failed to create temporary directory: {}", e))?;
+    let file = dir.path().join(name);
+    std::fs::write(&file, contents)
+        .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+    Ok((dir, file))
+}
+
+fn read_temporary_file(file: &Path) -> Result<String, String> {
+    std::fs::read_to_string(file).map_err(|e| format!("Formatted file could not be read: {}", e))
+}
+
+/// Runs `cmd`, optionally feeding it `stdin`, and returns its stdout.
+///
+/// Fails if the command exits unsuccessfully or is still running after [FORMAT_TIMEOUT]
+fn run_with_timeout(mut cmd: Command, stdin: Option<&str>) -> Result<String, String> {
+    let mut child = cmd
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start: {}", e))?;
+
+    /* Feed and drain the pipes on their own threads, a full pipe would otherwise block the child forever */
+    let writer = match (child.stdin.take(), stdin) {
+        (Some(mut pipe), Some(input)) => {
+            let input = input.to_owned();
+            Some(thread::spawn(move || pipe.write_all(input.as_bytes())))
+        }
+        _ => None,
+    };
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() > FORMAT_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Timed out after {}s", FORMAT_TIMEOUT.as_secs()));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(format!("Failed to wait for process: {}", e)),
+        }
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(format!("{}: {}", status, stderr.trim()));
+    }
+    if let Some(w) = writer {
+        if let Ok(Err(e)) = w.join() {
+            return Err(format!("Failed to write to stdin: {}", e));
+        }
+    }
+    Ok(stdout)
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = String::new();
+        if let Some(mut p) = pipe {
+            let _ = p.read_to_string(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Runs `program` with `args`, feeding it the code through stdin
+    struct Shell {
+        program: &'static str,
+        args: &'static [&'static str],
+    }
+
+    impl Formatter for Shell {
+        fn program(&self) -> &'static str {
+            self.program
+        }
+
+        fn format(&self, program: &Path, code: &str) -> Result<String, String> {
+            let mut cmd = Command::new(program);
+            cmd.args(self.args);
+            run_with_timeout(cmd, Some(code))
+        }
+    }
+
+    #[test]
+    fn formats_through_the_program() {
+        let upper = Shell {
+            program: "tr",
+            args: &["a-z", "A-Z"],
+        };
+        assert_eq!(format_or_warn(&upper, "func f()".to_owned()), "FUNC F()");
+    }
+
+    #[test]
+    fn leaves_code_unformatted_without_the_program() {
+        let missing = Shell {
+            program: "clang2src-no-such-formatter",
+            args: &[],
+        };
+        assert_eq!(find_program(missing.program()), None);
+        assert_eq!(format_or_warn(&missing, "func f()".to_owned()), "func f()");
+    }
+
+    #[test]
+    fn leaves_code_unformatted_when_the_program_fails() {
+        let failing = Shell {
+            program: "sh",
+            args: &["-c", "echo broken >&2; exit 3"],
+        };
+        let program = find_program("sh").unwrap();
+        let error = failing.format(&program, "func f()").unwrap_err();
+        assert!(error.contains("broken"), "{}", error);
+        assert_eq!(format_or_warn(&failing, "func f()".to_owned()), "func f()");
+    }
+
+    #[test]
+    fn formats_a_copy_in_a_temporary_directory() {
+        let (dir, file) = write_temporary_file("bindings.dart", "void f() {}").unwrap();
+        assert_eq!(read_temporary_file(&file).unwrap(), "void f() {}");
+        let path = dir.path().to_owned();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
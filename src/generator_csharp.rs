@@ -1,11 +1,22 @@
-use std::{collections::HashMap, fmt::Display};
+use std::fmt::Display;
 
 use serde::Serialize;
 
+use crate::bytes::Bytes;
+use crate::cancel::Cancellation;
+use crate::completion::Completion;
+use crate::cursor::Cursor;
+use crate::default_value::{string_literal, DefaultValue};
+use crate::error_codes::{error_codes, ErrorCode};
+use crate::hashmap::Hashmap;
 use crate::lexer::{
     CEnum, CFunction, CIdentifier, CStruct, CType, CVariableDeclaration, HeaderFile,
 };
-use crate::meta::{MetaValue, META_PARAM_TOKEN, META_TOKEN};
+use crate::list::{Element, List};
+use crate::meta::{MetaValue, META_TOKEN};
+use crate::naming::Language;
+use crate::property::Property;
+use crate::value_type::ValueType;
 
 #[derive(Serialize)]
 struct Data<'a> {
@@ -16,6 +27,19 @@ struct Data<'a> {
     enums: Vec<CSharpEnum>,
     structs: Vec<CSharpStruct>,
     functions: Vec<CSharpFunction>,
+    error_codes: Vec<CSharpErrorCode>,
+    has_error_codes: bool,
+    handles: Vec<CSharpHandle>,
+    has_handles: bool,
+    /// Whether a handle has an `Items()` method, which needs the `NativeIterator` helper
+    has_iterators: bool,
+    /// Whether a function is `cancellable`, which needs the `RunCancellable` helper
+    has_cancellables: bool,
+    records: Vec<CSharpRecord>,
+    /// Whether a struct is marked `value`, which needs the `ValueEquality` helper
+    has_records: bool,
+    /// Base class of the error code exceptions, named after the last part of the namespace
+    exception: String,
 }
 
 impl<'a> Data<'a> {
@@ -29,16 +53,6 @@ impl<'a> Data<'a> {
         functions: &mut Vec<CSharpFunction>,
     ) -> Self {
         // /* attach functions to structs */
-        for f in functions.iter_mut() {
-            if let Some(cmt) = &f.c_comment {
-                let splits = cmt.split('\n').into_iter();
-                for split in splits {
-                    if split.contains("#meta:") {
-                        f.meta = MetaValue::from_meta_comment(split);
-                    }
-                }
-            }
-        }
         for f in functions.iter_mut() {
             if let Some(meta) = &f.meta {
                 if meta.for_struct {
@@ -66,6 +80,160 @@ impl<'a> Data<'a> {
             enums,
             structs: structs.to_vec(),
             functions: functions.clone(),
+            error_codes: vec![],
+            has_error_codes: false,
+            handles: vec![],
+            has_handles: false,
+            has_iterators: false,
+            has_cancellables: false,
+            records: vec![],
+            has_records: false,
+            exception: format!(
+                "{}Exception",
+                namespace.rsplit('.').next().unwrap_or(namespace)
+            ),
+        }
+    }
+
+    /// Throws each of `error_codes` as its own subclass of the library exception
+    fn with_error_codes(mut self, error_codes: Vec<CSharpErrorCode>) -> Self {
+        self.has_error_codes = !error_codes.is_empty();
+        self.error_codes = error_codes;
+        self
+    }
+
+    /// Gives every function with several outputs a twin returning them as a tuple, see `CSharpOutputs`.
+    /// Failures are thrown as the error code exceptions, so this comes after `with_error_codes`
+    fn with_outputs(mut self, header: &HeaderFile) -> Self {
+        for src in &header.functions {
+            if let Some(f) = self.functions.iter_mut().find(|f| f.c_label == src.label) {
+                f.outputs = CSharpOutputs::from(
+                    src,
+                    &f.parameters,
+                    &f.return_type.data_type,
+                    self.has_error_codes,
+                );
+            }
+        }
+        self
+    }
+
+    /// Wraps every struct with properties in a handle that reads and writes them, see `CSharpHandle`.
+    /// Failures are thrown as the error code exceptions, so this comes after `with_error_codes`
+    fn with_properties(mut self, properties: &[Property], header: &HeaderFile) -> Self {
+        for p in properties {
+            let property = match CSharpProperty::from(p, self.has_error_codes) {
+                Some(property) => property,
+                None => continue,
+            };
+            match self.handles.iter_mut().find(|h| h.label == p.owner) {
+                Some(handle) => handle.properties.push(property),
+                None => self.handles.push(CSharpHandle {
+                    label: p.owner.to_owned(),
+                    name: Language::CSharp.struct_label(header, p.owner).to_owned(),
+                    properties: vec![property],
+                    iterator: None,
+                    obsolete: header
+                        .structs
+                        .iter()
+                        .find(|s| s.identifier.label == p.owner)
+                        .and_then(|s| obsolete_attribute(&s.deprecated)),
+                }),
+            }
+        }
+        self.has_handles = !self.handles.is_empty();
+        self
+    }
+
+    /// Gives the handle of every struct marked `iterator` an `Items()` method, see `CSharpIterator`.
+    /// `persistent` items are handed out as handles of their own, so their structs get one as well
+    fn with_iterators(mut self, cursors: &[Cursor], header: &HeaderFile) -> Self {
+        for cursor in cursors {
+            let iterator = CSharpIterator::from(cursor, header);
+            if cursor.is_item_persistent() {
+                self.handle(cursor.item);
+            }
+            self.handle(cursor.owner).iterator = Some(iterator);
+        }
+        self.has_handles = !self.handles.is_empty();
+        self.has_iterators = !cursors.is_empty();
+        self
+    }
+
+    /// Gives every `cancellable` function an `Async` twin, which runs it with a new cancel handle, see `CSharpCancellable`
+    fn with_cancellations(mut self, cancellations: &[Cancellation]) -> Self {
+        for c in cancellations {
+            if let Some(f) = self
+                .functions
+                .iter_mut()
+                .find(|f| f.c_label == c.function.label)
+            {
+                f.cancellable = Some(CSharpCancellable::from(c, &f.return_type.data_type));
+            }
+        }
+        self.has_cancellables = !cancellations.is_empty();
+        if self.has_cancellables {
+            self.usings.push("System.Threading");
+            self.usings.push("System.Threading.Tasks");
+        }
+        self
+    }
+
+    /// Gives every `async_callback` function an `Async` twin, which completes a task from the callback, see `CSharpCompletion`.
+    /// `persistent` results are handed out as handles, so their structs get one
+    fn with_completions(mut self, completions: &[Completion], value_types: &[ValueType]) -> Self {
+        for c in completions {
+            if let Some(Element::StructPointer(s)) = &c.result_element {
+                if c.is_result_persistent() {
+                    self.handle(s);
+                    self.has_handles = true;
+                }
+            }
+            if let Some(f) = self
+                .functions
+                .iter_mut()
+                .find(|f| f.c_label == c.function.label)
+            {
+                f.completion = Some(CSharpCompletion::from(c, &f.parameters, value_types));
+            }
+        }
+        if !completions.is_empty() && !self.usings.contains(&"System.Threading.Tasks") {
+            self.usings.push("System.Threading.Tasks");
+        }
+        self
+    }
+
+    /// Hands out every struct marked `value` as a record, see `CSharpRecord`
+    fn with_value_types(mut self, value_types: &[ValueType], header: &HeaderFile) -> Self {
+        self.records = value_types
+            .iter()
+            .map(|v| CSharpRecord::from(v, header))
+            .collect();
+        self.has_records = !self.records.is_empty();
+        if self.has_records {
+            self.usings.push("System.Text");
+        }
+        self
+    }
+
+    /// The handle of the struct `s`, which is added if there is none yet
+    fn handle(&mut self, s: &CStruct) -> &mut CSharpHandle {
+        match self
+            .handles
+            .iter()
+            .position(|h| h.label == s.identifier.label)
+        {
+            Some(i) => &mut self.handles[i],
+            None => {
+                self.handles.push(CSharpHandle {
+                    label: s.identifier.label.to_owned(),
+                    name: Language::CSharp.struct_name(s).to_owned(),
+                    properties: vec![],
+                    iterator: None,
+                    obsolete: obsolete_attribute(&s.deprecated),
+                });
+                self.handles.last_mut().unwrap()
+            }
         }
     }
 }
@@ -131,6 +299,24 @@ impl Display for DataType {
     }
 }
 
+/// A failure code of the library, thrown as its own subclass of the library's exception
+#[derive(Serialize, Clone)]
+struct CSharpErrorCode {
+    /// e.g., `OauthtoolFailNullPointerException`
+    label: String,
+    value: i32,
+    csharp_comment: Option<String>,
+}
+impl From<&ErrorCode> for CSharpErrorCode {
+    fn from(e: &ErrorCode) -> Self {
+        CSharpErrorCode {
+            label: format!("{}Exception", e.name),
+            value: e.value,
+            csharp_comment: transform_comment(e.comment.to_owned()),
+        }
+    }
+}
+
 #[derive(Serialize, Clone)]
 struct CSharpStruct {
     label: String,
@@ -138,76 +324,1313 @@ struct CSharpStruct {
     csharp_comment: Option<String>,
     members: Vec<CSharpVariable>,
     functions: Vec<CSharpFunction>,
+    accessors: Vec<CSharpAccessor>,
+    /// Set for a deprecated struct, see `obsolete_attribute`
+    obsolete: Option<String>,
 }
 
 impl From<&CStruct> for CSharpStruct {
     fn from(v: &CStruct) -> Self {
         CSharpStruct {
-            label: v.identifier.label.to_owned(),
+            label: Language::CSharp.struct_name(v).to_owned(),
             c_comment: v.comment.to_owned(),
             csharp_comment: transform_comment(v.comment.to_owned()),
+            obsolete: obsolete_attribute(&v.deprecated),
             members: v
                 .declarations
                 .iter()
-                .map(|decl| CSharpVariable::from(decl))
+                .map(|decl| {
+                    let mut member = CSharpVariable::from(decl);
+                    member.label = Language::CSharp.name_of(&decl.label, &decl.meta).to_owned();
+                    /* Lists point to their first element, so that they can be read as arrays */
+                    if let Some(e) = list_element(decl) {
+                        member.data_type = format!("{}*", element_type(&e));
+                    }
+                    member
+                })
                 .collect(),
             functions: vec![],
+            accessors: Hashmap::all(&v.declarations)
+                .iter()
+                .filter_map(CSharpAccessor::from_hashmap)
+                .chain(
+                    Bytes::all(&v.declarations)
+                        .iter()
+                        .filter_map(CSharpAccessor::from_bytes),
+                )
+                .chain(
+                    List::all(&v.declarations)
+                        .iter()
+                        .filter_map(CSharpAccessor::from_list),
+                )
+                .collect(),
         }
     }
 }
 
+/// A method of a struct that reads fields which belong together,
+/// a `hashmap` as a `Dictionary<string, string>`, a buffer of `bytes` as a `ReadOnlySpan<byte>`
+/// or a `list` as an array
+#[derive(Serialize, Clone)]
+struct CSharpAccessor {
+    /// The whole method, e.g., `HeadersDictionary()`
+    method: String,
+}
+
+impl CSharpAccessor {
+    fn from_hashmap(h: &Hashmap) -> Option<Self> {
+        Some(CSharpAccessor {
+            method: format!(
+                "
+            internal Dictionary<string, string> {}Dictionary()
+            {{
+                return FFIInterface.Arrays2Dictionary({}, {}, {});
+            }}
+",
+                pascal_case(field_name(h.keys)),
+                field_name(h.keys),
+                field_name(h.values?),
+                field_name(h.length?)
+            ),
+        })
+    }
+
+    /// The span is only valid for as long as the struct it was read from
+    fn from_bytes(b: &Bytes) -> Option<Self> {
+        Some(CSharpAccessor {
+            method: format!(
+                "
+            internal ReadOnlySpan<byte> {}Span()
+            {{
+                return new ReadOnlySpan<byte>({}, checked((int){}));
+            }}
+",
+                pascal_case(field_name(b.data)),
+                field_name(b.data),
+                field_name(b.length?)
+            ),
+        })
+    }
+
+    /// The elements are copied, the array itself is left to its owner
+    fn from_list(l: &List) -> Option<Self> {
+        let element = l.element()?;
+        Some(CSharpAccessor {
+            method: format!(
+                "
+            internal {}[] {}Array()
+            {{
+                return FFIInterface.{}({}, {});
+            }}
+",
+                match element {
+                    Element::String => "string".to_owned(),
+                    _ => element_type(&element),
+                },
+                pascal_case(field_name(l.items)),
+                match element {
+                    Element::String => "FFIPointerToStrings",
+                    _ => "FFIPointerToArray",
+                },
+                field_name(l.items),
+                field_name(l.length?)
+            ),
+        })
+    }
+}
+
+/// A pointer to a struct of the library, whose properties are read and written with its getters and setters
+#[derive(Serialize)]
+struct CSharpHandle {
+    /// Name of the struct in C
+    label: String,
+    /// Name of the struct in C#, the handle is `{name}Handle`
+    name: String,
+    properties: Vec<CSharpProperty>,
+    /// Set for a struct marked `iterator`
+    iterator: Option<CSharpIterator>,
+    /// Set for a deprecated struct, see `obsolete_attribute`
+    obsolete: Option<String>,
+}
+
+/// The `Items()` method of a `CSharpHandle` of a cursor, which walks through the items handed out by `{cursor}_next`.
+///
+/// A `persistent` item is handed out as its own handle. Any other item is handed out as its `C_` struct, whose
+/// pointers stay valid until the caller moves on to the next item, which leaves the method `internal`
+#[derive(Serialize)]
+struct CSharpIterator {
+    /// `public` or `internal`
+    visibility: &'static str,
+    /// e.g., `TokenResponseHandle`, or `FFIInterface.C_TokenResponse`
+    item_type: String,
+    /// `{cursor}_next`
+    next: String,
+    /// Converts the pointer `itemPtr` to `item_type`
+    from_native: String,
+    /// Releases the item `current` points to, left out for `persistent` items
+    release: Option<String>,
+    /// Releases the cursor once the items run out
+    done: Option<String>,
+}
+
+impl CSharpIterator {
+    fn from(c: &Cursor, header: &HeaderFile) -> Self {
+        let item = Language::CSharp.struct_name(c.item);
+        let (visibility, item_type, from_native) = if c.is_item_persistent() {
+            (
+                "public",
+                format!("{}Handle", item),
+                format!("new {}Handle(itemPtr)", item),
+            )
+        } else {
+            let item_type = format!("FFIInterface.C_{}", item);
+            (
+                "internal",
+                item_type.to_owned(),
+                format!("Marshal.PtrToStructure<{}>(itemPtr)", item_type),
+            )
+        };
+        CSharpIterator {
+            visibility,
+            item_type,
+            next: Language::CSharp
+                .name_of(&c.next.label, &c.next.meta)
+                .to_owned(),
+            from_native,
+            release: (!c.is_item_persistent()).then(|| match c.item_release() {
+                Some(f) => format!(
+                    "FFIInterface.{}(current)",
+                    Language::CSharp.function_label(header, f)
+                ),
+                None => "FFIInterface.FreeNative(current)".to_owned(),
+            }),
+            done: c.destructor.map(|f| {
+                format!(
+                    "FFIInterface.{}(self)",
+                    Language::CSharp.name_of(&f.label, &f.meta)
+                )
+            }),
+        }
+    }
+}
+
+/// A struct marked `value`, handed out as a positional record that `FromNative` copies out of its `C_` struct, see `ValueType`.
+///
+/// Lists, maps and buffers of bytes become arrays and dictionaries, which leaves out their lengths and capacities.
+/// Records compare those by reference, so a record holding any compares, hashes and prints them by their elements
+/// through `ValueEquality` instead of the synthesized `Equals`, `GetHashCode` and `PrintMembers`
+#[derive(Serialize)]
+struct CSharpRecord {
+    /// Name of the struct in C#, e.g., `TokenResponse`
+    name: String,
+    csharp_comment: Option<String>,
+    /// Set for a deprecated struct, see `obsolete_attribute`
+    obsolete: Option<String>,
+    members: Vec<CSharpRecordMember>,
+    /// Whether a member is an array or a dictionary
+    has_elements: bool,
+}
+
+#[derive(Serialize)]
+struct CSharpRecordMember {
+    /// e.g., `RefreshToken`
+    label: String,
+    /// e.g., `/// <param name="RefreshToken">If not null, ...</param>`
+    param_comment: Option<String>,
+    /// Set for a deprecated field, e.g., `[property: Obsolete]`
+    obsolete: Option<String>,
+    /// e.g., `string?`
+    data_type: String,
+    /// Reads the member out of the `C_` struct `native`
+    from_native: String,
+    /// e.g., `ValueEquality.ListEquals(Scopes, other.Scopes)`
+    equals: String,
+    /// e.g., `hash.Add(ValueEquality.ListHash(Scopes))`
+    hash: String,
+    /// e.g., `builder.Append(", Scopes = ").Append(ValueEquality.Print(Scopes))`
+    print: String,
+    is_last: bool,
+}
+
+impl CSharpRecord {
+    fn from(v: &ValueType, header: &HeaderFile) -> Self {
+        let declarations = &v.owner.declarations;
+        let mut members: Vec<CSharpRecordMember> = declarations
+            .iter()
+            .filter(|d| {
+                !List::is_part(declarations, d)
+                    && !Hashmap::is_part(declarations, d)
+                    && !Bytes::is_part(declarations, d)
+            })
+            .filter_map(|d| CSharpRecordMember::of(d, declarations, header))
+            .collect();
+        for (i, member) in members.iter_mut().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            member.print = format!(
+                "builder.Append(\"{}{} = \").Append({})",
+                separator, member.label, member.print
+            );
+        }
+        if let Some(last) = members.last_mut() {
+            last.is_last = true;
+        }
+        CSharpRecord {
+            name: Language::CSharp.struct_name(v.owner).to_owned(),
+            csharp_comment: transform_comment(v.owner.comment.to_owned()),
+            obsolete: obsolete_attribute(&v.owner.deprecated),
+            has_elements: members
+                .iter()
+                .any(|m| m.equals.starts_with("ValueEquality")),
+            members,
+        }
+    }
+}
+
+impl CSharpRecordMember {
+    /// Returns `None` for a list, map or buffer of bytes that cannot be read, see `CSharpAccessor`
+    fn of(
+        d: &CVariableDeclaration,
+        declarations: &[CVariableDeclaration],
+        header: &HeaderFile,
+    ) -> Option<Self> {
+        let meta = d.meta.clone().unwrap_or_else(MetaValue::new);
+        let label = pascal_case(field_name(d));
+        let native = format!("native.{}", field_name(d));
+        let struct_name = |s: &CStruct| {
+            Language::CSharp
+                .struct_label(header, &s.identifier.label)
+                .to_owned()
+        };
+        let (data_type, from_native, collection) = if meta.is_hashmap {
+            let h = Hashmap::all(declarations)
+                .into_iter()
+                .find(|h| h.keys.label == d.label)?;
+            h.values?;
+            h.length?;
+            (
+                "Dictionary<string, string>".to_owned(),
+                format!("native.{}Dictionary()", label),
+                Some("Map"),
+            )
+        } else if meta.is_bytes {
+            Bytes::all(declarations)
+                .into_iter()
+                .find(|b| b.data.label == d.label)?
+                .length?;
+            (
+                "byte[]".to_owned(),
+                format!("native.{}Span().ToArray()", label),
+                Some("List"),
+            )
+        } else if meta.is_list {
+            let l = List::all(declarations)
+                .into_iter()
+                .find(|l| l.items.label == d.label)?;
+            l.length?;
+            let array = format!("native.{}Array()", label);
+            let (data_type, from_native) = match l.element()? {
+                Element::String => ("string[]".to_owned(), array),
+                Element::Struct(s) => (
+                    format!("{}[]", struct_name(s)),
+                    format!(
+                        "{}.Select(e => {}.FromNative(e)).ToArray()",
+                        array,
+                        struct_name(s)
+                    ),
+                ),
+                Element::StructPointer(s) => (
+                    format!("{}[]", struct_name(s)),
+                    format!(
+                        "{}.Select(e => {}.FromNative(Marshal.PtrToStructure<FFIInterface.C_{}>(e))).ToArray()",
+                        array,
+                        struct_name(s),
+                        struct_name(s)
+                    ),
+                ),
+                e => (format!("{}[]", element_type(&e)), array),
+            };
+            (data_type, from_native, Some("List"))
+        } else {
+            let (data_type, from_native) = CSharpRecordMember::scalar(d, &meta, &native, header)?;
+            (data_type, from_native, None)
+        };
+        Some(CSharpRecordMember {
+            param_comment: transform_comment(d.comment.to_owned()).map(|c| {
+                let text: Vec<&str> = c
+                    .lines()
+                    .filter(|l| !l.contains("summary>"))
+                    .map(|l| l.trim_start_matches("///").trim())
+                    .collect();
+                format!("/// <param name=\"{}\">{}</param>", label, text.join(" "))
+            }),
+            obsolete: obsolete_attribute(&d.deprecated).map(|a| a.replacen('[', "[property: ", 1)),
+            equals: match collection {
+                Some(kind) => format!("ValueEquality.{}Equals({}, other.{})", kind, label, label),
+                None => format!(
+                    "EqualityComparer<{}>.Default.Equals({}, other.{})",
+                    data_type, label, label
+                ),
+            },
+            hash: match collection {
+                Some(kind) => format!("hash.Add(ValueEquality.{}Hash({}))", kind, label),
+                None => format!("hash.Add({})", label),
+            },
+            print: match collection {
+                Some(_) => format!("ValueEquality.Print({})", label),
+                None => label.to_owned(),
+            },
+            data_type,
+            from_native,
+            label,
+            is_last: false,
+        })
+    }
+
+    /// The type and the conversion of a field that is not a list, map or buffer, which is null where the field is `nullable`
+    fn scalar(
+        d: &CVariableDeclaration,
+        meta: &MetaValue,
+        native: &str,
+        header: &HeaderFile,
+    ) -> Option<(String, String)> {
+        let is_pointer = d.variable_type.pointer_count > 0;
+        let (data_type, from_native, null) = match &d.variable_type.kind {
+            CType::Char(_) if d.variable_type.pointer_count == 1 => (
+                if meta.is_url { "Uri" } else { "string" }.to_owned(),
+                if meta.is_url {
+                    format!("new Uri(FFIInterface.PtrToString({}))", native)
+                } else {
+                    format!("FFIInterface.PtrToString({})", native)
+                },
+                "IntPtr.Zero",
+            ),
+            CType::Struct(s) => {
+                let name = Language::CSharp.struct_label(header, &s.identifier.label);
+                (
+                    name.to_owned(),
+                    format!(
+                        "{}.FromNative(Marshal.PtrToStructure<FFIInterface.C_{}>({}))",
+                        name, name, native
+                    ),
+                    "IntPtr.Zero",
+                )
+            }
+            kind => {
+                /* a number handed over as a pointer is read where it points */
+                let value = format!("{}{}", if is_pointer { "*" } else { "" }, native);
+                let (data_type, from_native) = if meta.is_duration {
+                    (
+                        "TimeSpan".to_owned(),
+                        format!("TimeSpan.FromMilliseconds({})", value),
+                    )
+                } else if meta.is_datetime || meta.is_timestamp {
+                    (
+                        "DateTimeOffset".to_owned(),
+                        format!("DateTimeOffset.FromUnixTimeMilliseconds((long){})", value),
+                    )
+                } else if let CType::Enum(e) = kind {
+                    (
+                        e.identifier.label.to_owned(),
+                        format!("({}){}", e.identifier.label, value),
+                    )
+                } else {
+                    match CSharpVariable::sub_variable(kind, 0).1 {
+                        DataType::Void | DataType::IntPtr => return None,
+                        data_type => (data_type.to_string(), value),
+                    }
+                };
+                (data_type, from_native, "null")
+            }
+        };
+        Some(match meta.is_nullable && is_pointer {
+            true => (
+                format!("{}?", data_type),
+                format!("{} == {} ? null : {}", native, null, from_native),
+            ),
+            false => (data_type, from_native),
+        })
+    }
+}
+
+/// A property of a `CSharpHandle`, e.g., `public TimeSpan Timeout { get { ... } set { ... } }`.
+///
+/// Values that C takes in several parameters, lists, buffers and maps, are left to the `FFIInterface` functions
+#[derive(Serialize)]
+struct CSharpProperty {
+    /// e.g., `Timeout`
+    label: String,
+    csharp_comment: Option<String>,
+    /// The type the value is read and written as, e.g., `TimeSpan` for a `duration`
+    data_type: String,
+    /// Body of the `get` accessor, if the property can be read
+    get: Option<String>,
+    /// Body of the `set` accessor, if the property can be written
+    set: Option<String>,
+}
+
+impl CSharpProperty {
+    fn from(p: &Property, has_error_codes: bool) -> Option<Self> {
+        /* both sides must fit, or the property is left out altogether */
+        let getter = match p.getter {
+            Some(f) => Some(Accessor::of(f)?),
+            None => None,
+        };
+        let setter = match p.setter {
+            Some(f) => Some(Accessor::of(f)?),
+            None => None,
+        };
+        Some(CSharpProperty {
+            label: pascal_case(p.name),
+            csharp_comment: p
+                .getter
+                .or(p.setter)
+                .and_then(|f| transform_comment(f.comment.to_owned())),
+            data_type: getter.as_ref().or(setter.as_ref())?.data_type.to_owned(),
+            get: getter.map(|a| a.body(has_error_codes)),
+            set: setter.map(|a| a.body(has_error_codes)),
+        })
+    }
+}
+
+/// The getter or setter of a `CSharpProperty`, and how its value is converted between C# and C
+struct Accessor<'a> {
+    function: &'a CFunction,
+    is_getter: bool,
+    /// e.g., `TimeSpan`
+    data_type: String,
+    /// The type C hands back or takes, e.g., `ulong`
+    native_type: String,
+    /// Converts the C value `native` to `data_type`
+    from_native: String,
+    /// Converts `value` to the C value
+    to_native: String,
+    /// The function releasing the C value once it is converted, if it must be, e.g., `FFIInterface.FreeString`.
+    /// A getter does so in `from_native`
+    release: Option<String>,
+}
+
+impl<'a> Accessor<'a> {
+    /// Returns `None` if the value cannot be a C# property, see `CSharpProperty`
+    fn of(function: &'a CFunction) -> Option<Self> {
+        let (_, is_getter) = Property::of(function)?;
+        let value = Property::value(function)?;
+        let meta = value.meta.clone().unwrap_or_else(MetaValue::new);
+        if meta.is_list || meta.is_bytes || meta.is_hashmap {
+            return None;
+        }
+        let mut native = CSharpVariable::from(value);
+        if is_getter {
+            native.pointer_count = native.pointer_count.checked_sub(1)?;
+        }
+        let native_type = format!(
+            "{}{}",
+            native.value.as_ref()?,
+            "*".repeat(native.pointer_count.into())
+        );
+        let is_string =
+            matches!(value.variable_type.kind, CType::Char(_)) && native_type == "IntPtr";
+        let (data_type, from_native, to_native) = if is_string {
+            (
+                "string".to_owned(),
+                "FFIInterface.PtrToString(native)".to_owned(),
+                "FFIInterface.StringToFFIPointer(value)".to_owned(),
+            )
+        } else if meta.is_duration {
+            (
+                "TimeSpan".to_owned(),
+                "TimeSpan.FromMilliseconds(native)".to_owned(),
+                format!("({})value.TotalMilliseconds", native_type),
+            )
+        } else if meta.is_datetime || meta.is_timestamp {
+            (
+                "DateTimeOffset".to_owned(),
+                "DateTimeOffset.FromUnixTimeMilliseconds((long)native)".to_owned(),
+                format!("({})value.ToUnixTimeMilliseconds()", native_type),
+            )
+        } else {
+            (
+                native_type.to_owned(),
+                "native".to_owned(),
+                "value".to_owned(),
+            )
+        };
+        /* strings handed back are released as they are for any other function, strings handed in are our own copy */
+        let release = match (is_string, is_getter) {
+            (false, _) => None,
+            (true, true) => string_release(&meta),
+            (true, false) => Some("FFIInterface.FreeString".to_owned()),
+        };
+        /* the string is released as soon as it is read, see `PtrToString` */
+        let from_native = match &release {
+            Some(release) if is_getter => format!("FFIInterface.PtrToString(native, {})", release),
+            _ => from_native,
+        };
+        Some(Accessor {
+            function,
+            is_getter,
+            data_type,
+            native_type,
+            from_native,
+            to_native,
+            release,
+        })
+    }
+
+    /// The statements of the `get` or `set` accessor, which call the C function through the handle's `selfPtr`
+    fn body(&self, has_error_codes: bool) -> String {
+        let meta = self.function.meta.clone().unwrap_or_else(MetaValue::new);
+        let arguments: Vec<&str> = self
+            .function
+            .parameters
+            .iter()
+            .map(|p| match &p.meta {
+                Some(m) if m.is_this => "selfPtr",
+                Some(m) if m.is_error => "&err",
+                _ if self.is_getter => "&native",
+                _ => "native",
+            })
+            .collect();
+        let call = format!(
+            "FFIInterface.{}({})",
+            Language::CSharp.name_of(&self.function.label, &self.function.meta),
+            arguments.join(", ")
+        );
+        let mut lines = vec![if self.is_getter {
+            format!("{} native = default;", self.native_type)
+        } else {
+            format!("{} native = {};", self.native_type, self.to_native)
+        }];
+        if meta.throws {
+            lines.push("IntPtr err = IntPtr.Zero;".to_owned());
+            lines.push(format!("var code = {};", call));
+        } else {
+            lines.push(format!("{};", call));
+        }
+        lines.extend(
+            self.release
+                .iter()
+                .filter(|_| !self.is_getter)
+                .map(|r| format!("{}(native);", r)),
+        );
+        if meta.throws {
+            /* a string the library handed back anyway is released before throwing */
+            let releases: Vec<String> = self
+                .release
+                .iter()
+                .filter(|_| self.is_getter)
+                .map(|r| {
+                    [
+                        "if (native != IntPtr.Zero)",
+                        "{",
+                        &format!("    {}(native);", r),
+                        "}",
+                    ]
+                    .join("\n")
+                })
+                .collect();
+            lines.extend(throw_on_failure(
+                self.function,
+                "err",
+                has_error_codes,
+                &releases,
+            ));
+        }
+        if self.is_getter {
+            lines.push(format!("return {};", self.from_native));
+        }
+        lines
+            .iter()
+            .map(|l| format!("            {}", l))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// The elements of `d`, if it is a `list` whose elements are known, see `Element`
+fn list_element(d: &CVariableDeclaration) -> Option<Element<'_>> {
+    Element::of(d).filter(|_| d.meta.as_ref().is_some_and(|m| m.is_list))
+}
+
+/// The type of each element of a list in the native array, e.g., `C_TokenResponse`.
+/// Numbers keep their exact width, so that the array can be indexed
+fn element_type(e: &Element) -> String {
+    match e {
+        Element::String | Element::StructPointer(_) => "IntPtr".to_owned(),
+        Element::Enum(e) => e.identifier.label.to_owned(),
+        Element::Struct(s) => format!("C_{}", Language::CSharp.struct_name(s)),
+        Element::Primitive(kind) => match kind {
+            CType::Int8T(_) => "sbyte",
+            CType::UInt8T(_) => "byte",
+            CType::SignedShort(_) | CType::Int16T(_) => "short",
+            CType::UnsignedShort(_) | CType::UInt16T(_) => "ushort",
+            CType::SignedInteger(_) | CType::Int32T(_) => "int",
+            CType::UnsignedInteger(_) | CType::UInt32T(_) => "uint",
+            CType::SignedLong(_) | CType::Int64T(_) => "long",
+            CType::UnsignedLong(_) | CType::UInt64T(_) => "ulong",
+            CType::IntPtrT(_) => "nint",
+            CType::UIntPtrT(_) => "nuint",
+            CType::Float(_) => "float",
+            _ => "double",
+        }
+        .to_owned(),
+    }
+}
+
+/// Types the array handed to the `free_with` function of an `output` list as the list hands it out, e.g., `C_TokenResponse*`,
+/// so that it can be handed back as it is. The array is the first parameter of that function, see `List`
+fn with_list_releases(
+    mut functions: Vec<CSharpFunction>,
+    header: &HeaderFile,
+) -> Vec<CSharpFunction> {
+    for p in header.functions.iter().flat_map(|f| &f.parameters) {
+        let (Some(meta), Some(e)) = (&p.meta, list_element(p)) else {
+            continue;
+        };
+        let Some(free_with) = meta.free_with.as_ref().filter(|_| meta.is_output) else {
+            continue;
+        };
+        if let Some(items) = functions
+            .iter_mut()
+            .find(|f| &f.csharp_label == free_with)
+            .and_then(|f| f.parameters.first_mut())
+        {
+            items.data_type = format!("{}*", element_type(&e));
+            items.optional = None;
+        }
+    }
+    functions
+}
+
+/// Points every `free_with` at the extern of the function it names, which goes by its C# name, see `Language`
+fn rename_releases(header: &mut HeaderFile) {
+    let names: Vec<(String, String)> = header
+        .functions
+        .iter()
+        .map(|f| {
+            let name = Language::CSharp.name_of(&f.label, &f.meta);
+            (f.label.to_owned(), name.to_owned())
+        })
+        .filter(|(label, name)| label != name)
+        .collect();
+    let metas = header
+        .functions
+        .iter_mut()
+        .flat_map(|f| f.parameters.iter_mut())
+        .filter_map(|p| p.meta.as_mut());
+    for meta in metas {
+        if let Some((_, name)) = names
+            .iter()
+            .find(|(label, _)| meta.free_with.as_ref() == Some(label))
+        {
+            meta.free_with = Some(name.to_owned());
+        }
+    }
+}
+
+/// The name of the struct field `d` in C#, which may be renamed, see `Language`
+fn field_name(d: &CVariableDeclaration) -> &str {
+    Language::CSharp.name_of(&d.label, &d.meta)
+}
+
+/// `raw_data` as `RawData`
+fn pascal_case(label: &str) -> String {
+    label
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| format!("{}{}", part[..1].to_ascii_uppercase(), &part[1..]))
+        .collect()
+}
+
 #[derive(Serialize, Clone)]
 struct CSharpFunction {
     c_label: String,
     csharp_label: String,
+    /// The C function the extern calls, when it is renamed for C#, see `Language`
+    entry_point: Option<String>,
     c_comment: Option<String>,
     csharp_comment: Option<String>,
     return_type: CSharpVariable,
     parameters: Vec<CSharpVariable>,
     meta: Option<MetaValue>,
+    /// Set for a `cancellable` function
+    cancellable: Option<CSharpCancellable>,
+    /// Set for an `async_callback` function
+    completion: Option<CSharpCompletion>,
+    /// Set for a function with a string parameter that has a default
+    defaults: Option<CSharpDefaults>,
+    /// Set for a function with several outputs, see `CSharpOutputs`
+    outputs: Option<CSharpOutputs>,
+    /// Set for a deprecated function, which marks its twins as well, see `obsolete_attribute`
+    obsolete: Option<String>,
+}
+
+/// The twin of a function with string parameters that have a default, which takes those as `string`,
+/// as the `IntPtr` handed to the library cannot have a default of its own, see `DefaultValue`.
+///
+/// Numbers and `null` have their default set on the parameters of the function itself.
+/// Parameters with a default are `[Optional]` wherever they are, and are left out by naming the ones after them
+#[derive(Serialize, Clone)]
+struct CSharpDefaults {
+    /// The parameters of the twin
+    parameters: String,
+    /// The body of the twin, which copies the strings for the call and frees them once it returns
+    body: String,
+}
+
+impl CSharpDefaults {
+    fn from(src: &CFunction, parameters: &[CSharpVariable], return_type: &str) -> Option<Self> {
+        let defaults: Vec<(&CSharpVariable, DefaultValue)> = src
+            .parameters
+            .iter()
+            .zip(parameters)
+            .filter_map(|(p, v)| match DefaultValue::of(p, src) {
+                Ok(Some(d)) if p.meta.as_ref().is_some_and(|m| m.is_string) => Some((v, d)),
+                _ => None,
+            })
+            .collect();
+        if !defaults
+            .iter()
+            .any(|(_, d)| matches!(d, DefaultValue::Str(_)))
+        {
+            return None;
+        }
+        let name = Language::CSharp.name_of(&src.label, &src.meta);
+        let is_string = |v: &CSharpVariable| defaults.iter().any(|(d, _)| d.label == v.label);
+        let twin_parameters: Vec<String> = parameters
+            .iter()
+            .map(
+                |v| match defaults.iter().find(|(d, _)| d.label == v.label) {
+                    Some((_, DefaultValue::Str(s))) => format!(
+                        "[Optional, DefaultParameterValue({})] string {}",
+                        string_literal(s),
+                        v.label
+                    ),
+                    Some(_) => {
+                        format!("[Optional, DefaultParameterValue(null)] string {}", v.label)
+                    }
+                    None => match &v.optional {
+                        Some(optional) => format!("{} {} {}", optional, v.data_type, v.label),
+                        None => format!("{} {}", v.data_type, v.label),
+                    },
+                },
+            )
+            .collect();
+        let arguments: Vec<String> = parameters
+            .iter()
+            .map(|v| match is_string(v) {
+                true => format!("{}Ptr", v.label),
+                false => v.label.to_owned(),
+            })
+            .collect();
+        let mut lines: Vec<String> = defaults
+            .iter()
+            .map(|(v, _)| {
+                format!(
+                    "IntPtr {}Ptr = {} == null ? IntPtr.Zero : StringToFFIPointer({});",
+                    v.label, v.label, v.label
+                )
+            })
+            .collect();
+        lines.push("try".to_owned());
+        lines.push("{".to_owned());
+        lines.push(match return_type {
+            "void" => format!("    {}({});", name, arguments.join(", ")),
+            _ => format!("    return {}({});", name, arguments.join(", ")),
+        });
+        lines.push("}".to_owned());
+        lines.push("finally".to_owned());
+        lines.push("{".to_owned());
+        for (v, _) in &defaults {
+            lines.push(format!("    FreeString({}Ptr);", v.label));
+        }
+        lines.push("}".to_owned());
+        Some(CSharpDefaults {
+            parameters: twin_parameters.join(", "),
+            body: lines
+                .iter()
+                .map(|l| format!("            {}", l))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        })
+    }
+}
+
+/// The twin of a function with several outputs, which returns them as a `ValueTuple` rather than as `out` parameters,
+/// e.g., `(string name, ulong count)`, and throws if the call fails.
+///
+/// Strings are read and released as `PtrToString` does. Only strings and plain values are returned this way,
+/// a function with other outputs, e.g., lists, or with a result of its own, is left with its `out` parameters
+#[derive(Serialize, Clone)]
+struct CSharpOutputs {
+    /// e.g., `(string name, ulong count)`
+    tuple_type: String,
+    /// The parameters of the twin, which are those of the function that are not outputs or errors
+    parameters: String,
+    /// The body of the twin, which calls the function and reads its outputs
+    body: String,
+}
+
+impl CSharpOutputs {
+    fn from(
+        src: &CFunction,
+        parameters: &[CSharpVariable],
+        return_type: &str,
+        has_error_codes: bool,
+    ) -> Option<Self> {
+        let meta = src.meta.clone().unwrap_or_else(MetaValue::new);
+        let outputs: Vec<(&CVariableDeclaration, &CSharpVariable)> = src
+            .parameters
+            .iter()
+            .zip(parameters)
+            .filter(|(p, _)| p.meta.as_ref().is_some_and(|m| m.is_output))
+            .collect();
+        if outputs.len() < 2 || (return_type != "void" && !meta.throws) {
+            return None;
+        }
+        let mut elements: Vec<String> = vec![];
+        let mut locals: Vec<String> = vec![];
+        let mut reads: Vec<String> = vec![];
+        let mut releases: Vec<String> = vec![];
+        for (p, v) in &outputs {
+            let m = p.meta.clone().unwrap_or_else(MetaValue::new);
+            if m.is_list || m.is_bytes || m.is_hashmap || v.pointer_count > 0 {
+                return None;
+            }
+            let native_type = v.value.as_ref()?.to_string();
+            match (&p.variable_type.kind, native_type.as_str()) {
+                (CType::Char(_), "IntPtr") => {
+                    let release = string_release(&m);
+                    elements.push(format!("string {}", p.label));
+                    locals.push(format!("IntPtr {} = IntPtr.Zero;", p.label));
+                    reads.push(match &release {
+                        Some(r) => format!("FFIInterface.PtrToString({}, {})", p.label, r),
+                        None => format!("FFIInterface.PtrToString({})", p.label),
+                    });
+                    releases.extend(release.map(|r| {
+                        [
+                            format!("if ({} != IntPtr.Zero)", p.label),
+                            "{".to_owned(),
+                            format!("    {}({});", r, p.label),
+                            "}".to_owned(),
+                        ]
+                        .join("\n")
+                    }));
+                }
+                (CType::Struct(_), _) => return None,
+                _ => {
+                    elements.push(format!("{} {}", native_type, p.label));
+                    locals.push(format!("{} {} = default;", native_type, p.label));
+                    reads.push(p.label.to_owned());
+                }
+            }
+        }
+        let twin_parameters: Vec<String> = src
+            .parameters
+            .iter()
+            .zip(parameters)
+            .filter(|(p, _)| !p.meta.as_ref().is_some_and(|m| m.is_output || m.is_error))
+            .map(|(_, v)| match &v.optional {
+                Some(optional) => format!("{} {} {}", optional, v.data_type, v.label),
+                None => format!("{} {}", v.data_type, v.label),
+            })
+            .collect();
+        let arguments: Vec<String> = src
+            .parameters
+            .iter()
+            .map(|p| match &p.meta {
+                Some(m) if m.is_output => format!("out {}", p.label),
+                Some(m) if m.is_error => format!("&{}", p.label),
+                _ => p.label.to_owned(),
+            })
+            .collect();
+        let call = format!(
+            "{}({})",
+            Language::CSharp.name_of(&src.label, &src.meta),
+            arguments.join(", ")
+        );
+        let mut lines = locals;
+        for p in src
+            .parameters
+            .iter()
+            .filter(|p| p.meta.as_ref().is_some_and(|m| m.is_error))
+        {
+            lines.push(format!("IntPtr {} = IntPtr.Zero;", p.label));
+        }
+        if meta.throws {
+            lines.push(format!("var code = {};", call));
+            let error = src
+                .parameters
+                .iter()
+                .find(|p| p.meta.as_ref().is_some_and(|m| m.is_error))
+                .map_or("IntPtr.Zero", |p| p.label.as_str());
+            lines.extend(throw_on_failure(src, error, has_error_codes, &releases));
+        } else {
+            lines.push(format!("{};", call));
+        }
+        lines.push(format!("return ({});", reads.join(", ")));
+        Some(CSharpOutputs {
+            tuple_type: format!("({})", elements.join(", ")),
+            parameters: twin_parameters.join(", "),
+            body: lines
+                .iter()
+                .flat_map(|l| l.split('\n'))
+                .map(|l| format!("            {}", l))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        })
+    }
+}
+
+/// The function releasing a string handed back by the library once it is read, `None` if it is `borrowed`
+fn string_release(meta: &MetaValue) -> Option<String> {
+    match &meta.free_with {
+        _ if meta.is_borrowed => None,
+        Some(free_with) => Some(format!("FFIInterface.{}", free_with)),
+        None => Some("FFIInterface.FreeNative".to_owned()),
+    }
+}
+
+/// The statements throwing if the call of `function`, whose result is in `code`, failed, with the message behind `error`.
+/// `releases` run first, e.g., releasing the outputs the library may have handed back anyway
+fn throw_on_failure(
+    function: &CFunction,
+    error: &str,
+    has_error_codes: bool,
+    releases: &[String],
+) -> Vec<String> {
+    /* the message is released as any other string handed back, see `string_release` */
+    let error_release = function
+        .parameters
+        .iter()
+        .filter_map(|p| p.meta.as_ref())
+        .find(|m| m.is_error)
+        .and_then(string_release)
+        .map(|r| format!(", {}", r))
+        .unwrap_or_default();
+    let mut lines = vec!["if (code != 0)".to_owned(), "{".to_owned()];
+    lines.extend(
+        releases
+            .iter()
+            .flat_map(|r| r.split('\n'))
+            .map(|l| format!("    {}", l)),
+    );
+    lines.push(if has_error_codes {
+        format!(
+            "    throw FFIInterface.ErrorForCode(code, {}{});",
+            error, error_release
+        )
+    } else {
+        format!(
+            "    throw new Exception(FFIInterface.GetErrorMessage({}{}));",
+            error, error_release
+        )
+    });
+    lines.push("}".to_owned());
+    lines
+}
+
+/// The `{function}Async` twin of an `async_callback` function, which hands the library a static callback,
+/// along with a `GCHandle` of the `TaskCompletionSource` the callback completes, see `Completion`.
+///
+/// The result is read, and released, within the callback: strings as `string`, numbers as they are,
+/// `persistent` structs as their handle, and `value` structs as their record.
+/// Any other struct has nothing to be copied to, so the pointer to it is handed to the caller, who releases it
+#[derive(Serialize, Clone)]
+struct CSharpCompletion {
+    /// e.g., `Task<string>`, or `Task` for a callback that hands over no result
+    task_type: String,
+    /// e.g., `TaskCompletionSource<string>`
+    source_type: String,
+    /// The parameters of the twin, i.e., those of the function without the callback and the user data
+    parameters: String,
+    /// The arguments handed to the function
+    arguments: String,
+    /// The parameters of the callback
+    callback_parameters: String,
+    /// The user data parameter of the callback
+    user_data: String,
+    /// The body of the callback, once it has the `completion` it is to complete
+    complete: String,
+}
+
+impl CSharpCompletion {
+    fn from(c: &Completion, parameters: &[CSharpVariable], value_types: &[ValueType]) -> Self {
+        let release = |label: &str, free_with: Option<&str>, free: &str| match free_with {
+            Some(f) => format!("{}({});", f, label),
+            None => format!("{}({});", free, label),
+        };
+        let mut complete: Vec<String> = vec![];
+        if let Some(error) = c.error {
+            let meta = error.meta.to_owned().unwrap_or_else(MetaValue::new);
+            complete.push(format!("if ({} != IntPtr.Zero)", error.label));
+            complete.push("{".to_owned());
+            complete.push(format!(
+                "    var errorMessage = GetErrorMessage({});",
+                error.label
+            ));
+            if !meta.is_borrowed {
+                complete.push(format!(
+                    "    {}",
+                    release(&error.label, meta.free_with.as_deref(), "FreeNative")
+                ));
+            }
+            complete.push("    completion.SetException(new Exception(errorMessage));".to_owned());
+            complete.push("    return;".to_owned());
+            complete.push("}".to_owned());
+        }
+        let result_type = match (c.result, &c.result_element) {
+            (Some(result), Some(element)) => {
+                let label = &result.label;
+                let releases = !c.is_result_borrowed() && !c.is_result_persistent();
+                let (result_type, read, free) = match element {
+                    Element::String => (
+                        "string".to_owned(),
+                        format!("PtrToString({})", label),
+                        "FreeNative",
+                    ),
+                    Element::Enum(e) => (
+                        e.identifier.label.to_owned(),
+                        format!("({}){}", e.identifier.label, label),
+                        "",
+                    ),
+                    Element::StructPointer(s) if c.is_result_persistent() => (
+                        format!("{}Handle", Language::CSharp.struct_name(s)),
+                        format!("new {}Handle({})", Language::CSharp.struct_name(s), label),
+                        "",
+                    ),
+                    Element::StructPointer(s) | Element::Struct(s) => {
+                        /* the record is copied out before the native struct, and what its fields point to, is released */
+                        match value_types
+                            .iter()
+                            .find(|v| v.owner.identifier.label == s.identifier.label)
+                        {
+                            Some(v) => {
+                                let name = Language::CSharp.struct_name(v.owner);
+                                (
+                                    name.to_owned(),
+                                    format!(
+                                        "{}.FromNative(Marshal.PtrToStructure<C_{}>({}))",
+                                        name, name, label
+                                    ),
+                                    "FreeNative",
+                                )
+                            }
+                            None => ("IntPtr".to_owned(), label.to_owned(), ""),
+                        }
+                    }
+                    Element::Primitive(_) => {
+                        (CSharpVariable::from(result).data_type, label.to_owned(), "")
+                    }
+                };
+                complete.push(format!("var result = {};", read));
+                if releases && !free.is_empty() {
+                    complete.push(release(label, c.result_release(), free));
+                }
+                complete.push("completion.SetResult(result);".to_owned());
+                Some(result_type)
+            }
+            _ => {
+                complete.push("completion.SetResult();".to_owned());
+                None
+            }
+        };
+
+        let twin_parameters: Vec<String> = parameters
+            .iter()
+            .filter(|p| p.label != c.callback.label && p.label != c.user_data.label)
+            .map(|p| format!("{} {}", p.data_type, p.label))
+            .collect();
+        let arguments: Vec<String> = parameters
+            .iter()
+            .map(|p| {
+                if p.label == c.callback.label {
+                    format!(
+                        "&{}Done",
+                        Language::CSharp.name_of(&c.function.label, &c.function.meta)
+                    )
+                } else if p.label == c.user_data.label {
+                    "GCHandle.ToIntPtr(handle)".to_owned()
+                } else {
+                    p.label.to_owned()
+                }
+            })
+            .collect();
+        let callback_parameters: Vec<String> = c
+            .signature
+            .parameters
+            .iter()
+            .map(|p| format!("{} {}", CSharpVariable::from(p).data_type, p.label))
+            .collect();
+        CSharpCompletion {
+            task_type: match &result_type {
+                Some(t) => format!("Task<{}>", t),
+                None => "Task".to_owned(),
+            },
+            source_type: match &result_type {
+                Some(t) => format!("TaskCompletionSource<{}>", t),
+                None => "TaskCompletionSource".to_owned(),
+            },
+            parameters: twin_parameters.join(", "),
+            arguments: arguments.join(", "),
+            callback_parameters: callback_parameters.join(", "),
+            user_data: c.callback_user_data.label.to_owned(),
+            complete: complete
+                .iter()
+                .map(|l| format!("            {}", l))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// The `{function}Async` twin of a `cancellable` function, which runs the call it is handed on the thread pool,
+/// and hands its cancel handle to the cancel function once the `CancellationToken` is cancelled, see `Cancellation`.
+///
+/// The native call itself is left to the caller, who hands the handle over along with the other arguments
+#[derive(Serialize, Clone)]
+struct CSharpCancellable {
+    /// The function cancelling the call
+    cancel: String,
+    /// e.g., `Task<uint>`, or `Task` for a `void` function
+    task_type: String,
+    /// e.g., `Func<ulong, uint>`, or `Action<ulong>` for a `void` function
+    call_type: String,
+    /// Statement running `call`, see `RunCancellable`
+    run: String,
+}
+
+impl CSharpCancellable {
+    fn from(c: &Cancellation, return_type: &str) -> Self {
+        let handle_type = CSharpVariable::from(c.handle).data_type;
+        let is_void = return_type == "void";
+        let throws = c.function.meta.as_ref().is_some_and(|m| m.throws);
+        let call = if is_void {
+            format!(
+                "handle => {{ call(({})handle); return true; }}",
+                handle_type
+            )
+        } else {
+            format!("handle => call(({})handle)", handle_type)
+        };
+        CSharpCancellable {
+            cancel: Language::CSharp
+                .name_of(&c.cancel.label, &c.cancel.meta)
+                .to_owned(),
+            task_type: match is_void {
+                true => "Task".to_owned(),
+                false => format!("Task<{}>", return_type),
+            },
+            call_type: match is_void {
+                true => format!("Action<{}>", handle_type),
+                false => format!("Func<{}, {}>", handle_type, return_type),
+            },
+            run: format!(
+                "return RunCancellable({}, handle => {}(({})handle), {}, cancellationToken);",
+                call,
+                Language::CSharp.name_of(&c.cancel.label, &c.cancel.meta),
+                handle_type,
+                /* a failure once cancelled is the cancellation, which only a function that throws can report */
+                if throws && !is_void {
+                    "code => code != 0"
+                } else {
+                    "_ => false"
+                }
+            ),
+        }
+    }
 }
+
 impl From<&CFunction> for CSharpFunction {
     fn from(src: &CFunction) -> Self {
         let (ret_pointer_count, ret_data_type) =
             CSharpVariable::sub_variable(&src.return_type.kind, src.return_type.pointer_count);
 
+        let output_count = src
+            .parameters
+            .iter()
+            .filter(|p| p.meta.as_ref().is_some_and(|m| m.is_output))
+            .count();
+
         let mut params: Vec<CSharpVariable> = vec![];
-        let mut param2meta: HashMap<String, Option<MetaValue>> = HashMap::new();
-        if let Some(cmt) = &src.comment {
-            let mut iter = cmt
-                .split('\n')
-                .skip_while(|&x| !x.contains(META_PARAM_TOKEN));
-            while let Some(c) = iter.next() {
-                if !c.contains(META_PARAM_TOKEN) {
-                    continue;
-                }
-                let items: Vec<&str> = c.split(';').collect();
-                let car = items.first().unwrap().split(':').collect::<Vec<&str>>()[1]
-                    .trim()
-                    .to_string();
-                let cdr = items[1..].join(";");
-                let meta_value = MetaValue::from_meta_comment(&cdr);
-                param2meta.insert(car, meta_value);
-            }
-        }
         for n in 0..src.parameters.len() {
             let mut v = CSharpVariable::from(&src.parameters[n]);
             if n == src.parameters.len() - 1 {
                 v.is_last = true;
             }
+            v.meta = src.parameters[n].meta.to_owned();
+            /* Input buffers are pinned and handed over as they are */
+            if v.meta
+                .as_ref()
+                .is_some_and(|m| m.is_bytes && !m.is_output && v.pointer_count == 1)
+            {
+                v.data_type = "byte[]".to_owned();
+            }
+            /* Several outputs are marshalled as `out` parameters, rather than raw pointers */
+            let is_output = v.meta.as_ref().is_some_and(|m| m.is_output);
+            if output_count > 1 && is_output && v.pointer_count > 0 {
+                v.pointer_count -= 1;
+                v.data_type = format!(
+                    "out {}{}",
+                    v.value
+                        .as_ref()
+                        .map_or("IntPtr".to_owned(), |t| t.to_string()),
+                    "*".repeat(v.pointer_count.into())
+                );
+            }
+
+            /* Callbacks are handed over as unmanaged function pointers */
+            if let CType::Function(f) = &src.parameters[n].variable_type.kind {
+                v.data_type = format!(
+                    "delegate* unmanaged<{}, {}>",
+                    f.parameters
+                        .iter()
+                        .map(|p| CSharpVariable::from(p).data_type)
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    CSharpVariable::sub_variable(&f.return_type.kind, f.return_type.pointer_count)
+                        .1
+                );
+            }
 
-            if let Some(meta_value) = &param2meta.get(&v.label) {
-                v.meta = (*meta_value).to_owned();
+            /* Lists are handed over as arrays, and handed back as a pointer to their first element */
+            if let Some(e) = list_element(&src.parameters[n]) {
+                v.data_type = match (&e, is_output) {
+                    (_, true) if output_count > 1 => format!("out {}*", element_type(&e)),
+                    (_, true) => format!("{}**", element_type(&e)),
+                    (Element::String, false) => {
+                        "[MarshalAs(UnmanagedType.LPArray, ArraySubType = UnmanagedType.LPStr)] string[]"
+                            .to_owned()
+                    }
+                    (_, false) => format!("{}[]", element_type(&e)),
+                };
+            }
+
+            /* Numbers and `null` are defaults C# can set on the parameter itself */
+            match DefaultValue::of(&src.parameters[n], src) {
+                Ok(Some(DefaultValue::Number(value))) => {
+                    v.optional = Some(format!(
+                        "[Optional, DefaultParameterValue(({}){})]",
+                        v.data_type, value
+                    ));
+                }
+                Ok(Some(DefaultValue::Null)) => v.optional = Some("[Optional]".to_owned()),
+                _ => {}
             }
 
             params.push(v);
         }
 
         CSharpFunction {
+            defaults: CSharpDefaults::from(src, &params, &ret_data_type.to_string()),
             c_label: src.label.to_owned(),
-            csharp_label: src.label.to_owned(),
+            csharp_label: Language::CSharp.name_of(&src.label, &src.meta).to_owned(),
+            entry_point: src
+                .meta
+                .as_ref()
+                .and_then(|m| m.rename_for(Language::CSharp))
+                .map(|_| src.label.to_owned()),
             c_comment: src.comment.to_owned(),
-            csharp_comment: transform_comment(src.comment.to_owned()),
+            csharp_comment: with_ownership_remarks(transform_comment(src.comment.to_owned()), src),
             parameters: params,
             return_type: CSharpVariable {
                 label: "".into(),
@@ -218,8 +1641,14 @@ impl From<&CFunction> for CSharpFunction {
                 pointer_count: ret_pointer_count,
                 is_last: true,
                 meta: None,
+                optional: None,
+                obsolete: None,
             },
-            meta: None,
+            meta: src.meta.to_owned(),
+            cancellable: None,
+            completion: None,
+            outputs: None,
+            obsolete: obsolete_attribute(&src.deprecated),
         }
     }
 }
@@ -234,6 +1663,10 @@ struct CSharpVariable {
     pointer_count: u8,
     is_last: bool,
     meta: Option<MetaValue>,
+    /// The attributes making a parameter that has a default optional, e.g., `[Optional, DefaultParameterValue((ulong)30000)]`
+    optional: Option<String>,
+    /// Set for a deprecated struct field, see `obsolete_attribute`
+    obsolete: Option<String>,
 }
 impl Display for CSharpVariable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -251,7 +1684,17 @@ impl CSharpVariable {
         let data_type: DataType;
         let mut adjusted_pointer_count: u8 = pointer_count;
         match var_type {
+            /* e.g., the user data handed back to a callback */
+            CType::Void if pointer_count > 0 => {
+                data_type = DataType::IntPtr;
+                adjusted_pointer_count = pointer_count - 1;
+            }
             CType::Void => data_type = DataType::Void,
+            /* the callback of an `async_callback` function, whose signature is filled in by `CSharpFunction` */
+            CType::Function(_) => {
+                data_type = DataType::IntPtr;
+                adjusted_pointer_count = 0;
+            }
 
             CType::Enum(_) => data_type = DataType::Int(0),
 
@@ -301,6 +1744,8 @@ impl From<&CVariableDeclaration> for CSharpVariable {
             value: Some(data_type),
             is_last: false,
             meta: None,
+            optional: None,
+            obsolete: obsolete_attribute(&src.deprecated),
         }
     }
 }
@@ -309,12 +1754,15 @@ impl From<&CVariableDeclaration> for CSharpVariable {
 struct CSharpIdentifier {
     label: String,
     comment: Option<String>,
+    /// Set for a deprecated enum member, see `obsolete_attribute`
+    obsolete: Option<String>,
 }
 impl From<&CIdentifier> for CSharpIdentifier {
     fn from(src: &CIdentifier) -> Self {
         CSharpIdentifier {
             label: src.label.to_owned(),
             comment: transform_comment(src.comment.to_owned()),
+            obsolete: obsolete_attribute(&src.deprecated),
         }
     }
 }
@@ -334,7 +1782,10 @@ impl From<&CEnum> for CSharpEnum {
             members: src
                 .declarations
                 .iter()
-                .map(|decl| CSharpIdentifier::from(decl))
+                .map(|decl| CSharpIdentifier {
+                    label: Language::CSharp.member_name(decl),
+                    ..CSharpIdentifier::from(decl)
+                })
                 .collect(),
         }
     }
@@ -381,10 +1832,97 @@ fn transform_comment(cmt: Option<String>) -> Option<String> {
     }
 }
 
-pub fn generate(header: HeaderFile, namespace: &str, dll_location: &str) {
+/// The attribute that marks an item deprecated for `reason`, e.g., `[Obsolete("use FetchToken instead")]`,
+/// or `[Obsolete]` if no reason is given
+fn obsolete_attribute(reason: &Option<String>) -> Option<String> {
+    match reason.as_deref() {
+        None => None,
+        Some("") => Some("[Obsolete]".to_owned()),
+        Some(r) => Some(format!(
+            "[Obsolete(\"{}\")]",
+            r.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )),
+    }
+}
+
+/// Appends a `<remarks>` line for every output or error parameter of `f` that is not released with `FreeString`.
+/// Strings name the helper that reads and releases them at once, the rest are left to the caller
+fn with_ownership_remarks(comment: Option<String>, f: &CFunction) -> Option<String> {
+    let lists = List::all(&f.parameters);
+    let remarks: Vec<String> = f
+        .parameters
+        .iter()
+        .filter_map(|p| {
+            let meta = p.meta.as_ref()?;
+            if meta.is_borrowed {
+                Some(format!(
+                    "/// <remarks><c>{}</c> is owned by the library and must not be freed</remarks>",
+                    p.label
+                ))
+            } else {
+                meta.free_with.as_ref().map(|free_with| {
+                    let list = lists.iter().find(|l| l.items.label == p.label);
+                    /* a list is handed back along with its length and capacity */
+                    let sizes = list
+                        .map(|l| {
+                            l.length
+                                .into_iter()
+                                .chain(l.capacity)
+                                .map(|d| format!("<c>{}</c>", d.label))
+                                .collect::<Vec<String>>()
+                                .join(" and ")
+                        })
+                        .map(|sizes| format!(" along with {}", sizes))
+                        .unwrap_or_default();
+                    let helper = match &p.variable_type.kind {
+                        _ if list.is_some() || meta.is_bytes => None,
+                        CType::Char(_) if meta.is_error => Some("GetErrorMessage"),
+                        CType::Char(_) => Some("PtrToString"),
+                        _ => None,
+                    };
+                    let read = helper
+                        .map(|h| {
+                            format!(
+                                ", which <c>FFIInterface.{}({}, {})</c> does once it is read",
+                                h, p.label, free_with
+                            )
+                        })
+                        .unwrap_or_default();
+                    format!(
+                        "/// <remarks><c>{}</c> must be released with <see cref=\"{}\"/>{}{}</remarks>",
+                        p.label, free_with, sizes, read
+                    )
+                })
+            }
+        })
+        .collect();
+    if remarks.is_empty() {
+        return comment;
+    }
+    Some(
+        comment
+            .into_iter()
+            .chain(remarks)
+            .collect::<Vec<String>>()
+            .join("\n"),
+    )
+}
+
+pub fn generate(header: &HeaderFile, namespace: &str, dll_location: &str) -> String {
+    let mut header = Language::CSharp.header(header);
+    rename_releases(&mut header);
+    let header = &header;
+    let value_types = ValueType::all(header);
     let csharp = Data::new(
         dll_location,
-        vec!["System", "System.Runtime.InteropServices", "System.Linq"],
+        vec![
+            "System",
+            "System.Collections.Generic",
+            "System.Runtime.InteropServices",
+            "System.Linq",
+        ],
         namespace,
         header
             .defines
@@ -397,12 +1935,27 @@ pub fn generate(header: HeaderFile, namespace: &str, dll_location: &str) {
             .iter()
             .map(|s| CSharpStruct::from(s))
             .collect(),
-        &mut header
-            .functions
+        &mut with_list_releases(
+            header
+                .functions
+                .iter()
+                .map(|f| CSharpFunction::from(f))
+                .collect(),
+            header,
+        ),
+    )
+    .with_error_codes(
+        error_codes(header)
             .iter()
-            .map(|f| CSharpFunction::from(f))
+            .map(CSharpErrorCode::from)
             .collect(),
-    );
+    )
+    .with_outputs(header)
+    .with_properties(&Property::all(header), header)
+    .with_iterators(&Cursor::all(header), header)
+    .with_cancellations(&Cancellation::all(header))
+    .with_completions(&Completion::all(header), &value_types)
+    .with_value_types(&value_types, header);
 
     let header = mustache::compile_str(TEMPLATE_HEADER)
         .unwrap()
@@ -420,6 +1973,18 @@ pub fn generate(header: HeaderFile, namespace: &str, dll_location: &str) {
         .unwrap()
         .render_to_string(&csharp)
         .unwrap();
+    let errors = mustache::compile_str(TEMPLATE_ERRORS)
+        .unwrap()
+        .render_to_string(&csharp)
+        .unwrap();
+    let handles = mustache::compile_str(TEMPLATE_HANDLES)
+        .unwrap()
+        .render_to_string(&csharp)
+        .unwrap();
+    let records = mustache::compile_str(TEMPLATE_RECORDS)
+        .unwrap()
+        .render_to_string(&csharp)
+        .unwrap();
     let helpers = mustache::compile_str(TEMPLATE_HELPERS)
         .unwrap()
         .render_to_string(&csharp)
@@ -428,8 +1993,10 @@ pub fn generate(header: HeaderFile, namespace: &str, dll_location: &str) {
         .unwrap()
         .render_to_string(&csharp)
         .unwrap();
-    let total: Vec<String> = vec![header, enums, ffi_body, helpers, footer];
-    println!("{}", total.join(""));
+    let total: Vec<String> = vec![
+        header, enums, ffi_body, errors, handles, records, helpers, footer,
+    ];
+    total.join("")
 }
 
 const TEMPLATE_CLASSES: &str = "
@@ -508,22 +2075,285 @@ const TEMPLATE_ENUMS: &str = "
     {{#comment}}
     {{{comment}}}
     {{/comment}}
-    {{label}},
+    {{#obsolete}}{{{obsolete}}} {{/obsolete}}{{label}},
     {{/members}}
 }
 {{/enums}}
 #endregion
 ";
 
+const TEMPLATE_ERRORS: &str = "{{#has_error_codes}}
+#region errors
+/// <summary>
+/// A failure reported by the native library
+/// </summary>
+public class {{exception}} : Exception
+{
+    /// <summary>
+    /// The failure code returned by the native library
+    /// </summary>
+    public int Code { get; }
+
+    public {{exception}}(string message, int code) : base(message)
+    {
+        Code = code;
+    }
+
+    /// <summary>
+    /// The exception for <paramref name=\"code\"/>, or a plain <see cref=\"{{exception}}\"/> if it is not a known failure code
+    /// </summary>
+    public static {{exception}} FromCode(string message, int code)
+    {
+        switch (code)
+        {
+            {{#error_codes}}
+            case {{value}}:
+                return new {{label}}(message);
+            {{/error_codes}}
+            default:
+                return new {{exception}}(message, code);
+        }
+    }
+}
+
+{{#error_codes}}
+{{#csharp_comment}}{{{csharp_comment}}}
+{{/csharp_comment}}public class {{label}} : {{exception}}
+{
+    public {{label}}(string message) : base(message, {{value}}) { }
+}
+
+{{/error_codes}}
+internal unsafe partial class FFIInterface
+{
+    /// <summary>
+    /// The exception for a failed call that returned <paramref name=\"code\"/>, with the message behind <paramref name=\"ptrErrMsg\"/>,
+    /// which is handed to <paramref name=\"release\"/> once it is read, see <see cref=\"GetErrorMessage\"/>
+    /// </summary>
+    internal static {{exception}} ErrorForCode(long code, IntPtr ptrErrMsg, Action<IntPtr> release = null)
+    {
+        return {{exception}}.FromCode(GetErrorMessage(ptrErrMsg, release), (int)code);
+    }
+}
+#endregion
+{{/has_error_codes}}";
+
+const TEMPLATE_HANDLES: &str = "{{#has_handles}}
+#region handles
+{{#handles}}
+/// <summary>
+/// A pointer to a native <c>{{label}}</c>, whose properties are read and written through the library
+/// </summary>
+{{#obsolete}}{{{obsolete}}}
+{{/obsolete}}public readonly unsafe struct {{name}}Handle
+{
+    internal readonly IntPtr selfPtr;
+
+    public {{name}}Handle(IntPtr selfPtr)
+    {
+        this.selfPtr = selfPtr;
+    }
+{{#properties}}
+
+    {{#csharp_comment}}{{{csharp_comment}}}
+    {{/csharp_comment}}public {{data_type}} {{label}}
+    {
+{{#get}}
+        get
+        {
+{{{get}}}
+        }
+{{/get}}
+{{#set}}
+        set
+        {
+{{{set}}}
+        }
+{{/set}}
+    }
+{{/properties}}
+{{#iterator}}
+
+    /// <summary>
+    /// Iterates over the items handed out by <c>{{next}}</c>, releasing the cursor once they run out.
+    /// The handle must not be used once the items have run out
+    /// </summary>
+    {{visibility}} IEnumerable<{{item_type}}> Items()
+    {
+        IntPtr self = selfPtr;
+{{#release}}
+        IntPtr current = IntPtr.Zero;
+{{/release}}
+        return NativeIterator.Iterate(
+            (out {{item_type}} item) =>
+            {
+                IntPtr itemPtr;
+                bool hasItem = FFIInterface.{{next}}(self, &itemPtr) != 0;
+{{#release}}
+                current = itemPtr;
+{{/release}}
+                item = hasItem ? {{{from_native}}} : default;
+                return hasItem;
+            },
+            {{#release}}() => {{{release}}}{{/release}}{{^release}}null{{/release}},
+            {{#done}}() => {{{done}}}{{/done}}{{^done}}null{{/done}});
+    }
+{{/iterator}}
+}
+
+{{/handles}}
+{{#has_iterators}}
+/// <summary>
+/// Hands out the items of a native cursor one at a time
+/// </summary>
+internal delegate bool TryNext<T>(out T item);
+
+internal static class NativeIterator
+{
+    /// <summary>
+    /// Yields the items of <paramref name=\"next\"/> until it runs out, then calls <paramref name=\"done\"/>.
+    /// Each item is released with <paramref name=\"release\"/> once the caller moves past it, so iterators are kept out of the unsafe handles
+    /// </summary>
+    internal static IEnumerable<T> Iterate<T>(TryNext<T> next, Action release, Action done)
+    {
+        while (next(out T item))
+        {
+            try
+            {
+                yield return item;
+            }
+            finally
+            {
+                release?.Invoke();
+            }
+        }
+        done?.Invoke();
+    }
+}
+{{/has_iterators}}
+#endregion
+{{/has_handles}}";
+
+const TEMPLATE_RECORDS: &str = "{{#has_records}}
+#region records
+#nullable enable
+{{#records}}
+{{#csharp_comment}}{{{csharp_comment}}}
+{{/csharp_comment}}{{#members}}{{#param_comment}}{{{param_comment}}}
+{{/param_comment}}{{/members}}{{#obsolete}}{{{obsolete}}}
+{{/obsolete}}public sealed record {{name}}(
+{{#members}}
+    {{#obsolete}}{{{obsolete}}} {{/obsolete}}{{{data_type}}} {{label}}{{^is_last}},{{/is_last}}
+{{/members}}
+)
+{
+    /// <summary>
+    /// Copies the fields of <paramref name=\"native\"/>, whose pointers are left to its owner
+    /// </summary>
+    internal static unsafe {{name}} FromNative(in FFIInterface.C_{{name}} native)
+    {
+        return new {{name}}(
+{{#members}}
+            {{{from_native}}}{{^is_last}},{{/is_last}}
+{{/members}}
+        );
+    }
+{{#has_elements}}
+
+    /// <summary>
+    /// Compares arrays and dictionaries by their elements, rather than by reference
+    /// </summary>
+    public bool Equals({{name}}? other)
+    {
+        return other is not null{{#members}}
+            && {{{equals}}}{{/members}};
+    }
+
+    public override int GetHashCode()
+    {
+        var hash = new HashCode();
+{{#members}}
+        {{{hash}}};
+{{/members}}
+        return hash.ToHashCode();
+    }
+
+    private bool PrintMembers(StringBuilder builder)
+    {
+{{#members}}
+        {{{print}}};
+{{/members}}
+        return true;
+    }
+{{/has_elements}}
+}
+
+{{/records}}
+/// <summary>
+/// Compares, hashes and prints the arrays and dictionaries of records by their elements
+/// </summary>
+internal static class ValueEquality
+{
+    internal static bool ListEquals<T>(IReadOnlyList<T>? a, IReadOnlyList<T>? b)
+    {
+        return a == null || b == null ? a == b : a.SequenceEqual(b);
+    }
+
+    internal static bool MapEquals(IReadOnlyDictionary<string, string>? a, IReadOnlyDictionary<string, string>? b)
+    {
+        if (a == null || b == null)
+        {
+            return a == b;
+        }
+        return a.Count == b.Count && a.All(kv => b.TryGetValue(kv.Key, out string? value) && value == kv.Value);
+    }
+
+    internal static int ListHash<T>(IReadOnlyList<T>? list)
+    {
+        var hash = new HashCode();
+        foreach (T item in list ?? Array.Empty<T>())
+        {
+            hash.Add(item);
+        }
+        return hash.ToHashCode();
+    }
+
+    /// <summary>
+    /// XORs the hashes of the entries, as dictionaries that are equal need not list them in the same order
+    /// </summary>
+    internal static int MapHash(IReadOnlyDictionary<string, string>? map)
+    {
+        int hash = 0;
+        foreach (KeyValuePair<string, string> kv in map ?? new Dictionary<string, string>())
+        {
+            hash ^= HashCode.Combine(kv.Key, kv.Value);
+        }
+        return hash;
+    }
+
+    internal static string Print<T>(IEnumerable<T>? items)
+    {
+        return items == null ? \"null\" : \"[\" + string.Join(\", \", items) + \"]\";
+    }
+}
+#nullable restore
+#endregion
+{{/has_records}}";
+
 const TEMPLATE_HELPERS: &str = "
 #region helpers
 
 
 internal unsafe partial class FFIInterface
 {
-    internal static string GetErrorMessage(IntPtr ptrErrMsg)
+    /// <summary>
+    /// Copies the error message behind <paramref name=\"ptrErrMsg\"/>, then hands it to <paramref name=\"release\"/>, if set,
+    /// e.g., the <c>free_with</c> function of the error parameter
+    /// </summary>
+    internal static string GetErrorMessage(IntPtr ptrErrMsg, Action<IntPtr> release = null)
     {
         string errmsg = Marshal.PtrToStringAnsi(ptrErrMsg);
+        release?.Invoke(ptrErrMsg);
         if (errmsg == null)
         {
             throw new Exception(\"Failed to extract error message\");
@@ -531,9 +2361,14 @@ internal unsafe partial class FFIInterface
         return errmsg;
     }
 
-    internal static string PtrToString(IntPtr ptr)
+    /// <summary>
+    /// Copies the string behind <paramref name=\"ptr\"/>, then hands it to <paramref name=\"release\"/>, if set,
+    /// e.g., the <c>free_with</c> function of the output
+    /// </summary>
+    internal static string PtrToString(IntPtr ptr, Action<IntPtr> release = null)
     {
         string errmsg = Marshal.PtrToStringAnsi(ptr);
+        release?.Invoke(ptr);
         if (errmsg == null)
         {
             throw new Exception(\"Failed to extract string\");
@@ -551,20 +2386,123 @@ internal unsafe partial class FFIInterface
         Marshal.FreeHGlobal(ptr);
     }
 
+    /// <summary>
+    /// Releases memory the library allocated with <c>malloc</c>, e.g., a string it hands back that has no <c>free_with</c> function
+    /// </summary>
+    internal static void FreeNative(IntPtr ptr)
+    {
+        NativeMemory.Free((void*)ptr);
+    }
+
     internal static void FreeStruct(IntPtr ptr)
     {
         Marshal.FreeHGlobal(ptr);
     }
+{{#has_cancellables}}
+
+    private static long lastCancelHandle;
+
+    /// <summary>
+    /// Runs <paramref name=\"call\"/> on the thread pool with a new cancel handle, which is handed to <paramref name=\"cancel\"/>
+    /// if <paramref name=\"cancellationToken\"/> is cancelled while the call runs.
+    /// A call that has <paramref name=\"failed\"/> once cancelled throws <see cref=\"OperationCanceledException\"/>
+    /// </summary>
+    internal static Task<T> RunCancellable<T>(Func<long, T> call, Action<long> cancel, Func<T, bool> failed, CancellationToken cancellationToken)
+    {
+        return Task.Run(() =>
+        {
+            long handle = Interlocked.Increment(ref lastCancelHandle);
+            using (cancellationToken.Register(() => cancel(handle)))
+            {
+                T result = call(handle);
+                if (failed(result))
+                {
+                    cancellationToken.ThrowIfCancellationRequested();
+                }
+                return result;
+            }
+        }, cancellationToken);
+    }
+{{/has_cancellables}}
+
+    /// <summary>
+    /// Copies a dictionary into the parallel arrays of keys and values that C expects for a map.
+    /// Release them with <c>FreeArrays</c> once the native call returns
+    /// </summary>
+    internal static void Dictionary2Arrays(IDictionary<string, string> dict, out IntPtr* keys, out IntPtr* values, out ulong length)
+    {
+        length = (ulong)dict.Count;
+        keys = (IntPtr*)Marshal.AllocHGlobal(IntPtr.Size * Math.Max(dict.Count, 1));
+        values = (IntPtr*)Marshal.AllocHGlobal(IntPtr.Size * Math.Max(dict.Count, 1));
+        int i = 0;
+        foreach (KeyValuePair<string, string> kv in dict)
+        {
+            keys[i] = StringToFFIPointer(kv.Key);
+            values[i] = StringToFFIPointer(kv.Value);
+            i++;
+        }
+    }
 
-    internal static IntPtr HashMap2IntPtr(IList<string> dict)
+    internal static void FreeArrays(IntPtr* keys, IntPtr* values, ulong length)
     {
-        if (dict.Count == 0)
+        for (ulong i = 0; i < length; i++)
         {
-            return StringToFFIPointer(\"\");
+            FreeString(keys[i]);
+            FreeString(values[i]);
         }
-        List<string> strings = dict.Where((x) => x.Split('=').Length == 2).ToList();
-        string final = String.Join(\";\", strings);
-        return StringToFFIPointer(final);
+        Marshal.FreeHGlobal((IntPtr)keys);
+        Marshal.FreeHGlobal((IntPtr)values);
+    }
+
+    /// <summary>
+    /// Copies a buffer of <c>length</c> bytes handed back by the library, which is left to the caller to release
+    /// </summary>
+    internal static byte[] FFIPointerToBytes(byte* data, ulong length)
+    {
+        if (data == null)
+        {
+            return Array.Empty<byte>();
+        }
+        return new ReadOnlySpan<byte>(data, checked((int)length)).ToArray();
+    }
+
+    /// <summary>
+    /// Copies the <c>length</c> elements of an array handed back by the library, which is left to the caller to release
+    /// </summary>
+    internal static T[] FFIPointerToArray<T>(T* data, ulong length) where T : unmanaged
+    {
+        if (data == null)
+        {
+            return Array.Empty<T>();
+        }
+        return new ReadOnlySpan<T>(data, checked((int)length)).ToArray();
+    }
+
+    /// <summary>
+    /// Copies the <c>length</c> strings of an array handed back by the library, which are left to the caller to release
+    /// </summary>
+    internal static string[] FFIPointerToStrings(IntPtr* data, ulong length)
+    {
+        if (data == null)
+        {
+            return Array.Empty<string>();
+        }
+        string[] strings = new string[length];
+        for (ulong i = 0; i < length; i++)
+        {
+            strings[i] = PtrToString(data[i]);
+        }
+        return strings;
+    }
+
+    internal static Dictionary<string, string> Arrays2Dictionary(IntPtr* keys, IntPtr* values, ulong length)
+    {
+        Dictionary<string, string> dict = new Dictionary<string, string>();
+        for (ulong i = 0; i < length; i++)
+        {
+            dict[PtrToString(keys[i])] = PtrToString(values[i]);
+        }
+        return dict;
     }
 }
 
@@ -598,12 +2536,14 @@ const TEMPLATE_BODY: &str = "
         {{#structs}}
         [StructLayout(LayoutKind.Sequential)]
         {{#csharp_comment}}{{{csharp_comment}}}{{/csharp_comment}}
-        internal readonly struct C_{{label}} {
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}internal readonly struct C_{{label}} {
             {{#members}}
             {{#csharp_comment}}{{{csharp_comment}}}{{/csharp_comment}}
-            internal readonly {{data_type}} {{label}};
+            {{#obsolete}}{{{obsolete}}}
+            {{/obsolete}}internal readonly {{data_type}} {{label}};
             {{/members}}
-        }
+{{#accessors}}{{{method}}}{{/accessors}}        }
 
         {{/structs}}
         #endregion
@@ -611,8 +2551,58 @@ const TEMPLATE_BODY: &str = "
         #region functions
         {{#functions}}
         {{#csharp_comment}}{{{csharp_comment}}}{{/csharp_comment}}
-        [DllImport(\"{{dll_location}}\")]
-        internal static extern {{#return_type}}{{data_type}}{{/return_type}} {{c_label}}({{#parameters}}{{data_type}} {{label}}{{^is_last}}, {{/is_last}}{{/parameters}});
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}[DllImport(\"{{dll_location}}\"{{#entry_point}}, EntryPoint = \"{{entry_point}}\"{{/entry_point}})]
+        internal static extern {{#return_type}}{{data_type}}{{/return_type}} {{csharp_label}}({{#parameters}}{{#optional}}{{{optional}}} {{/optional}}{{{data_type}}} {{label}}{{^is_last}}, {{/is_last}}{{/parameters}});{{#defaults}}
+
+        /// <summary>
+        /// Calls <see cref=\"{{csharp_label}}\"/>, taking the strings that have a default as <c>string</c>
+        /// </summary>
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}internal static {{#return_type}}{{data_type}}{{/return_type}} {{csharp_label}}({{{parameters}}})
+        {
+{{{body}}}
+        }{{/defaults}}{{#outputs}}
+
+        /// <summary>
+        /// Calls <see cref=\"{{csharp_label}}\"/>, returning its outputs as a tuple
+        /// </summary>
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}internal static {{{tuple_type}}} {{csharp_label}}({{{parameters}}})
+        {
+{{{body}}}
+        }{{/outputs}}{{#cancellable}}
+
+        /// <summary>
+        /// Runs <paramref name=\"call\"/> on the thread pool with a new cancel handle, to hand to <see cref=\"{{csharp_label}}\"/>.
+        /// The handle is handed to <see cref=\"{{cancel}}\"/> once <paramref name=\"cancellationToken\"/> is cancelled
+        /// </summary>
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}internal static {{{task_type}}} {{csharp_label}}Async({{{call_type}}} call, CancellationToken cancellationToken)
+        {
+            {{{run}}}
+        }{{/cancellable}}{{#completion}}
+
+        /// <summary>
+        /// Calls <see cref=\"{{csharp_label}}\"/>, which returns at once, and completes the task once the library hands the result to the callback
+        /// </summary>
+        {{#obsolete}}{{{obsolete}}}
+        {{/obsolete}}internal static {{{task_type}}} {{csharp_label}}Async({{{parameters}}})
+        {
+            var completion = new {{{source_type}}}(TaskCreationOptions.RunContinuationsAsynchronously);
+            var handle = GCHandle.Alloc(completion);
+            {{csharp_label}}({{{arguments}}});
+            return completion.Task;
+        }
+
+        [UnmanagedCallersOnly]
+        private static void {{csharp_label}}Done({{{callback_parameters}}})
+        {
+            var handle = GCHandle.FromIntPtr({{user_data}});
+            var completion = ({{{source_type}}})handle.Target;
+            handle.Free();
+{{{complete}}}
+        }{{/completion}}
         {{/functions}}
         #endregion
     }
@@ -622,3 +2612,287 @@ const TEMPLATE_BODY: &str = "
 const TEMPLATE_FOOTER: &str = "
 }
 ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    fn render(source: &str) -> String {
+        let mut header = parse_source(source);
+        crate::list::resolve(&mut header);
+        generate(&header, "Lib", "lib.dll")
+    }
+
+    /// Asserts that `code` contains `fragment`, ignoring differences in whitespace
+    fn assert_renders(code: &str, fragment: &str) {
+        let squash = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(
+            squash(code).contains(&squash(fragment)),
+            "`{}` not found in:\n{}",
+            fragment,
+            code
+        );
+    }
+
+    const OWNERSHIP: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\nvoid lib_free_string(char *s);\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;free_with(lib_free_string);\n * #meta_param: err;error;free_with(lib_free_string);\n */\nuint32_t Engine_describe(const struct Engine *this_, char **out, char **err);\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;borrowed;\n */\nvoid Engine_label(const struct Engine *this_, char **out);";
+
+    #[test]
+    fn documents_who_releases_outputs_and_errors() {
+        let code = render(OWNERSHIP);
+        assert_renders(
+            &code,
+            "/// <remarks><c>out</c> must be released with <see cref=\"lib_free_string\"/>, which <c>FFIInterface.PtrToString(out, lib_free_string)</c> does once it is read</remarks>",
+        );
+        assert_renders(
+            &code,
+            "/// <remarks><c>err</c> must be released with <see cref=\"lib_free_string\"/>, which <c>FFIInterface.GetErrorMessage(err, lib_free_string)</c> does once it is read</remarks>",
+        );
+        assert_renders(
+            &code,
+            "/// <remarks><c>out</c> is owned by the library and must not be freed</remarks>",
+        );
+    }
+
+    const OUTPUTS: &str = "/**\n * #meta: throws;\n * #meta_param: name;output;string;\n * #meta_param: count;output;\n * #meta_param: err;error;\n */\nuint32_t describe_lib(char **name, uint64_t *count, char **err);";
+
+    #[test]
+    fn returns_several_outputs_as_a_tuple() {
+        let code = render(OUTPUTS);
+        assert_renders(
+            &code,
+            "internal static extern uint describe_lib(out IntPtr name, out ulong count, IntPtr* err);",
+        );
+        assert_renders(
+            &code,
+            "internal static (string name, ulong count) describe_lib() { IntPtr name = IntPtr.Zero; ulong count = default; IntPtr err = IntPtr.Zero; var code = describe_lib(out name, out count, &err);",
+        );
+        assert_renders(
+            &code,
+            "if (name != IntPtr.Zero) { FFIInterface.FreeNative(name); }",
+        );
+        assert_renders(
+            &code,
+            "return (FFIInterface.PtrToString(name, FFIInterface.FreeNative), count);",
+        );
+    }
+
+    const GETTERS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\nvoid lib_free_string(char *s);\n\n/**\n * #meta: for_struct;throws;getter(name);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;free_with(lib_free_string);\n * #meta_param: err;error;free_with(lib_free_string);\n */\nuint32_t Engine_get_name(const struct Engine *this_, char **out, char **err);\n\n/**\n * #meta: for_struct;throws;getter(label);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;borrowed;\n * #meta_param: err;error;\n */\nuint32_t Engine_get_label(const struct Engine *this_, char **out, char **err);\n\n/**\n * #meta: for_struct;getter(title);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;\n */\nvoid Engine_get_title(const struct Engine *this_, char **out);";
+
+    #[test]
+    fn getters_release_strings_and_errors_as_they_are_owned() {
+        let code = render(GETTERS);
+        assert_renders(
+            &code,
+            "if (code != 0) { if (native != IntPtr.Zero) { FFIInterface.lib_free_string(native); } throw new Exception(FFIInterface.GetErrorMessage(err, FFIInterface.lib_free_string)); } return FFIInterface.PtrToString(native, FFIInterface.lib_free_string);",
+        );
+        /* a borrowed string is left alone, the message it comes with is not */
+        assert_renders(
+            &code,
+            "throw new Exception(FFIInterface.GetErrorMessage(err, FFIInterface.FreeNative)); } return FFIInterface.PtrToString(native); }",
+        );
+        assert_renders(
+            &code,
+            "FFIInterface.Engine_get_title(selfPtr, &native); return FFIInterface.PtrToString(native, FFIInterface.FreeNative);",
+        );
+        assert!(
+            !code.contains("PtrToString(native, FFIInterface.FreeString)"),
+            "{}",
+            code
+        );
+    }
+
+    const LIST_RELEASE: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\nvoid free_tokens(struct TokenResponse *items, uintptr_t items_len, uintptr_t items_cap);\n\n/**\n * #meta: throws;\n * #meta_param: items;output;list;free_with(free_tokens);\n * #meta_param: items_len;length(items);\n * #meta_param: items_cap;capacity(items);\n * #meta_param: err;error;\n */\nuint32_t get_tokens(struct TokenResponse **items, uintptr_t *items_len, uintptr_t *items_cap, char **err);";
+
+    #[test]
+    fn hands_output_lists_back_to_free_with_as_they_are_handed_out() {
+        let code = render(LIST_RELEASE);
+        assert_renders(
+            &code,
+            "internal static extern uint get_tokens(C_TokenResponse** items, ulong* items_len, ulong* items_cap, IntPtr* err);",
+        );
+        assert_renders(
+            &code,
+            "internal static extern void free_tokens(C_TokenResponse* items, ulong items_len, ulong items_cap);",
+        );
+    }
+
+    const ERROR_CODES: &str = "/**\n * #meta: error_codes;\n */\n#define LIB_OK 0\n#define LIB_FAIL 1\n#define LIB_FAIL_NULL 2\n\n/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: throws;\n * #meta_param: out;output;string;\n * #meta_param: err;error;\n */\nuint32_t lib_name(char **out, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: err;error;\n */\nuint32_t Engine_reset(struct Engine *this_, char **err);";
+
+    #[test]
+    fn throws_an_exception_class_per_error_code() {
+        let code = render(ERROR_CODES);
+        assert_renders(
+            &code,
+            "public class LibFailNullException : LibException { public LibFailNullException(string message) : base(message, 2) { } }",
+        );
+        assert_renders(&code, "case 1: return new LibFailException(message);");
+    }
+
+    const MAP: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: headers;hashmap;\n * #meta_param: header_values;values(headers);\n * #meta_param: headers_len;length(headers);\n * #meta_param: err;error;\n */\nuint32_t Engine_set_headers(const struct Engine *this_, const char *const *headers, const char *const *header_values, uintptr_t headers_len, char **err);";
+
+    #[test]
+    fn hands_maps_over_as_keys_values_and_length() {
+        let code = render(MAP);
+        assert_renders(
+            &code,
+            "internal static extern uint Engine_set_headers(IntPtr this_, IntPtr* headers, IntPtr* header_values, ulong headers_len, IntPtr* err);",
+        );
+        assert_renders(
+            &code,
+            "internal static void Dictionary2Arrays(IDictionary<string, string> dict, out IntPtr* keys, out IntPtr* values, out ulong length)",
+        );
+    }
+
+    const BYTES: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\nvoid lib_free_bytes(uint8_t *data, uintptr_t data_len);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: data;bytes;\n * #meta_param: data_len;length(data);\n * #meta_param: err;error;\n */\nuint32_t Engine_feed(const struct Engine *this_, const uint8_t *data, uintptr_t data_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;bytes;free_with(lib_free_bytes);\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_read(const struct Engine *this_, uint8_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_buffers_over_as_byte_arrays() {
+        let code = render(BYTES);
+        assert_renders(
+            &code,
+            "internal static extern uint Engine_feed(IntPtr this_, byte[] data, ulong data_len, IntPtr* err);",
+        );
+        assert_renders(
+            &code,
+            "internal static extern uint Engine_read(IntPtr this_, byte** out, ulong* out_len, IntPtr* err);",
+        );
+    }
+
+    const LISTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: scopes;list;\n * #meta_param: scopes_len;length(scopes);\n * #meta_param: err;error;\n */\nuint32_t Engine_set_scopes(const struct Engine *this_, const char *const *scopes, uintptr_t scopes_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;list;\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_ports(const struct Engine *this_, uint16_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_lists_over_as_arrays() {
+        let code = render(LISTS);
+        assert_renders(
+            &code,
+            "internal static extern uint Engine_set_scopes(IntPtr this_, [MarshalAs(UnmanagedType.LPArray, ArraySubType = UnmanagedType.LPStr)] string[] scopes, ulong scopes_len, IntPtr* err);",
+        );
+        assert_renders(
+            &code,
+            "internal static extern uint Engine_ports(IntPtr this_, ushort** out, ulong* out_len, IntPtr* err);",
+        );
+    }
+
+    const PROPERTY: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;getter(timeout);\n * #meta_param: this_;this;\n * #meta_param: out;output;duration;\n */\nvoid Engine_get_timeout(const struct Engine *this_, uint64_t *out);\n\n/**\n * #meta: for_struct;setter(timeout);\n * #meta_param: this_;this;\n * #meta_param: timeout;duration;\n */\nvoid Engine_set_timeout(struct Engine *this_, uint64_t timeout);";
+
+    #[test]
+    fn reads_and_writes_properties_through_their_accessors() {
+        let code = render(PROPERTY);
+        assert_renders(
+            &code,
+            "public TimeSpan Timeout { get { ulong native = default; FFIInterface.Engine_get_timeout(selfPtr, &native); return TimeSpan.FromMilliseconds(native); }",
+        );
+        assert_renders(
+            &code,
+            "set { ulong native = (ulong)value.TotalMilliseconds; FFIInterface.Engine_set_timeout(selfPtr, native);",
+        );
+    }
+
+    const CURSOR: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\n/**\n * #meta: persistent;iterator(TokenResponse);\n */\ntypedef struct TokenCursor TokenCursor;\n\nvoid TokenResponse_free(struct TokenResponse *this_);\n\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;free_with(TokenResponse_free);\n */\nuint8_t TokenCursor_next(struct TokenCursor *this_, struct TokenResponse **out);";
+
+    #[test]
+    fn walks_through_cursors_with_an_enumerable() {
+        let code = render(CURSOR);
+        assert_renders(
+            &code,
+            "internal IEnumerable<FFIInterface.C_TokenResponse> Items()",
+        );
+        assert_renders(
+            &code,
+            "bool hasItem = FFIInterface.TokenCursor_next(self, &itemPtr) != 0;",
+        );
+        assert_renders(&code, "() => FFIInterface.TokenResponse_free(current),");
+    }
+
+    const CANCELLABLE: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;async;cancellable(Engine_cancel);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;\n * #meta_param: cancel;cancel_handle;\n * #meta_param: err;error;\n */\nuint32_t Engine_fetch(struct Engine *this_, char **out, uint64_t cancel, char **err);\n\nvoid Engine_cancel(uint64_t cancel);";
+
+    #[test]
+    fn cancels_calls_once_their_token_is_cancelled() {
+        let code = render(CANCELLABLE);
+        assert_renders(
+            &code,
+            "internal static Task<uint> Engine_fetchAsync(Func<ulong, uint> call, CancellationToken cancellationToken)",
+        );
+        assert_renders(
+            &code,
+            "return RunCancellable(handle => call((ulong)handle), handle => Engine_cancel((ulong)handle), code => code != 0, cancellationToken);",
+        );
+    }
+
+    const COMPLETION: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;async_callback(on_done);\n * #meta_param: this_;this;\n * #meta_param: url;string;\n * #meta_param: err;error;\n */\nvoid Engine_fetch(struct Engine *this_, const char *url, void (*on_done)(void *user_data, char *body, char *err), void *user_data);";
+
+    #[test]
+    fn completes_calls_from_an_unmanaged_callback() {
+        let code = render(COMPLETION);
+        assert_renders(
+            &code,
+            "internal static Task<string> Engine_fetchAsync(IntPtr this_, IntPtr url)",
+        );
+        assert_renders(
+            &code,
+            "Engine_fetch(this_, url, &Engine_fetchDone, GCHandle.ToIntPtr(handle));",
+        );
+        /* what the library hands over is released as it allocated it */
+        assert_renders(
+            &code,
+            "var errorMessage = GetErrorMessage(err); FreeNative(err);",
+        );
+        assert_renders(
+            &code,
+            "var result = PtrToString(body); FreeNative(body); completion.SetResult(result);",
+        );
+    }
+
+    #[test]
+    fn releases_items_without_a_free_with_function_with_c_free() {
+        let code = render("typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\n/**\n * #meta: persistent;iterator(TokenResponse);\n */\ntypedef struct TokenCursor TokenCursor;\n\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;\n */\nuint8_t TokenCursor_next(struct TokenCursor *this_, struct TokenResponse **out);");
+        assert_renders(&code, "() => FFIInterface.FreeNative(current),");
+    }
+
+    const DEFAULTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;constructor;throws;\n * #meta_param: this_;output;this;\n * #meta_param: scopes;string;default(\"openid\");\n * #meta_param: extra;string;nullable;default(null);\n * #meta_param: timeout;duration;default(30000);\n * #meta_param: err;error;\n */\nuint32_t Engine_new(struct Engine **this_, const char *scopes, const char *extra, uint64_t timeout, char **err);";
+
+    #[test]
+    fn turns_parameters_with_a_default_into_optional_parameters() {
+        let code = render(DEFAULTS);
+        assert_renders(
+            &code,
+            "internal static uint Engine_new(IntPtr* this_, [Optional, DefaultParameterValue(\"openid\")] string scopes, [Optional, DefaultParameterValue(null)] string extra, [Optional, DefaultParameterValue((ulong)30000)] ulong timeout, IntPtr* err)",
+        );
+    }
+
+    const RENAMED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;rename(fetch_token);rename_csharp(Engine_get_token);\n * #meta_param: this_;this;\n */\nvoid Engine_get_token_automatic(struct Engine *this_);\n/**\n * #meta: skip_go;skip_csharp;\n */\nvoid reset_engine(void);\n/**\n * #meta: skip_dart;\n */\nvoid warm_engine(void);";
+
+    #[test]
+    fn renames_and_skips_items_for_csharp() {
+        let code = render(RENAMED);
+        assert_renders(
+            &code,
+            "[DllImport(\"lib.dll\", EntryPoint = \"Engine_get_token_automatic\")] internal static extern void Engine_get_token(IntPtr this_);",
+        );
+        assert_renders(&code, "internal static extern void warm_engine();");
+        assert!(!code.contains("reset_engine"));
+    }
+
+    const DEPRECATED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;deprecated(\"use Engine_run instead\");\n * #meta_param: this_;this;\n */\nvoid Engine_start(struct Engine *this_);";
+
+    #[test]
+    fn marks_deprecated_functions() {
+        let code = render(DEPRECATED);
+        assert_renders(
+            &code,
+            "[Obsolete(\"use Engine_run instead\")] [DllImport(\"lib.dll\")] internal static extern void Engine_start(IntPtr this_);",
+        );
+    }
+
+    const VALUE: &str = "/**\n * #meta: value;\n */\ntypedef struct TokenResponse {\n  /**\n   * #meta: string;nullable;\n   */\n  const char *refresh_token;\n  int64_t expires_at;\n} TokenResponse;";
+
+    #[test]
+    fn makes_records_of_value_types() {
+        let code = render(VALUE);
+        assert_renders(
+            &code,
+            "public sealed record TokenResponse( string? RefreshToken, long ExpiresAt )",
+        );
+    }
+}
@@ -8,10 +8,21 @@ use tera::{Context, Tera};
 
 use serde::{Serialize, Serializer};
 
+use crate::bytes::Bytes;
+use crate::cancel::Cancellation;
+use crate::completion::Completion;
+use crate::cursor::Cursor;
+use crate::default_value::{string_literal, DefaultValue};
+use crate::error_codes::{error_codes, ErrorCode};
+use crate::hashmap::Hashmap;
 use crate::lexer::{
     CEnum, CFunction, CStruct, CType, CVariableDeclaration, CVariableType, HeaderFile,
 };
+use crate::list::{Element, List};
 use crate::meta::{MetaValue, META_TOKEN};
+use crate::naming::Language;
+use crate::property::Property;
+use crate::value_type::ValueType;
 
 const C_PREFIX: &str = "C_";
 const C_FUNCTION_PREFIX: &str = "ffi_";
@@ -100,6 +111,11 @@ struct Data<'a> {
     constants: Vec<DartVariable>,
     native_free_functions: Vec<DartFunction>,
     dart_classes: Vec<DartClass>,
+    error_codes: Vec<DartErrorCode>,
+    /// Whether a function is `cancellable`, see `Cancellation`
+    has_cancellables: bool,
+    /// Whether a struct is marked `value`, see `ValueType`
+    has_value_types: bool,
     // functions: Vec<CSharpFunction>,
 }
 
@@ -109,10 +125,16 @@ impl<'a> Data<'a> {
         library_path: &'a str,
         library_name: &'a str,
         use_finalize: bool,
-        usings: Vec<&'a str>,
+        mut usings: Vec<&'a str>,
     ) -> Self {
         let enums: Vec<DartEnum> = header.enums.iter().map(DartEnum::from).collect();
 
+        let error_codes: Vec<DartErrorCode> = error_codes(header)
+            .iter()
+            .map(DartErrorCode::from)
+            .collect();
+        let has_error_codes = !error_codes.is_empty();
+
         let ffi_structs: Vec<DartFFIStruct> =
             header.structs.iter().map(DartFFIStruct::from).collect();
 
@@ -130,27 +152,54 @@ impl<'a> Data<'a> {
 
         let mut dart_classes: Vec<DartClass> = ffi_structs.iter().map(DartClass::from).collect();
 
-        let native_free_functions: Vec<DartFunction> = header
+        let mut native_free_functions: Vec<DartFunction> = header
             .functions
             .iter()
             .filter(|f| match &f.meta {
                 None => true,
                 Some(m) => !m.for_struct,
             })
-            .map(|f| DartFunction::from(f, false))
+            .filter(|f| !Cursor::is_next(header, f))
+            .flat_map(|f| {
+                if let Ok(Some(c)) = Completion::of(f, header) {
+                    let identifier =
+                        DartIdentifier::new_from_raw(Language::Dart.name_of(&f.label, &f.meta));
+                    return vec![DartFunction::completes(
+                        f,
+                        &c,
+                        None,
+                        identifier,
+                        library_name,
+                    )];
+                }
+                let mut df = DartFunction::from(f, false);
+                return_outputs_as_record(&mut df, f, library_name, has_error_codes);
+                match Cancellation::of(f, header) {
+                    Ok(Some(c)) => vec![DartFunction::cancellable(&mut df, &c), df],
+                    _ => vec![df],
+                }
+            })
             .collect();
 
-        /* Attach methods to classes */
-        for f in header.functions.iter().filter(|m| match &m.meta {
-            Some(meta) => meta.for_struct,
-            None => false,
-        }) {
+        /* Attach methods to classes, save for the `next` of cursors, which is wrapped by `items` below */
+        for f in header
+            .functions
+            .iter()
+            .filter(|m| match &m.meta {
+                Some(meta) => meta.for_struct,
+                None => false,
+            })
+            .filter(|f| !Cursor::is_next(header, f))
+        {
             let idx_of_under = f.label.find('_').unwrap();
-            let struct_name = &f.label[..idx_of_under];
-            let func_name = &f.label[idx_of_under..];
+            let struct_name = Language::Dart.struct_label(header, &f.label[..idx_of_under]);
 
             let struct_name = DartIdentifier::make_label_for_custom_type(struct_name);
-            let func_name = DartIdentifier::new(func_name, None);
+            let func_name = DartIdentifier::new(Language::Dart.method_name(f), None);
+            let is_renamed = f
+                .meta
+                .as_ref()
+                .is_some_and(|m| m.rename_for(Language::Dart).is_some());
 
             let on_class = dart_classes
                 .iter_mut()
@@ -158,16 +207,32 @@ impl<'a> Data<'a> {
                 .next()
                 .unwrap();
 
+            if let Ok(Some(c)) = Completion::of(f, header) {
+                let on_class_identifier = Some(on_class.identifier.to_owned());
+                on_class.functions.push(DartFunction::completes(
+                    f,
+                    &c,
+                    on_class_identifier,
+                    func_name,
+                    library_name,
+                ));
+                continue;
+            }
+
             /* Find Main Constructor */
             if f.meta.as_ref().map_or(false, |m| m.is_constructor) {
                 let meta = f.meta.to_owned().unwrap();
                 let df = DartFunction {
+                    error_release: release_callback(f, |m| m.is_error),
+                    output_release: release_callback(f, |m| m.is_output),
+                    accessor: None,
                     is_private: false,
                     on_class: Some(on_class.identifier.to_owned()),
-                    identifier: DartIdentifier::new_from_raw(&format!(
-                        "{}Create",
-                        func_name.dart_label
-                    )),
+                    identifier: if is_renamed {
+                        func_name
+                    } else {
+                        DartIdentifier::new_from_raw(&format!("{}Create", func_name.dart_label))
+                    },
                     c_function_name: Some(f.label.to_owned()),
                     dart_comment: f.comment.to_owned().map(DartComment::from),
                     is_void: false,
@@ -183,7 +248,8 @@ impl<'a> Data<'a> {
                             }
                             None => true,
                         })
-                        .map(|c| DartParameter::from(c, false))
+                        .filter(|param| !is_part(&f.parameters, param))
+                        .map(|c| DartParameter::from(c, false).with_default(c, f))
                         .collect(),
                     meta: Some(meta.to_owned()),
                     return_type: DartDataType::NativeType(DartNativeDataType::CustomClass(
@@ -205,6 +271,9 @@ impl<'a> Data<'a> {
                     let mut is_return_struct: bool = false;
                     // FYI(nf, 04/25/23): routine for generating Free/Dispose methods
                     let df = DartFunction {
+                        error_release: release_callback(f, |m| m.is_error),
+                        output_release: release_callback(f, |m| m.is_output),
+                        accessor: None,
                         is_private: false,
                         is_async: fmeta.is_async,
                         is_factory: false,
@@ -250,7 +319,8 @@ impl<'a> Data<'a> {
                                 }
                                 None => true,
                             })
-                            .map(|p| DartParameter::from(p, false))
+                            .filter(|p| !is_part(&f.parameters, p))
+                            .map(|p| DartParameter::from(p, false).with_default(p, f))
                             .collect(),
                         meta: f.meta.to_owned(),
                         ffi_return_type: Some(DartFFIDataType::from(&*f.return_type)),
@@ -261,7 +331,10 @@ impl<'a> Data<'a> {
                 if !fmeta.is_destructor {
                     // FYI(nf, 04/21/23): dont worry about destructors. They are handled with _finalizer attachements
                     let mut is_return_struct: bool = false;
-                    let df = DartFunction {
+                    let mut df = DartFunction {
+                        error_release: release_callback(f, |m| m.is_error),
+                        output_release: release_callback(f, |m| m.is_output),
+                        accessor: None,
                         is_private: false,
                         is_async: fmeta.is_async,
                         is_factory: false,
@@ -272,7 +345,10 @@ impl<'a> Data<'a> {
                         c_function_name: Some(f.label.to_owned()),
                         dart_comment: f.comment.to_owned().map(DartComment::from),
                         on_class: Some(on_class.identifier.to_owned()),
-                        identifier: func_name,
+                        identifier: match Property::of(f) {
+                            Some((name, _)) => DartIdentifier::new(name, None),
+                            None => func_name,
+                        },
                         return_type: if fmeta.is_void {
                             DartDataType::NativeType(DartNativeDataType::Void)
                         } else {
@@ -307,17 +383,82 @@ impl<'a> Data<'a> {
                                 }
                                 None => true,
                             })
-                            .map(|p| DartParameter::from(p, false))
+                            .filter(|p| !is_part(&f.parameters, p))
+                            .map(|p| DartParameter::from(p, false).with_default(p, f))
                             .collect(),
                         meta: f.meta.to_owned(),
                         ffi_return_type: Some(DartFFIDataType::from(&*f.return_type)),
                         body: None,
                     };
+                    return_outputs_as_record(&mut df, f, library_name, has_error_codes);
+                    if let Some((_, is_getter)) = Property::of(f) {
+                        df.accessor = Some(if is_getter { "get" } else { "set" });
+                        /* a setter hands nothing back, whatever the C function returns */
+                        if !is_getter {
+                            df.is_void = true;
+                            df.output_requires_pointer = false;
+                            df.return_type = DartDataType::NativeType(DartNativeDataType::Void);
+                        }
+                    }
+                    if let Ok(Some(c)) = Cancellation::of(f, header) {
+                        on_class
+                            .functions
+                            .push(DartFunction::cancellable(&mut df, &c));
+                    }
                     on_class.functions.push(df);
                 }
             }
         }
 
+        for cursor in Cursor::all(header) {
+            let owner = DartIdentifier::make_label_for_custom_type(
+                Language::Dart.struct_name(cursor.owner),
+            );
+            if let Some(on_class) = dart_classes
+                .iter_mut()
+                .find(|dc| dc.identifier.dart_label == owner)
+            {
+                on_class
+                    .functions
+                    .push(DartFunction::items(&cursor, on_class));
+            }
+        }
+
+        let value_types = ValueType::all(header);
+        for v in &value_types {
+            let name =
+                DartIdentifier::make_label_for_custom_type(Language::Dart.struct_name(v.owner));
+            if let Some(class) = dart_classes
+                .iter_mut()
+                .find(|dc| dc.identifier.dart_label == name && !dc.is_opaque)
+            {
+                class.value = Some(DartValueType::from(class.fields.as_slice()));
+            }
+        }
+
+        /* Methods and free functions alike are deprecated along with the C function they call */
+        for df in dart_classes
+            .iter_mut()
+            .flat_map(|c| c.functions.iter_mut())
+            .chain(native_free_functions.iter_mut())
+        {
+            let deprecated = header
+                .functions
+                .iter()
+                .find(|f| df.c_function_name.as_ref() == Some(&f.label))
+                .and_then(|f| deprecation_annotation(&f.deprecated));
+            df.annotations.extend(deprecated);
+        }
+
+        if !Completion::all(header).is_empty() {
+            usings.push("'dart:async' show Completer");
+        }
+        let has_cancellables = !Cancellation::all(header).is_empty();
+        if has_cancellables {
+            usings.push("'dart:async' show TimeoutException");
+            usings.push("'package:async/async.dart' show CancelableOperation");
+        }
+
         Data {
             library_path,
             library_name,
@@ -329,6 +470,9 @@ impl<'a> Data<'a> {
             ffi_functions,
             native_free_functions,
             dart_classes,
+            error_codes,
+            has_cancellables,
+            has_value_types: !value_types.is_empty(),
         }
     }
 
@@ -339,8 +483,12 @@ impl<'a> Data<'a> {
             &DataDartInformation {
                 library_name: self.library_name.to_owned(),
                 library_path: self.library_path.to_owned(),
+                has_error_codes: !self.error_codes.is_empty(),
+                has_cancellables: self.has_cancellables,
+                has_value_types: self.has_value_types,
             },
         );
+        context.insert("error_codes", &self.error_codes);
         context.insert("usings", &self.usings);
         context.insert("enums", &self.enums);
         context.insert("ffi_structs", &self.ffi_structs);
@@ -371,6 +519,12 @@ impl<'a> Serialize for _SerdeVecDartIdentifier<'a> {
 struct DataDartInformation {
     library_path: String,
     library_name: String,
+    /// Whether failure codes are thrown as their own exception types, see `error_codes`
+    has_error_codes: bool,
+    /// Whether a function is `cancellable`, which needs the cancel handle helpers
+    has_cancellables: bool,
+    /// Whether a class is a value, which needs the `_valueEquals` and `_valueHash` helpers
+    has_value_types: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -426,6 +580,22 @@ impl From<String> for DartComment {
     }
 }
 
+/// The annotation that marks an item deprecated for `reason`, e.g., `@Deprecated('use fetchToken instead')`,
+/// or `@deprecated` if no reason is given
+fn deprecation_annotation(reason: &Option<String>) -> Option<String> {
+    match reason.as_deref() {
+        None => None,
+        Some("") => Some("@deprecated".to_owned()),
+        Some(r) => Some(format!(
+            "@Deprecated('{}')",
+            r.replace('\\', "\\\\")
+                .replace('\'', "\\'")
+                .replace('$', "\\$")
+                .replace('\n', "\\n")
+        )),
+    }
+}
+
 #[derive(Debug, Clone)]
 struct DartIdentifier {
     dart_label: String,
@@ -587,7 +757,13 @@ impl From<&CEnum> for DartEnum {
             .declarations
             .iter()
             .enumerate()
-            .map(|(idx, ident)| DartEnumOption::new(&ident.label, ident.comment.to_owned(), idx))
+            .map(|(idx, ident)| {
+                let label = Language::Dart.member_name(ident);
+                DartEnumOption {
+                    annotation: deprecation_annotation(&ident.deprecated),
+                    ..DartEnumOption::new(&label, ident.comment.to_owned(), idx)
+                }
+            })
             .collect();
 
         DartEnum {
@@ -606,6 +782,8 @@ struct DartEnumOption {
     /// e.g., 'S256'
     label: String,
     dart_comment: Option<DartComment>,
+    /// e.g., `@Deprecated('use S256')`
+    annotation: Option<String>,
 
     /// To traverse across C, can only be integers
     value: usize,
@@ -615,11 +793,30 @@ impl DartEnumOption {
         DartEnumOption {
             label: label.to_owned(),
             dart_comment: comment.map(DartComment::from),
+            annotation: None,
             value,
         }
     }
 }
 
+/// A failure code of the library, thrown as its own subclass of the library's exception
+#[derive(Clone, Serialize, Debug)]
+struct DartErrorCode {
+    /// e.g., 'OauthtoolFailNullPointerException'
+    class_name: String,
+    value: i32,
+    dart_comment: Option<DartComment>,
+}
+impl From<&ErrorCode> for DartErrorCode {
+    fn from(e: &ErrorCode) -> Self {
+        DartErrorCode {
+            class_name: format!("{}Exception", e.name),
+            value: e.value,
+            dart_comment: e.comment.to_owned().map(DartComment::from),
+        }
+    }
+}
+
 /// A field, as in a member of a class
 #[derive(Serialize, Clone, Debug)]
 struct DartField {
@@ -652,6 +849,14 @@ struct DartField {
 
     reads_capacity_from: Option<String>,
     reads_length_from: Option<String>,
+    /// For a `hashmap` field, the field holding the values of the map
+    reads_values_from: Option<String>,
+    /// For a `list` field, what its elements are
+    list_of: Option<DartList>,
+    /// For a `list` field, the expression that copies it out of the struct `c`, once its length is known
+    reads_list: Option<String>,
+    /// Why the field of the C struct is deprecated, if it is, which marks the field of the class
+    deprecated: Option<String>,
 
     /// Optionally in-line defined text for an immediate assignment
     assign_statement: Option<String>,
@@ -668,14 +873,25 @@ impl DartField {
             is_private: self.is_private,
             identifier: DartIdentifier::new_from_raw(&self.identifier.dart_label),
             comment: self.comment.to_owned(),
-            annotations: vec![],
+            annotations: deprecation_annotation(&self.deprecated)
+                .into_iter()
+                .collect(),
             modifiers: vec!["final".to_owned()],
-            kind: self.kind.to_upper_type(self.meta.as_ref()),
+            kind: match &self.list_of {
+                Some(list) => DartDataType::NativeType(DartNativeDataType::List {
+                    sub_type: Box::new(list.kind.to_owned()),
+                }),
+                None => self.kind.to_upper_type(self.meta.as_ref()),
+            },
             meta: self.meta.to_owned(),
             assign_statement: None,
             reads_length_from: None,
             reads_capacity_from: None,
+            reads_values_from: None,
+            list_of: self.list_of.to_owned(),
+            reads_list: None,
             as_primitive_kind: self.as_primitive_kind.to_owned(),
+            deprecated: self.deprecated.to_owned(),
         }
     }
 }
@@ -696,6 +912,15 @@ struct DartParameter {
     /// Whether this item needs to be wrapped in an additional Pointer<> container
     requires_pointer: bool,
 
+    /// Whether this item is a `hashmap`, handed to C as its keys, values and length
+    is_hashmap: bool,
+
+    /// Whether this item is a buffer of `bytes`, handed to C as its data and length
+    is_bytes: bool,
+
+    /// For a `list`, the expression that copies it into an `_FFIList`, handed to C as its data and length
+    list_to_ffi: Option<String>,
+
     /// The Dart FFI type that backs this type
     ffi_kind: DartFFIDataType,
 
@@ -720,24 +945,59 @@ struct DartParameter {
 impl DartParameter {
     fn from(c: &CVariableDeclaration, as_ffi: bool) -> Self {
         let identifier = DartIdentifier::new_from_raw(&c.label);
-        let kind = DartDataType::from_meta(&c.label, &c.variable_type, &c.meta, as_ffi);
+        let is_list = c.meta.as_ref().is_some_and(|m| m.is_list);
+        let element = Element::of(c).filter(|_| is_list && !as_ffi);
+        let kind = if element.is_some() {
+            DartDataType::NativeType(DartNativeDataType::from(c))
+        } else {
+            DartDataType::from_meta(&c.label, &c.variable_type, &c.meta, as_ffi)
+        };
+        let is_hashmap = !as_ffi && c.meta.as_ref().is_some_and(|m| m.is_hashmap);
+        let is_bytes = !as_ffi && c.meta.as_ref().is_some_and(|m| m.is_bytes);
+        let list_to_ffi = element
+            .filter(|_| !c.meta.as_ref().is_some_and(|m| m.is_output))
+            .and_then(|e| DartList::from(&e).to_ffi(&identifier.dart_label));
         DartParameter {
             is_nullable: c.meta.as_ref().map_or(false, |f| f.is_nullable),
             is_required: false,
             is_persistent: c.variable_type.is_struct,
             ffi_kind: DartFFIDataType::from(c),
-            as_ffi_value: Some(if matches!(c.variable_type.kind, CType::Enum(_)) {
-                format!("{}.getValueAsInt", identifier.dart_label)
-            } else {
-                identifier.clone().dart_label
-            }),
-            requires_pointer: kind.requires_pointer(),
+            as_ffi_value: Some(
+                if matches!(c.variable_type.kind, CType::Enum(_)) && !is_list {
+                    format!("{}.getValueAsInt", identifier.dart_label)
+                } else {
+                    identifier.clone().dart_label
+                },
+            ),
+            requires_pointer: !is_hashmap && list_to_ffi.is_none() && kind.requires_pointer(),
+            is_hashmap,
+            is_bytes,
+            list_to_ffi,
             as_primitive_kind: kind.to_primitive(),
             identifier,
             kind,
             default_value: None,
         }
     }
+
+    /// Makes this an optional named parameter, if `p` of `f` has a default, see `DefaultValue`
+    fn with_default(mut self, p: &CVariableDeclaration, f: &CFunction) -> Self {
+        self.default_value = match DefaultValue::of(p, f) {
+            Ok(Some(DefaultValue::Null)) => Some(DartValue::new("null")),
+            Ok(Some(DefaultValue::Str(s))) => {
+                Some(DartValue::new(&string_literal(s).replace('$', "\\$")))
+            }
+            Ok(Some(DefaultValue::Number(n))) if p.meta.as_ref().is_some_and(|m| m.is_duration) => {
+                Some(DartValue::new(&format!(
+                    "const Duration(milliseconds: {})",
+                    n
+                )))
+            }
+            Ok(Some(DefaultValue::Number(n))) => Some(DartValue::new(n)),
+            _ => None,
+        };
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -771,7 +1031,7 @@ impl From<&CType> for DartValue {
             )),
             CType::Struct(v) => DartValue::new(&format!(
                 "{}()",
-                &DartIdentifier::make_label_for_custom_type(v.identifier.label.as_str())
+                &DartIdentifier::make_label_for_custom_type(Language::Dart.struct_name(v))
             )),
 
             CType::Include(_) | CType::UNINITIALIZED => {
@@ -867,6 +1127,8 @@ struct DartClass {
     is_private: bool,
     identifier: DartIdentifier,
     backing_ffi_struct: Option<DartIdentifier>,
+    /// Whether the class keeps the pointer it was created from, rather than a copy of the struct's fields
+    is_opaque: bool,
     fields: Vec<DartField>,
     functions: Vec<DartFunction>,
     constructors: Vec<DartConstructor>,
@@ -875,6 +1137,10 @@ struct DartClass {
     /// Implements interfaces
     implements: Vec<String>,
     meta: Option<MetaValue>,
+    /// e.g., `@Deprecated('use Token instead')`
+    annotations: Vec<String>,
+    /// Set for a class whose struct is marked `value`
+    value: Option<DartValueType>,
 }
 
 impl From<&DartFFIStruct> for DartClass {
@@ -907,6 +1173,12 @@ impl From<&DartFFIStruct> for DartClass {
                     }) {
                         length_item.reads_length_from =
                             Some(field.identifier.dart_label.to_owned());
+                        length_item.reads_list = length_item.list_of.as_ref().map(|list| {
+                            list.read(
+                                &format!("c.{}", length_item.identifier.dart_label),
+                                &format!("c.{}", field.identifier.dart_label),
+                            )
+                        });
                     }
                 } else if let Some(capacity_for_label) = &field_meta.capacity_for {
                     if let Some(capacity_item) = fields.iter_mut().find(|field| {
@@ -916,10 +1188,31 @@ impl From<&DartFFIStruct> for DartClass {
                         capacity_item.reads_capacity_from =
                             Some(field.identifier.dart_label.to_owned());
                     }
+                } else if let Some(values_for_label) = &field_meta.values_for {
+                    if let Some(keys_item) = fields.iter_mut().find(|field| {
+                        field.identifier.dart_label
+                            == DartIdentifier::new_from_raw(values_for_label).dart_label
+                    }) {
+                        keys_item.reads_values_from = Some(field.identifier.dart_label.to_owned());
+                    }
                 }
             }
         }
 
+        /* The values and length of a map are read along with its keys, and are not fields of their own */
+        let map_parts: Vec<String> = fields
+            .iter()
+            .filter(|field| field.reads_values_from.is_some())
+            .flat_map(|field| {
+                [
+                    field.reads_values_from.clone(),
+                    field.reads_length_from.clone(),
+                ]
+            })
+            .flatten()
+            .collect();
+        fields.retain(|field| !map_parts.contains(&field.identifier.dart_label));
+
         if f.is_opaque {
             /* _fromCPtr */
             constructors.push(DartConstructor {
@@ -954,6 +1247,9 @@ impl From<&DartFFIStruct> for DartClass {
         let mut functions: Vec<DartFunction> = vec![
             /* fromCPointer */
             DartFunction {
+                error_release: None,
+                output_release: None,
+                accessor: None,
                 is_private: false,
                 modifiers: vec![DART_FACTORY_KEYWORD.to_owned()],
                 annotations: vec![],
@@ -985,6 +1281,9 @@ impl From<&DartFFIStruct> for DartClass {
                         is_persistent: false,
                         default_value: None,
                         requires_pointer: false,
+                        is_hashmap: false,
+                        is_bytes: false,
+                        list_to_ffi: None,
                         identifier: DartIdentifier::new_from_raw("voidPtr"),
                         as_ffi_value: None,
                         as_primitive_kind: dkind.to_primitive(),
@@ -1014,6 +1313,9 @@ impl From<&DartFFIStruct> for DartClass {
             },
             /* fromCPointerPointer */
             DartFunction {
+                error_release: None,
+                output_release: None,
+                accessor: None,
                 is_private: false,
                 modifiers: vec![DART_FACTORY_KEYWORD.to_owned()],
                 annotations: vec![],
@@ -1047,6 +1349,9 @@ impl From<&DartFFIStruct> for DartClass {
                         is_persistent: false,
                         default_value: None,
                         requires_pointer: false,
+                        is_hashmap: false,
+                        is_bytes: false,
+                        list_to_ffi: None,
                         identifier: DartIdentifier::new_from_raw("voidPtr"),
                         as_ffi_value: None,
                         as_primitive_kind: dkind.to_primitive(),
@@ -1078,6 +1383,9 @@ impl From<&DartFFIStruct> for DartClass {
         /* fromCStruct */
         if !f.is_opaque {
             functions.push(DartFunction {
+                error_release: None,
+                output_release: None,
+                accessor: None,
                 is_private: false,
                 modifiers: vec![DART_FACTORY_KEYWORD.to_owned()],
                 c_function_name: None,
@@ -1107,6 +1415,9 @@ impl From<&DartFFIStruct> for DartClass {
                         as_ffi_value: None,
                         default_value: None,
                         requires_pointer: false,
+                        is_hashmap: false,
+                        is_bytes: false,
+                        list_to_ffi: None,
                         as_primitive_kind: ddt.to_owned(),
                         kind: ddt,
                         identifier: DartIdentifier::new_from_raw(C_PARAMETER_NAME),
@@ -1140,7 +1451,11 @@ impl From<&DartFFIStruct> for DartClass {
                         kind: dkind,
                         assign_statement: None,
                         reads_capacity_from: None,
+                        reads_values_from: None,
+                        list_of: None,
+                        reads_list: None,
                         reads_length_from: None,
+                        deprecated: None,
                     };
 
                     fields.push(_self_ptr);
@@ -1150,6 +1465,9 @@ impl From<&DartFFIStruct> for DartClass {
                 implements.push("_IWithPtr".to_owned());
 
                 functions.push(DartFunction {
+                    error_release: None,
+                    output_release: None,
+                    accessor: None,
                     is_private: true,
                     on_class: Some(class_identifier.to_owned()),
                     identifier: DartIdentifier::new_from_raw("getPointer"),
@@ -1181,6 +1499,9 @@ impl From<&DartFFIStruct> for DartClass {
                     is_nullable: false,
                     is_private: true,
                     reads_capacity_from: None,
+                        reads_values_from: None,
+                        list_of: None,
+                        reads_list: None,
                     reads_length_from: None,
                     identifier: DartIdentifier::new_from_raw("finalizer"),
                     comment: Some(DartComment::from_raw("/// Pointer to the backing `free` function which disposes the backing pointer when this dart object is GC'd")),
@@ -1194,6 +1515,7 @@ impl From<&DartFFIStruct> for DartClass {
                         meta_value.is_static = true;
                         meta_value
                     }),
+                    deprecated: None,
                 })
                 }
             }
@@ -1203,6 +1525,7 @@ impl From<&DartFFIStruct> for DartClass {
             is_private: false,
             identifier: class_identifier,
             backing_ffi_struct: Some(backing_struct_identifier),
+            is_opaque: f.is_opaque,
             constructors,
             extends,
             implements,
@@ -1210,6 +1533,74 @@ impl From<&DartFFIStruct> for DartClass {
             /// appended to at a later stage, after all the functions have been parsed
             functions,
             meta: f.meta.to_owned(),
+            annotations: deprecation_annotation(&f.deprecated).into_iter().collect(),
+            value: None, // is re-assessed later on
+        }
+    }
+}
+
+/// `==`, `hashCode`, `toString` and `copyWith` of a class whose struct is marked `value`, see `ValueType`.
+///
+/// Lists and maps are compared and hashed by their elements with `_valueEquals` and `_valueHash`.
+/// `copyWith` tells a nullable field that is left out from one set to null with `_valueUnset`
+#[derive(Serialize, Clone, Debug)]
+struct DartValueType {
+    fields: Vec<DartValueField>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct DartValueField {
+    /// Name of the field, e.g., `refreshToken`
+    label: String,
+    /// Name of the parameter of `copyWith`, which is the field's without its leading underscore
+    parameter: String,
+    /// e.g., `List<String>`
+    kind: String,
+    is_nullable: bool,
+    /// Whether the value is compared and hashed by this field, see `ValueType::compares`
+    is_compared: bool,
+    /// e.g., `refreshToken == other.refreshToken`
+    equals: String,
+    /// e.g., `_valueHash(scopes)`
+    hash: String,
+}
+
+impl From<&[DartField]> for DartValueType {
+    fn from(fields: &[DartField]) -> Self {
+        DartValueType {
+            fields: fields
+                .iter()
+                .map(|field| {
+                    let parameter = field.identifier.dart_label.to_owned();
+                    let label = match field.is_private {
+                        true => format!("_{}", parameter),
+                        false => parameter.to_owned(),
+                    };
+                    let has_elements = matches!(
+                        field.kind,
+                        DartDataType::NativeType(
+                            DartNativeDataType::List { .. }
+                                | DartNativeDataType::Map { .. }
+                                | DartNativeDataType::Bytes
+                        )
+                    );
+                    DartValueField {
+                        equals: match has_elements {
+                            true => format!("_valueEquals({0}, other.{0})", label),
+                            false => format!("{0} == other.{0}", label),
+                        },
+                        hash: match has_elements {
+                            true => format!("_valueHash({})", label),
+                            false => label.to_owned(),
+                        },
+                        is_compared: ValueType::compares(field.meta.as_ref()),
+                        kind: field.kind.to_string(),
+                        is_nullable: field.is_nullable,
+                        parameter,
+                        label,
+                    }
+                })
+                .collect(),
         }
     }
 }
@@ -1265,9 +1656,343 @@ struct DartFunction {
 
     /// Optional concrete implenetation of this method
     body: Option<String>,
+
+    /// Dart callback that releases the error string, when it is not freed with `calloc`
+    error_release: Option<String>,
+
+    /// Dart callback that releases the output value, when it is not freed with `calloc`
+    output_release: Option<String>,
+
+    /// `get` or `set`, when this method reads or writes a property of its class, see `Property`
+    accessor: Option<&'static str>,
 }
 
 impl DartFunction {
+    /// `Iterable<Item> items()`, which calls `next` as the items are asked for, see `Cursor`.
+    /// Each item is copied and released, unless it is `persistent`, and the cursor is released once it runs out
+    fn items(cursor: &Cursor, on_class: &DartClass) -> Self {
+        let item =
+            DartIdentifier::make_label_for_custom_type(Language::Dart.struct_name(cursor.item));
+        let release_item = if cursor.is_item_persistent() {
+            None
+        } else {
+            Some(match cursor.item_release() {
+                Some(f) => format!("{}{}(itemPtr);", C_FUNCTION_PREFIX, f),
+                None => "calloc.free(itemPtr);".to_owned(),
+            })
+        };
+        let release_cursor = match cursor.destructor {
+            Some(f) => format!(
+                "
+                    {}{}(_selfPtr);
+                    _selfPtr = ffi.nullptr;",
+                C_FUNCTION_PREFIX, f.label
+            ),
+            None => "".to_owned(),
+        };
+        let body = format!(
+            "
+        /* A generator of its own, so that nothing is read until the items are asked for */
+        Iterable<{item}> generate() sync* {{
+            while (_selfPtr.address != ffi.nullptr.address) {{
+                final cItemPtr = calloc<ffi.Pointer<{c_prefix}{c_item}>>();
+                final hasItem = {ffi}{next}(_selfPtr, cItemPtr) != 0;
+                final itemPtr = cItemPtr.value;
+                calloc.free(cItemPtr);
+                if (!hasItem) {{{release_cursor}
+                    return;
+                }}
+                final item = {item}._fromCPointer(itemPtr.cast());{release_item}
+                yield item;
+            }}
+        }}
+        return generate();",
+            item = item,
+            c_prefix = C_PREFIX,
+            c_item = cursor.item.identifier.label,
+            ffi = C_FUNCTION_PREFIX,
+            next = cursor.next.label,
+            release_cursor = release_cursor,
+            release_item = release_item
+                .map(|r| format!("\n                {}", r))
+                .unwrap_or_default(),
+        );
+        DartFunction {
+            is_private: false,
+            on_class: Some(on_class.identifier.to_owned()),
+            identifier: DartIdentifier::new_from_raw("items"),
+            c_function_name: Some(cursor.next.label.to_owned()),
+            dart_comment: Some(DartComment::from_raw(&format!(
+                "/// Iterates over the items handed out by `{}`, releasing the cursor once they run out",
+                cursor.next.label
+            ))),
+            is_void: false,
+            throws: false,
+            output_requires_pointer: false,
+            is_async: false,
+            parameters: vec![],
+            meta: None,
+            return_type: DartDataType::NativeType(DartNativeDataType::Iterable {
+                sub_type: Box::new(DartNativeDataType::CustomClass(item)),
+            }),
+            ffi_return_type: None,
+            is_return_struct: false,
+            requires_ffi_function_pointers: false,
+            annotations: vec![],
+            modifiers: vec![],
+            is_factory: false,
+            body: Some(body),
+            error_release: None,
+            output_release: None,
+            accessor: None,
+        }
+    }
+
+    /// The public face of a `cancellable` function, which hands `inner` a new cancel handle, and returns a
+    /// `CancelableOperation` whose `cancel()` hands the same handle to the cancel function, see `Cancellation`.
+    /// `inner` is made private, as it is only called through this
+    fn cancellable(inner: &mut DartFunction, cancellation: &Cancellation) -> Self {
+        inner.is_private = true;
+        let handle = DartParameter::from(cancellation.handle, false)
+            .identifier
+            .dart_label;
+        let arguments: Vec<String> = inner
+            .parameters
+            .iter()
+            .map(|p| match p.identifier.dart_label == handle {
+                true => "cancelHandle".to_owned(),
+                false => p.identifier.dart_label.to_owned(),
+            })
+            .collect();
+        let body = format!(
+            "
+        final cancelHandle = _nextCancelHandle();
+        return CancelableOperation.fromFuture(
+            _{}({}),
+            onCancel: () => {}{}(cancelHandle),
+        );",
+            inner.identifier.dart_label,
+            arguments.join(", "),
+            C_FUNCTION_PREFIX,
+            cancellation.cancel.label
+        );
+        DartFunction {
+            is_private: false,
+            on_class: inner.on_class.to_owned(),
+            identifier: inner.identifier.to_owned(),
+            c_function_name: inner.c_function_name.to_owned(),
+            dart_comment: inner.dart_comment.take(),
+            is_void: false,
+            throws: false,
+            output_requires_pointer: false,
+            is_async: false,
+            parameters: inner
+                .parameters
+                .iter()
+                .filter(|p| p.identifier.dart_label != handle)
+                .cloned()
+                .collect(),
+            meta: None,
+            return_type: DartDataType::NativeType(DartNativeDataType::CancelableOperation {
+                sub_type: Box::new(if inner.is_void {
+                    DartDataType::NativeType(DartNativeDataType::Void)
+                } else {
+                    inner.return_type.to_owned()
+                }),
+            }),
+            ffi_return_type: None,
+            is_return_struct: false,
+            requires_ffi_function_pointers: false,
+            annotations: vec![],
+            modifiers: vec![],
+            is_factory: false,
+            body: Some(body),
+            error_release: None,
+            output_release: None,
+            accessor: None,
+        }
+    }
+
+    /// A function that hands its result to a callback, see `Completion`, which returns a `Future` completed by a
+    /// `NativeCallable.listener` handed to C as the callback. The listener is closed once it is called,
+    /// and copies the result and error into Dart before releasing them
+    fn completes(
+        c: &CFunction,
+        completion: &Completion,
+        on_class: Option<DartIdentifier>,
+        identifier: DartIdentifier,
+        library_name: &str,
+    ) -> Self {
+        let result_type = match &completion.result_element {
+            Some(e) => DartList::from(e).kind,
+            None => DartNativeDataType::Void,
+        };
+        let mut body: Vec<String> = vec![];
+        if on_class.is_some() {
+            body.push("if (this._selfPtr.address == ffi.nullptr.address) {".to_owned());
+            body.push(format!(
+                "    throw {}Exception('Cannot call function. This object has been reclaimed by the system', -10);",
+                library_name
+            ));
+            body.push("}".to_owned());
+        }
+
+        let callback_type = DartFFIDataType::Function {
+            return_type: Box::new(DartFFIDataType::Void),
+            parameters: completion
+                .signature
+                .parameters
+                .iter()
+                .map(DartFFIDataType::from)
+                .collect(),
+        };
+        let label_of = |p: &CVariableDeclaration| DartIdentifier::new_from_raw(&p.label).dart_label;
+        let listener_parameters: Vec<String> = completion
+            .signature
+            .parameters
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} {}",
+                    DartDataType::from(&p.variable_type).to_primitive(),
+                    label_of(p)
+                )
+            })
+            .collect();
+        body.push(format!("final completer = Completer<{}>();", result_type));
+        body.push(format!(
+            "late final ffi.NativeCallable<{}> onDone;",
+            callback_type
+        ));
+        body.push(format!(
+            "onDone = ffi.NativeCallable<{}>.listener(({}) {{",
+            callback_type,
+            listener_parameters.join(", ")
+        ));
+        body.push("    onDone.close();".to_owned());
+        if let Some(error) = completion.error {
+            let release = error
+                .meta
+                .as_ref()
+                .and_then(param_release)
+                .map(|r| format!(", release: {}", r))
+                .unwrap_or_default();
+            body.push(format!(
+                "    if ({}.address != ffi.nullptr.address) {{",
+                label_of(error)
+            ));
+            body.push(format!(
+                "        completer.completeError({}Exception(_getDartStringFromPtr({}{}), -1));",
+                library_name,
+                label_of(error),
+                release
+            ));
+            body.push("        return;".to_owned());
+            body.push("    }".to_owned());
+        }
+        match (completion.result, &completion.result_element) {
+            (Some(result), Some(element)) => {
+                let label = label_of(result);
+                let read = match element {
+                    Element::String => format!(
+                        "_getDartStringFromPtr({}{})",
+                        label,
+                        result
+                            .meta
+                            .as_ref()
+                            .and_then(param_release)
+                            .map(|r| format!(", release: {}", r))
+                            .unwrap_or_default()
+                    ),
+                    _ => DartList::from(element)
+                        .read_element
+                        .replace("data[i]", &label),
+                };
+                body.push(format!("    final result = {};", read));
+                if matches!(element, Element::StructPointer(_))
+                    && !completion.is_result_persistent()
+                    && !completion.is_result_borrowed()
+                {
+                    body.push(match completion.result_release() {
+                        Some(f) => format!("    {}{}({});", C_FUNCTION_PREFIX, f, label),
+                        None => format!("    calloc.free({});", label),
+                    });
+                }
+                body.push("    completer.complete(result);".to_owned());
+            }
+            _ => body.push("    completer.complete();".to_owned()),
+        }
+        body.push("});".to_owned());
+
+        let mut arguments: Vec<String> = vec![];
+        let mut input_frees: Vec<String> = vec![];
+        let lists = List::all(&c.parameters);
+        for p in &c.parameters {
+            let label = label_of(p);
+            if p.meta.as_ref().is_some_and(|m| m.is_this) {
+                arguments.push("this._selfPtr".to_owned());
+            } else if p.label == completion.callback.label {
+                arguments.push("onDone.nativeFunction".to_owned());
+            } else if p.label == completion.user_data.label {
+                arguments.push("ffi.nullptr".to_owned());
+            } else if let Some(l) = lists
+                .iter()
+                .find(|l| l.length.is_some_and(|len| len.label == p.label))
+            {
+                arguments.push(format!(
+                    "c{}List.length",
+                    DartIdentifier::new_from_raw(&l.items.label).dart_label
+                ));
+            } else if is_part(&c.parameters, p) {
+                /* handed in along with the keys, or the buffer */
+            } else {
+                hand_in(p, &label, &mut body, &mut arguments, &mut input_frees);
+            }
+        }
+        body.push("/* call native function, which returns at once */".to_owned());
+        body.push(format!("{}{}(", C_FUNCTION_PREFIX, c.label));
+        body.extend(arguments.iter().map(|a| format!("    {},", a)));
+        body.push(");".to_owned());
+        if !input_frees.is_empty() {
+            body.push("/* Free allocated pointers */".to_owned());
+            body.extend(input_frees);
+        }
+        body.push("return completer.future;".to_owned());
+
+        DartFunction {
+            is_private: false,
+            on_class,
+            identifier,
+            c_function_name: Some(c.label.to_owned()),
+            dart_comment: c.comment.to_owned().map(DartComment::from),
+            is_void: false,
+            throws: false,
+            output_requires_pointer: false,
+            is_async: false,
+            parameters: c
+                .parameters
+                .iter()
+                .filter(|p| !p.meta.as_ref().is_some_and(|m| m.is_this))
+                .filter(|p| !completion.is_part(p) && !is_part(&c.parameters, p))
+                .map(|p| DartParameter::from(p, false).with_default(p, c))
+                .collect(),
+            meta: None,
+            return_type: DartDataType::NativeType(DartNativeDataType::Future {
+                sub_type: Box::new(DartDataType::NativeType(result_type)),
+            }),
+            ffi_return_type: None,
+            is_return_struct: false,
+            requires_ffi_function_pointers: false,
+            annotations: vec![],
+            modifiers: vec![],
+            is_factory: false,
+            body: Some(body.join("\n    ")),
+            error_release: None,
+            output_release: None,
+            accessor: None,
+        }
+    }
+
     fn from(c: &CFunction, as_ffi: bool) -> Self {
         let mut return_type = DartDataType::from(&*c.return_type);
 
@@ -1276,6 +2001,9 @@ impl DartFunction {
         let mut requires_pointer: bool = false;
 
         DartFunction {
+            error_release: release_callback(c, |m| m.is_error),
+            output_release: release_callback(c, |m| m.is_output),
+            accessor: None,
             is_private: false,
             on_class: None,
             is_factory: false,
@@ -1284,7 +2012,7 @@ impl DartFunction {
                 if as_ffi {
                     DartIdentifier::new_from_raw(&format!("{}{}", C_FUNCTION_PREFIX, label))
                 } else {
-                    DartIdentifier::new_from_raw(label)
+                    DartIdentifier::new_from_raw(Language::Dart.name_of(label, &c.meta))
                 }
             },
             c_function_name: Some(c.label.to_owned()),
@@ -1305,7 +2033,8 @@ impl DartFunction {
                             Some(m) => !(m.is_output || m.is_error),
                             None => true,
                         })
-                        .map(|p| DartParameter::from(p, false))
+                        .filter(|p| !is_part(&c.parameters, p))
+                        .map(|p| DartParameter::from(p, false).with_default(p, c))
                         .collect();
 
                     if c.parameters
@@ -1379,6 +2108,526 @@ impl DartFunction {
     }
 }
 
+/// Whether `p` is marshalled along with another of `parameters`, i.e., the values or length of a map,
+/// or the length of a buffer or list
+fn is_part(parameters: &[CVariableDeclaration], p: &CVariableDeclaration) -> bool {
+    Hashmap::is_part(parameters, p) || Bytes::is_part(parameters, p) || List::is_part(parameters, p)
+}
+
+/// How the elements of a `list` are read out of, and written into, a native array, see `Element`
+#[derive(Serialize, Clone, Debug)]
+struct DartList {
+    /// The Dart type of each element, e.g., `TokenResponse`
+    kind: DartNativeDataType,
+    /// The FFI type of each element in the array, e.g., `C_TokenResponse`
+    ffi_kind: DartFFIDataType,
+    /// Reads element `i` of the array `data`, leaving it to the owner of the array
+    read_element: String,
+    /// Reads element `i` of the array `data`, and frees what it points to, for arrays the caller owns
+    take_element: String,
+    /// Converts the Dart element in place of `{}` for the array, if Dart can hand such elements to C
+    write_element: Option<String>,
+    /// Whether what `write_element` allocates is freed along with the array
+    frees_elements: bool,
+}
+
+impl DartList {
+    fn from(e: &Element) -> Self {
+        let custom_type = |label: &str| DartIdentifier::make_label_for_custom_type(label);
+        let struct_type = |s: &CStruct| custom_type(Language::Dart.struct_name(s));
+        let ffi_struct = |s: &CStruct| {
+            DartFFIDataType::Struct(DartIdentifier::new_for_custom_type(
+                Language::Dart.struct_name(s),
+                None,
+                true,
+            ))
+        };
+        match e {
+            Element::String => DartList {
+                kind: DartNativeDataType::String,
+                ffi_kind: DartFFIDataType::Pointer {
+                    sub_type: Box::new(DartFFIDataType::Char),
+                },
+                read_element: "_getDartStringFromPtr(data[i], release: (_) {})".to_owned(),
+                take_element: "_getDartStringFromPtr(data[i])".to_owned(),
+                write_element: Some("_stringToFFIPointer({})".to_owned()),
+                frees_elements: true,
+            },
+            Element::Primitive(kind) => DartList {
+                kind: DartNativeDataType::from(*kind),
+                ffi_kind: DartFFIDataType::from(*kind),
+                read_element: "data[i]".to_owned(),
+                take_element: "data[i]".to_owned(),
+                write_element: Some("{}".to_owned()),
+                frees_elements: false,
+            },
+            Element::Enum(e) => DartList {
+                kind: DartNativeDataType::CustomEnum(custom_type(&e.identifier.label)),
+                ffi_kind: DartFFIDataType::Int32,
+                read_element: format!(
+                    "{}.values.firstWhere((e) => e.value == data[i])",
+                    custom_type(&e.identifier.label)
+                ),
+                take_element: format!(
+                    "{}.values.firstWhere((e) => e.value == data[i])",
+                    custom_type(&e.identifier.label)
+                ),
+                write_element: Some("{}.value".to_owned()),
+                frees_elements: false,
+            },
+            Element::Struct(s) => DartList {
+                kind: DartNativeDataType::CustomClass(struct_type(s)),
+                ffi_kind: ffi_struct(s),
+                read_element: format!("{}._fromCStruct(data[i])", struct_type(s)),
+                take_element: format!("{}._fromCStruct(data[i])", struct_type(s)),
+                write_element: None,
+                frees_elements: false,
+            },
+            Element::StructPointer(s) => DartList {
+                kind: DartNativeDataType::CustomClass(struct_type(s)),
+                ffi_kind: DartFFIDataType::Pointer {
+                    sub_type: Box::new(ffi_struct(s)),
+                },
+                read_element: format!("{}._fromCPointer(data[i].cast())", struct_type(s)),
+                take_element: format!("{}._fromCPointer(data[i].cast())", struct_type(s)),
+                write_element: s
+                    .meta
+                    .as_ref()
+                    .is_some_and(|m| m.is_persistent)
+                    .then(|| "{}._getPointer().cast()".to_owned()),
+                frees_elements: false,
+            },
+        }
+    }
+
+    /// Copies the `length` elements of the native array `items` into a Dart `List`.
+    /// The array belongs to the struct it was read from, and is released along with it
+    fn read(&self, items: &str, length: &str) -> String {
+        format!(
+            "_getDartListFromPtr<{}, {}>({}, {}, (data, i) => {})",
+            self.kind, self.ffi_kind, items, length, self.read_element
+        )
+    }
+
+    /// Copies the native array handed back through `pointer`, whose length and capacity were written to `length` and `capacity`,
+    /// then releases it as `meta` says, see `_getDartListFromDoublePtr`:
+    /// with the library's `free_with` function, which gets back the array, its length and capacity,
+    /// not at all if it is `borrowed`, or else along with its elements
+    fn read_output(
+        &self,
+        pointer: &str,
+        length: &str,
+        capacity: Option<&str>,
+        meta: &MetaValue,
+    ) -> String {
+        let (read, release) = match &meta.free_with {
+            _ if meta.is_borrowed => (
+                &self.read_element,
+                Some("(data, length, capacity) {}".to_owned()),
+            ),
+            Some(free_with) => (
+                &self.read_element,
+                Some(format!(
+                    "(data, length, capacity) => {}{}(data, length{})",
+                    C_FUNCTION_PREFIX,
+                    free_with,
+                    if capacity.is_some() { ", capacity" } else { "" }
+                )),
+            ),
+            None => (&self.take_element, None),
+        };
+        format!(
+            "_getDartListFromDoublePtr<{}, {}>({}, {}, (data, i) => {}{}{})",
+            self.kind,
+            self.ffi_kind,
+            pointer,
+            length,
+            read,
+            capacity
+                .map(|c| format!(", capacityPtr: {}", c))
+                .unwrap_or_default(),
+            release
+                .map(|r| format!(", release: {}", r))
+                .unwrap_or_default()
+        )
+    }
+
+    /// Copies the Dart `List` named `label` into an `_FFIList`, or `None` if its elements cannot be handed to C
+    fn to_ffi(&self, label: &str) -> Option<String> {
+        let write = self
+            .write_element
+            .as_ref()?
+            .replace("{}", &format!("{}[i]", label));
+        Some(format!(
+            "_FFIList<{ffi}>(calloc<{ffi}>({l}.isEmpty ? 1 : {l}.length), {l}.length, (data, i) => data[i] = {write}{release})",
+            ffi = self.ffi_kind,
+            l = label,
+            write = write,
+            release = if self.frees_elements {
+                ", release: (data, i) => calloc.free(data[i])"
+            } else {
+                ""
+            }
+        ))
+    }
+}
+
+/// The `release` callback handed to the string helpers for the first parameter of `c` that `is_param` matches,
+/// if its memory is released by the library itself, or must not be released at all
+fn release_callback(c: &CFunction, is_param: fn(&MetaValue) -> bool) -> Option<String> {
+    let meta = c
+        .parameters
+        .iter()
+        .filter_map(|p| p.meta.as_ref())
+        .find(|m| is_param(m))?;
+    param_release(meta)
+}
+
+/// The `release` callback for a single output or error parameter, see `release_callback`
+fn param_release(meta: &MetaValue) -> Option<String> {
+    if meta.is_borrowed {
+        Some("(_) {}".to_owned())
+    } else {
+        meta.free_with
+            .as_ref()
+            .map(|f| format!("(p) => {}{}(p.cast())", C_FUNCTION_PREFIX, f))
+    }
+}
+
+/// Makes `df` return every `output` parameter of `c`, in declaration order, as a record if there is more than one.
+///
+/// The templates only know how to hand back a single output, and not a buffer of `bytes` or a `list` with its length,
+/// nor the plain value of a getter, so the body is written out here instead. Lists and buffers are handed in here as well,
+/// along with their length, and so are maps, along with their values and length
+fn return_outputs_as_record(
+    df: &mut DartFunction,
+    c: &CFunction,
+    library_name: &str,
+    has_error_codes: bool,
+) {
+    let is_output = |p: &&CVariableDeclaration| p.meta.as_ref().is_some_and(|m| m.is_output);
+    let outputs: Vec<&CVariableDeclaration> = c.parameters.iter().filter(is_output).collect();
+    let lists = List::all(&c.parameters);
+    let maps = Hashmap::all(&c.parameters);
+    let buffers = Bytes::all(&c.parameters);
+    /* a getter of a number or a duration reads it from memory of its own, which `_getPointerForType` cannot make */
+    let is_value_getter = matches!(Property::of(c), Some((_, true)))
+        && outputs.iter().all(|p| {
+            p.variable_type.pointer_count == 1
+                && !p.variable_type.is_struct
+                && !p.meta.as_ref().is_some_and(|m| m.is_bytes || m.is_list)
+        });
+    if outputs.len() < 2
+        && buffers.is_empty()
+        && lists.is_empty()
+        && maps.is_empty()
+        && !is_value_getter
+    {
+        return;
+    }
+
+    let mut body: Vec<String> = vec![];
+    let (self_ptr, self_address) = if df.is_async {
+        (
+            "ffi.Pointer<ffi.Void>.fromAddress(_selfPtrAddress).cast()",
+            "_selfPtrAddress",
+        )
+    } else {
+        ("this._selfPtr", "this._selfPtr.address")
+    };
+    let exception = format!("{}Exception", library_name);
+    if df.on_class.is_some() {
+        body.push(format!("if ({} == ffi.nullptr.address) {{", self_address));
+        body.push(format!(
+            "    throw {}('Cannot call function. This object has been reclaimed by the system', -10);",
+            exception
+        ));
+        body.push("}".to_owned());
+    }
+    if df.throws {
+        body.push("/* Get error pointer in case function returns failure */".to_owned());
+        body.push(
+            "final ffi.Pointer<ffi.Pointer<ffi.Char>> cErrPtr = _getPointerForType<String>().cast();"
+                .to_owned(),
+        );
+    }
+
+    let mut arguments: Vec<String> = vec![];
+    let mut input_frees: Vec<String> = vec![];
+    let mut error_release: Option<String> = None;
+    /* (label, pointers, read expression, whether the pointers are left for the caller to free) */
+    let mut reads: Vec<(String, Vec<String>, String, bool)> = vec![];
+    for p in &c.parameters {
+        let meta = p.meta.to_owned().unwrap_or_else(MetaValue::new);
+        let label = DartIdentifier::new_from_raw(&p.label).dart_label;
+        if meta.is_this {
+            arguments.push(self_ptr.to_owned());
+        } else if meta.is_error {
+            error_release = param_release(&meta);
+            arguments.push("cErrPtr".to_owned());
+        } else if meta.is_output {
+            let kind = DartNativeDataType::from(p);
+            let pointer = format!("c{}OutputPtr", label);
+            let release = param_release(&meta)
+                .map(|r| format!(", release: {}", r))
+                .unwrap_or_default();
+            if let Some(list) = Element::of(p)
+                .filter(|_| meta.is_list)
+                .map(|e| DartList::from(&e))
+            {
+                let length = format!("c{}LengthPtr", label);
+                let capacity = lists
+                    .iter()
+                    .find(|l| l.items.label == p.label && l.capacity.is_some())
+                    .map(|_| format!("c{}CapacityPtr", label));
+                body.push(format!(
+                    "final {} = calloc<ffi.Pointer<{}>>();",
+                    pointer, list.ffi_kind
+                ));
+                let mut pointers = vec![pointer.to_owned(), length.to_owned()];
+                pointers.extend(capacity.to_owned());
+                for size in &pointers[1..] {
+                    body.push(format!("final {} = calloc<ffi.UintPtr>();", size));
+                }
+                reads.push((
+                    label,
+                    pointers,
+                    list.read_output(&pointer, &length, capacity.as_deref(), &meta),
+                    /* all of them are consumed as the list is read */
+                    false,
+                ));
+                arguments.push(pointer);
+            } else if meta.is_bytes {
+                let length = format!("c{}LengthPtr", label);
+                /* the library's own function gets back the buffer along with its length */
+                let release = match &meta.free_with {
+                    _ if meta.is_borrowed => ", release: (data, length) {}".to_owned(),
+                    Some(free_with) => format!(
+                        ", release: (data, length) => {}{}(data, length)",
+                        C_FUNCTION_PREFIX, free_with
+                    ),
+                    None => String::new(),
+                };
+                body.push(format!(
+                    "final {} = calloc<ffi.Pointer<ffi.Uint8>>();",
+                    pointer
+                ));
+                body.push(format!("final {} = calloc<ffi.UintPtr>();", length));
+                reads.push((
+                    label,
+                    vec![pointer.to_owned(), length.to_owned()],
+                    format!(
+                        "_getDartBytesFromDoublePtr({}, {}{})",
+                        pointer, length, release
+                    ),
+                    /* both are consumed as the buffer is read */
+                    false,
+                ));
+                arguments.push(pointer);
+            } else if p.variable_type.pointer_count > 1 {
+                let primitive = DartDataType::NativeType(kind.to_owned()).to_primitive();
+                body.push(format!(
+                    "final {} = _getPointerForType<{}>();",
+                    pointer, primitive
+                ));
+                let is_uri = if meta.is_url { ", isUri: true" } else { "" };
+                reads.push((
+                    label,
+                    vec![pointer.to_owned()],
+                    format!(
+                        "_transformFromFFI<{}>({}, isDoublePointer: true{}{})!",
+                        kind, pointer, is_uri, release
+                    ),
+                    /* strings free their pointer as they are read */
+                    !matches!(kind, DartNativeDataType::String | DartNativeDataType::Uri),
+                ));
+                arguments.push(format!("{}.cast()", pointer));
+            } else {
+                body.push(format!(
+                    "final {} = calloc<{}>();",
+                    pointer,
+                    DartFFIDataType::from(&p.variable_type.kind)
+                ));
+                let conversion = if meta.is_duration {
+                    ", isDuration: true"
+                } else if meta.is_datetime {
+                    ", isDateTime: true"
+                } else {
+                    ""
+                };
+                reads.push((
+                    label,
+                    vec![pointer.to_owned()],
+                    format!(
+                        "_transformFromFFI<{}>({}.value{})!",
+                        kind, pointer, conversion
+                    ),
+                    true,
+                ));
+                arguments.push(pointer);
+            }
+        } else if let Some(b) = buffers
+            .iter()
+            .find(|b| b.length.is_some_and(|l| l.label == p.label) && b.is_output())
+        {
+            /* written to along with the buffer */
+            arguments.push(format!(
+                "c{}LengthPtr",
+                DartIdentifier::new_from_raw(&b.data.label).dart_label
+            ));
+        } else if let Some(l) = lists.iter().find(|l| {
+            l.length.is_some_and(|len| len.label == p.label)
+                || l.capacity.is_some_and(|cap| cap.label == p.label)
+        }) {
+            /* written to along with the list, or read from the copy handed in, which has no room to spare */
+            let items = DartIdentifier::new_from_raw(&l.items.label).dart_label;
+            let is_length = l.length.is_some_and(|len| len.label == p.label);
+            arguments.push(match (l.is_output(), is_length) {
+                (true, true) => format!("c{}LengthPtr", items),
+                (true, false) => format!("c{}CapacityPtr", items),
+                (false, _) => format!("c{}List.length", items),
+            });
+        } else if is_part(&c.parameters, p) {
+            /* handed in along with the keys, or the buffer */
+        } else {
+            hand_in(p, &label, &mut body, &mut arguments, &mut input_frees);
+        }
+    }
+
+    /* with nothing to hand back, the function returns whatever C does, unless that is its error code */
+    let returns_result = outputs.is_empty() && !df.throws && !df.is_void;
+    body.push("/* call native function */".to_owned());
+    body.push(format!(
+        "{}{}{}(",
+        if df.throws {
+            "int errCode = "
+        } else if returns_result {
+            "final result = "
+        } else {
+            ""
+        },
+        C_FUNCTION_PREFIX,
+        c.label
+    ));
+    body.extend(arguments.iter().map(|a| format!("    {},", a)));
+    body.push(");".to_owned());
+
+    if !input_frees.is_empty() {
+        body.push("/* Free allocated pointers */".to_owned());
+        body.extend(input_frees);
+    }
+    if df.throws {
+        body.push("if (errCode != C_FALSE) {".to_owned());
+        body.extend(
+            reads
+                .iter()
+                .flat_map(|(_, pointers, _, _)| pointers)
+                .map(|p| format!("    calloc.free({});", p)),
+        );
+        let exception = if has_error_codes {
+            format!("{}ErrorCodeException.fromCode", library_name)
+        } else {
+            exception.to_owned()
+        };
+        body.push(format!(
+            "    throw {}(_getDartStringFromDoublePtr(cErrPtr{}), errCode);",
+            exception,
+            error_release
+                .map(|r| format!(", release: {}", r))
+                .unwrap_or_default()
+        ));
+        body.push("}".to_owned());
+        body.push("calloc.free(cErrPtr);".to_owned());
+    }
+
+    if !reads.is_empty() {
+        body.push("/* return every output */".to_owned());
+    }
+    for (label, pointers, read, free_pointers) in &reads {
+        body.push(format!("final {} = {};", label, read));
+        if *free_pointers {
+            body.extend(pointers.iter().map(|p| format!("calloc.free({});", p)));
+        }
+    }
+    let labels: Vec<String> = reads
+        .iter()
+        .map(|(label, _, _, _)| label.to_owned())
+        .collect();
+    match labels.as_slice() {
+        [] if returns_result => body.push("return result;".to_owned()),
+        [] => {}
+        [single] => body.push(format!("return {};", single)),
+        _ => body.push(format!("return ({});", labels.join(", "))),
+    }
+
+    df.return_type = DartDataType::NativeType(match outputs.as_slice() {
+        [] if returns_result => DartNativeDataType::from(&*c.return_type),
+        [] => DartNativeDataType::Void,
+        [single] => DartNativeDataType::from(*single),
+        _ => DartNativeDataType::Record(
+            outputs
+                .iter()
+                .map(|p| {
+                    (
+                        DartNativeDataType::from(*p),
+                        DartIdentifier::new_from_raw(&p.label).dart_label,
+                    )
+                })
+                .collect(),
+        ),
+    });
+    df.is_void = outputs.is_empty() && !returns_result;
+    df.output_requires_pointer = false;
+    df.body = Some(body.join("\n    "));
+}
+
+/// Converts the input `p`, named `label` in Dart, for a native call: what it allocates is pushed to `body`,
+/// what is handed to C to `arguments`, and how it is freed once the call returns to `input_frees`
+fn hand_in(
+    p: &CVariableDeclaration,
+    label: &str,
+    body: &mut Vec<String>,
+    arguments: &mut Vec<String>,
+    input_frees: &mut Vec<String>,
+) {
+    let parameter = DartParameter::from(p, false);
+    if let Some(to_ffi) = &parameter.list_to_ffi {
+        let list = format!("c{}List", label);
+        body.push(format!("final {} = {};", list, to_ffi));
+        arguments.push(format!("{}.data", list));
+        input_frees.push(format!("{}.free();", list));
+    } else if parameter.is_bytes {
+        let bytes = format!("c{}Bytes", label);
+        body.push(format!("final {} = _FFIBytes({});", bytes, label));
+        arguments.push(format!("{}.data", bytes));
+        arguments.push(format!("{}.length", bytes));
+        input_frees.push(format!("{}.free();", bytes));
+    } else if parameter.is_hashmap {
+        let map = format!("c{}Map", label);
+        body.push(format!("final {} = _FFIMap({});", map, label));
+        arguments.push(format!("{}.keys", map));
+        arguments.push(format!("{}.values", map));
+        arguments.push(format!("{}.length", map));
+        input_frees.push(format!("{}.free();", map));
+    } else if parameter.requires_pointer {
+        let pointer = format!("c{}Ptr", label);
+        body.push(if parameter.is_nullable {
+            format!(
+                "final {} = {} == null ? ffi.nullptr : _getPointerForData({});",
+                pointer, label, label
+            )
+        } else {
+            format!("final {} = _getPointerForData({});", pointer, label)
+        });
+        arguments.push(format!("{}.cast()", pointer));
+        if !parameter.is_persistent {
+            input_frees.push(format!("calloc.free({});", pointer));
+        }
+    } else {
+        arguments.push(parameter.as_ffi_value.unwrap_or(label.to_owned()));
+    }
+}
+
 /// The FFI backing structure for a Dart class
 #[derive(Serialize)]
 struct DartFFIStruct {
@@ -1407,6 +2656,9 @@ struct DartFFIStruct {
     meta: Option<MetaValue>,
 
     comment: Option<DartComment>,
+
+    /// Why the C struct is deprecated, if it is, which marks the class made from it
+    deprecated: Option<String>,
 }
 
 impl From<&CStruct> for DartFFIStruct {
@@ -1418,7 +2670,10 @@ impl From<&CStruct> for DartFFIStruct {
             .iter()
             .map(|decl| {
                 let ffi_kind = DartFFIDataType::from(&decl.variable_type);
-                let kind = if decl.variable_type.is_struct {
+                let is_list = decl.meta.as_ref().is_some_and(|m| m.is_list);
+                let kind = if is_list {
+                    DartDataType::FFIType(DartFFIDataType::from(decl))
+                } else if decl.variable_type.is_struct {
                     DartDataType::FFIType(DartFFIDataType::Pointer {
                         sub_type: Box::new(ffi_kind.to_owned()),
                     })
@@ -1433,15 +2688,28 @@ impl From<&CStruct> for DartFFIStruct {
                 DartField {
                     is_nullable: c.meta.as_ref().map_or(false, |f| f.is_nullable),
                     is_private: false,
-                    identifier: DartIdentifier::new(&decl.label, None),
+                    identifier: DartIdentifier::new(
+                        Language::Dart.name_of(&decl.label, &decl.meta),
+                        None,
+                    ),
                     comment: decl.comment.to_owned().map(DartComment::from),
-                    annotations: vec![ffi_kind.get_dart_annotation_string()],
+                    annotations: if is_list {
+                        vec![]
+                    } else {
+                        vec![ffi_kind.get_dart_annotation_string()]
+                    },
                     modifiers: vec!["external".to_owned()],
                     as_primitive_kind: kind.for_struct(),
                     assign_statement: None,
                     reads_capacity_from: None,
+                    reads_values_from: None,
+                    list_of: Element::of(decl)
+                        .filter(|_| is_list)
+                        .map(|e| DartList::from(&e)),
+                    reads_list: None,
                     reads_length_from: None,
                     meta: decl.meta.to_owned(),
+                    deprecated: decl.deprecated.to_owned(),
                     kind,
                 }
             })
@@ -1452,11 +2720,12 @@ impl From<&CStruct> for DartFFIStruct {
             label: format!(
                 "{}{}",
                 C_PREFIX,
-                DartIdentifier::make_label_for_custom_type(&c.identifier.label)
+                DartIdentifier::make_label_for_custom_type(Language::Dart.struct_name(c))
             ),
             comment,
             fields,
             meta: c.meta.to_owned(),
+            deprecated: c.deprecated.to_owned(),
             extends: vec![if c.declarations.len() == 0 {
                 "ffi.Opaque".to_owned()
             } else {
@@ -1469,12 +2738,21 @@ impl From<&CStruct> for DartFFIStruct {
 
 #[derive(Debug, Clone)]
 enum DartFFIDataType {
-    Pointer { sub_type: Box<DartFFIDataType> },
+    Pointer {
+        sub_type: Box<DartFFIDataType>,
+    },
     NativeType,
     Opaque(DartIdentifier),
     Struct(DartIdentifier),
     Handle,
-    NativeFunction { sub_type: Box<DartFFIDataType> },
+    NativeFunction {
+        sub_type: Box<DartFFIDataType>,
+    },
+    /// The signature a `NativeFunction` points to, e.g., `ffi.Void Function(ffi.Pointer<ffi.Void>)`
+    Function {
+        return_type: Box<DartFFIDataType>,
+        parameters: Vec<DartFFIDataType>,
+    },
     NativeFinalizer,
     Char,
     Void,
@@ -1513,6 +2791,10 @@ impl Into<DartNativeDataType> for DartFFIDataType {
             DartFFIDataType::NativeType
             | DartFFIDataType::Handle
             | DartFFIDataType::NativeFunction { sub_type: _ }
+            | DartFFIDataType::Function {
+                return_type: _,
+                parameters: _,
+            }
             | DartFFIDataType::NativeFinalizer => panic!(
                 "Cannot convert this FFI Type into a Dart: Native Type: {}",
                 self
@@ -1547,16 +2829,19 @@ impl Serialize for DartFFIDataType {
 impl From<&CType> for DartFFIDataType {
     fn from(c: &CType) -> Self {
         match &c {
-            CType::Include(_)
-            | CType::VoidStar
-            | CType::Define(_, _)
-            | CType::Function(_)
-            | CType::UNINITIALIZED => {
+            CType::Include(_) | CType::VoidStar | CType::Define(_, _) | CType::UNINITIALIZED => {
                 panic!("Cannot create a Dart FFI Type from this ctype: {}", c)
             }
+            /* a function pointer, i.e., the callback of an `async_callback` function, see `Completion` */
+            CType::Function(f) => DartFFIDataType::NativeFunction {
+                sub_type: Box::new(DartFFIDataType::Function {
+                    return_type: Box::new(DartFFIDataType::from(&*f.return_type)),
+                    parameters: f.parameters.iter().map(DartFFIDataType::from).collect(),
+                }),
+            },
             CType::Enum(e) => DartFFIDataType::Int32,
             CType::Struct(c) => DartFFIDataType::Struct(DartIdentifier::new_for_custom_type(
-                &c.identifier.label,
+                Language::Dart.struct_name(c),
                 None,
                 true,
             )),
@@ -1605,6 +2890,10 @@ impl DartFFIDataType {
             | DartFFIDataType::Struct(_)
             | DartFFIDataType::Handle
             | DartFFIDataType::NativeFunction { sub_type: _ }
+            | DartFFIDataType::Function {
+                return_type: _,
+                parameters: _,
+            }
             | DartFFIDataType::Pointer { sub_type: _ }
             | DartFFIDataType::Char
             | DartFFIDataType::NativeFinalizer
@@ -1631,7 +2920,11 @@ impl DartFFIDataType {
             DartFFIDataType::NativeType
             | DartFFIDataType::Handle
             | DartFFIDataType::NativeFinalizer
-            | DartFFIDataType::NativeFunction { sub_type: _ } => {
+            | DartFFIDataType::NativeFunction { sub_type: _ }
+            | DartFFIDataType::Function {
+                return_type: _,
+                parameters: _,
+            } => {
                 panic!("cannot check pointerness of this type: {}", &self)
             }
             DartFFIDataType::Pointer { sub_type: _ }
@@ -1668,6 +2961,18 @@ impl Display for DartFFIDataType {
             DartFFIDataType::NativeFunction { sub_type } => {
                 f.write_fmt(format_args!("ffi.NativeFunction<{}>", sub_type))
             }
+            DartFFIDataType::Function {
+                return_type,
+                parameters,
+            } => f.write_fmt(format_args!(
+                "{} Function({})",
+                return_type,
+                parameters
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
             DartFFIDataType::Int8 => f.write_str("ffi.Int8"),
             DartFFIDataType::Int16 => f.write_str("ffi.Int16"),
             DartFFIDataType::Int32 => f.write_str("ffi.Int32"),
@@ -1705,15 +3010,45 @@ enum DartNativeDataType {
         key_type: Box<DartNativeDataType>,
         value_type: Box<DartNativeDataType>,
     },
+    /// A buffer of raw bytes, see `Bytes`
+    Bytes,
+    /// Named fields, e.g., `(int w, int h)`
+    Record(Vec<(DartNativeDataType, String)>),
+    /// Items handed out one at a time, see `Cursor`
+    Iterable {
+        sub_type: Box<DartNativeDataType>,
+    },
+    /// The result of a call that can be cancelled while it runs, see `Cancellation`
+    CancelableOperation {
+        sub_type: Box<DartDataType>,
+    },
+    /// The result of a call that completes through a callback, see `Completion`
+    Future {
+        sub_type: Box<DartDataType>,
+    },
     Void,
 }
 
 impl From<&MetaValue> for DartNativeDataType {
     fn from(meta_value: &MetaValue) -> Self {
-        if meta_value.is_datetime {
+        DartNativeDataType::from_meta_value(meta_value).unwrap_or_else(|| {
+            panic!(
+                "No known dart native type for this Meta Type: {:#?}",
+                meta_value,
+            )
+        })
+    }
+}
+
+impl DartNativeDataType {
+    /// The type described by the meta keywords alone, or `None` if they say nothing about the type, e.g., a plain `output`
+    fn from_meta_value(meta_value: &MetaValue) -> Option<Self> {
+        Some(if meta_value.is_datetime {
             DartNativeDataType::DateTime
         } else if meta_value.is_duration {
             DartNativeDataType::Duration
+        } else if meta_value.is_bytes {
+            DartNativeDataType::Bytes
         } else if meta_value.is_hashmap {
             DartNativeDataType::Map {
                 key_type: Box::new(DartNativeDataType::String),
@@ -1732,11 +3067,8 @@ impl From<&MetaValue> for DartNativeDataType {
         } else if meta_value.is_error {
             DartNativeDataType::String
         } else {
-            panic!(
-                "No known dart native type for this Meta Type: {:#?}",
-                meta_value,
-            )
-        }
+            return None;
+        })
     }
 }
 
@@ -1745,15 +3077,22 @@ impl From<&CVariableDeclaration> for DartNativeDataType {
         match &c.meta {
             None => DartNativeDataType::from(&c.variable_type),
             Some(meta_value) => {
-                if c.variable_type.is_struct {
+                if let Some(e) = Element::of(c).filter(|_| meta_value.is_list) {
+                    DartNativeDataType::List {
+                        sub_type: Box::new(DartList::from(&e).kind),
+                    }
+                } else if c.variable_type.is_struct {
                     match &c.variable_type.kind {
                         CType::Struct(s) => DartNativeDataType::CustomClass(
-                            DartIdentifier::make_label_for_custom_type(&s.identifier.label),
+                            DartIdentifier::make_label_for_custom_type(
+                                Language::Dart.struct_name(s),
+                            ),
                         ),
                         _ => panic!("Expected to extract type name of struct, got something else"),
                     }
                 } else {
-                    DartNativeDataType::from(meta_value)
+                    DartNativeDataType::from_meta_value(meta_value)
+                        .unwrap_or_else(|| DartNativeDataType::from(&c.variable_type))
                 }
             }
         }
@@ -1777,12 +3116,17 @@ impl DartNativeDataType {
                 key_type: _,
                 value_type: _,
             }
-            | DartNativeDataType::List { sub_type: _ } => true,
+            | DartNativeDataType::List { sub_type: _ }
+            | DartNativeDataType::Iterable { sub_type: _ }
+            | DartNativeDataType::CancelableOperation { sub_type: _ }
+            | DartNativeDataType::Future { sub_type: _ } => true,
 
             DartNativeDataType::Bool
             | DartNativeDataType::Int
             | DartNativeDataType::Double
             | DartNativeDataType::CustomEnum(_)
+            | DartNativeDataType::Record(_)
+            | DartNativeDataType::Bytes
             | DartNativeDataType::Duration => false,
             DartNativeDataType::Void => {
                 panic!("Cannot test pointerness of this data type: {}", &self)
@@ -1807,7 +3151,7 @@ impl From<&CType> for DartNativeDataType {
                 DartIdentifier::make_label_for_custom_type(&v.identifier.label),
             ),
             CType::Struct(v) => DartNativeDataType::CustomClass(
-                DartIdentifier::make_label_for_custom_type(&v.identifier.label),
+                DartIdentifier::make_label_for_custom_type(Language::Dart.struct_name(v)),
             ),
             CType::Void => DartNativeDataType::Void,
             CType::Include(_)
@@ -1853,13 +3197,31 @@ impl Display for DartNativeDataType {
             DartNativeDataType::List { sub_type } => {
                 f.write_fmt(format_args!("List<{}>", sub_type))
             }
+            DartNativeDataType::Iterable { sub_type } => {
+                f.write_fmt(format_args!("Iterable<{}>", sub_type))
+            }
+            DartNativeDataType::CancelableOperation { sub_type } => {
+                f.write_fmt(format_args!("CancelableOperation<{}>", sub_type))
+            }
+            DartNativeDataType::Future { sub_type } => {
+                f.write_fmt(format_args!("Future<{}>", sub_type))
+            }
             DartNativeDataType::Uri => f.write_str("Uri"),
+            DartNativeDataType::Bytes => f.write_str("Uint8List"),
             DartNativeDataType::Duration => f.write_str("Duration"),
             DartNativeDataType::DateTime => f.write_str("DateTime"),
             DartNativeDataType::Map {
                 key_type,
                 value_type,
             } => f.write_fmt(format_args!("Map<{}, {}>", key_type, value_type)),
+            DartNativeDataType::Record(fields) => f.write_fmt(format_args!(
+                "({})",
+                fields
+                    .iter()
+                    .map(|(kind, label)| format!("{} {}", kind, label))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
             DartNativeDataType::Void => f.write_str("void"),
         }
     }
@@ -1910,6 +3272,10 @@ impl DartDataType {
                 | DartFFIDataType::Struct(_)
                 | DartFFIDataType::Handle
                 | DartFFIDataType::NativeFunction { sub_type: _ }
+                | DartFFIDataType::Function {
+                    return_type: _,
+                    parameters: _,
+                }
                 | DartFFIDataType::Void => self.to_owned(),
                 DartFFIDataType::NativeFinalizer => {
                     panic!("Native Finalizer not valid on for_struct")
@@ -1927,6 +3293,11 @@ impl DartDataType {
                 | DartNativeDataType::Int
                 | DartNativeDataType::Bool
                 | DartNativeDataType::Void
+                | DartNativeDataType::Record(_)
+                | DartNativeDataType::Bytes
+                | DartNativeDataType::Iterable { sub_type: _ }
+                | DartNativeDataType::CancelableOperation { sub_type: _ }
+                | DartNativeDataType::Future { sub_type: _ }
                 | DartNativeDataType::Map {
                     key_type: _,
                     value_type: _,
@@ -1971,6 +3342,8 @@ impl DartDataType {
                     DartNativeDataType::List {
                         sub_type: Box::new(inner_type),
                     }
+                } else if m.is_bytes {
+                    DartNativeDataType::Bytes
                 } else if m.is_hashmap {
                     // TODO(nf, 04/20/23): Currently the MetaValues only accept string keys
                     let key = DartNativeDataType::String;
@@ -2012,7 +3385,7 @@ impl DartDataType {
                 Some(m) => {
                     if cvariable.is_struct {
                         let label = if let CType::Struct(s) = &cvariable.kind {
-                            &s.identifier.label
+                            Language::Dart.struct_name(s)
                         } else {
                             label
                         };
@@ -2024,7 +3397,10 @@ impl DartDataType {
                             DartIdentifier::make_label_for_custom_type(&e.identifier.label),
                         ))
                     } else {
-                        DartDataType::NativeType(DartNativeDataType::from(m))
+                        DartDataType::NativeType(
+                            DartNativeDataType::from_meta_value(m)
+                                .unwrap_or_else(|| DartNativeDataType::from(cvariable)),
+                        )
                     }
                 }
                 .to_upper_type(meta.as_ref()),
@@ -2085,9 +3461,10 @@ fn is_some(value: Option<&Value>, _: &[Value]) -> tera::Result<bool> {
     }
 }
 
-pub fn generate(header: HeaderFile, library_path: &str, library_name: &str) -> String {
+pub fn generate(header: &HeaderFile, library_path: &str, library_name: &str) -> String {
+    let header = &Language::Dart.header(header);
     let data = Data::new(
-        &header,
+        header,
         library_path,
         library_name,
         false,
@@ -2096,6 +3473,7 @@ pub fn generate(header: HeaderFile, library_path: &str, library_name: &str) -> S
             "'dart:ffi' as ffi",
             "'dart:io' show Platform, Directory",
             "'dart:isolate'",
+            "'dart:typed_data'",
             "'package:path/path.dart' as path",
         ],
     );
@@ -2152,7 +3530,29 @@ class {{meta.library_name}}Exception implements Exception {
 
     const {{meta.library_name}}Exception(this.msg, this.code);
 }
-";
+{% if error_codes | length %}
+/// A known failure code returned by the native library
+sealed class {{meta.library_name}}ErrorCodeException extends {{meta.library_name}}Exception {
+    const {{meta.library_name}}ErrorCodeException(super.msg, super.code);
+
+    /// The exception for `code`, or a plain [{{meta.library_name}}Exception] if it is not a known failure code
+    static {{meta.library_name}}Exception fromCode(String msg, int code) {
+        switch (code) {
+{%- for error_code in error_codes %}
+            case {{ error_code.value }}:
+                return {{ error_code.class_name }}(msg);
+{%- endfor %}
+            default:
+                return {{meta.library_name}}Exception(msg, code);
+        }
+    }
+}
+{% for error_code in error_codes %}
+{% if error_code.dart_comment is some %}{{ error_code.dart_comment | trim }}
+{% endif %}final class {{ error_code.class_name }} extends {{meta.library_name}}ErrorCodeException {
+    const {{ error_code.class_name }}(String msg) : super(msg, {{ error_code.value }});
+}
+{% endfor %}{% endif %}";
 
 const TEMPLATE_C_CONSTANTS: &str = "
 /* Region: C Constants */
@@ -2202,7 +3602,8 @@ abstract class _IAsFFIInt {
 enum {% if enum.is_private %}_{% endif %}{{ enum.name }} implements _IAsFFIInt {
     {% for value in enum.values %}
     {% if value.dart_comment is some %}{{ value.dart_comment }}{% endif %}
-    {{value.label}}({{ value.value }}),
+    {% if value.annotation is some %}{{ value.annotation }}
+    {% endif %}{{value.label}}({{ value.value }}),
     {% endfor %}
     ;
 
@@ -2250,7 +3651,8 @@ const TEMPLATE_DART_CLASSES: &str = "
 {% if dart_classes | length %}
 /* Region: Dart Classes for use by the end-user */
     {% for class in dart_classes %}
-class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% if class.implements | length %} implements {% for implementer in class.implements %} {{ implementer }}{% if not loop.last %}, {% endif %} {% endfor %} {% endif %} {% if class.extends | length %} extends {% for extender in class.extends %} {{ extender }}{% if not loop.last %}, {% endif %} {% endfor %} {% endif %} {
+{% for annotation in class.annotations %}{{ annotation }}
+{% endfor %}class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% if class.implements | length %} implements {% for implementer in class.implements %} {{ implementer }}{% if not loop.last %}, {% endif %} {% endfor %} {% endif %} {% if class.extends | length %} extends {% for extender in class.extends %} {{ extender }}{% if not loop.last %}, {% endif %} {% endfor %} {% endif %} {
     {% if class.fields | length %}
     /* Fields */
     {% for field in class.fields %}
@@ -2275,7 +3677,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
     {% for function in class.functions %}
         {% if function.dart_comment is some %}{{ function.dart_comment }}{% endif %}
         {% for annotation in function.annotations %}{{ annotation }}{% endfor %}
-        {% for modifier in function.modifiers %}{{ modifier }} {% endfor %} {% if function.is_async %} Future< {% endif %}{% if function.is_factory %} {% elif function.is_void %} void {% else %} {{ function.return_type }} {% endif %}{% if function.is_async %}>{% endif %} {% if function.is_factory %}{{ class.identifier.dart_label }}.{% endif %}{% if function.is_private %}_{% endif %}{{ function.identifier.dart_label}}({% for parameter in function.parameters %} {% if parameter.is_required %}required {% endif %}{{ parameter.kind }} {{ parameter.identifier.dart_label}}, {% endfor %}) {% if function.is_async %} async {% endif %} {
+        {% for modifier in function.modifiers %}{{ modifier }} {% endfor %} {% if function.is_async %} Future< {% endif %}{% if function.is_factory %} {% elif function.is_void %} void {% else %} {{ function.return_type }} {% endif %}{% if function.is_async %}>{% endif %} {% if function.is_factory %}{{ class.identifier.dart_label }}.{% endif %}{% if function.accessor %}{{ function.accessor }} {% endif %}{% if function.is_private %}_{% endif %}{{ function.identifier.dart_label}}{% if function.accessor != \"get\" %}({% for parameter in function.parameters %}{% if not parameter.default_value %} {% if parameter.is_required %}required {% endif %}{{ parameter.kind }}{% if parameter.is_nullable %}?{% endif %} {{ parameter.identifier.dart_label}}{% if function.accessor != \"set\" %}, {% endif %}{% endif %}{% endfor %}{% set optional = function.parameters | filter(attribute=\"default_value\") %}{% if optional | length %} { {% for parameter in optional %}{{ parameter.kind }}{% if parameter.is_nullable %}?{% endif %} {{ parameter.identifier.dart_label }} = {{ parameter.default_value }}, {% endfor %}}{% endif %}){% endif %} {% if function.is_async %} async {% endif %} {
             {% if function.is_async %} 
             final _selfPtrAddress = this._selfPtr.address;
             return await Isolate.run(() { {% endif %}
@@ -2283,7 +3685,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
         {{ function.body }}
             {% elif function.identifier.dart_label is containing(\"fromCStruct\") %}
                 {% for field in class.fields %}
-        final {{ field.kind }}{% if field.is_nullable %}?{% endif %} _c{{field.identifier.dart_label}} = _transformFromFFI<{{ field.kind }}>(c.{{field.identifier.dart_label}}, {% if field.meta is some %} {% if field.meta.is_list %}isList: true, {% elif field.meta.is_url %}isUri: true,{% elif field.meta.is_duration%}isDuration: true, {% elif field.meta.is_datetime%}isDateTime: true,  {% endif %}{% endif %} {% if field.reads_length_from is some %} listSize: c.{{ field.reads_length_from }}, {% endif %}){% if not field.is_nullable %}!{% endif %};
+        final {{ field.kind }}{% if field.is_nullable %}?{% endif %} _c{{field.identifier.dart_label}} = {% if field.reads_list is some %}{{ field.reads_list }};{% else %}_transformFromFFI<{{ field.kind }}>(c.{{field.identifier.dart_label}}, {% if field.meta is some %} {% if field.meta.is_url %}isUri: true,{% elif field.meta.is_duration%}isDuration: true, {% elif field.meta.is_datetime%}isDateTime: true,  {% endif %}{% endif %} {% if field.reads_values_from is some %}isHashMap: true, mapValues: c.{{ field.reads_values_from }}, {% endif %}{% if field.reads_length_from is some %} listSize: c.{{ field.reads_length_from }}, {% endif %}){% if not field.is_nullable %}!{% endif %};{% endif %}
                 {% endfor %}
             final _{{class.identifier.dart_label}}Ret = {{ class.identifier.dart_label }}._fromFields({% for field in class.fields %}_c{{ field.identifier.dart_label }}, {% endfor %});
             return _{{class.identifier.dart_label}}Ret;
@@ -2293,14 +3695,14 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                 final ffi.Pointer<ffi.Pointer<ffi.Char>> cErrPtr = _getPointerForType<String>().cast();
                 {% endif %}
                 {% for parameter in function.parameters %}
-                final dynamic c{{ parameter.identifier.dart_label }}ForFFI = _transformToFFI({{ parameter.identifier.dart_label }});
+                final dynamic c{{ parameter.identifier.dart_label }}ForFFI = {% if parameter.list_to_ffi %}{{ parameter.list_to_ffi }}{% elif parameter.is_nullable %}{{ parameter.identifier.dart_label }} == null ? ffi.nullptr : _transformToFFI({{ parameter.identifier.dart_label }}!){% else %}_transformToFFI({{ parameter.identifier.dart_label }}){% endif %};
                 {% endfor %}
                 /* call native function */
                 {% if function.throws %}
                 int errCode = ffi_{{ function.c_function_name }}(
                         c{{function.c_function_name}}OutputPtr.cast(),
                         {% for parameter in function.parameters %}
-                        c{{ parameter.identifier.dart_label }}ForFFI,
+                        {% if parameter.is_hashmap %}c{{ parameter.identifier.dart_label }}ForFFI.keys, c{{ parameter.identifier.dart_label }}ForFFI.values, c{{ parameter.identifier.dart_label }}ForFFI.length,{% elif parameter.is_bytes or parameter.list_to_ffi %}c{{ parameter.identifier.dart_label }}ForFFI.data, c{{ parameter.identifier.dart_label }}ForFFI.length,{% else %}c{{ parameter.identifier.dart_label }}ForFFI,{% endif %}
                         {% endfor %}
                     cErrPtr,
                 );
@@ -2311,6 +3713,8 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% for parameter in function.parameters %}
                         {% if parameter.requires_pointer and not parameter.is_persistent %}
                     calloc.free(c{{ parameter.identifier.dart_label }}ForFFI);
+                        {% elif parameter.is_hashmap or parameter.is_bytes or parameter.list_to_ffi %}
+                    c{{ parameter.identifier.dart_label }}ForFFI.free();
                         {% endif %}
                     {% endfor %}
                     {% if function.output_requires_pointer %}
@@ -2318,13 +3722,13 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% endif %}
 
                     /* throw final Exception */
-                    throw {{meta.library_name}}Exception(_getDartStringFromDoublePtr(cErrPtr), errCode);
+                    throw {{meta.library_name}}{% if meta.has_error_codes %}ErrorCodeException.fromCode{% else %}Exception{% endif %}(_getDartStringFromDoublePtr(cErrPtr{% if function.error_release %}, release: {{ function.error_release }}{% endif %}), errCode);
                 }
                 {% else %}
                 ffi_{{ function.c_function_name }}(
                     c{{function.c_function_name}}OutputPtr.cast(),
                     {% for parameter in function.parameters %}
-                    c{{ parameter.identifier.dart_label }}ForFFI,
+                    {% if parameter.is_hashmap %}c{{ parameter.identifier.dart_label }}ForFFI.keys, c{{ parameter.identifier.dart_label }}ForFFI.values, c{{ parameter.identifier.dart_label }}ForFFI.length,{% elif parameter.is_bytes or parameter.list_to_ffi %}c{{ parameter.identifier.dart_label }}ForFFI.data, c{{ parameter.identifier.dart_label }}ForFFI.length,{% else %}c{{ parameter.identifier.dart_label }}ForFFI,{% endif %}
                     {% endfor %}
                 );
                 {% endif %}
@@ -2333,6 +3737,8 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% for parameter in function.parameters %}
                         {% if parameter.requires_pointer and not parameter.is_persistent %}
                 calloc.free(c{{ parameter.identifier.dart_label}}ForFFI);
+                        {% elif parameter.is_hashmap or parameter.is_bytes or parameter.list_to_ffi %}
+                c{{ parameter.identifier.dart_label }}ForFFI.free();
                         {% endif %}
                     {% endfor %}
                     {% if function.throws %}
@@ -2353,7 +3759,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% endif %}
                 {% endif %}
                 {% for parameter in function.parameters %}
-            final c{{ parameter.identifier.dart_label }}ForFFI = _transformToFFI({{ parameter.identifier.dart_label }});
+            final c{{ parameter.identifier.dart_label }}ForFFI = {% if parameter.is_nullable %}{{ parameter.identifier.dart_label }} == null ? ffi.nullptr : _transformToFFI({{ parameter.identifier.dart_label }}!){% else %}_transformToFFI({{ parameter.identifier.dart_label }}){% endif %};
                 {% endfor %}
                 /* call native function */
                 {% if function.throws %}
@@ -2370,7 +3776,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                         {% if function.is_async %}ffi.Pointer<ffi.Void>.fromAddress(_selfPtrAddress).cast(){% else %}this._selfPtr{% endif %},
                         {% if function.output_requires_pointer %}_c{{ function.c_function_name }}OutputPtr.cast(),{% endif %}
                         {% for parameter in function.parameters %}
-                        c{{ parameter.identifier.dart_label }}ForFFI,
+                        {% if parameter.is_hashmap %}c{{ parameter.identifier.dart_label }}ForFFI.keys, c{{ parameter.identifier.dart_label }}ForFFI.values, c{{ parameter.identifier.dart_label }}ForFFI.length,{% elif parameter.is_bytes or parameter.list_to_ffi %}c{{ parameter.identifier.dart_label }}ForFFI.data, c{{ parameter.identifier.dart_label }}ForFFI.length,{% else %}c{{ parameter.identifier.dart_label }}ForFFI,{% endif %}
                         {% endfor %}
                     cErrPtr,
                 );
@@ -2381,6 +3787,8 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% for parameter in function.parameters %}
                         {% if parameter.requires_pointer and not parameter.is_persistent %}
                     calloc.free(c{{ parameter.identifier.dart_label }}ForFFI);
+                        {% elif parameter.is_hashmap or parameter.is_bytes or parameter.list_to_ffi %}
+                    c{{ parameter.identifier.dart_label }}ForFFI.free();
                         {% endif %}
                     {% endfor %}
                     {% if function.output_requires_pointer %}
@@ -2388,7 +3796,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% endif %}
 
                     /* throw final Exception */
-                    throw {{meta.library_name}}Exception(_getDartStringFromDoublePtr(cErrPtr), errCode);
+                    throw {{meta.library_name}}{% if meta.has_error_codes %}ErrorCodeException.fromCode{% else %}Exception{% endif %}(_getDartStringFromDoublePtr(cErrPtr{% if function.error_release %}, release: {{ function.error_release }}{% endif %}), errCode);
                 }
                 {% else %}
                     
@@ -2403,7 +3811,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% if function.is_async %}ffi.Pointer<ffi.Void>.fromAddress(_selfPtrAddress).cast(){% else %}this._selfPtr{% endif %},
                     {% if function.output_requires_pointer %}_c{{ function.c_function_name }}OutputPtr.cast(),{% endif %}
                     {% for parameter in function.parameters %}
-                    c{{ parameter.identifier.dart_label }}ForFFI,
+                    {% if parameter.is_hashmap %}c{{ parameter.identifier.dart_label }}ForFFI.keys, c{{ parameter.identifier.dart_label }}ForFFI.values, c{{ parameter.identifier.dart_label }}ForFFI.length,{% elif parameter.is_bytes or parameter.list_to_ffi %}c{{ parameter.identifier.dart_label }}ForFFI.data, c{{ parameter.identifier.dart_label }}ForFFI.length,{% else %}c{{ parameter.identifier.dart_label }}ForFFI,{% endif %}
                     {% endfor %}
                 );
                 {% endif %}
@@ -2412,6 +3820,8 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% for parameter in function.parameters %}
                         {% if parameter.requires_pointer and not parameter.is_persistent %}
                 calloc.free(c{{ parameter.identifier.dart_label}}ForFFI);
+                        {% elif parameter.is_hashmap or parameter.is_bytes or parameter.list_to_ffi %}
+                c{{ parameter.identifier.dart_label }}ForFFI.free();
                         {% endif %}
                     {% endfor %}
                     {% if function.throws %}
@@ -2423,7 +3833,7 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
                     {% if function.return_type_struct %}
         return {{ function.return_type }}._fromCPointerPointer(_c{{function.c_function_name}}OutputPtr.cast());
                     {% else %}
-        return _transformFromFFI<{{ function.return_type }}>(_c{{function.c_function_name}}OutputPtr, isDoublePointer: true)!;
+        return _transformFromFFI<{{ function.return_type }}>(_c{{function.c_function_name}}OutputPtr, isDoublePointer: true{% if function.output_release %}, release: {{ function.output_release }}{% endif %})!;
                     {% endif %}
                 {% else %}
                 return ??? // todo: what do when function is not void, and doesnt return a struct;
@@ -2434,6 +3844,25 @@ class {% if class.is_private %}_{% endif %}{{ class.identifier.dart_label}} {% i
 
     }
     {% endfor %}
+    {% endif %}{% if class.value is some %}
+    /* Value */
+    @override
+    bool operator ==(Object other) =>
+        identical(this, other) ||
+        other is {{ class.identifier.dart_label }}{% for field in class.value.fields %}{% if field.is_compared %} &&
+            {{ field.equals }}{% endif %}{% endfor %};
+
+    @override
+    int get hashCode => Object.hashAll([{% for field in class.value.fields %}{% if field.is_compared %}{{ field.hash }}, {% endif %}{% endfor %}]);
+
+    @override
+    String toString() => '{{ class.identifier.dart_label }}({% for field in class.value.fields %}{{ field.parameter }}: ${{ field.label }}{% if not loop.last %}, {% endif %}{% endfor %})';
+
+    /// A copy of this value with the given fields replaced, a nullable field is cleared by handing in `null`
+    {{ class.identifier.dart_label }} copyWith({ {% for field in class.value.fields %}{% if field.is_nullable %}Object? {{ field.parameter }} = _valueUnset{% else %}{{ field.kind }}? {{ field.parameter }}{% endif %}, {% endfor %}}) =>
+        {{ class.identifier.dart_label }}._fromFields({% for field in class.value.fields %}
+            {% if field.is_nullable %}identical({{ field.parameter }}, _valueUnset) ? this.{{ field.label }} : {{ field.parameter }} as {{ field.kind }}?{% else %}{{ field.parameter }} ?? this.{{ field.label }}{% endif %},{% endfor %}
+        );
     {% endif %}
 }
     {% endfor %}
@@ -2446,7 +3875,7 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
 /* Region: Dart Free Functions */
 {% for function in dart_native_free_functions %}
 {% for annotation in function.annotations %}{{ annotation }}{% endfor %}
-{% for modifier in function.modifiers %} {{ modifier }} {% endfor %} {% if function.is_async %}  Future< {% endif %}{{ function.return_type }} {% if function.is_async %}>{% endif %} {% if function.is_private %}_{% endif %}{{ function.identifier.dart_label}}({% for parameter in function.parameters %} {% if parameter.is_required %}required {% endif %}{{ parameter.kind }} {{ parameter.identifier.dart_label }}, {% endfor %}) {% if function.is_async %} async {% endif %}{
+{% for modifier in function.modifiers %} {{ modifier }} {% endfor %} {% if function.is_async %}  Future< {% endif %}{{ function.return_type }} {% if function.is_async %}>{% endif %} {% if function.is_private %}_{% endif %}{{ function.identifier.dart_label}}({% for parameter in function.parameters %}{% if not parameter.default_value %} {% if parameter.is_required %}required {% endif %}{{ parameter.kind }}{% if parameter.is_nullable %}?{% endif %} {{ parameter.identifier.dart_label }}, {% endif %}{% endfor %}{% set optional = function.parameters | filter(attribute=\"default_value\") %}{% if optional | length %} { {% for parameter in optional %}{{ parameter.kind }}{% if parameter.is_nullable %}?{% endif %} {{ parameter.identifier.dart_label }} = {{ parameter.default_value }}, {% endfor %}}{% endif %}) {% if function.is_async %} async {% endif %}{
     {% if function.is_async %}
     return await Isolate.run(() {
     {% endif %}
@@ -2460,8 +3889,12 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
             {% if function.parameters | length %}
     /* get pointer types for items that require it*/
                 {% for parameter in function.parameters %}
-                    {% if parameter.requires_pointer %}
-    final c{{ parameter.identifier.dart_label}}Ptr = _getPointerForData({{ parameter.identifier.dart_label }});
+                    {% if parameter.is_hashmap %}
+    final c{{ parameter.identifier.dart_label}}Map = _FFIMap({{ parameter.identifier.dart_label }});
+                    {% elif parameter.is_bytes %}
+    final c{{ parameter.identifier.dart_label}}Map = _FFIBytes({{ parameter.identifier.dart_label }});
+                    {% elif parameter.requires_pointer %}
+    final c{{ parameter.identifier.dart_label}}Ptr = {% if parameter.is_nullable %}{{ parameter.identifier.dart_label }} == null ? ffi.nullptr : {% endif %}_getPointerForData({{ parameter.identifier.dart_label }});
                     {% endif %}
                 {% endfor %}
             {% endif %}
@@ -2477,7 +3910,7 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
         _c{{function.c_function_name}}OutputPtr.cast(),
             {% endif %}
             {% for parameter in function.parameters %}
-                {% if parameter.requires_pointer %} c{{ parameter.identifier.dart_label }}Ptr.cast(){% else %} {{ parameter.identifier.dart_label }}{% endif %},
+                {% if parameter.is_hashmap %} c{{ parameter.identifier.dart_label }}Map.keys, c{{ parameter.identifier.dart_label }}Map.values, c{{ parameter.identifier.dart_label }}Map.length{% elif parameter.is_bytes %} c{{ parameter.identifier.dart_label }}Map.data, c{{ parameter.identifier.dart_label }}Map.length{% elif parameter.requires_pointer %} c{{ parameter.identifier.dart_label }}Ptr.cast(){% else %} {{ parameter.identifier.dart_label }}{% endif %},
             {% endfor %}
         cErrPtr,
     );
@@ -2488,6 +3921,8 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
         {% for parameter in function.parameters %}
             {% if parameter.requires_pointer and not parameter.is_persistent %}
         calloc.free(c{{ parameter.identifier.dart_label }}Ptr);
+            {% elif parameter.is_hashmap or parameter.is_bytes %}
+        c{{ parameter.identifier.dart_label }}Map.free();
             {% endif %}
         {% endfor %}
         {% if function.output_requires_pointer %}
@@ -2495,13 +3930,15 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
         {% endif %}
 
         /* throw final Exception */
-        throw {{meta.library_name}}Exception(_getDartStringFromDoublePtr(cErrPtr), errCode);
+        throw {{meta.library_name}}{% if meta.has_error_codes %}ErrorCodeException.fromCode{% else %}Exception{% endif %}(_getDartStringFromDoublePtr(cErrPtr{% if function.error_release %}, release: {{ function.error_release }}{% endif %}), errCode);
     }
 
     /* Free allocated pointers */
         {% for parameter in function.parameters %}
             {% if parameter.requires_pointer and not parameter.is_persistent %}
     calloc.free(c{{ parameter.identifier.dart_label}}Ptr);
+            {% elif parameter.is_hashmap or parameter.is_bytes %}
+    c{{ parameter.identifier.dart_label}}Map.free();
             {% endif %}
         {% endfor %}
         {% if function.throws %}
@@ -2514,7 +3951,7 @@ const TEMPLATE_DART_NATIVE_FUNCTIONS: &str = "
                 {% if function.return_type_struct %}
     return {{ function.return_type }}._fromCPointerPointer(_c{{function.c_function_name}}OutputPtr.cast());
                 {% else %}
-    return _transformFromFFI<{{ function.return_type }}>(_c{{function.c_function_name}}OutputPtr, isDoublePointer: true)!;
+    return _transformFromFFI<{{ function.return_type }}>(_c{{function.c_function_name}}OutputPtr, isDoublePointer: true{% if function.output_release %}, release: {{ function.output_release }}{% endif %})!;
                 {% endif %}
             {% else %}
             return ??? // todo: what do when function is not void, and doesnt return a struct;
@@ -2565,9 +4002,9 @@ dynamic _transformToFFI<T>(T upperData) {
         final s = (upperData as List<String>).join(' ');
         return _stringToFFIPointer(s);
       } else if (T == Map<String, String>) {
-        // TODO(nf, 04/20/23): This is only valid for String:String pairs
-        // It is not
-        return _hashMapToFFIPointer(upperData as Map<String, String>);
+        return _FFIMap(upperData as Map<String, String>);
+      } else if (T == Uint8List) {
+        return _FFIBytes(upperData as Uint8List);
       } else if (T == _IWithPtr) {
         return _getPointerForData(upperData);
       } else if (T == ffi.Pointer) {
@@ -2580,8 +4017,8 @@ dynamic _transformToFFI<T>(T upperData) {
   }
 
 
-  T? _transformFromFFI<T>(dynamic data, {bool isDoublePointer = false, bool isList = false, bool isHashMap = false, bool isUri = false, bool isDuration = false, bool isDateTime = false, int listSize = 0}) {
-    if ((T == double && (data is double || data is int)) || (T == int && data is int)) {
+  T? _transformFromFFI<T>(dynamic data, {bool isDoublePointer = false, bool isHashMap = false, bool isUri = false, bool isDuration = false, bool isDateTime = false, int listSize = 0, dynamic mapValues, void Function(ffi.Pointer<ffi.Void>)? release}) {
+    if ((T == double && (data is double || data is int)) || ((T == int || T == Duration || T == DateTime) && data is int)) {
         if (isDuration) {
         return Duration(milliseconds: data.toInt()) as T;
       } else if (isDateTime) {
@@ -2596,22 +4033,23 @@ dynamic _transformToFFI<T>(T upperData) {
     } else if (data is ffi.Pointer) {
       if (data.address == ffi.nullptr.address) {
         return null;
-      } else if (isList) {
-        // TODO(nf, 04/22/23): we assume lists are List<String> and are single pointer lists
-        ffi.Pointer<ffi.Pointer<ffi.Char>> ptr = data.cast();
-        List<String> lst = [];
+      } else if (isHashMap) {
+        ffi.Pointer<ffi.Pointer<ffi.Char>> keys = data.cast();
+        ffi.Pointer<ffi.Pointer<ffi.Char>> values = (mapValues as ffi.Pointer).cast();
+        Map<String, String> map = {};
         for (int i = 0; i < listSize; i++) {
-          final charPtr = ptr.elementAt(i).value;
-          final s = _getDartStringFromPtr(charPtr);
-          lst.add(s);
+          map[keys[i].cast<Utf8>().toDartString()] = values[i].cast<Utf8>().toDartString();
         }
-        return lst as T;
+        return map as T;
+      } else if (T == Uint8List) {
+        /* The buffer belongs to the struct it was read from, so it is copied */
+        return Uint8List.fromList((data as ffi.Pointer).cast<ffi.Uint8>().asTypedList(listSize)) as T;
     } else if (T == Uri || T == String) {
         late final String s;
         if (isDoublePointer) {
-          s = _getDartStringFromDoublePtr(data.cast());
+          s = _getDartStringFromDoublePtr(data.cast(), release: release);
         } else {
-          s = _getDartStringFromPtr(data.cast());
+          s = _getDartStringFromPtr(data.cast(), release: release);
         }
         if (isUri) {
           final uri = Uri.tryParse(s);
@@ -2624,24 +4062,34 @@ dynamic _transformToFFI<T>(T upperData) {
           return s as T;
         }
       } else if (data is ffi.Pointer<ffi.Pointer<ffi.NativeType>>) {
-        return transformFromPointer<T, ffi.NativeType>(data, isDoublePointer);
+        return transformFromPointer<T, ffi.NativeType>(data, isDoublePointer, release);
       }
     }
     throw {{meta.library_name}}Exception('Invalid data in transformFromFFI: $data', -4);
   }
 
-T transformFromPointer<T, E extends ffi.NativeType>(ffi.Pointer<E> data, bool isDoublePointer) {
+T transformFromPointer<T, E extends ffi.NativeType>(ffi.Pointer<E> data, bool isDoublePointer, [void Function(ffi.Pointer<ffi.Void>)? release]) {
     if (T == String) {
         if(isDoublePointer) {
-            return _getDartStringFromDoublePtr(data.cast()) as T;
+            return _getDartStringFromDoublePtr(data.cast(), release: release) as T;
         } else {
-            return _getDartStringFromPtr(data.cast()) as T;
+            return _getDartStringFromPtr(data.cast(), release: release) as T;
         }
     }
     {% for class in dart_classes %}
     else if(T == {{ class.backing_ffi_struct.dart_label }} || T == {{ class.identifier.dart_label }}) {
         if(isDoublePointer) {
+            {% if class.is_opaque %}
             return {{ class.identifier.dart_label }}._fromCPointerPointer(data.cast()) as T;
+            {% else %}
+            /* The fields are copied, and their strings consumed, so only the struct itself is left to release */
+            final ffi.Pointer<ffi.Pointer<ffi.Void>> doublePtr = data.cast();
+            final obj = {{ class.identifier.dart_label }}._fromCPointerPointer(doublePtr);
+            if (release != null) {
+                release(doublePtr.value);
+            }
+            return obj as T;
+            {% endif %}
         } else {
             return {{ class.identifier.dart_label }}._fromCPointer(data.cast()) as T;
         }
@@ -2683,11 +4131,17 @@ ffi.Pointer<ffi.Void> _getPointerForData(dynamic data) {
 /// n.b., THIS CONSUMES AND FREES THE POINTER
 /// Do not use `charPtr` after this
 ///
+/// The pointer is freed with `release` if given, e.g., the library's own free function, and with `calloc` otherwise
+///
 /// For double pointers `char**` use `_getDartStringFromDoublePtr`
-String _getDartStringFromPtr(ffi.Pointer<ffi.Char> charPtr) {
+String _getDartStringFromPtr(ffi.Pointer<ffi.Char> charPtr, {void Function(ffi.Pointer<ffi.Void>)? release}) {
   final asUtf8Ptr = charPtr.cast<Utf8>();
   final asDartString = asUtf8Ptr.toDartString();
-  calloc.free(charPtr);
+  if (release != null) {
+    release(charPtr.cast());
+  } else {
+    calloc.free(charPtr);
+  }
   return asDartString;
 }
 
@@ -2696,10 +4150,12 @@ String _getDartStringFromPtr(ffi.Pointer<ffi.Char> charPtr) {
 /// n.b., THIS CONSUMES AND FREES THE POINTER
 /// Do not use `charPtr` after this
 ///
+/// The inner pointer is freed with `release` if given, see `_getDartStringFromPtr`
+///
 /// For single pointers `char*` use `_getDartStringFromPtr`
-String _getDartStringFromDoublePtr(ffi.Pointer<ffi.Pointer<ffi.Char>> doublePtr) {
+String _getDartStringFromDoublePtr(ffi.Pointer<ffi.Pointer<ffi.Char>> doublePtr, {void Function(ffi.Pointer<ffi.Void>)? release}) {
   final asCharPtr = doublePtr.value.cast<ffi.Char>();
-  final dstr = _getDartStringFromPtr(asCharPtr);
+  final dstr = _getDartStringFromPtr(asCharPtr, release: release);
   calloc.free(doublePtr);
   return dstr;
 }
@@ -2708,9 +4164,139 @@ ffi.Pointer<ffi.Char> _stringToFFIPointer(String s) {
   return s.toNativeUtf8().cast<ffi.Char>();
 }
 
-ffi.Pointer<ffi.Char> _hashMapToFFIPointer(Map<String, String> dict) {
-  String val = dict.entries.map((e) => \"${e.key}:${e.value}\").join(';');
-  return _stringToFFIPointer(val);
+/// A `Map<String, String>` as the parallel `char**` arrays of keys and values that C expects
+///
+/// The arrays are owned by Dart, release them with `free` once the native call returns
+class _FFIMap {
+  final ffi.Pointer<ffi.Pointer<ffi.Char>> keys;
+  final ffi.Pointer<ffi.Pointer<ffi.Char>> values;
+  final int length;
+
+  _FFIMap._(this.keys, this.values, this.length);
+
+  factory _FFIMap(Map<String, String> dict) {
+    final keys = calloc<ffi.Pointer<ffi.Char>>(dict.length);
+    final values = calloc<ffi.Pointer<ffi.Char>>(dict.length);
+    var i = 0;
+    for (final e in dict.entries) {
+      keys[i] = _stringToFFIPointer(e.key);
+      values[i] = _stringToFFIPointer(e.value);
+      i++;
+    }
+    return _FFIMap._(keys, values, dict.length);
+  }
+
+  void free() {
+    for (int i = 0; i < length; i++) {
+      calloc.free(keys[i]);
+      calloc.free(values[i]);
+    }
+    calloc.free(keys);
+    calloc.free(values);
+  }
+}
+
+/// A `Uint8List` copied into native memory, for the `uint8_t*` and length that C expects
+///
+/// The buffer is owned by Dart, release it with `free` once the native call returns
+class _FFIBytes {
+  final ffi.Pointer<ffi.Uint8> data;
+  final int length;
+
+  _FFIBytes._(this.data, this.length);
+
+  factory _FFIBytes(Uint8List bytes) {
+    final data = calloc<ffi.Uint8>(bytes.isEmpty ? 1 : bytes.length);
+    data.asTypedList(bytes.length).setAll(0, bytes);
+    return _FFIBytes._(data, bytes.length);
+  }
+
+  void free() {
+    calloc.free(data);
+  }
+}
+
+/// Returns a Dart `Uint8List` from a `uint8_t**` and the `uintptr_t*` its length was written to
+///
+/// n.b., THIS CONSUMES AND FREES BOTH POINTERS
+///
+/// Without `release` the buffer is not copied, it is handed to the list and freed along with it.
+/// With `release`, e.g., the library's own free function, the buffer is copied and released right away
+Uint8List _getDartBytesFromDoublePtr(ffi.Pointer<ffi.Pointer<ffi.Uint8>> doublePtr, ffi.Pointer<ffi.UintPtr> lengthPtr, {void Function(ffi.Pointer<ffi.Uint8>, int)? release}) {
+  final dataPtr = doublePtr.value;
+  final length = lengthPtr.value;
+  calloc.free(doublePtr);
+  calloc.free(lengthPtr);
+  if (dataPtr.address == ffi.nullptr.address) {
+    return Uint8List(0);
+  }
+  if (release == null) {
+    return dataPtr.asTypedList(length, finalizer: calloc.nativeFree);
+  }
+  final bytes = Uint8List.fromList(dataPtr.asTypedList(length));
+  release(dataPtr, length);
+  return bytes;
+}
+
+/// A `List` copied into a native array of `E`, for the pointer and length that C expects
+///
+/// The array is owned by Dart, release it with `free` once the native call returns.
+/// `release` frees what `fill` allocated for each element, e.g., the copy of a string
+class _FFIList<E extends ffi.NativeType> {
+  final ffi.Pointer<E> data;
+  final int length;
+  final void Function(ffi.Pointer<E>, int)? release;
+
+  _FFIList(this.data, this.length, void Function(ffi.Pointer<E>, int) fill, {this.release}) {
+    for (int i = 0; i < length; i++) {
+      fill(data, i);
+    }
+  }
+
+  void free() {
+    if (release != null) {
+      for (int i = 0; i < length; i++) {
+        release!(data, i);
+      }
+    }
+    calloc.free(data);
+  }
+}
+
+/// Returns a Dart `List` of the `length` elements of a native array, each copied with `read`
+///
+/// The array itself is left to its owner
+List<T> _getDartListFromPtr<T, E extends ffi.NativeType>(ffi.Pointer<E> data, int length, T Function(ffi.Pointer<E>, int) read) {
+  if (data.address == ffi.nullptr.address) {
+    return [];
+  }
+  return List<T>.generate(length, (i) => read(data, i));
+}
+
+/// Returns a Dart `List` from a pointer to a native array and the `uintptr_t*` its length, and maybe capacity, were written to
+///
+/// n.b., THIS CONSUMES AND FREES THE POINTERS
+///
+/// Once its elements are copied, the array is handed to `release` if given, e.g., the library's own free function,
+/// along with its length and capacity, so that the library can rebuild what it handed out. Otherwise it is freed
+List<T> _getDartListFromDoublePtr<T, E extends ffi.NativeType>(ffi.Pointer<ffi.Pointer<E>> doublePtr, ffi.Pointer<ffi.UintPtr> lengthPtr, T Function(ffi.Pointer<E>, int) read, {ffi.Pointer<ffi.UintPtr>? capacityPtr, void Function(ffi.Pointer<E>, int, int)? release}) {
+  final data = doublePtr.value;
+  final length = lengthPtr.value;
+  final capacity = capacityPtr?.value ?? length;
+  calloc.free(doublePtr);
+  calloc.free(lengthPtr);
+  if (capacityPtr != null) {
+    calloc.free(capacityPtr);
+  }
+  final items = _getDartListFromPtr<T, E>(data, length, read);
+  if (data.address != ffi.nullptr.address) {
+    if (release != null) {
+      release(data, length, capacity);
+    } else {
+      calloc.free(data);
+    }
+  }
+  return items;
 }
 
 /// Returns the Dart equivalent of an empty `char**`
@@ -2750,5 +4336,295 @@ String getLibraryPath() {
 ffi.DynamicLibrary loadLibrary(String libraryPath) {
   final dylib = ffi.DynamicLibrary.open(libraryPath);
   return dylib;
+}{% if meta.has_cancellables %}
+
+/// The handle handed to the last `cancellable` call, so that each call is cancelled on its own
+int _lastCancelHandle = 0;
+
+/// A handle for a new `cancellable` call
+int _nextCancelHandle() => ++_lastCancelHandle;
+
+extension CancelableOperationTimeout<T> on CancelableOperation<T> {
+  /// The value of this operation, which is cancelled, along with the native call behind it, if it does not complete within [timeLimit]
+  Future<T> timeout(Duration timeLimit) {
+    return value.timeout(timeLimit, onTimeout: () async {
+      await cancel();
+      throw TimeoutException('The operation was cancelled', timeLimit);
+    });
+  }
+}
+{% endif %}{% if meta.has_value_types %}
+
+/// Stands in for a field left out of `copyWith`, so that a nullable field can still be set to null
+const Object _valueUnset = Object();
+
+/// Whether [a] and [b] hold the same values, comparing lists and maps by their elements
+bool _valueEquals(Object? a, Object? b) {
+  if (a is List && b is List) {
+    if (a.length != b.length) return false;
+    for (var i = 0; i < a.length; i++) {
+      if (!_valueEquals(a[i], b[i])) return false;
+    }
+    return true;
+  }
+  if (a is Map && b is Map) {
+    if (a.length != b.length) return false;
+    for (final key in a.keys) {
+      if (!b.containsKey(key) || !_valueEquals(a[key], b[key])) return false;
+    }
+    return true;
+  }
+  return a == b;
 }
+
+/// A hash code of [value] that agrees with [_valueEquals]
+int _valueHash(Object? value) {
+  if (value is List) return Object.hashAll(value.map(_valueHash));
+  if (value is Map) {
+    return Object.hashAllUnordered(value.entries.map((e) => Object.hash(e.key, _valueHash(e.value))));
+  }
+  return value.hashCode;
+}
+{% endif %}
 ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    fn render(source: &str) -> String {
+        let mut header = parse_source(source);
+        crate::list::resolve(&mut header);
+        generate(&header, "libs", "liboauthtool")
+    }
+
+    /// Asserts that `code` contains `fragment`, ignoring differences in whitespace
+    fn assert_renders(code: &str, fragment: &str) {
+        let squash = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(
+            squash(code).contains(&squash(fragment)),
+            "`{}` not found in:\n{}",
+            fragment,
+            code
+        );
+    }
+
+    const OWNERSHIP: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\nvoid lib_free_string(char *s);\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;free_with(lib_free_string);\n * #meta_param: err;error;free_with(lib_free_string);\n */\nuint32_t Engine_describe(const struct Engine *this_, char **out, char **err);\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;borrowed;\n */\nvoid Engine_label(const struct Engine *this_, char **out);";
+
+    #[test]
+    fn releases_outputs_and_errors_with_their_free_with_function() {
+        let code = render(OWNERSHIP);
+        assert_renders(
+            &code,
+            "return _transformFromFFI<String>(_cEngine_describeOutputPtr, isDoublePointer: true, release: (p) => ffi_lib_free_string(p.cast()))!;",
+        );
+        assert_renders(
+            &code,
+            "_getDartStringFromDoublePtr(cErrPtr, release: (p) => ffi_lib_free_string(p.cast()))",
+        );
+        assert_renders(
+            &code,
+            "return _transformFromFFI<String>(_cEngine_labelOutputPtr, isDoublePointer: true, release: (_) {})!;",
+        );
+    }
+
+    const OUTPUTS: &str = "/**\n * #meta: throws;\n * #meta_param: name;output;string;\n * #meta_param: count;output;\n * #meta_param: err;error;\n */\nuint32_t describe_lib(char **name, uint64_t *count, char **err);";
+
+    #[test]
+    fn returns_several_outputs_as_a_record() {
+        let code = render(OUTPUTS);
+        assert_renders(&code, "(String name, int count) describe_lib() {");
+        assert_renders(
+            &code,
+            "final name = _transformFromFFI<String>(cnameOutputPtr, isDoublePointer: true)!;",
+        );
+        assert_renders(&code, "return (name, count);");
+    }
+
+    const MAP: &str = "/**\n * #meta_param: keys;hashmap;\n * #meta_param: values;values(keys);\n * #meta_param: keys_len;length(keys);\n */\nvoid set_headers(const char *const *keys, const char *const *values, uintptr_t keys_len);";
+
+    #[test]
+    fn hands_maps_to_functions_that_do_not_throw() {
+        let code = render(MAP);
+        assert_renders(
+            &code,
+            "void set_headers( Map<String, String> keys, ) { final ckeysMap = _FFIMap(keys); /* call native function */ ffi_set_headers( ckeysMap.keys, ckeysMap.values, ckeysMap.length, ); /* Free allocated pointers */ ckeysMap.free(); }",
+        );
+        assert!(!code.contains("'lol?'"), "{}", code);
+    }
+
+    const BYTES_INPUT: &str = "/**\n * #meta_param: data;bytes;\n * #meta_param: data_len;length(data);\n */\nvoid feed(const uint8_t *data, uintptr_t data_len);";
+
+    #[test]
+    fn hands_buffers_to_functions_that_do_not_throw() {
+        let code = render(BYTES_INPUT);
+        assert_renders(
+            &code,
+            "void feed( Uint8List data, ) { final cdataBytes = _FFIBytes(data); /* call native function */ ffi_feed( cdataBytes.data, cdataBytes.length, ); /* Free allocated pointers */ cdataBytes.free(); }",
+        );
+        assert!(!code.contains("'lol?'"), "{}", code);
+    }
+
+    const ERROR_CODES: &str = "/**\n * #meta: error_codes;\n */\n#define LIB_OK 0\n#define LIB_FAIL 1\n#define LIB_FAIL_NULL 2\n\n/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: throws;\n * #meta_param: out;output;string;\n * #meta_param: err;error;\n */\nuint32_t lib_name(char **out, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: err;error;\n */\nuint32_t Engine_reset(struct Engine *this_, char **err);";
+
+    #[test]
+    fn throws_an_exception_class_per_error_code() {
+        let code = render(ERROR_CODES);
+        assert_renders(
+            &code,
+            "final class LibFailNullException extends liboauthtoolErrorCodeException { const LibFailNullException(String msg) : super(msg, 2);",
+        );
+        assert_renders(&code, "case 1: return LibFailException(msg);");
+        assert_renders(
+            &code,
+            "throw liboauthtoolErrorCodeException.fromCode(_getDartStringFromDoublePtr(cErrPtr), errCode);",
+        );
+    }
+
+    const BYTES: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\nvoid lib_free_bytes(uint8_t *data, uintptr_t data_len);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: data;bytes;\n * #meta_param: data_len;length(data);\n * #meta_param: err;error;\n */\nuint32_t Engine_feed(const struct Engine *this_, const uint8_t *data, uintptr_t data_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;bytes;free_with(lib_free_bytes);\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_read(const struct Engine *this_, uint8_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_buffers_back_to_their_free_with_function_with_their_length() {
+        let code = render(BYTES);
+        assert_renders(
+            &code,
+            "final out = _getDartBytesFromDoublePtr(coutOutputPtr, coutLengthPtr, release: (data, length) => ffi_lib_free_bytes(data, length));",
+        );
+    }
+
+    const LISTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: scopes;list;\n * #meta_param: scopes_len;length(scopes);\n * #meta_param: err;error;\n */\nuint32_t Engine_set_scopes(const struct Engine *this_, const char *const *scopes, uintptr_t scopes_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;list;\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_ports(const struct Engine *this_, uint16_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_lists_over_as_dart_lists() {
+        let code = render(LISTS);
+        assert_renders(
+            &code,
+            "final cscopesList = _FFIList<ffi.Pointer<ffi.Char>>(calloc<ffi.Pointer<ffi.Char>>(scopes.isEmpty ? 1 : scopes.length), scopes.length, (data, i) => data[i] = _stringToFFIPointer(scopes[i]), release: (data, i) => calloc.free(data[i]));",
+        );
+        assert_renders(
+            &code,
+            "final out = _getDartListFromDoublePtr<int, ffi.Uint16>(coutOutputPtr, coutLengthPtr, (data, i) => data[i]);",
+        );
+    }
+
+    const LIST_RELEASE: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\nvoid free_tokens(struct TokenResponse *items, uintptr_t items_len, uintptr_t items_cap);\n\n/**\n * #meta: throws;\n * #meta_param: items;output;list;free_with(free_tokens);\n * #meta_param: items_len;length(items);\n * #meta_param: items_cap;capacity(items);\n * #meta_param: err;error;\n */\nuint32_t get_tokens(struct TokenResponse **items, uintptr_t *items_len, uintptr_t *items_cap, char **err);";
+
+    #[test]
+    fn hands_output_lists_back_to_free_with_with_their_length_and_capacity() {
+        let code = render(LIST_RELEASE);
+        assert_renders(
+            &code,
+            "capacityPtr: citemsCapacityPtr, release: (data, length, capacity) => ffi_free_tokens(data, length, capacity));",
+        );
+    }
+
+    const PROPERTY: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;getter(timeout);\n * #meta_param: this_;this;\n * #meta_param: out;output;duration;\n */\nvoid Engine_get_timeout(const struct Engine *this_, uint64_t *out);\n\n/**\n * #meta: for_struct;setter(timeout);\n * #meta_param: this_;this;\n * #meta_param: timeout;duration;\n */\nvoid Engine_set_timeout(struct Engine *this_, uint64_t timeout);";
+
+    #[test]
+    fn reads_and_writes_properties_through_their_accessors() {
+        let code = render(PROPERTY);
+        assert_renders(&code, "Duration get timeout {");
+        assert_renders(
+            &code,
+            "final out = _transformFromFFI<Duration>(coutOutputPtr.value, isDuration: true)!; calloc.free(coutOutputPtr); return out;",
+        );
+        assert_renders(&code, "void set timeout( Duration timeout) {");
+    }
+
+    const CURSOR: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\n/**\n * #meta: persistent;iterator(TokenResponse);\n */\ntypedef struct TokenCursor TokenCursor;\n\nvoid TokenResponse_free(struct TokenResponse *this_);\n\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;free_with(TokenResponse_free);\n */\nuint8_t TokenCursor_next(struct TokenCursor *this_, struct TokenResponse **out);";
+
+    #[test]
+    fn walks_through_cursors_with_an_iterable() {
+        let code = render(CURSOR);
+        assert_renders(&code, "Iterable<TokenResponse> items() {");
+        assert_renders(
+            &code,
+            "final hasItem = ffi_TokenCursor_next(_selfPtr, cItemPtr) != 0;",
+        );
+        assert_renders(
+            &code,
+            "final item = TokenResponse._fromCPointer(itemPtr.cast()); ffi_TokenResponse_free(itemPtr); yield item;",
+        );
+    }
+
+    const CANCELLABLE: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;async;cancellable(Engine_cancel);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;\n * #meta_param: cancel;cancel_handle;\n * #meta_param: err;error;\n */\nuint32_t Engine_fetch(struct Engine *this_, char **out, uint64_t cancel, char **err);\n\nvoid Engine_cancel(uint64_t cancel);";
+
+    #[test]
+    fn cancels_calls_through_a_cancelable_operation() {
+        let code = render(CANCELLABLE);
+        assert_renders(&code, "CancelableOperation<String> fetch() {");
+        assert_renders(
+            &code,
+            "final cancelHandle = _nextCancelHandle(); return CancelableOperation.fromFuture( _fetch(cancelHandle), onCancel: () => ffi_Engine_cancel(cancelHandle),",
+        );
+    }
+
+    const COMPLETION: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;async_callback(on_done);\n * #meta_param: this_;this;\n * #meta_param: url;string;\n * #meta_param: err;error;\n */\nvoid Engine_fetch(struct Engine *this_, const char *url, void (*on_done)(void *user_data, char *body, char *err), void *user_data);";
+
+    #[test]
+    fn completes_calls_from_a_native_callable() {
+        let code = render(COMPLETION);
+        assert_renders(&code, "Future<String> fetch( String url, ) {");
+        assert_renders(
+            &code,
+            "onDone.close(); if (err.address != ffi.nullptr.address) { completer.completeError(liboauthtoolException(_getDartStringFromPtr(err), -1)); return; } final result = _getDartStringFromPtr(body); completer.complete(result);",
+        );
+        assert_renders(&code, "onDone.nativeFunction,");
+    }
+
+    const DEFAULTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;constructor;throws;\n * #meta_param: this_;output;this;\n * #meta_param: scopes;string;default(\"openid\");\n * #meta_param: extra;string;nullable;default(null);\n * #meta_param: timeout;duration;default(30000);\n * #meta_param: err;error;\n */\nuint32_t Engine_new(struct Engine **this_, const char *scopes, const char *extra, uint64_t timeout, char **err);";
+
+    #[test]
+    fn turns_parameters_with_a_default_into_named_parameters() {
+        let code = render(DEFAULTS);
+        assert_renders(
+            &code,
+            "Engine.newCreate( { String scopes = \"openid\", String? extra = null, Duration timeout = const Duration(milliseconds: 30000), })",
+        );
+    }
+
+    const RENAMED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;rename(fetch_token);rename_csharp(Engine_get_token);\n * #meta_param: this_;this;\n */\nvoid Engine_get_token_automatic(struct Engine *this_);\n/**\n * #meta: skip_go;skip_csharp;\n */\nvoid reset_engine(void);\n/**\n * #meta: skip_dart;\n */\nvoid warm_engine(void);";
+
+    #[test]
+    fn renames_and_skips_items_for_dart() {
+        let code = render(RENAMED);
+        assert_renders(&code, "void fetchToken() {");
+        assert_renders(&code, "void reset_engine() {");
+        assert!(!code.contains("warm_engine"));
+    }
+
+    const DEPRECATED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;deprecated(\"use Engine_run instead\");\n * #meta_param: this_;this;\n */\nvoid Engine_start(struct Engine *this_);";
+
+    #[test]
+    fn marks_deprecated_functions() {
+        let code = render(DEPRECATED);
+        assert_renders(
+            &code,
+            "@Deprecated('use Engine_run instead') void start() {",
+        );
+    }
+
+    const VALUE: &str = "/**\n * #meta: value;\n */\ntypedef struct TokenResponse {\n  /**\n   * #meta: string;nullable;\n   */\n  const char *refresh_token;\n  int64_t expires_at;\n} TokenResponse;";
+
+    #[test]
+    fn compares_hashes_prints_and_copies_value_types_by_their_fields() {
+        let code = render(VALUE);
+        assert_renders(
+            &code,
+            "refreshToken == other.refreshToken && expiresAt == other.expiresAt;",
+        );
+        assert_renders(
+            &code,
+            "int get hashCode => Object.hashAll([refreshToken, expiresAt, ]);",
+        );
+        assert_renders(
+            &code,
+            "String toString() => 'TokenResponse(refreshToken: $refreshToken, expiresAt: $expiresAt)';",
+        );
+        assert_renders(
+            &code,
+            "TokenResponse copyWith({ Object? refreshToken = _valueUnset, int? expiresAt, }) =>",
+        );
+    }
+}
@@ -1,5 +1,5 @@
 // see also: https://gist.github.com/tosone/2e4120e7d28fd1c42adfb2ab12ab5bb5
-use std::{collections::HashMap, fmt};
+use std::fmt;
 
 use serde::{
     ser::{SerializeMap, SerializeSeq},
@@ -9,11 +9,22 @@ use serde_json::Value;
 use tera::{Context, Tera};
 
 use crate::{
+    bytes::Bytes,
+    cancel::Cancellation,
+    completion::Completion,
+    cursor::Cursor,
+    default_value::{string_literal, DefaultValue},
+    error_codes::{error_codes, success_code, ErrorCode},
+    hashmap::Hashmap,
     lexer::{
         CEnum, CFunction, CIdentifier, CStruct, CType, CVariableDeclaration, CVariableType,
         HeaderFile,
     },
-    meta::{MetaValue, META_PARAM_TOKEN, META_TOKEN},
+    list::{Element, List},
+    meta::{MetaValue, META_TOKEN},
+    naming::Language,
+    property::Property,
+    value_type::ValueType,
 };
 
 const FIELD_PTR: &'static str = "ptr";
@@ -43,6 +54,7 @@ enum GoTypeBasic {
     Rune,
     Float32,
     Float64,
+    Bytes, // see `Bytes`
 }
 
 impl Serialize for GoTypeBasic {
@@ -74,6 +86,7 @@ impl fmt::Display for GoTypeBasic {
             GoTypeBasic::Float32 => "float32",
             GoTypeBasic::Float64 => "float64",
             GoTypeBasic::Error => "error",
+            GoTypeBasic::Bytes => "[]byte",
         };
         f.write_fmt(format_args!("{}", s))
     }
@@ -113,6 +126,8 @@ impl GoTypeBasic {
             GoTypeBasic::Rune => "TODO(nf, not_implemented_from_c_value) rune".to_owned(),
             GoTypeBasic::Float32 => "TODO(nf, not_implemented_from_c_value) f32".to_owned(),
             GoTypeBasic::Float64 => "TODO(nf, not_implemented_from_c_value) f64".to_owned(),
+            /* Buffers are read along with their length, see `GoParameter::from_c_bytes` */
+            GoTypeBasic::Bytes => val.to_owned(),
         }
     }
 
@@ -140,6 +155,7 @@ impl GoTypeBasic {
             GoTypeBasic::Rune => format!("C.CString({})", val),
             GoTypeBasic::Float32 => format!("C.float({})", val),
             GoTypeBasic::Float64 => format!("C.double({})", val),
+            GoTypeBasic::Bytes => format!("(*C.uint8_t)(C.CBytes({}))", val),
             _ => panic!("Cannot make C_value from go value of type {}", &self),
         }
         .to_owned()
@@ -188,6 +204,25 @@ impl GoComment {
             inner: n.join("\n"),
         };
     }
+
+    /// `comment` with the `Deprecated:` paragraph that tools such as `staticcheck` look for,
+    /// if the item is deprecated for `reason`
+    fn with_deprecation(comment: Option<GoComment>, reason: &Option<String>) -> Option<GoComment> {
+        let reason = match reason.as_deref() {
+            None => return comment,
+            Some("") => "do not use.",
+            Some(r) => r,
+        };
+        let paragraph = format!("// Deprecated: {}", reason.replace('\n', "\n// "));
+        Some(GoComment {
+            inner: match comment {
+                Some(c) if !c.inner.trim().is_empty() => {
+                    format!("{}\n//\n{}", c.inner.trim_end(), paragraph)
+                }
+                _ => paragraph,
+            },
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -200,6 +235,7 @@ enum GoTypeComplex {
     Struct(GoIdentifier), // GoLabel name for the struct
     Alias(GoIdentifier),  // GoLabel name for the type alias
     List(Box<GoType>),    // GoLabel data-type for the list
+    Map,                  // string to string map, see `Hashmap`
 }
 
 impl Serialize for GoTypeComplex {
@@ -224,6 +260,7 @@ impl fmt::Display for GoTypeComplex {
                 s
             }
             GoTypeComplex::UnsafePointer(identifier) => "unsafe.Pointer".to_owned(),
+            GoTypeComplex::Map => "map[string]string".to_owned(),
         };
         f.write_fmt(format_args!("{}", s))
     }
@@ -253,6 +290,8 @@ impl GoTypeComplex {
             GoTypeComplex::Struct(_) => "TODO(nf, not_implemented_from_c_value) struct".to_owned(),
             GoTypeComplex::Alias(_) => "TODO(nf, not_implemented_from_c_value) alis".to_owned(),
             GoTypeComplex::List(_) => "TODO(nf, not_implemented_from_c_value) list".to_owned(),
+            /* Maps are read along with their values and length, see `_fromCMap` */
+            GoTypeComplex::Map => val.to_owned(),
         }
     }
 
@@ -268,6 +307,8 @@ impl GoTypeComplex {
             GoTypeComplex::Alias(a) => "TODO(nf, not_implemented_make_c_value) alias ??".to_owned(),
             GoTypeComplex::List(_) => "TODO(nf, not_implemented_make_c_value) list ??".to_owned(),
             GoTypeComplex::UnsafePointer(ptr) => format!("unsafe.Pointer({})", ptr),
+            /* Maps are made along with their values and length, see `_toCMap` */
+            GoTypeComplex::Map => val.to_owned(),
         }
     }
 }
@@ -282,7 +323,7 @@ impl GoType {
     fn requires_free(&self) -> bool {
         match &self {
             GoType::Basic(gtype, _) => match gtype {
-                GoTypeBasic::String => true,
+                GoTypeBasic::String | GoTypeBasic::Bytes => true,
                 _ => false,
             },
             GoType::Complex(gtype, _) => match gtype {
@@ -304,7 +345,13 @@ impl GoType {
 
         let gt: GoType;
 
-        gt = if meta.is_string {
+        gt = if meta.is_hashmap {
+            return GoType::Complex(GoTypeComplex::Map, 0);
+        } else if meta.is_bytes {
+            return GoType::Basic(GoTypeBasic::Bytes, 0);
+        } else if let Some(e) = Element::of(c).filter(|_| meta.is_list) {
+            return GoType::Complex(GoTypeComplex::List(Box::new(GoList::from(&e).go_type)), 0);
+        } else if meta.is_string {
             GoType::Basic(GoTypeBasic::String, pcount)
         } else if meta.is_url {
             GoType::Complex(GoTypeComplex::Url, pcount)
@@ -341,7 +388,8 @@ impl GoType {
                     }
                 }
                 CType::Struct(s) => {
-                    let complex = GoTypeComplex::Enum(GoIdentifier::new(&s.identifier.label, None));
+                    let complex =
+                        GoTypeComplex::Enum(GoIdentifier::new(Language::Go.struct_name(s), None));
                     GoType::Complex(complex, pcount)
                 }
                 CType::IntPtrT(_) => GoType::Basic(GoTypeBasic::Int32, pcount),
@@ -354,6 +402,15 @@ impl GoType {
                 CType::UInt32T(_) => GoType::Basic(GoTypeBasic::Uint32, pcount),
                 CType::UInt64T(_) => GoType::Basic(GoTypeBasic::Uint64, pcount),
                 CType::VoidStar => GoType::Basic(GoTypeBasic::Uintptr, pcount),
+                /* the callback and user data of an `async_callback` function, see `Completion` */
+                CType::Function(_) => GoType::Complex(
+                    GoTypeComplex::UnsafePointer(GoIdentifier::new(&c.label, None)),
+                    0,
+                ),
+                CType::Void if c.variable_type.pointer_count > 0 => GoType::Complex(
+                    GoTypeComplex::UnsafePointer(GoIdentifier::new(&c.label, None)),
+                    0,
+                ),
                 CType::Void => GoType::Void,
                 _ => panic!(
                     "Not a valid GoType for given CType. Got: {}",
@@ -387,7 +444,8 @@ impl From<&CVariableType> for GoType {
             CType::Double(_) => GoType::Basic(GoTypeBasic::Float64, c.pointer_count),
             CType::Char(_) => GoType::Basic(GoTypeBasic::String, c.pointer_count),
             CType::Struct(s) => {
-                let complex = GoTypeComplex::Struct(GoIdentifier::new(&s.identifier.label, None));
+                let complex =
+                    GoTypeComplex::Struct(GoIdentifier::new(Language::Go.struct_name(s), None));
                 GoType::Complex(complex, c.pointer_count)
             }
             CType::IntPtrT(_) => GoType::Basic(GoTypeBasic::Int32, c.pointer_count),
@@ -407,8 +465,7 @@ impl From<&CVariableType> for GoType {
 }
 impl From<&CVariableDeclaration> for GoType {
     fn from(c: &CVariableDeclaration) -> Self {
-        let meta_opt = MetaValue::from_meta_comment_dontcare(&c.comment);
-        let mm = meta_opt.map_or(MetaValue::new(), |x| x.to_owned());
+        let mm = c.meta.to_owned().unwrap_or_else(MetaValue::new);
         GoType::_from(c, &mm)
     }
 }
@@ -520,6 +577,44 @@ struct GoFunction {
     return_type: GoType,
     return_signature: String,
     is_return_struct: bool,
+    /// Whether the function has several `output` parameters, which are all returned, in order, as named results
+    has_multiple_outputs: bool,
+    /// Whether this is the getter of a property, whose value is returned as is rather than through a pointer
+    returns_by_value: bool,
+    /// For a `cancellable` function, the C function cancelling it once its `ctx` is done, see `Cancellation`
+    cancel_with: Option<String>,
+    /// For a `cancellable` function, the go label of its cancel handle
+    cancel_handle: Option<String>,
+    /// For an `async_callback` function, how its result is awaited, see `Completion`
+    completion: Option<GoCompletion>,
+    /// For a function with parameters that have a default, the struct they are set through, see `GoOptions`
+    options: Option<GoOptions>,
+    /// The `Deprecated:` paragraph on its own, for constructors, which do not carry the rest of their comment
+    deprecation: Option<GoComment>,
+}
+
+/// The parameters of a function that have a default, which are left out of its signature
+/// and set by the functions handed in as its trailing `options ...func(*{label})`, see `DefaultValue`
+#[derive(Serialize, Clone)]
+struct GoOptions {
+    /// e.g., `OAuth2PKCENewOptions`
+    label: String,
+    /// The declaration of the struct, with a field for each of the parameters
+    declaration: String,
+    /// The statements at the start of the function, which apply the options over the defaults
+    apply: String,
+}
+
+/// How the result of an `async_callback` function is handed back: the library calls an `//export`ed function
+/// with the key the call was handed as its user data, which hands the result over to the caller waiting on it
+#[derive(Serialize, Clone)]
+struct GoCompletion {
+    /// The statements after the call, which wait for the result and return it
+    returns: String,
+    /// The `//export`ed function handed to the library as the callback
+    export: String,
+    /// The C declaration of `export`, for the preamble, e.g., `extern void _Engine_fetch_done(void *, char *);`
+    prototype: String,
 }
 
 impl GoFunction {
@@ -541,16 +636,249 @@ impl GoFunction {
         s
     }
 
+    /// Takes a `ctx` to cancel the call with, and makes up the cancel handle for each call, rather than taking it
+    fn make_cancellable(&mut self, c: &Cancellation) {
+        let handle = GoIdentifier::new(&c.handle.label, None).go_label;
+        for param in self.parameters.iter_mut() {
+            if param.identifier.go_label == handle {
+                param.show_in_signature = false;
+                param.as_c_field = format!(
+                    "{}(_nextCancelHandle())",
+                    cgo_type(&c.handle.variable_type.kind)
+                );
+            }
+        }
+        self.cancel_with = Some(c.cancel.label.to_owned());
+        self.cancel_handle = Some(handle);
+    }
+
+    /// Leaves the parameters of `c` that have a default out of the signature, for the caller to set through `options`
+    fn make_options(&mut self, c: &CFunction) {
+        let (label, name) = match &self.on_struct {
+            Some(s) if !self.meta.is_constructor => (
+                format!("{}{}Options", s.go_label, self.identifier.go_label),
+                format!("{}.{}", s.go_label, self.identifier.go_label),
+            ),
+            _ => (
+                format!("{}Options", self.identifier.go_label),
+                self.identifier.go_label.to_owned(),
+            ),
+        };
+        let mut fields: Vec<String> = vec![];
+        let mut defaults: Vec<String> = vec![];
+        let mut locals: Vec<String> = vec![];
+        for p in &c.parameters {
+            let value = match DefaultValue::of(p, c) {
+                Ok(Some(v)) => v,
+                _ => continue,
+            };
+            let param = match self
+                .parameters
+                .iter_mut()
+                .find(|g| g.c_identifier.label == p.label)
+            {
+                Some(param) => param,
+                None => continue,
+            };
+            param.show_in_signature = false;
+            let go_label = param.identifier.go_label.to_owned();
+            let go_value = match value {
+                DefaultValue::Null => "nil".to_owned(),
+                DefaultValue::Str(s) if param.meta.is_nullable => {
+                    format!(
+                        "func() *string {{ v := {}; return &v }}()",
+                        string_literal(s)
+                    )
+                }
+                DefaultValue::Str(s) => string_literal(s),
+                DefaultValue::Number(n) if param.meta.is_duration => {
+                    format!("{} * time.Millisecond", n)
+                }
+                DefaultValue::Number(n) => n.to_owned(),
+            };
+            fields.push(format!(
+                "    // Defaults to {}\n    {} {}",
+                match value {
+                    DefaultValue::Null => "nil".to_owned(),
+                    DefaultValue::Str(s) => string_literal(s),
+                    DefaultValue::Number(n) if param.meta.is_duration => {
+                        format!("{} milliseconds", n)
+                    }
+                    DefaultValue::Number(n) => n.to_owned(),
+                },
+                go_label,
+                param.go_type
+            ));
+            defaults.push(format!("        {}: {},", go_label, go_value));
+            locals.push(format!("    {} := _options.{}", go_label, go_label));
+        }
+        if fields.is_empty() {
+            return;
+        }
+        let declaration = format!(
+            "// {} holds the optional parameters of {}, each of which is left at its default unless an option sets it\ntype {} struct {{\n{}\n}}\n",
+            label,
+            name,
+            label,
+            fields.join("\n")
+        );
+        let apply = format!(
+            "    _options := {}{{\n{}\n    }}\n    for _, option := range options {{\n        option(&_options)\n    }}\n{}",
+            label,
+            defaults.join("\n"),
+            locals.join("\n")
+        );
+        self.options = Some(GoOptions {
+            label,
+            declaration,
+            apply,
+        });
+    }
+
+    /// Hands the library an `//export`ed callback and a key to call it back with, and waits for the result
+    /// rather than taking either of them
+    fn make_completion(&mut self, c: &Completion) {
+        let export = format!("_{}_done", c.function.label);
+        let value = match &c.result_element {
+            Some(e) => GoList::from(e),
+            None => GoList {
+                c_type: "".to_owned(),
+                go_type: GoType::Void,
+                from_c: "".to_owned(),
+                to_c: None,
+                release: "".to_owned(),
+            },
+        };
+        let value_type = match &value.go_type {
+            GoType::Void => "struct{}".to_owned(),
+            t => t.to_string(),
+        };
+        let user_data = GoIdentifier::new(&c.user_data.label, None).go_label;
+        for param in self.parameters.iter_mut() {
+            if param.c_identifier.label == c.callback.label {
+                param.as_c_field = format!("(*[0]byte)(C.{})", export);
+            } else if param.c_identifier.label == c.user_data.label {
+                param.as_c_field = format!("_newCompletion[{}]()", value_type);
+            } else {
+                continue;
+            }
+            param.show_in_signature = false;
+            param.requires_free = false;
+            param.is_complex = false;
+        }
+
+        let meta = MetaValue {
+            throws: c.error.is_some(),
+            ..MetaValue::new()
+        };
+        let await_result = format!(
+            "_awaitCompletion[{}]({}{})",
+            value_type, C_PREFACE, user_data
+        );
+        let returns = match (&value.go_type, c.error.is_some()) {
+            (GoType::Void, true) => format!("_, err := {}\n    return err", await_result),
+            (GoType::Void, false) => await_result,
+            (_, true) => format!("return {}", await_result),
+            (GoType::Complex(_, _), false) => format!("_v, _ := {}\n    return _v", await_result),
+            (_, false) => format!("_v, _ := {}\n    return *_v", await_result),
+        };
+        self.return_signature = GoFunction::make_return_signature(&value.go_type, &meta);
+        self.is_void = matches!(value.go_type, GoType::Void);
+        self.return_type = value.go_type.to_owned();
+
+        /* the callback releases what it is handed once it is copied */
+        let c_label = |p: &CVariableDeclaration| {
+            format!(
+                "{}{}",
+                C_PREFACE,
+                GoIdentifier::new(&p.label, None).go_label
+            )
+        };
+        let release = |p: &CVariableDeclaration, free_with: Option<&str>| match free_with {
+            Some(f) => format!("C.{}({})", f, c_label(p)),
+            None => format!("C.free(unsafe.Pointer({}))", c_label(p)),
+        };
+        let mut body: Vec<String> = vec![];
+        if let Some(error) = c.error {
+            let meta = error.meta.to_owned().unwrap_or_else(MetaValue::new);
+            body.push(format!("if {} != nil {{", c_label(error)));
+            body.push(format!(
+                "    _err := errors.New(C.GoString({}))",
+                c_label(error)
+            ));
+            if !meta.is_borrowed {
+                body.push(format!("    {}", release(error, meta.free_with.as_deref())));
+            }
+            body.push(format!("    _complete[{}](_key, nil, _err)", value_type));
+            body.push("    return".to_owned());
+            body.push("}".to_owned());
+        }
+        match c.result {
+            Some(result) => {
+                body.push(format!("_v := ({})({})", value.from_c, c_label(result)));
+                let is_pointer = matches!(
+                    c.result_element,
+                    Some(Element::String | Element::StructPointer(_))
+                );
+                if is_pointer && !c.is_result_borrowed() && !c.is_result_persistent() {
+                    body.push(release(result, c.result_release()));
+                }
+                body.push("_complete(_key, &_v, nil)".to_owned());
+            }
+            None => body.push("_complete(_key, &struct{}{}, nil)".to_owned()),
+        }
+        let parameters: Vec<String> = c
+            .signature
+            .parameters
+            .iter()
+            .map(|p| {
+                let label = if p.label == c.callback_user_data.label {
+                    "_key".to_owned()
+                } else {
+                    c_label(p)
+                };
+                let go_type = match (&p.variable_type.kind, p.variable_type.pointer_count) {
+                    (CType::Void, _) => "unsafe.Pointer".to_owned(),
+                    (kind, count) => {
+                        format!("{}{}", "*".repeat(count.into()), cgo_type(kind))
+                    }
+                };
+                format!("{} {}", label, go_type)
+            })
+            .collect();
+        let export_function = format!(
+            "//export {export}\nfunc {export}({parameters}) {{\n    {body}\n}}",
+            export = export,
+            parameters = parameters.join(", "),
+            body = body.join("\n    ")
+        );
+        let prototype = format!(
+            "extern void {}({});",
+            export,
+            c.signature
+                .parameters
+                .iter()
+                .map(c_declaration)
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        self.completion = Some(GoCompletion {
+            returns,
+            export: export_function,
+            prototype,
+        });
+    }
+
     fn from_cfunc(all_enums: &Vec<GoEnum>, all_structs: &mut Vec<GoStruct>, c: &CFunction) -> Self {
-        let function_meta_values: MetaValue = MetaValue::from_meta_comment_dontcare(&c.comment)
-            .map_or(MetaValue::new(), |x| x.to_owned());
+        let function_meta_values: MetaValue = c.meta.to_owned().unwrap_or_else(MetaValue::new);
 
-        let mut identifier = GoIdentifier::new(&c.label, None);
+        let mut identifier = GoIdentifier::new(Language::Go.name_of(&c.label, &c.meta), None);
 
         let go_comment = match &c.comment {
             Some(cmt) => Some(GoComment::new(cmt)),
             None => None,
         };
+        let go_comment = GoComment::with_deprecation(go_comment, &c.deprecated);
         let mut return_type = GoType::from(&*c.return_type);
 
         let mut on_struct: Option<GoIdentifier> = None;
@@ -564,14 +892,19 @@ impl GoFunction {
             let s_name = *splits.first().unwrap();
             struct_name = Some(s_name);
 
-            /* get new func name (minus struct name) */
-            splits.remove(0);
-            let func_name = splits.join("_");
-
-            let struct_identifier = GoIdentifier::new(s_name, None);
+            let struct_identifier = match all_structs.iter().find(|s| s.c_label == s_name) {
+                Some(s) => s.identifier.clone(),
+                None => GoIdentifier::new(s_name, None),
+            };
             on_struct = Some(struct_identifier.clone());
 
-            identifier.go_label = GoIdentifier::transform_label(&func_name);
+            identifier.go_label = match Property::of(c) {
+                /* `Timeout()` and `SetTimeout()`, as Go has no properties */
+                Some((name, true)) => GoIdentifier::transform_label(name),
+                Some((name, false)) => format!("Set{}", GoIdentifier::transform_label(name)),
+                None => GoIdentifier::transform_label(Language::Go.method_name(c)),
+            };
+            let is_renamed = function_meta_values.rename_for(Language::Go).is_some();
 
             /* Return type massaging  */
             if matches!(return_type, GoType::Basic(GoTypeBasic::Uint32, _)) {
@@ -581,38 +914,19 @@ impl GoFunction {
                 };
 
                 /* Constructor specific stuff */
+                if function_meta_values.is_constructor && !is_renamed {
+                    identifier.go_label = format!("{}New", struct_identifier.go_label);
+                }
                 if function_meta_values.is_constructor {
-                    identifier.go_label = format!("{}New", s_name);
                     return_type = GoType::Complex(GoTypeComplex::Struct(struct_identifier), pcount);
                 }
             }
         }
 
         /* Parameter stuff */
-        // let param2meta: HashMap<String, Option<&MetaValue>> = HashMap::new();
-        let mut param2meta: HashMap<String, MetaValue> = HashMap::new();
-        if let Some(cmt) = &c.comment {
-            let mut iter = cmt
-                .split('\n')
-                .skip_while(|&x| !x.contains(META_PARAM_TOKEN));
-            while let Some(c) = iter.next() {
-                if !c.contains(META_PARAM_TOKEN) {
-                    continue;
-                }
-                let items: Vec<&str> = c.split(';').collect();
-                let car = items.first().unwrap().split(':').collect::<Vec<&str>>()[1]
-                    .trim()
-                    .to_string();
-                let cdr = items[1..].join(";");
-                let meta_value =
-                    MetaValue::from_meta_comment(&cdr).map_or(MetaValue::new(), |x| x.to_owned());
-                param2meta.insert(car, meta_value);
-            }
-        }
-
         let mut params: Vec<GoParameter> = vec![];
         for cparam in &c.parameters {
-            let meta_option = param2meta.get(&cparam.label);
+            let meta_option = cparam.meta.as_ref();
             let mut gparam = GoParameter::from(cparam, meta_option);
             if let Some(sname) = struct_name {
                 /* Attached function parameters */
@@ -622,9 +936,13 @@ impl GoFunction {
                         gparam.as_c_field = format!("{}{}", C_STRUCT_PREFACE, sname);
                     } else if mv.is_output {
                         gparam.as_c_field = match &gparam.go_type {
-                            GoType::Complex(c, _) => {
-                                format!("{}{}", C_STRUCT_PREFACE, c.to_string())
-                            }
+                            GoType::Complex(c, _) => match &cparam.variable_type.kind {
+                                CType::Struct(s) => {
+                                    format!("{}{}", C_STRUCT_PREFACE, s.identifier.label)
+                                }
+                                _ => format!("{}{}", C_STRUCT_PREFACE, c.to_string()),
+                            },
+                            GoType::Basic(_, _) => gparam.as_c_field.to_owned(),
                             _ => panic!("cannot modify gparam. Invalid go_type for c_field"),
                         }
                     }
@@ -650,6 +968,160 @@ impl GoFunction {
             params.push(gparam);
         }
 
+        /* The values and length of a map are made from the map itself */
+        for h in Hashmap::all(&c.parameters) {
+            for gparam in params.iter_mut() {
+                let label = gparam.c_identifier.label.to_owned();
+                if label == h.keys.label {
+                    gparam.map_values =
+                        h.values.map(|v| GoIdentifier::new(&v.label, None).go_label);
+                    gparam.map_length =
+                        h.length.map(|l| GoIdentifier::new(&l.label, None).go_label);
+                    gparam.map_length_type = h.length.map(|l| cgo_type(&l.variable_type.kind));
+                } else if h.values.is_some_and(|v| v.label == label)
+                    || h.length.is_some_and(|l| l.label == label)
+                {
+                    gparam.is_map_part = true;
+                    gparam.show_in_signature = false;
+                    gparam.requires_free = false;
+                }
+            }
+        }
+
+        /* The length of a buffer is taken from the buffer itself, or, for outputs, is written to by the library */
+        for b in Bytes::all(&c.parameters) {
+            let data = GoIdentifier::new(&b.data.label, None).go_label;
+            let length = match b.length {
+                Some(l) => l,
+                None => continue,
+            };
+            let length_label = GoIdentifier::new(&length.label, None).go_label;
+            for gparam in params.iter_mut() {
+                let label = gparam.c_identifier.label.to_owned();
+                if label == b.data.label && b.is_output() {
+                    gparam.c_output_type =
+                        Some(format!("*{}", cgo_type(&b.data.variable_type.kind)));
+                    gparam.from_c_field = GoParameter::from_c_bytes(
+                        &format!("{}{}", C_PREFACE, data),
+                        &format!("{}{}", C_PREFACE, length_label),
+                    );
+                    /* the library's own function gets back the buffer along with its length */
+                    gparam.list_free = gparam
+                        .meta
+                        .free_with
+                        .as_ref()
+                        .filter(|_| !gparam.meta.is_borrowed)
+                        .map(|free_with| {
+                            format!(
+                                "C.{}({}{}, {}{})",
+                                free_with, C_PREFACE, data, C_PREFACE, length_label
+                            )
+                        });
+                } else if label == length.label {
+                    let length_type = cgo_type(&length.variable_type.kind);
+                    gparam.show_in_signature = false;
+                    gparam.requires_free = false;
+                    gparam.by_reference = b.is_output();
+                    gparam.as_c_field = if b.is_output() {
+                        format!("{}(0)", length_type)
+                    } else {
+                        format!("{}(len({}))", length_type, data)
+                    };
+                }
+            }
+        }
+
+        /* The elements of a list are converted one by one, and its length and capacity are taken from the slice,
+        or written to by the library */
+        for l in List::all(&c.parameters) {
+            let (element, length) = match (l.element(), l.length) {
+                (Some(e), Some(len)) => (GoList::from(&e), len),
+                _ => continue,
+            };
+            let items = GoIdentifier::new(&l.items.label, None).go_label;
+            let c_items = format!("{}{}", C_PREFACE, items);
+            let c_length = format!(
+                "{}{}",
+                C_PREFACE,
+                GoIdentifier::new(&length.label, None).go_label
+            );
+            for gparam in params.iter_mut() {
+                let label = gparam.c_identifier.label.to_owned();
+                if label == l.items.label {
+                    gparam.is_struct = false;
+                    gparam.list_c_type = Some(element.c_type.to_owned());
+                    if l.is_output() {
+                        gparam.from_c_field = element.read(&c_items, &c_length);
+                        /* The array is only known once the library has written it */
+                        gparam.requires_free = false;
+                        gparam.list_free = match &gparam.meta.free_with {
+                            _ if gparam.meta.is_borrowed => None,
+                            Some(free_with) => Some(format!(
+                                "C.{}({}, {}{})",
+                                free_with,
+                                c_items,
+                                c_length,
+                                l.capacity
+                                    .map(|cap| format!(
+                                        ", {}{}",
+                                        C_PREFACE,
+                                        GoIdentifier::new(&cap.label, None).go_label
+                                    ))
+                                    .unwrap_or_default()
+                            )),
+                            None => Some(format!(
+                                "_freeCList({}, int({}), {})",
+                                c_items, c_length, element.release
+                            )),
+                        };
+                    } else {
+                        gparam.requires_free = false;
+                        gparam.list_to_c = element.to_c.to_owned();
+                        gparam.list_release = Some(element.release.to_owned());
+                    }
+                } else if let Some(size) = [Some(length), l.capacity]
+                    .into_iter()
+                    .flatten()
+                    .find(|size| size.label == label)
+                {
+                    /* a slice has no room to spare, so an input's capacity is its length */
+                    let size_type = cgo_type(&size.variable_type.kind);
+                    gparam.show_in_signature = false;
+                    gparam.requires_free = false;
+                    gparam.by_reference = l.is_output();
+                    gparam.as_c_field = if l.is_output() {
+                        format!("{}(0)", size_type)
+                    } else {
+                        format!("{}(len({}))", size_type, items)
+                    };
+                }
+            }
+        }
+
+        let outputs: Vec<&GoParameter> = params.iter().filter(|p| p.meta.is_output).collect();
+        let has_multiple_outputs = outputs.len() > 1 && !function_meta_values.is_constructor;
+        let multiple_return_signature = format!(
+            "({}{})",
+            outputs
+                .iter()
+                .map(|p| match &p.go_type {
+                    GoType::Complex(_, _) => format!("{} *{}", p.identifier.go_label, p.go_type),
+                    _ => format!("{} {}", p.identifier.go_label, p.go_type),
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+            if function_meta_values.throws {
+                ", err error"
+            } else {
+                ""
+            }
+        );
+
+        /* A setter hands nothing back, whatever the C function returns */
+        if matches!(Property::of(c), Some((_, false))) {
+            return_type = GoType::Void;
+        }
+
         /* Massage return types if not constructor and has output */
         if !function_meta_values.is_constructor && !function_meta_values.is_destructor {
             for param in &params {
@@ -660,15 +1132,50 @@ impl GoFunction {
             }
         }
 
+        /* A getter of a number or a duration reads it into a plain C value, and returns it as is */
+        let mut returns_by_value = false;
+        if matches!(Property::of(c), Some((_, true))) {
+            for (param, value) in params.iter_mut().zip(&c.parameters) {
+                if !param.meta.is_output {
+                    continue;
+                }
+                let c_label = format!("{}{}", C_PREFACE, param.identifier.go_label);
+                let from_c_field = match &param.go_type {
+                    GoType::Complex(GoTypeComplex::Duration, _) => {
+                        format!("time.Duration({}) * time.Millisecond", c_label)
+                    }
+                    GoType::Basic(GoTypeBasic::String | GoTypeBasic::Bytes, _) => continue,
+                    GoType::Basic(basic, _) => format!("{}({})", basic, c_label),
+                    _ => continue,
+                };
+                param.c_output_type = Some(cgo_type(&value.variable_type.kind));
+                param.from_c_field = from_c_field;
+                param.is_complex = false;
+                returns_by_value = true;
+            }
+        }
+
         let gf = GoFunction {
             on_struct,
             identifier,
             c_function_name: c.label.to_owned(),
             go_comment,
-            return_signature: GoFunction::make_return_signature(
-                &return_type,
-                &function_meta_values,
-            ),
+            return_signature: if has_multiple_outputs {
+                multiple_return_signature
+            } else if returns_by_value && function_meta_values.throws {
+                format!("({}, error)", return_type)
+            } else if returns_by_value {
+                return_type.to_string()
+            } else {
+                GoFunction::make_return_signature(&return_type, &function_meta_values)
+            },
+            has_multiple_outputs,
+            returns_by_value,
+            cancel_with: None, // is re-assessed later on
+            cancel_handle: None,
+            completion: None,
+            options: None,
+            deprecation: GoComment::with_deprecation(None, &c.deprecated),
             meta: function_meta_values,
             is_void: matches!(return_type, GoType::Void),
             is_return_struct: matches!(return_type, GoType::Complex(GoTypeComplex::Struct(_), _)),
@@ -689,19 +1196,23 @@ struct GoStruct {
     constructor: Option<GoFunction>,
     fields: Vec<GoField>,
     meta: MetaValue,
+    /// Set for a struct marked `iterator`, see `Cursor`
+    iterator: Option<GoIterator>,
+    /// Set for a struct marked `value`
+    value: Option<GoValueType>,
 }
 impl From<&CStruct> for GoStruct {
     fn from(c: &CStruct) -> Self {
-        let identifier = GoIdentifier::new(&c.identifier.label, None);
+        let identifier = GoIdentifier::new(Language::Go.struct_name(c), None);
         let c_identifier = CIdentifier::new(&c.identifier.label, None);
         let comment = match &c.comment {
             Some(cmt) => Some(GoComment::new(&cmt)),
 
             None => None,
         };
+        let comment = GoComment::with_deprecation(comment, &c.deprecated);
 
-        let meta: MetaValue = MetaValue::from_meta_comment_dontcare(&c.comment)
-            .map_or(MetaValue::new(), |x| x.to_owned());
+        let meta: MetaValue = c.meta.to_owned().unwrap_or_else(MetaValue::new);
 
         let mut fields: Vec<GoField> =
             c.declarations
@@ -727,13 +1238,52 @@ impl From<&CStruct> for GoStruct {
                 })
                 .collect();
 
+        for h in Hashmap::all(&c.declarations) {
+            for field in fields.iter_mut() {
+                let label = field.c_identifier.label.to_owned();
+                if label == h.keys.label {
+                    field.map_values = h.values.map(|v| v.label.to_owned());
+                    field.map_length = h.length.map(|l| l.label.to_owned());
+                } else if h.values.is_some_and(|v| v.label == label)
+                    || h.length.is_some_and(|l| l.label == label)
+                {
+                    field.is_map_part = true;
+                }
+            }
+        }
+
+        for b in Bytes::all(&c.declarations) {
+            if let Some(length) = b.length {
+                for field in fields.iter_mut() {
+                    if field.c_identifier.label == b.data.label {
+                        field.from_c_field = GoParameter::from_c_bytes(
+                            &format!("{}.{}", FIELD_SELF, b.data.label),
+                            &format!("{}.{}", FIELD_SELF, length.label),
+                        );
+                    }
+                }
+            }
+        }
+
+        for l in List::all(&c.declarations) {
+            if let (Some(e), Some(length)) = (l.element(), l.length) {
+                for field in fields.iter_mut() {
+                    if field.c_identifier.label == l.items.label {
+                        field.list_from_c = Some(GoList::from(&e).read(
+                            &format!("{}.{}", FIELD_SELF, l.items.label),
+                            &format!("{}.{}", FIELD_SELF, length.label),
+                        ));
+                    }
+                }
+            }
+        }
+
         if meta.is_persistent {
             let gf = GoField {
                 as_c_field: format!("{}{}", FIELD_SELF, FIELD_PTR),
                 from_c_field: "TOFO(NF, IS_PERSISTENT)".to_owned(),
                 go_comment: Some(GoComment::new("// reference to C pointer")),
-                meta: MetaValue::from_meta_comment_dontcare(&None)
-                    .map_or(MetaValue::new(), |x| x.to_owned()),
+                meta: MetaValue::new(),
                 identifier: GoIdentifier {
                     go_label: FIELD_PTR.to_owned(),
                     go_comment: None,
@@ -743,6 +1293,10 @@ impl From<&CStruct> for GoStruct {
                 is_complex: true,
                 is_struct: true,
                 is_list: false,
+                map_values: None,
+                map_length: None,
+                is_map_part: false,
+                list_from_c: None,
                 c_identifier,
                 pointer_count: 0,
                 go_type: GoType::Complex(
@@ -767,6 +1321,130 @@ impl From<&CStruct> for GoStruct {
             functions,
             fields,
             meta,
+            iterator: None, // is re-assessed later on
+            value: None,    // is re-assessed later on
+        }
+    }
+}
+
+/// The `Equal` and `String` methods of a struct marked `value`, see `ValueType`.
+///
+/// Slices and maps are compared by their elements, and pointers, which stand for `nullable` fields, by what they point to.
+/// The helpers doing so are our own, so that the bindings need no more than `fmt` for them
+#[derive(Serialize)]
+struct GoValueType {
+    /// What `Equal` compares `v` and `other` by, e.g., `_valueEqualPtr(v.RefreshToken, other.RefreshToken)`
+    comparisons: Vec<String>,
+    /// The format `String` prints the fields with, e.g., `{AccessToken: %v, RefreshToken: %v}`
+    format: String,
+    /// The arguments of `format`, e.g., `_valueString(v.RefreshToken)`
+    arguments: Vec<String>,
+}
+
+impl From<&[GoField]> for GoValueType {
+    fn from(fields: &[GoField]) -> Self {
+        let mut comparisons: Vec<String> = vec![];
+        let mut names: Vec<String> = vec![];
+        let mut arguments: Vec<String> = vec![];
+        for field in fields.iter().filter(|f| !f.is_map_part) {
+            let label = &field.identifier.go_label;
+            let (x, y) = (format!("v.{}", label), format!("other.{}", label));
+            let is_pointer = matches!(
+                field.go_type,
+                GoType::Basic(_, 1..) | GoType::Complex(_, 1..)
+            );
+            /* compares what a pointer points to, and is called as a method where it is one */
+            let equal = match &field.go_type {
+                GoType::Complex(GoTypeComplex::Timestamp, _) => Some("time.Time.Equal".to_owned()),
+                GoType::Complex(GoTypeComplex::Url, _) => Some("_valueEqualURL".to_owned()),
+                GoType::Complex(GoTypeComplex::Enum(s), _) if field.is_struct => {
+                    Some(format!("{}.Equal", s.go_label))
+                }
+                _ => None,
+            };
+            let comparison = match (&field.go_type, &equal) {
+                (GoType::Complex(GoTypeComplex::Map, _), _) => {
+                    format!("_valueEqualMap({}, {})", x, y)
+                }
+                (GoType::Complex(GoTypeComplex::List(element), _), _) => match element.as_ref() {
+                    GoType::Complex(GoTypeComplex::Struct(s), _) => {
+                        format!("_valueEqualSliceFunc({}, {}, {}.Equal)", x, y, s.go_label)
+                    }
+                    _ => format!("_valueEqualSlice({}, {})", x, y),
+                },
+                (GoType::Basic(GoTypeBasic::Bytes, _), _) => {
+                    format!("_valueEqualSlice({}, {})", x, y)
+                }
+                (_, Some(equal)) if is_pointer => {
+                    format!("_valueEqualFunc({}, {}, {})", x, y, equal)
+                }
+                (_, None) if is_pointer => format!("_valueEqualPtr({}, {})", x, y),
+                (_, Some(equal)) if equal.ends_with(".Equal") => format!("{}.Equal({})", x, y),
+                (_, Some(equal)) => format!("{}({}, {})", equal, x, y),
+                (_, None) => format!("{} == {}", x, y),
+            };
+            if ValueType::compares(Some(&field.meta)) {
+                comparisons.push(comparison);
+            }
+            let is_url = matches!(field.go_type, GoType::Complex(GoTypeComplex::Url, _));
+            arguments.push(match (is_pointer, is_url) {
+                /* `*url.URL` prints itself, and `nil` as `<nil>` */
+                (true, true) => x,
+                (true, false) => format!("_valueString({})", x),
+                (false, true) => format!("{}.String()", x),
+                (false, false) => x,
+            });
+            names.push(format!("{}: %v", label));
+        }
+        if comparisons.is_empty() {
+            comparisons.push("true".to_owned());
+        }
+        GoValueType {
+            comparisons,
+            format: format!("{{{}}}", names.join(", ")),
+            arguments,
+        }
+    }
+}
+
+/// The `Items` method of a cursor, which walks through its items as an `iter.Seq`
+#[derive(Serialize)]
+struct GoIterator {
+    go_comment: GoComment,
+    /// Go type of an item, e.g., `TokenResponse`
+    item_type: String,
+    /// cgo type of an item, e.g., `C.struct_TokenResponse`
+    c_item_type: String,
+    /// `{cursor}_next`
+    next: String,
+    /// `func(e *c_item_type) item_type`, copying or wrapping an item
+    from_c: String,
+    /// Statement releasing `c_Item` once it is copied, left out for `persistent` items
+    release: Option<String>,
+    /// Destructor of the cursor, called once the items run out
+    free_cursor: Option<String>,
+}
+impl From<&Cursor<'_>> for GoIterator {
+    fn from(c: &Cursor) -> Self {
+        let release = if c.is_item_persistent() {
+            None
+        } else {
+            Some(match c.item_release() {
+                Some(f) => format!("C.{}(c_Item)", f),
+                None => "C.free(unsafe.Pointer(c_Item))".to_owned(),
+            })
+        };
+        GoIterator {
+            go_comment: GoComment::new(&format!(
+                "// Items iterates over the items handed out by `{}`, releasing the cursor once they run out",
+                c.next.label
+            )),
+            item_type: GoIdentifier::new(Language::Go.struct_name(c.item), None).go_label,
+            c_item_type: format!("{}{}", C_STRUCT_PREFACE, c.item.identifier.label),
+            next: c.next.label.to_owned(),
+            from_c: GoList::from(&Element::StructPointer(c.item)).from_c,
+            release,
+            free_cursor: c.destructor.map(|f| f.label.to_owned()),
         }
     }
 }
@@ -779,8 +1457,7 @@ struct GoEnum {
 }
 impl From<&CEnum> for GoEnum {
     fn from(c: &CEnum) -> Self {
-        let meta: MetaValue = MetaValue::from_meta_comment_dontcare(&c.comment)
-            .map_or(MetaValue::new(), |x| x.to_owned());
+        let meta: MetaValue = c.meta.to_owned().unwrap_or_else(MetaValue::new);
 
         let identifier = GoIdentifier::new(&c.identifier.label, None);
         let comment = match &c.comment {
@@ -790,7 +1467,12 @@ impl From<&CEnum> for GoEnum {
         let values: Vec<GoIdentifier> = c
             .declarations
             .iter()
-            .map(|m| GoIdentifier::new(&m.label, m.comment.to_owned()))
+            .map(|m| {
+                let mut value =
+                    GoIdentifier::new(&Language::Go.member_name(m), m.comment.to_owned());
+                value.go_comment = GoComment::with_deprecation(value.go_comment, &m.deprecated);
+                value
+            })
             .collect();
 
         GoEnum {
@@ -852,9 +1534,36 @@ struct GoParameter {
     from_c_field: String,
     requires_free: bool,
     show_in_signature: bool,
+    /// The cgo type of a numeric `output`, which is declared as a plain C value and handed over by reference
+    c_output_type: Option<String>,
+    /// For the keys of a `hashmap`, the go label of its values parameter
+    map_values: Option<String>,
+    /// For the keys of a `hashmap`, the go label of its length parameter
+    map_length: Option<String>,
+    /// For the keys of a `hashmap`, the cgo type of its length parameter
+    map_length_type: Option<String>,
+    /// Whether this is the values or the length of a `hashmap`, which are made along with its keys
+    is_map_part: bool,
+    /// Whether this is handed over by reference for the library to write to, e.g., the length of an output buffer
+    by_reference: bool,
+    /// For a `list`, the cgo type of its elements
+    list_c_type: Option<String>,
+    /// For a `list` handed to the library, the function converting each element to C, see `_toCList`
+    list_to_c: Option<String>,
+    /// For a `list` handed to the library, the function releasing each C element, or `nil`
+    list_release: Option<String>,
+    /// For a `list` or `bytes` handed back by the library, the statement releasing the C array once its elements are copied
+    list_free: Option<String>,
+    /// Whether this is a string `output` or `error` the library allocates, released with `C.free` once it is read
+    frees_output: bool,
     meta: MetaValue,
 }
 impl GoParameter {
+    /// Copies the C buffer `data` of `length` bytes into a `[]byte`
+    fn from_c_bytes(data: &str, length: &str) -> String {
+        format!("C.GoBytes(unsafe.Pointer({}), C.int({}))", data, length)
+    }
+
     fn from(c: &CVariableDeclaration, meta: Option<&MetaValue>) -> Self {
         let meta_value = match meta {
             Some(m) => m.clone(),
@@ -870,10 +1579,54 @@ impl GoParameter {
             GoType::Complex(gtype, _) => gtype.make_c_value(&format!("{}", identifier.go_label)),
         };
 
+        /* a nullable string or url is handed over as a null pointer when it is nil */
+        let as_c_field = match &go_type {
+            GoType::Basic(GoTypeBasic::String, 1) | GoType::Complex(GoTypeComplex::Url, 1)
+                if meta_value.is_nullable && !meta_value.is_output && !meta_value.is_error =>
+            {
+                format!(
+                    "func() *C.char {{ if {} == nil {{ return nil }}; return {} }}()",
+                    identifier.go_label,
+                    match &go_type {
+                        GoType::Basic(_, _) => format!("C.CString(*{})", identifier.go_label),
+                        _ => as_c_field,
+                    }
+                )
+            }
+            _ => as_c_field,
+        };
+
+        let c_output_type = match &go_type {
+            GoType::Basic(GoTypeBasic::String, _) => None,
+            GoType::Basic(_, _) if meta_value.is_output => Some(cgo_type(&c.variable_type.kind)),
+            _ => None,
+        };
+
         GoParameter {
             pointer_count: c.variable_type.pointer_count,
+            c_output_type,
+            map_values: None,
+            map_length: None,
+            map_length_type: None,
+            is_map_part: false,
+            by_reference: false,
+            list_c_type: None,
+            list_to_c: None,
+            list_release: None,
+            list_free: None,
             as_c_field,
-            requires_free: meta_value.is_error || go_type.requires_free(),
+            frees_output: (meta_value.is_error
+                || (meta_value.is_output
+                    && matches!(go_type, GoType::Basic(GoTypeBasic::String, _))))
+                && !meta_value.is_borrowed
+                && meta_value.free_with.is_none(),
+            /* Values released by the library's own function, or not at all, are left out of the `C.free` calls,
+            and so are outputs, which are only set by the call */
+            requires_free: go_type.requires_free()
+                && !meta_value.is_output
+                && !meta_value.is_error
+                && !meta_value.is_borrowed
+                && meta_value.free_with.is_none(),
             show_in_signature: !meta_value.is_this && !meta_value.is_output && !meta_value.is_error,
             meta: meta_value,
             is_complex: matches!(go_type, GoType::Complex(_, _)),
@@ -895,6 +1648,166 @@ impl GoParameter {
     }
 }
 
+/// How the elements of a `List` are converted between C and Go
+struct GoList {
+    /// cgo type of an element, e.g., `*C.char`
+    c_type: String,
+    /// Go type of an element, e.g., `string`
+    go_type: GoType,
+    /// `func(e c_type) go_type`, handed to `_fromCList`
+    from_c: String,
+    /// `func(v go_type) c_type`, handed to `_toCList`, for the elements the library can be handed
+    to_c: Option<String>,
+    /// `func(e c_type)` releasing what `to_c` made, or `nil`
+    release: String,
+}
+
+impl GoList {
+    fn from(e: &Element) -> Self {
+        match e {
+            Element::String => GoList {
+                c_type: "*C.char".to_owned(),
+                go_type: GoType::Basic(GoTypeBasic::String, 0),
+                from_c: "func(e *C.char) string { return C.GoString(e) }".to_owned(),
+                to_c: Some("func(v string) *C.char { return C.CString(v) }".to_owned()),
+                release: "func(e *C.char) { C.free(unsafe.Pointer(e)) }".to_owned(),
+            },
+            Element::Primitive(kind) => GoList::converted(
+                cgo_type(kind),
+                GoType::from(&CVariableType {
+                    kind: (*kind).clone(),
+                    is_struct: false,
+                    pointer_count: 0,
+                }),
+            ),
+            Element::Enum(en) => GoList::converted(
+                format!("{}{}", C_ENUM_PREFACE, en.identifier.label),
+                GoType::Complex(
+                    GoTypeComplex::Enum(GoIdentifier::new(&en.identifier.label, None)),
+                    0,
+                ),
+            ),
+            Element::Struct(st) | Element::StructPointer(st) => {
+                let go_label = GoIdentifier::new(Language::Go.struct_name(st), None).go_label;
+                let c_struct = format!("{}{}", C_STRUCT_PREFACE, st.identifier.label);
+                let is_pointer = matches!(e, Element::StructPointer(_));
+                let c_type = if is_pointer {
+                    format!("*{}", c_struct)
+                } else {
+                    c_struct.to_owned()
+                };
+                let is_persistent = st.meta.as_ref().is_some_and(|m| m.is_persistent);
+                GoList {
+                    from_c: if is_persistent {
+                        format!(
+                            "func(e {}) {} {{ return {}{{ptr: unsafe.Pointer(e)}} }}",
+                            c_type, go_label, go_label
+                        )
+                    } else {
+                        /* An element that cannot be read is left empty, rather than failing the whole list */
+                        format!(
+                            "func(e {}) (v {}) {{ if p, err := _{}FromCStruct({}e); err == nil {{ v = *p }}; return }}",
+                            c_type,
+                            go_label,
+                            go_label,
+                            if is_pointer { "" } else { "&" }
+                        )
+                    },
+                    to_c: (is_pointer && is_persistent).then(|| {
+                        format!(
+                            "func(v {}) {} {{ return ({})(v.ptr) }}",
+                            go_label, c_type, c_type
+                        )
+                    }),
+                    release: "nil".to_owned(),
+                    go_type: GoType::Complex(
+                        GoTypeComplex::Struct(GoIdentifier::new(
+                            Language::Go.struct_name(st),
+                            None,
+                        )),
+                        0,
+                    ),
+                    c_type,
+                }
+            }
+        }
+    }
+
+    /// Numbers and enums, which are converted to and from C with a plain type conversion
+    fn converted(c_type: String, go_type: GoType) -> Self {
+        GoList {
+            from_c: format!("func(e {}) {} {{ return {}(e) }}", c_type, go_type, go_type),
+            to_c: Some(format!(
+                "func(v {}) {} {{ return {}(v) }}",
+                go_type, c_type, c_type
+            )),
+            release: "nil".to_owned(),
+            c_type,
+            go_type,
+        }
+    }
+
+    /// Copies the C array `items` of `length` elements into a slice
+    fn read(&self, items: &str, length: &str) -> String {
+        format!("_fromCList({}, int({}), {})", items, length, self.from_c)
+    }
+}
+
+/// The name cgo gives the C type `kind`
+fn cgo_type(kind: &CType) -> String {
+    let name = match kind {
+        CType::Char(_) => "char",
+        CType::SignedShort(_) => "short",
+        CType::UnsignedShort(_) => "ushort",
+        CType::SignedInteger(_) => "int",
+        CType::UnsignedInteger(_) => "uint",
+        CType::SignedLong(_) => "long",
+        CType::UnsignedLong(_) => "ulong",
+        CType::Float(_) => "float",
+        CType::Double(_) => "double",
+        CType::Int64T(_) => "int64_t",
+        CType::IntPtrT(_) => "intptr_t",
+        CType::UIntPtrT(_) => "uintptr_t",
+        CType::Int8T(_) => "int8_t",
+        CType::Int16T(_) => "int16_t",
+        CType::Int32T(_) => "int32_t",
+        CType::UInt8T(_) => "uint8_t",
+        CType::UInt16T(_) => "uint16_t",
+        CType::UInt32T(_) => "uint32_t",
+        CType::UInt64T(_) => "uint64_t",
+        CType::Enum(e) => return format!("{}{}", C_ENUM_PREFACE, e.identifier.label),
+        CType::Struct(s) => return format!("{}{}", C_STRUCT_PREFACE, s.identifier.label),
+        _ => return "unsafe.Pointer".to_owned(),
+    };
+    format!("C.{}", name)
+}
+
+/// The C spelling of the type of `p`, for declarations in the preamble, e.g., `struct Token *`
+fn c_declaration(p: &CVariableDeclaration) -> String {
+    let name = match &p.variable_type.kind {
+        CType::Enum(e) => format!("enum {}", e.identifier.label),
+        CType::Struct(s) => format!("struct {}", s.identifier.label),
+        CType::Void => "void".to_owned(),
+        CType::SignedShort(_) => "short".to_owned(),
+        CType::UnsignedShort(_) => "unsigned short".to_owned(),
+        CType::SignedInteger(_) => "int".to_owned(),
+        CType::UnsignedInteger(_) => "unsigned int".to_owned(),
+        CType::SignedLong(_) => "long".to_owned(),
+        CType::UnsignedLong(_) => "unsigned long".to_owned(),
+        kind => cgo_type(kind).trim_start_matches("C.").to_owned(),
+    };
+    format!(
+        "{}{}{}",
+        if p.is_const { "const " } else { "" },
+        name,
+        if p.variable_type.pointer_count > 0 {
+            format!(" {}", "*".repeat(p.variable_type.pointer_count.into()))
+        } else {
+            "".to_owned()
+        }
+    )
+}
+
 #[derive(Serialize, Clone)]
 struct GoField {
     identifier: GoIdentifier,
@@ -910,28 +1823,38 @@ struct GoField {
     is_complex: bool,
     is_struct: bool,
     is_list: bool,
+    /// For the keys of a `hashmap`, the C labels of its values and length fields
+    map_values: Option<String>,
+    map_length: Option<String>,
+    /// Whether this is the values or the length of a `hashmap`, which are read along with its keys
+    is_map_part: bool,
+    /// For a `list`, the slice copied from its elements, see `_fromCList`
+    list_from_c: Option<String>,
     meta: MetaValue,
 }
 
 impl From<&CVariableDeclaration> for GoField {
     fn from(c: &CVariableDeclaration) -> Self {
         let go_type = GoType::from(c);
-        let go_identifier = GoIdentifier::new(&c.label, None);
-        let meta_value = MetaValue::from_meta_comment_dontcare(&c.comment)
-            .map_or(MetaValue::new(), |x| x.to_owned());
+        let go_identifier = GoIdentifier::new(Language::Go.name_of(&c.label, &c.meta), None);
+        let meta_value = c.meta.to_owned().unwrap_or_else(MetaValue::new);
         GoField {
             identifier: go_identifier,
             c_identifier: CIdentifier::new(&c.label, None),
-            go_comment: match &c.comment {
-                Some(cmt) => Some(GoComment::new(&cmt)),
-                None => None,
-            },
-            requires_pointer_dereference: if meta_value.is_url || c.variable_type.is_struct {
+            go_comment: GoComment::with_deprecation(
+                c.comment.as_deref().map(GoComment::new),
+                &c.deprecated,
+            ),
+            requires_pointer_dereference: if meta_value.is_list {
+                false
+            } else if meta_value.is_url || c.variable_type.is_struct {
                 true
             } else {
                 false
             },
-            requires_pointer_reference: if meta_value.is_nullable {
+            requires_pointer_reference: if meta_value.is_hashmap || meta_value.is_bytes {
+                false
+            } else if meta_value.is_nullable {
                 true
             } else if meta_value.is_string {
                 if c.variable_type.pointer_count > 1 {
@@ -952,6 +1875,10 @@ impl From<&CVariableDeclaration> for GoField {
             pointer_count: c.variable_type.pointer_count,
             as_c_field: "".to_owned(),
             from_c_field: "".to_owned(),
+            map_values: None,
+            map_length: None,
+            is_map_part: false,
+            list_from_c: None,
             meta: meta_value,
         }
     }
@@ -997,6 +1924,18 @@ struct DataGoInformation {
     package_name: String,
     ld_flags: String,
     header_file_location: String,
+    /// Whether failures are reported with the sentinel error of their code, see `error_codes`
+    has_error_codes: bool,
+    /// What a throwing function returns on success, the code of `0` of the error codes if there is one
+    success_code: String,
+    /// Whether a struct is marked `iterator`, which needs the `iter` package
+    has_iterators: bool,
+    /// Whether a function is `cancellable`, which needs the `context` package and the cancel handle helpers
+    has_cancellables: bool,
+    /// Whether a struct is marked `value`, which needs the `fmt` package and the `_valueEqual` helpers
+    has_value_types: bool,
+    /// The declarations of the callbacks of `async_callback` functions, see `GoCompletion`
+    completion_prototypes: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -1004,8 +1943,30 @@ struct Data {
     go_enums: Vec<GoEnum>,
     go_structs: Vec<GoStruct>,
     go_functions: Vec<GoFunction>,
+    go_error_codes: Vec<GoErrorCode>,
     meta: DataGoInformation,
 }
+
+/// A failure code of the library, reported as a sentinel error that can be matched with `errors.Is`
+#[derive(Serialize)]
+struct GoErrorCode {
+    /// e.g., `ErrOauthtoolFailNullPointer`
+    go_label: String,
+    /// C name of the code, used as the message of the sentinel error
+    c_label: String,
+    value: i32,
+    go_comment: Option<GoComment>,
+}
+impl From<&ErrorCode> for GoErrorCode {
+    fn from(e: &ErrorCode) -> Self {
+        GoErrorCode {
+            go_label: format!("Err{}", e.name),
+            c_label: e.label.to_owned(),
+            value: e.value,
+            go_comment: e.comment.as_deref().map(GoComment::new),
+        }
+    }
+}
 impl Data {
     fn new(
         header: &HeaderFile,
@@ -1019,9 +1980,43 @@ impl Data {
         let mut go_functions: Vec<GoFunction> = header
             .functions
             .iter()
+            .filter(|f| !Cursor::is_next(header, f))
             .map(|f| GoFunction::from_cfunc(&go_enums, &mut go_structs, f))
             .collect();
 
+        let cancellations = Cancellation::all(header);
+        for c in &cancellations {
+            if let Some(gfunc) = go_functions
+                .iter_mut()
+                .find(|f| f.c_function_name == c.function.label)
+            {
+                gfunc.make_cancellable(c);
+            }
+        }
+
+        let completions = Completion::all(header);
+        for c in &completions {
+            if let Some(gfunc) = go_functions
+                .iter_mut()
+                .find(|f| f.c_function_name == c.function.label)
+            {
+                gfunc.make_completion(c);
+            }
+        }
+        for f in &header.functions {
+            if let Some(gfunc) = go_functions
+                .iter_mut()
+                .find(|g| g.c_function_name == f.label)
+            {
+                gfunc.make_options(f);
+            }
+        }
+        let completion_prototypes: Vec<String> = go_functions
+            .iter()
+            .filter_map(|f| f.completion.as_ref())
+            .map(|c| c.prototype.to_owned())
+            .collect();
+
         /* attach to structs as necessary */
         for gfunc in &go_functions {
             if let Some(n) = &gfunc.on_struct {
@@ -1040,6 +2035,29 @@ impl Data {
         }
         go_functions.retain(|f| matches!(f.on_struct, None));
 
+        let cursors = Cursor::all(header);
+        for cursor in &cursors {
+            if let Some(gstruct) = go_structs
+                .iter_mut()
+                .find(|s| s.c_label == cursor.owner.identifier.label)
+            {
+                gstruct.iterator = Some(GoIterator::from(cursor));
+            }
+        }
+
+        let value_types = ValueType::all(header);
+        for v in &value_types {
+            if let Some(gstruct) = go_structs
+                .iter_mut()
+                .find(|s| s.c_label == v.owner.identifier.label)
+            {
+                gstruct.value = Some(GoValueType::from(gstruct.fields.as_slice()));
+            }
+        }
+
+        let go_error_codes: Vec<GoErrorCode> =
+            error_codes(header).iter().map(GoErrorCode::from).collect();
+
         return Data {
             go_enums,
             go_structs,
@@ -1048,7 +2066,16 @@ impl Data {
                 package_name: package_name.to_owned(),
                 ld_flags: ld_flags.to_owned(),
                 header_file_location: header_file_location.to_owned(),
+                has_error_codes: !go_error_codes.is_empty(),
+                success_code: success_code(header)
+                    .map(|code| format!("C.{}", code))
+                    .unwrap_or_else(|| "0".to_owned()),
+                has_iterators: !cursors.is_empty(),
+                has_cancellables: !cancellations.is_empty(),
+                has_value_types: !value_types.is_empty(),
+                completion_prototypes,
             },
+            go_error_codes,
         };
     }
 }
@@ -1062,13 +2089,14 @@ fn is_some(value: Option<&Value>, _: &[Value]) -> tera::Result<bool> {
 }
 
 pub fn generate(
-    header: HeaderFile,
+    header: &HeaderFile,
     package_name: &str,
     ld_flags: &str,
     header_file_location: &str,
 ) -> String {
+    let header = &Language::Go.header(header);
     let data = Data::new(
-        &header,
+        header,
         package_name,
         ld_flags,
         header_file_location,
@@ -1082,6 +2110,7 @@ pub fn generate(
     context.insert("structs", &data.go_structs);
     context.insert("functions", &data.go_functions);
     context.insert("go_data", &data.meta);
+    context.insert("error_codes", &data.go_error_codes);
 
     let mut tera = Tera::default();
     tera.register_tester("some", is_some);
@@ -1091,6 +2120,7 @@ pub fn generate(
         ("go_header", TEMPLATE_GO_HEADER),
         ("enum_definitions", TEMPLATE_ENUM_DEFINITIONS),
         ("enum_declarations", TEMPLATE_ENUM_DECLARATIONS),
+        ("error_codes", TEMPLATE_ERROR_CODES),
         (
             "struct_inner_declarations",
             TEMPLATE_STRUCT_INNER_DECLARATIONS,
@@ -1125,6 +2155,7 @@ pub fn generate(
         s,
         tera.render("enum_definitions", &context).unwrap()
     );
+    s = format!("{}{}", s, tera.render("error_codes", &context).unwrap());
     s = format!(
         "{}{}",
         s,
@@ -1153,7 +2184,92 @@ pub fn generate(
     s
 }
 
-const TEMPLATE_HELPERS: &'static str = "
+const TEMPLATE_ERROR_CODES: &str = "{% if error_codes|length %}
+// Failure codes of the library, match the errors returned by functions against them with errors.Is
+var (
+    {%- for error_code in error_codes %}
+    {% if error_code.go_comment is some %}{{ error_code.go_comment | trim }}
+    {% endif %}{{ error_code.go_label }} = errors.New(\"{{ error_code.c_label }}\")
+    {%- endfor %}
+)
+
+// codeError is a failure reported by the library, which wraps the sentinel error of its code
+type codeError struct {
+	sentinel error
+	code     int
+	message  string
+}
+
+func (e *codeError) Error() string { return e.message }
+
+func (e *codeError) Unwrap() error { return e.sentinel }
+
+// Code is the failure code returned by the library
+func (e *codeError) Code() int { return e.code }
+
+// errorForCode is the error for the failure `code`, carrying `message`
+func errorForCode(code int, message string) error {
+	var sentinel error
+	switch code {
+	{%- for error_code in error_codes %}
+	case {{ error_code.value }}:
+		sentinel = {{ error_code.go_label }}
+	{%- endfor %}
+	default:
+		return errors.New(message)
+	}
+	return &codeError{sentinel: sentinel, code: code, message: message}
+}
+{% endif %}";
+
+const TEMPLATE_HELPERS: &'static str = "{% if go_data.completion_prototypes %}
+// _completions holds the channel of each call waiting for its callback, keyed by the user data it was handed
+var _completions sync.Map
+
+// _completion is what the callback of a call hands over, see `_complete`
+type _completion[T any] struct {
+	value *T
+	err   error
+}
+
+// _newCompletion makes up the user data of a new call, a key that is handed back to its callback
+func _newCompletion[T any]() unsafe.Pointer {
+	key := C.malloc(1)
+	_completions.Store(uintptr(key), make(chan _completion[T], 1))
+	return key
+}
+
+// _awaitCompletion waits for the callback of the call handed `key`, then releases the key
+func _awaitCompletion[T any](key unsafe.Pointer) (*T, error) {
+	ch, _ := _completions.Load(uintptr(key))
+	c := <-ch.(chan _completion[T])
+	_completions.Delete(uintptr(key))
+	C.free(key)
+	return c.value, c.err
+}
+
+// _complete hands the result of the call handed `key` to the caller waiting for it
+func _complete[T any](key unsafe.Pointer, value *T, err error) {
+	if ch, ok := _completions.Load(uintptr(key)); ok {
+		ch.(chan _completion[T]) <- _completion[T]{value, err}
+	}
+}
+{% endif %}{% if go_data.has_cancellables %}
+var _lastCancelHandle atomic.Uint64
+
+// _nextCancelHandle makes up the handle of a new cancellable call, so that each call is cancelled on its own
+func _nextCancelHandle() uint64 {
+	return _lastCancelHandle.Add(1)
+}
+
+// _orCancelled reports a call that failed once `ctx` was done as cancelled, along with the error of the library
+func _orCancelled(ctx context.Context, err error) error {
+	if ctxErr := ctx.Err(); ctxErr != nil {
+		return errors.Join(ctxErr, err)
+	}
+	return err
+}
+{% endif %}
 func deserializeTime(ts C.longlong) time.Time {
 	var t time.Time
 	if ts > 0 {
@@ -1163,17 +2279,142 @@ func deserializeTime(ts C.longlong) time.Time {
 	return t
 }
 
-func _fromListStruct(arr **C.char, length uint, capacity uint) []string {
-	var go_Arr []string
-	_c_elements := (*[1 << 30]*C.char)(unsafe.Pointer(arr))[length:length]
-	if _c_elements != nil {
-		for _, _c_iter_item := range _c_elements {
-			go_iter_item := C.GoString(_c_iter_item)
-			go_Arr = append(go_Arr, go_iter_item)
+// _toCMap copies `m` into parallel C arrays of keys and values, which are released with _freeCMap
+func _toCMap(m map[string]string) (**C.char, **C.char, int) {
+	length := len(m)
+	if length == 0 {
+		return nil, nil, 0
+	}
+	size := C.size_t(length) * C.size_t(unsafe.Sizeof(uintptr(0)))
+	keys := unsafe.Slice((**C.char)(C.malloc(size)), length)
+	values := unsafe.Slice((**C.char)(C.malloc(size)), length)
+	i := 0
+	for k, v := range m {
+		keys[i] = C.CString(k)
+		values[i] = C.CString(v)
+		i++
+	}
+	return &keys[0], &values[0], length
+}
+
+func _freeCMap(keys **C.char, values **C.char, length int) {
+	if length == 0 {
+		return
+	}
+	for _, arr := range []**C.char{keys, values} {
+		for _, s := range unsafe.Slice(arr, length) {
+			C.free(unsafe.Pointer(s))
+		}
+		C.free(unsafe.Pointer(arr))
+	}
+}
+
+// _fromCMap copies parallel C arrays of keys and values into a map, leaving the arrays to their owner
+func _fromCMap(keys **C.char, values **C.char, length int) map[string]string {
+	m := make(map[string]string, length)
+	if length == 0 || keys == nil || values == nil {
+		return m
+	}
+	ks := unsafe.Slice(keys, length)
+	vs := unsafe.Slice(values, length)
+	for i := range ks {
+		m[C.GoString(ks[i])] = C.GoString(vs[i])
+	}
+	return m
+}
+
+// _toCList copies `items` into a C array, converting each with `convert`. It is released with _freeCList
+func _toCList[E any, T any](items []T, convert func(T) E) *E {
+	if len(items) == 0 {
+		return nil
+	}
+	var e E
+	arr := unsafe.Slice((*E)(C.malloc(C.size_t(len(items))*C.size_t(unsafe.Sizeof(e)))), len(items))
+	for i, item := range items {
+		arr[i] = convert(item)
+	}
+	return &arr[0]
+}
+
+// _freeCList releases a C array made by _toCList, or handed back by the library, along with each of its elements if `release` is set
+func _freeCList[E any](arr *E, length int, release func(E)) {
+	if arr == nil {
+		return
+	}
+	if release != nil {
+		for _, e := range unsafe.Slice(arr, length) {
+			release(e)
+		}
+	}
+	C.free(unsafe.Pointer(arr))
+}
+
+// _fromCList copies the `length` elements of a C array into a slice, converting each with `convert`.
+// The array is left to its owner
+func _fromCList[E any, T any](arr *E, length int, convert func(E) T) []T {
+	items := make([]T, 0, length)
+	if length == 0 || arr == nil {
+		return items
+	}
+	for _, e := range unsafe.Slice(arr, length) {
+		items = append(items, convert(e))
+	}
+	return items
+}{% if go_data.has_value_types %}
+
+// _valueEqualPtr reports whether `a` and `b` are both nil, or point to equal values
+func _valueEqualPtr[T comparable](a, b *T) bool {
+	return a == b || (a != nil && b != nil && *a == *b)
+}
+
+// _valueEqualFunc reports whether `a` and `b` are both nil, or point to values that `equal` says are equal
+func _valueEqualFunc[T any](a, b *T, equal func(T, T) bool) bool {
+	return a == b || (a != nil && b != nil && equal(*a, *b))
+}
+
+// _valueEqualSlice reports whether `a` and `b` hold the same elements in the same order, a nil slice being an empty one
+func _valueEqualSlice[T comparable](a, b []T) bool {
+	return _valueEqualSliceFunc(a, b, func(x, y T) bool { return x == y })
+}
+
+// _valueEqualSliceFunc reports whether `a` and `b` hold elements that `equal` says are equal, in the same order
+func _valueEqualSliceFunc[T any](a, b []T, equal func(T, T) bool) bool {
+	if len(a) != len(b) {
+		return false
+	}
+	for i := range a {
+		if !equal(a[i], b[i]) {
+			return false
+		}
+	}
+	return true
+}
+
+// _valueEqualMap reports whether `a` and `b` hold the same keys and values, a nil map being an empty one
+func _valueEqualMap(a, b map[string]string) bool {
+	if len(a) != len(b) {
+		return false
+	}
+	for k, v := range a {
+		if w, ok := b[k]; !ok || v != w {
+			return false
 		}
 	}
-	return go_Arr
+	return true
+}
+
+// _valueEqualURL reports whether `a` and `b` are the same URL
+func _valueEqualURL(a, b url.URL) bool {
+	return a.String() == b.String()
 }
+
+// _valueString prints what `p` points to, or nil
+func _valueString[T any](p *T) string {
+	if p == nil {
+		return \"nil\"
+	}
+	return fmt.Sprint(*p)
+}{% endif %}
 ";
 
 const TEMPLATE_EXTENDER: &'static str =
@@ -1192,13 +2433,19 @@ package {{go_data.package_name}}
 /*
 #cgo CFLAGS: -g -Wall
 #cgo LDFLAGS: {{go_data.ld_flags}}
-#include \"{{go_data.header_file_location}}\"
+#include \"{{go_data.header_file_location}}\"{% for prototype in go_data.completion_prototypes %}
+{{ prototype }}{% endfor %}
 */
 import \"C\"
 
-import (
-	\"errors\"
-	\"net/url\"
+import ({% if go_data.has_cancellables %}
+	\"context\"{% endif %}
+	\"errors\"{% if go_data.has_value_types %}
+	\"fmt\"{% endif %}{% if go_data.has_iterators %}
+	\"iter\"{% endif %}
+	\"net/url\"{% if go_data.completion_prototypes %}
+	\"sync\"{% endif %}{% if go_data.has_cancellables %}
+	\"sync/atomic\"{% endif %}
 	\"time\"
 	\"unsafe\"
 )
@@ -1248,10 +2495,12 @@ const TEMPLATE_FROM_C_STRUCT: &'static str = "
 {% if structs|length %}
 {% for struct in structs|filter(attribute=\"meta.is_persistent\", value=false) %}
 func _{{struct.identifier.go_label}}FromCStruct(_obj *C.struct_{{struct.c_label}}) (*{{struct.identifier.go_label}}, error) {
-    {% for field in struct.fields %}
+    {% for field in struct.fields %}{% if not field.is_map_part %}
         {% if field.is_complex %}
-        var go_{{field.identifier.go_label}}  {% if field.pointer_count > 0  and not field.meta.is_list%}*{% endif %}{{field.go_type}}
-        {% if field.go_type == \"time.Time\" %}
+        var go_{{field.identifier.go_label}}  {% if field.pointer_count > 0  and not field.meta.is_list and not field.meta.is_hashmap %}*{% endif %}{{field.go_type}}
+        {% if field.list_from_c %}
+        go_{{field.identifier.go_label}} = {{field.list_from_c}}
+        {% elif field.go_type == \"time.Time\" %}
         go_{{field.identifier.go_label}} = deserializeTime(_obj.{{field.c_identifier.label}})
         {% elif field.go_type == \"url.URL\" %}
         go_{{field.identifier.go_label}}, err := url.Parse(C.GoString(_obj.{{field.c_identifier.label}}))
@@ -1266,8 +2515,8 @@ func _{{struct.identifier.go_label}}FromCStruct(_obj *C.struct_{{struct.c_label}
             }
             go_{{field.identifier.go_label}} = go_{{field.identifier.go_label}}NotNil
         }
-        {% elif field.meta.is_list %}
-        go_{{field.identifier.go_label}} = _fromListStruct(_obj.{{field.c_identifier.label}}, go_len_arr, go_capacity_arr)        
+        {% elif field.map_values %}
+        go_{{field.identifier.go_label}} = _fromCMap(_obj.{{field.c_identifier.label}}, _obj.{{field.map_values}}, int(_obj.{{field.map_length}}))
         {% endif %}
         {% else %}
         {% if field.meta.length_for is some %}
@@ -1286,13 +2535,13 @@ func _{{struct.identifier.go_label}}FromCStruct(_obj *C.struct_{{struct.c_label}
         {% endif %}
         {% endif %}
         {% endif %}
-    {% endfor %}
+    {% endif %}{% endfor %}
 
     _res := {{struct.identifier.go_label}} {
-        {% for field in struct.fields %}
+        {% for field in struct.fields %}{% if not field.is_map_part %}
         {{field.identifier.go_label}}: {%if field.requires_pointer_dereference %}*{% elif field.requires_pointer_reference %} &{% endif %}{% if field.meta.capacity_for is some %}go_capacity_{{field.meta.capacity_for}}
         {% elif field.meta.length_for is some %}go_len_{{field.meta.length_for}}{% else %}go_{{field.identifier.go_label}}{% endif %},
-        {% endfor %}
+        {% endif %}{% endfor %}
     }
 
     return &_res, nil
@@ -1305,11 +2554,21 @@ const TEMPLATE_STRUCT_EXTERNAL_DECLARATIONS: &'static str = "
 {% for struct in structs %}
 {% if struct.go_comment is defined %}{{ struct.go_comment }}{% endif %}
 type {{ struct.identifier.go_label }} struct {
-    {% for field in struct.fields %}
+    {% for field in struct.fields %}{% if not field.is_map_part %}
     {% if field.go_comment is defined %}{{ field.go_comment }}{% endif %}
     {{ field.identifier.go_label }} {{ field.go_type }}
-    {% endfor %}
+    {% endif %}{% endfor %}
+}{% if struct.value %}
+
+// Equal reports whether v and other hold the same values, comparing slices and maps by their elements
+func (v {{ struct.identifier.go_label }}) Equal(other {{ struct.identifier.go_label }}) bool {
+    return {{ struct.value.comparisons | join(sep=\" &&\n        \") }}
 }
+
+// String prints the fields of v
+func (v {{ struct.identifier.go_label }}) String() string {
+    return fmt.Sprintf(\"{{ struct.identifier.go_label }}{{ struct.value.format }}\", {{ struct.value.arguments | join(sep=\", \") }})
+}{% endif %}
 {% endfor %}
 {% endif %}
 ";
@@ -1318,14 +2577,24 @@ const TEMPLATE_STRUCT_CONSTRUCTOR: &'static str = "
 {% block title %}{% endblock %}
 {% for struct in structs %}
 {% if struct.constructor is some %}
-func {{struct.constructor.identifier.go_label}}({% for param in struct.constructor.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}) {{struct.constructor.return_signature}} {
-    
+{% if struct.constructor.options %}{{struct.constructor.options.declaration}}
+{% endif %}{% if struct.constructor.deprecation %}{{ struct.constructor.deprecation }}
+{% endif %}func {{struct.constructor.identifier.go_label}}({% for param in struct.constructor.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}{% if struct.constructor.options %}options ...func(*{{struct.constructor.options.label}}), {% endif %}) {{struct.constructor.return_signature}} {
+{% if struct.constructor.options %}{{struct.constructor.options.apply}}
+{% endif %}    
     // make C Objects
     {% for param in struct.constructor.parameters %}
-    {% if param.meta.is_this %}
+    {% if param.is_map_part %}{% elif param.map_values %}
+    c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}} := _toCMap({{param.identifier.go_label}})
+    defer _freeCMap(c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}})
+    {% if param.map_length %}c_{{param.map_length}} := {{param.map_length_type}}(_len_{{param.identifier.go_label}}){% endif %}
+    {% elif param.list_c_type %}
+    c_{{param.identifier.go_label}} := _toCList({{param.identifier.go_label}}, {{param.list_to_c}})
+    defer _freeCList(c_{{param.identifier.go_label}}, len({{param.identifier.go_label}}), {{param.list_release}})
+    {% elif param.meta.is_this %}
     var c_This *{{param.as_c_field}}
     {% elif param.meta.is_error %}
-    c_{{param.identifier.go_label}} := C.CString(\"\")
+    var c_{{param.identifier.go_label}} *C.char
     {% else %}
     c_{{param.identifier.go_label}} := {{param.as_c_field}}
     {% endif %}
@@ -1341,12 +2610,31 @@ func {{struct.constructor.identifier.go_label}}({% for param in struct.construct
 	}([]unsafe.Pointer{ {% for param in struct.constructor.parameters | filter(attribute=\"requires_free\", value=true)|filter(attribute=\"meta.is_this\", value=false) %}unsafe.Pointer(c_{{param.identifier.go_label}}), {% endfor %} })
     
     {% endif %}
+    {%- for param in struct.constructor.parameters %}{% if param.list_free %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            {{param.list_free}}
+        }
+    }()
+    {% elif param.meta.free_with and not param.meta.is_this %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.{{param.meta.free_with}}(c_{{param.identifier.go_label}})
+        }
+    }()
+    {% elif param.frees_output %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.free(unsafe.Pointer(c_{{param.identifier.go_label}}))
+        }
+    }()
+    {% endif %}{% endfor %}
 
     {% if struct.constructor.meta.throws %}
-    if C.{{struct.constructor.c_function_name}}({% for param in struct.constructor.parameters %}
-        {% if param.meta.is_this or param.meta.is_output or param.meta.is_error %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
-        {% endfor %}) != C.OAUTHTOOL_PASS {
-        return nil, errors.New(C.GoString({% for err_param in struct.constructor.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %}))
+    if {% if go_data.has_error_codes %}_code := {% endif %}C.{{struct.constructor.c_function_name}}({% for param in struct.constructor.parameters %}
+        {% if param.meta.is_this or param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %}){% if go_data.has_error_codes %}; _code{% endif %} != {{ go_data.success_code }} {
+        return nil, {% if go_data.has_error_codes %}errorForCode(int(_code), {% else %}errors.New({% endif %}C.GoString({% for err_param in struct.constructor.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %}))
     }
     go_ret := {{struct.identifier.go_label}} {
         ptr: unsafe.Pointer(c_This),
@@ -1355,7 +2643,7 @@ func {{struct.constructor.identifier.go_label}}({% for param in struct.construct
     {% else %}
     // TODO(nf, struct_constructor): only handles functions that return pointers to output. Does not handle passing parameter pointers-to-be-filled
     _res_from_c := C.{{struct.constructor.c_function_name}}({% for param in struct.constructor.parameters %}
-        {% if param.meta.is_output or param.meta.is_error %}&{% endif %}c_{{param.identifier.go_label}}, 
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}c_{{param.identifier.go_label}}, 
         {% endfor %})
     _res_s, err := _{{struct.constructor.return_type}}FromCStruct(_res_from_c)
     {% endif %}
@@ -1368,20 +2656,29 @@ func {{struct.constructor.identifier.go_label}}({% for param in struct.construct
 const TEMPLATE_STRUCT_FUNCTIONS: &'static str = "
 {% for struct in structs %}
 {% for function in struct.functions %}
-{% if function.go_comment is defined %}{{ function.go_comment }}{% endif %}
-func (_obj *{{struct.identifier.go_label}}) {{function.identifier.go_label}}({% for param in function.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}) {{function.return_signature}} {
-    // make C Objects
+{% if function.options %}{{function.options.declaration}}
+{% endif %}{% if function.go_comment is defined %}{{ function.go_comment }}{% endif %}
+func (_obj *{{struct.identifier.go_label}}) {{function.identifier.go_label}}({% if function.cancel_with %}ctx context.Context, {% endif %}{% for param in function.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}{% if function.options %}options ...func(*{{function.options.label}}), {% endif %}) {{function.return_signature}} {
+{% if function.options %}{{function.options.apply}}
+{% endif %}    // make C Objects
     {% for param in function.parameters %}
-    {% if param.meta.is_this %}
-    c_This := (*C.struct_{{struct.identifier.go_label}})(_obj.ptr)
+    {% if param.is_map_part %}{% elif param.map_values %}
+    c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}} := _toCMap({{param.identifier.go_label}})
+    defer _freeCMap(c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}})
+    {% if param.map_length %}c_{{param.map_length}} := {{param.map_length_type}}(_len_{{param.identifier.go_label}}){% endif %}
+    {% elif param.meta.is_this %}
+    c_This := (*C.struct_{{struct.c_label}})(_obj.ptr)
+    {% elif param.list_c_type %}
+    {% if param.meta.is_output %}var c_{{param.identifier.go_label}} *{{param.list_c_type}}{% else %}c_{{param.identifier.go_label}} := _toCList({{param.identifier.go_label}}, {{param.list_to_c}})
+    defer _freeCList(c_{{param.identifier.go_label}}, len({{param.identifier.go_label}}), {{param.list_release}}){% endif %}
     {% elif param.meta.is_output %}
         {% if param.is_complex %}
     var c_{{param.identifier.go_label}} *{{param.as_c_field}}
         {% else %}
-    c_{{param.identifier.go_label}} := C.CString(\"\")
+    {% if param.c_output_type %}var c_{{param.identifier.go_label}} {{param.c_output_type}}{% else %}var c_{{param.identifier.go_label}} *C.char{% endif %}
         {% endif %}
     {% elif param.meta.is_error %}
-    c_{{param.identifier.go_label}} := C.CString(\"\")
+    var c_{{param.identifier.go_label}} *C.char
     {% else %}
     c_{{param.identifier.go_label}} := {{param.as_c_field}}
     {% endif %}
@@ -1396,13 +2693,73 @@ func (_obj *{{struct.identifier.go_label}}) {{function.identifier.go_label}}({%
 	}([]unsafe.Pointer{ {% for param in function.parameters | filter(attribute=\"requires_free\", value=true)|filter(attribute=\"meta.is_this\", value=false) %}unsafe.Pointer(c_{{param.identifier.go_label}}), {% endfor %} })
     
     {% endif %}
+    {%- for param in function.parameters %}{% if param.list_free %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            {{param.list_free}}
+        }
+    }()
+    {% elif param.meta.free_with and not param.meta.is_this %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.{{param.meta.free_with}}(c_{{param.identifier.go_label}})
+        }
+    }()
+    {% elif param.frees_output %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.free(unsafe.Pointer(c_{{param.identifier.go_label}}))
+        }
+    }()
+    {% endif %}{% endfor %}{% if function.cancel_with %}
+    // cancel the call once ctx is done, for as long as it runs
+    _done := make(chan struct{})
+    defer close(_done)
+    go func() {
+        select {
+        case <-ctx.Done():
+            C.{{function.cancel_with}}(c_{{function.cancel_handle}})
+        case <-_done:
+        }
+    }(){% endif %}
+
 
+    {% if function.completion %}
+    // the library returns at once, and hands the result to the callback once it is done
+    C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %})
+    {{function.completion.returns}}
+    {% elif function.has_multiple_outputs %}
+    // every output is returned, in declaration order
+    {% if function.meta.throws %}if {% if go_data.has_error_codes %}_code := {% endif %}{% endif %}C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %}){% if function.meta.throws %}{% if go_data.has_error_codes %}; _code{% endif %} != {{ go_data.success_code }} {
+        return {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{{param.identifier.go_label}}, {% endfor %}{% if function.cancel_with %}_orCancelled(ctx, {% endif %}{% if go_data.has_error_codes %}errorForCode(int(_code), {% else %}errors.New({% endif %}C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %})){% if function.cancel_with %}){% endif %}
+    }{% endif %}
 
-    {% if function.meta.throws %}
-    if C.{{function.c_function_name}}({% for param in function.parameters %}
-        {% if param.meta.is_output or param.meta.is_error %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
-        {% endfor %}) != C.OAUTHTOOL_PASS {
-        return nil, errors.New(C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %}))
+    {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}
+        {% if param.is_struct %}
+            {% if function.meta.throws %}
+    _res_{{param.identifier.go_label}}, err := _{{param.go_type}}FromCStruct(c_{{param.identifier.go_label}})
+    if err != nil {
+        return {% for output in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{{output.identifier.go_label}}, {% endfor %}fmt.Errorf(\"Failed to create cstruct for item {{param.identifier.go_label}}: %s\", err.Error())
+    }
+            {% else %}
+    _res_{{param.identifier.go_label}}, _ := _{{param.go_type}}FromCStruct(c_{{param.identifier.go_label}})
+            {% endif %}
+        {% elif param.c_output_type and not param.meta.is_bytes %}
+    _res_{{param.identifier.go_label}} := {{param.go_type}}(c_{{param.identifier.go_label}})
+        {% else %}
+    _res_{{param.identifier.go_label}} := {{param.from_c_field}}
+        {% endif %}
+    {% endfor %}
+    return {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{% if param.is_complex and not param.is_struct %}&{% endif %}_res_{{param.identifier.go_label}}{% if not loop.last %}, {% endif %}{% endfor %}{% if function.meta.throws %}, nil{% endif %}
+    {% elif function.meta.throws %}
+    if {% if go_data.has_error_codes %}_code := {% endif %}C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %}){% if go_data.has_error_codes %}; _code{% endif %} != {{ go_data.success_code }} {
+        return {% if function.returns_by_value %}0, {% elif not function.is_void %}nil, {% endif %}{% if function.cancel_with %}_orCancelled(ctx, {% endif %}{% if go_data.has_error_codes %}errorForCode(int(_code), {% else %}errors.New({% endif %}C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %})){% if function.cancel_with %}){% endif %}
     }
 
     {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}
@@ -1412,16 +2769,46 @@ func (_obj *{{struct.identifier.go_label}}) {{function.identifier.go_label}}({%
         return nil, fmt.Errorf(\"Failed to create cstruct for item {{param.identifier.go_label}}: %s\", err.Error())
     }
         {% else %}
-    _res_{{param.identifier.go_label}} := {{param.as_c_field}}
+    _res_{{param.identifier.go_label}} := {{param.from_c_field}}
         {% endif %}
-    return _res_{{param.identifier.go_label}}, nil
+    return {% if function.returns_by_value %}{% elif not param.is_complex or param.meta.is_list %}&{% endif %}_res_{{param.identifier.go_label}}, nil
     {% endfor %}
+    {% if function.is_void %}return nil{% endif %}
+    {% elif function.is_void %}
+    C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %})
+    {% elif function.returns_by_value %}
+    C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_output or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %})
+    {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}return {{param.from_c_field}}{% endfor %}
     {% else %}
     C.{{function.c_function_name}}()
     return TODO(NF, fix for non-throws return values), nil
     {% endif %}
-}
-{% endfor %}
+}{% if function.completion %}
+
+{{function.completion.export}}{% endif %}
+{% endfor %}{% if struct.iterator %}
+{{ struct.iterator.go_comment }}
+func (_obj *{{struct.identifier.go_label}}) Items() iter.Seq[{{struct.iterator.item_type}}] {
+    return func(yield func({{struct.iterator.item_type}}) bool) {
+        for _obj.ptr != nil {
+            var c_Item *{{struct.iterator.c_item_type}}
+            if C.{{struct.iterator.next}}((*C.struct_{{struct.c_label}})(_obj.ptr), &c_Item) == 0 {
+                {% if struct.iterator.free_cursor %}C.{{struct.iterator.free_cursor}}((*C.struct_{{struct.c_label}})(_obj.ptr))
+                _obj.ptr = nil
+                {% endif %}return
+            }
+            v := ({{struct.iterator.from_c}})(c_Item)
+            {% if struct.iterator.release %}{{struct.iterator.release}}
+            {% endif %}if !yield(v) {
+                return
+            }
+        }
+    }
+}{% endif %}
 {% endfor %}
 ";
 
@@ -1429,18 +2816,27 @@ const TEMPLATE_FREE_FUNCTIONS: &'static str = "
 {% block title %}{% endblock %}
 {% if functions|length %}
 {% for function in functions %}
-{% if function.go_comment is defined %}{{ function.go_comment }}{% endif %}
-func {{function.identifier.go_label}}({% for param in function.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}) {{function.return_signature}} {
-    // make C Objects
+{% if function.options %}{{function.options.declaration}}
+{% endif %}{% if function.go_comment is defined %}{{ function.go_comment }}{% endif %}
+func {{function.identifier.go_label}}({% if function.cancel_with %}ctx context.Context, {% endif %}{% for param in function.parameters|filter(attribute=\"show_in_signature\", value=true) %}{{param.identifier.go_label}} {{param.go_type}}, {% endfor %}{% if function.options %}options ...func(*{{function.options.label}}), {% endif %}) {{function.return_signature}} {
+{% if function.options %}{{function.options.apply}}
+{% endif %}    // make C Objects
     {% for param in function.parameters %}
-        {% if param.meta.is_output %}
+        {% if param.is_map_part %}{% elif param.map_values %}
+    c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}} := _toCMap({{param.identifier.go_label}})
+    defer _freeCMap(c_{{param.identifier.go_label}}, c_{{param.map_values}}, _len_{{param.identifier.go_label}})
+    {% if param.map_length %}c_{{param.map_length}} := {{param.map_length_type}}(_len_{{param.identifier.go_label}}){% endif %}
+        {% elif param.list_c_type %}
+    {% if param.meta.is_output %}var c_{{param.identifier.go_label}} *{{param.list_c_type}}{% else %}c_{{param.identifier.go_label}} := _toCList({{param.identifier.go_label}}, {{param.list_to_c}})
+    defer _freeCList(c_{{param.identifier.go_label}}, len({{param.identifier.go_label}}), {{param.list_release}}){% endif %}
+        {% elif param.meta.is_output %}
             {% if param.is_complex %}
     var c_{{param.identifier.go_label}} *{{param.as_c_field}}
             {% else %}
-    c_{{param.identifier.go_label}} := C.CString(\"\")
+    {% if param.c_output_type %}var c_{{param.identifier.go_label}} {{param.c_output_type}}{% else %}var c_{{param.identifier.go_label}} *C.char{% endif %}
             {% endif %}
         {% elif param.meta.is_error %}
-    c_{{param.identifier.go_label}} := C.CString(\"\")
+    var c_{{param.identifier.go_label}} *C.char
         {% else %}
             {% if param.is_struct %}
             // 	var c_Engine *C.struct_HubkitEngine = (*C.struct_HubkitEngine)(Engine.ptr)
@@ -1462,13 +2858,73 @@ func {{function.identifier.go_label}}({% for param in function.parameters|filter
 		}
 	}([]unsafe.Pointer{ {% for param in function.parameters | filter(attribute=\"requires_free\", value=true)|filter(attribute=\"meta.is_this\", value=false) %}unsafe.Pointer(c_{{param.identifier.go_label}}), {% endfor %} })
     {% endif %}
+    {%- for param in function.parameters %}{% if param.list_free %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            {{param.list_free}}
+        }
+    }()
+    {% elif param.meta.free_with and not param.meta.is_this %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.{{param.meta.free_with}}(c_{{param.identifier.go_label}})
+        }
+    }()
+    {% elif param.frees_output %}
+    defer func() {
+        if c_{{param.identifier.go_label}} != nil {
+            C.free(unsafe.Pointer(c_{{param.identifier.go_label}}))
+        }
+    }()
+    {% endif %}{% endfor %}{% if function.cancel_with %}
+    // cancel the call once ctx is done, for as long as it runs
+    _done := make(chan struct{})
+    defer close(_done)
+    go func() {
+        select {
+        case <-ctx.Done():
+            C.{{function.cancel_with}}(c_{{function.cancel_handle}})
+        case <-_done:
+        }
+    }(){% endif %}
+
 
+    {% if function.completion %}
+    // the library returns at once, and hands the result to the callback once it is done
+    C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %})
+    {{function.completion.returns}}
+    {% elif function.has_multiple_outputs %}
+    // every output is returned, in declaration order
+    {% if function.meta.throws %}if {% if go_data.has_error_codes %}_code := {% endif %}{% endif %}C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %}){% if function.meta.throws %}{% if go_data.has_error_codes %}; _code{% endif %} != {{ go_data.success_code }} {
+        return {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{{param.identifier.go_label}}, {% endfor %}{% if function.cancel_with %}_orCancelled(ctx, {% endif %}{% if go_data.has_error_codes %}errorForCode(int(_code), {% else %}errors.New({% endif %}C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %})){% if function.cancel_with %}){% endif %}
+    }{% endif %}
 
-    {% if function.meta.throws %}
-    if C.{{function.c_function_name}}({% for param in function.parameters %}
-        {% if param.meta.is_output or param.meta.is_error %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
-        {% endfor %}) != C.OAUTHTOOL_PASS {
-        return nil, errors.New(C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %}))
+    {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}
+        {% if param.is_struct %}
+            {% if function.meta.throws %}
+    _res_{{param.identifier.go_label}}, err := _{{param.go_type}}FromCStruct(c_{{param.identifier.go_label}})
+    if err != nil {
+        return {% for output in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{{output.identifier.go_label}}, {% endfor %}fmt.Errorf(\"Failed to create cstruct for item {{param.identifier.go_label}}: %s\", err.Error())
+    }
+            {% else %}
+    _res_{{param.identifier.go_label}}, _ := _{{param.go_type}}FromCStruct(c_{{param.identifier.go_label}})
+            {% endif %}
+        {% elif param.c_output_type and not param.meta.is_bytes %}
+    _res_{{param.identifier.go_label}} := {{param.go_type}}(c_{{param.identifier.go_label}})
+        {% else %}
+    _res_{{param.identifier.go_label}} := {{param.from_c_field}}
+        {% endif %}
+    {% endfor %}
+    return {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}{% if param.is_complex and not param.is_struct %}&{% endif %}_res_{{param.identifier.go_label}}{% if not loop.last %}, {% endif %}{% endfor %}{% if function.meta.throws %}, nil{% endif %}
+    {% elif function.meta.throws %}
+    if {% if go_data.has_error_codes %}_code := {% endif %}C.{{function.c_function_name}}({% for param in function.parameters %}
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}{% if param.meta.is_this %} c_This {% else %} c_{{param.identifier.go_label}} {% endif %}, 
+        {% endfor %}){% if go_data.has_error_codes %}; _code{% endif %} != {{ go_data.success_code }} {
+        return nil, {% if function.cancel_with %}_orCancelled(ctx, {% endif %}{% if go_data.has_error_codes %}errorForCode(int(_code), {% else %}errors.New({% endif %}C.GoString({% for err_param in function.parameters|filter(attribute=\"meta.is_error\", value=true) %}c_{{err_param.identifier.go_label}}{% endfor %})){% if function.cancel_with %}){% endif %}
     }
         {% for param in function.parameters|filter(attribute=\"meta.is_output\", value=true) %}
             {% if param.is_struct %}
@@ -1479,16 +2935,290 @@ func {{function.identifier.go_label}}({% for param in function.parameters|filter
             {% else %}
     _res_{{param.identifier.go_label}} := {{param.from_c_field}}
             {% endif %}
-    return {% if not param.is_complex %}&{% endif %}_res_{{param.identifier.go_label}}, nil
+    return {% if not param.is_complex or param.meta.is_list %}&{% endif %}_res_{{param.identifier.go_label}}, nil
         {% endfor %}
     {% else %}
     // TODO(nf, free_funtions): only handles functions that return pointers to output. Does not handle passing parameter pointers-to-be-filled
     _res_from_c := C.{{function.c_function_name}}({% for param in function.parameters %}
-        {% if param.meta.is_output or param.meta.is_error %}&{% endif %}c_{{param.identifier.go_label}}, 
+        {% if param.meta.is_output or param.meta.is_error or param.by_reference %}&{% endif %}c_{{param.identifier.go_label}}, 
         {% endfor %})
     _res_s, err := _{{function.return_type}}FromCStruct(_res_from_c)
     {% endif %}
-}
+}{% if function.completion %}
+
+{{function.completion.export}}{% endif %}
 {% endfor %}
 {% endif %}
 ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    fn render(source: &str) -> String {
+        let mut header = parse_source(source);
+        crate::list::resolve(&mut header);
+        generate(&header, "lib", "-llib", "lib.h")
+    }
+
+    /// Asserts that `code` contains `fragment`, ignoring differences in whitespace
+    fn assert_renders(code: &str, fragment: &str) {
+        let squash = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(
+            squash(code).contains(&squash(fragment)),
+            "`{}` not found in:\n{}",
+            fragment,
+            code
+        );
+    }
+
+    const OWNERSHIP: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\nvoid lib_free_string(char *s);\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;free_with(lib_free_string);\n * #meta_param: err;error;free_with(lib_free_string);\n */\nuint32_t Engine_describe(const struct Engine *this_, char **out, char **err);\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;borrowed;\n */\nvoid Engine_label(const struct Engine *this_, char **out);";
+
+    #[test]
+    fn releases_outputs_and_errors_with_their_free_with_function() {
+        let code = render(OWNERSHIP);
+        assert_renders(&code, "if c_Out != nil { C.lib_free_string(c_Out) }");
+        assert_renders(&code, "if c_Err != nil { C.lib_free_string(c_Err) }");
+        assert_renders(&code, "_res_Out := C.GoString(c_Out)");
+    }
+
+    const OUTPUTS: &str = "/**\n * #meta: throws;\n * #meta_param: name;output;string;\n * #meta_param: count;output;\n * #meta_param: err;error;\n */\nuint32_t describe_lib(char **name, uint64_t *count, char **err);";
+
+    #[test]
+    fn returns_several_outputs_and_releases_the_strings_once_read() {
+        let code = render(OUTPUTS);
+        assert_renders(
+            &code,
+            "func DescribeLib() (Name string, Count uint64, err error) {",
+        );
+        assert_renders(&code, "var c_Name *C.char");
+        assert_renders(&code, "var c_Count C.uint64_t");
+        assert_renders(&code, "if c_Name != nil { C.free(unsafe.Pointer(c_Name)) }");
+        assert_renders(&code, "if c_Err != nil { C.free(unsafe.Pointer(c_Err)) }");
+        assert!(!code.contains("C.CString(\"\")"), "{}", code);
+    }
+
+    const ERROR_CODES: &str = "/**\n * #meta: error_codes;\n */\n#define LIB_OK 0\n#define LIB_FAIL 1\n#define LIB_FAIL_NULL 2\n\n/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: throws;\n * #meta_param: out;output;string;\n * #meta_param: err;error;\n */\nuint32_t lib_name(char **out, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: err;error;\n */\nuint32_t Engine_reset(struct Engine *this_, char **err);";
+
+    #[test]
+    fn compares_results_against_the_success_code() {
+        let code = render(ERROR_CODES);
+        assert_renders(
+            &code,
+            "if _code := C.lib_name( & c_Out , & c_Err , ); _code != C.LIB_OK { return nil, errorForCode(int(_code), C.GoString(c_Err)) }",
+        );
+        assert_renders(
+            &code,
+            "if _code := C.Engine_reset( c_This , & c_Err , ); _code != C.LIB_OK {",
+        );
+        assert!(!code.contains(") != 0 {"), "{}", code);
+    }
+
+    #[test]
+    fn compares_results_against_zero_without_error_codes() {
+        let code = render(&ERROR_CODES.replace("#meta: error_codes;", ""));
+        assert_renders(
+            &code,
+            "if C.lib_name( & c_Out , & c_Err , ) != 0 { return nil, errors.New(C.GoString(c_Err)) }",
+        );
+    }
+
+    #[test]
+    fn wraps_failures_in_the_sentinel_of_their_code() {
+        let code = render(ERROR_CODES);
+        assert_renders(&code, "ErrLibFail = errors.New(\"LIB_FAIL\")");
+        assert_renders(&code, "case 2: sentinel = ErrLibFailNull");
+    }
+
+    const MAP: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: headers;hashmap;\n * #meta_param: header_values;values(headers);\n * #meta_param: headers_len;length(headers);\n * #meta_param: err;error;\n */\nuint32_t Engine_set_headers(const struct Engine *this_, const char *const *headers, const char *const *header_values, uintptr_t headers_len, char **err);";
+
+    #[test]
+    fn hands_maps_over_as_keys_values_and_length() {
+        let code = render(MAP);
+        assert_renders(
+            &code,
+            "func (_obj *Engine) SetHeaders(Headers map[string]string, )",
+        );
+        assert_renders(
+            &code,
+            "c_Headers, c_HeaderValues, _len_Headers := _toCMap(Headers) defer _freeCMap(c_Headers, c_HeaderValues, _len_Headers) c_HeadersLen := C.uintptr_t(_len_Headers)",
+        );
+        assert_renders(
+            &code,
+            "C.Engine_set_headers( c_This , c_Headers , c_HeaderValues , c_HeadersLen , & c_Err , )",
+        );
+    }
+
+    const BYTES: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\nvoid lib_free_bytes(uint8_t *data, uintptr_t data_len);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: data;bytes;\n * #meta_param: data_len;length(data);\n * #meta_param: err;error;\n */\nuint32_t Engine_feed(const struct Engine *this_, const uint8_t *data, uintptr_t data_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;bytes;free_with(lib_free_bytes);\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_read(const struct Engine *this_, uint8_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_buffers_over_as_byte_slices() {
+        let code = render(BYTES);
+        assert_renders(
+            &code,
+            "c_Data := (*C.uint8_t)(C.CBytes(Data)) c_DataLen := C.uintptr_t(len(Data))",
+        );
+        assert_renders(&code, "func (_obj *Engine) Read() (*[]byte, error) {");
+        assert_renders(
+            &code,
+            "if c_Out != nil { C.lib_free_bytes(c_Out, c_OutLen) }",
+        );
+        assert_renders(
+            &code,
+            "_res_Out := C.GoBytes(unsafe.Pointer(c_Out), C.int(c_OutLen))",
+        );
+    }
+
+    const LISTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: scopes;list;\n * #meta_param: scopes_len;length(scopes);\n * #meta_param: err;error;\n */\nuint32_t Engine_set_scopes(const struct Engine *this_, const char *const *scopes, uintptr_t scopes_len, char **err);\n\n/**\n * #meta: for_struct;throws;\n * #meta_param: this_;this;\n * #meta_param: out;output;list;\n * #meta_param: out_len;length(out);\n * #meta_param: err;error;\n */\nuint32_t Engine_ports(const struct Engine *this_, uint16_t **out, uintptr_t *out_len, char **err);";
+
+    #[test]
+    fn hands_lists_over_as_slices() {
+        let code = render(LISTS);
+        assert_renders(
+            &code,
+            "c_Scopes := _toCList(Scopes, func(v string) *C.char { return C.CString(v) }) defer _freeCList(c_Scopes, len(Scopes), func(e *C.char) { C.free(unsafe.Pointer(e)) }) c_ScopesLen := C.uintptr_t(len(Scopes))",
+        );
+        assert_renders(&code, "func (_obj *Engine) Ports() (*[]uint16, error) {");
+        assert_renders(
+            &code,
+            "_res_Out := _fromCList(c_Out, int(c_OutLen), func(e C.uint16_t) uint16 { return uint16(e) })",
+        );
+    }
+
+    const LIST_RELEASE: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\nvoid free_tokens(struct TokenResponse *items, uintptr_t items_len, uintptr_t items_cap);\n\n/**\n * #meta: throws;\n * #meta_param: items;output;list;free_with(free_tokens);\n * #meta_param: items_len;length(items);\n * #meta_param: items_cap;capacity(items);\n * #meta_param: err;error;\n */\nuint32_t get_tokens(struct TokenResponse **items, uintptr_t *items_len, uintptr_t *items_cap, char **err);";
+
+    #[test]
+    fn hands_output_lists_back_to_free_with_with_their_length_and_capacity() {
+        let code = render(LIST_RELEASE);
+        assert_renders(
+            &code,
+            "if c_Items != nil { C.free_tokens(c_Items, c_ItemsLen, c_ItemsCap) }",
+        );
+    }
+
+    const PROPERTY: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;getter(timeout);\n * #meta_param: this_;this;\n * #meta_param: out;output;duration;\n */\nvoid Engine_get_timeout(const struct Engine *this_, uint64_t *out);\n\n/**\n * #meta: for_struct;setter(timeout);\n * #meta_param: this_;this;\n * #meta_param: timeout;duration;\n */\nvoid Engine_set_timeout(struct Engine *this_, uint64_t timeout);";
+
+    #[test]
+    fn reads_and_writes_properties_through_their_accessors() {
+        let code = render(PROPERTY);
+        assert_renders(&code, "func (_obj *Engine) Timeout() time.Duration {");
+        assert_renders(&code, "return time.Duration(c_Out) * time.Millisecond");
+        assert_renders(
+            &code,
+            "func (_obj *Engine) SetTimeout(Timeout time.Duration, ) {",
+        );
+    }
+
+    const CURSOR: &str = "typedef struct TokenResponse { int32_t expires; } TokenResponse;\n\n/**\n * #meta: persistent;iterator(TokenResponse);\n */\ntypedef struct TokenCursor TokenCursor;\n\nvoid TokenResponse_free(struct TokenResponse *this_);\n\n/**\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;free_with(TokenResponse_free);\n */\nuint8_t TokenCursor_next(struct TokenCursor *this_, struct TokenResponse **out);";
+
+    #[test]
+    fn walks_through_cursors_with_an_iterator() {
+        let code = render(CURSOR);
+        assert_renders(
+            &code,
+            "func (_obj *TokenCursor) Items() iter.Seq[TokenResponse] {",
+        );
+        assert_renders(
+            &code,
+            "if C.TokenCursor_next((*C.struct_TokenCursor)(_obj.ptr), &c_Item) == 0 { return }",
+        );
+        assert_renders(
+            &code,
+            "C.TokenResponse_free(c_Item) if !yield(v) { return }",
+        );
+    }
+
+    const CANCELLABLE: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;throws;async;cancellable(Engine_cancel);\n * #meta_param: this_;this;\n * #meta_param: out;output;string;\n * #meta_param: cancel;cancel_handle;\n * #meta_param: err;error;\n */\nuint32_t Engine_fetch(struct Engine *this_, char **out, uint64_t cancel, char **err);\n\nvoid Engine_cancel(uint64_t cancel);";
+
+    #[test]
+    fn cancels_calls_once_their_context_is_done() {
+        let code = render(CANCELLABLE);
+        assert_renders(
+            &code,
+            "func (_obj *Engine) Fetch(ctx context.Context, ) (*string, error) {",
+        );
+        assert_renders(&code, "c_Cancel := C.uint64_t(_nextCancelHandle())");
+        assert_renders(
+            &code,
+            "case <-ctx.Done(): C.Engine_cancel(c_Cancel) case <-_done:",
+        );
+        assert_renders(
+            &code,
+            "return nil, _orCancelled(ctx, errors.New(C.GoString(c_Err)))",
+        );
+    }
+
+    const COMPLETION: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n\n/**\n * #meta: for_struct;async_callback(on_done);\n * #meta_param: this_;this;\n * #meta_param: url;string;\n * #meta_param: err;error;\n */\nvoid Engine_fetch(struct Engine *this_, const char *url, void (*on_done)(void *user_data, char *body, char *err), void *user_data);";
+
+    #[test]
+    fn completes_calls_from_an_exported_callback() {
+        let code = render(COMPLETION);
+        assert_renders(&code, "c_OnDone := (*[0]byte)(C._Engine_fetch_done)");
+        assert_renders(&code, "return _awaitCompletion[string](c_UserData)");
+        assert_renders(
+            &code,
+            "//export _Engine_fetch_done func _Engine_fetch_done(_key unsafe.Pointer, c_Body *C.char, c_Err *C.char) {",
+        );
+        assert_renders(
+            &code,
+            "_v := (func(e *C.char) string { return C.GoString(e) })(c_Body) C.free(unsafe.Pointer(c_Body)) _complete(_key, &_v, nil)",
+        );
+    }
+
+    const DEFAULTS: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;constructor;throws;\n * #meta_param: this_;output;this;\n * #meta_param: scopes;string;default(\"openid\");\n * #meta_param: extra;string;nullable;default(null);\n * #meta_param: timeout;duration;default(30000);\n * #meta_param: err;error;\n */\nuint32_t Engine_new(struct Engine **this_, const char *scopes, const char *extra, uint64_t timeout, char **err);";
+
+    #[test]
+    fn leaves_parameters_with_a_default_to_options() {
+        let code = render(DEFAULTS);
+        assert_renders(
+            &code,
+            "func EngineNew(options ...func(*EngineNewOptions), ) (*Engine, error) {",
+        );
+        assert_renders(
+            &code,
+            "Scopes: \"openid\", Extra: nil, Timeout: 30000 * time.Millisecond,",
+        );
+        assert_renders(
+            &code,
+            "for _, option := range options { option(&_options) }",
+        );
+    }
+
+    const RENAMED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;rename(fetch_token);rename_csharp(Engine_get_token);\n * #meta_param: this_;this;\n */\nvoid Engine_get_token_automatic(struct Engine *this_);\n/**\n * #meta: skip_go;skip_csharp;\n */\nvoid reset_engine(void);\n/**\n * #meta: skip_dart;\n */\nvoid warm_engine(void);";
+
+    #[test]
+    fn renames_and_skips_items_for_go() {
+        let code = render(RENAMED);
+        assert_renders(&code, "func (_obj *Engine) FetchToken() {");
+        assert_renders(&code, "C.Engine_get_token_automatic(");
+        assert_renders(&code, "func WarmEngine() {");
+        assert!(!code.contains("ResetEngine"));
+    }
+
+    const DEPRECATED: &str = "/**\n * #meta: persistent;\n */\ntypedef struct Engine { int32_t x; } Engine;\n/**\n * #meta: for_struct;deprecated(\"use Engine_run instead\");\n * #meta_param: this_;this;\n */\nvoid Engine_start(struct Engine *this_);";
+
+    #[test]
+    fn marks_deprecated_functions() {
+        let code = render(DEPRECATED);
+        assert_renders(
+            &code,
+            "// Deprecated: use Engine_run instead func (_obj *Engine) Start() {",
+        );
+    }
+
+    const VALUE: &str = "/**\n * #meta: value;\n */\ntypedef struct TokenResponse {\n  /**\n   * #meta: string;nullable;\n   */\n  const char *refresh_token;\n  int64_t expires_at;\n} TokenResponse;";
+
+    #[test]
+    fn compares_and_prints_value_types_by_their_fields() {
+        let code = render(VALUE);
+        assert_renders(
+            &code,
+            "func (v TokenResponse) Equal(other TokenResponse) bool { return _valueEqualPtr(v.RefreshToken, other.RefreshToken) && v.ExpiresAt == other.ExpiresAt",
+        );
+        assert_renders(
+            &code,
+            "return fmt.Sprintf(\"TokenResponse{RefreshToken: %v, ExpiresAt: %v}\", _valueString(v.RefreshToken), v.ExpiresAt)",
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::lexer::CVariableDeclaration;
+
+/// A string to string map, which C sees as parallel arrays of keys and values along with their length.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta_param: headers;hashmap;
+///  * #meta_param: header_values;values(headers);
+///  * #meta_param: headers_len;length(headers);
+///  */
+/// uint32_t Engine_set_headers(const struct Engine *this_, const char *const *headers, const char *const *header_values, uintptr_t headers_len);
+/// ```
+///
+/// The same annotations describe a map stored in the fields of a struct.
+/// Function parameters must be declared in the order keys, values, length, as they are passed along together
+pub struct Hashmap<'a> {
+    /// The `char **` of keys, marked `hashmap`, which names the map in the generated code
+    pub keys: &'a CVariableDeclaration,
+    /// The `char **` of values, marked `values(keys)`
+    pub values: Option<&'a CVariableDeclaration>,
+    /// Number of entries, marked `length(keys)`
+    pub length: Option<&'a CVariableDeclaration>,
+}
+
+impl<'a> Hashmap<'a> {
+    /// Every map among `declarations`, the parameters of a function or the fields of a struct
+    pub fn all(declarations: &'a [CVariableDeclaration]) -> Vec<Self> {
+        declarations
+            .iter()
+            .filter(|d| d.meta.as_ref().is_some_and(|m| m.is_hashmap))
+            .map(|keys| Hashmap {
+                keys,
+                values: declarations.iter().find(|d| {
+                    d.meta
+                        .as_ref()
+                        .is_some_and(|m| m.values_for.as_ref() == Some(&keys.label))
+                }),
+                length: declarations.iter().find(|d| {
+                    d.meta
+                        .as_ref()
+                        .is_some_and(|m| m.length_for.as_ref() == Some(&keys.label))
+                }),
+            })
+            .collect()
+    }
+
+    /// Whether `d` is the values or the length of one of the maps among `declarations`.
+    /// These are marshalled along with the keys, and are left out of generated signatures and classes
+    pub fn is_part(declarations: &[CVariableDeclaration], d: &CVariableDeclaration) -> bool {
+        Hashmap::all(declarations).iter().any(|h| {
+            h.values.is_some_and(|v| v.label == d.label)
+                || h.length.is_some_and(|l| l.label == d.label)
+        })
+    }
+}
@@ -0,0 +1,387 @@
+use fancy_regex::Regex;
+
+use crate::annotation::Annotation;
+use crate::lexer::{CFunction, CType, CVariableDeclaration, HeaderFile};
+use crate::meta::{MetaValue, META_PARAM_TOKEN, META_TOKEN};
+
+/// Annotations guessed for a function that has none, from its signature and the naming conventions of `Bindcgen`
+#[derive(Debug, Clone)]
+pub struct Inferred {
+    /// Name of the function
+    pub function: String,
+    /// Annotations for the function itself. Empty if it already has some
+    pub annotations: Vec<Annotation>,
+    /// Annotations for each parameter that has none yet, in declaration order
+    pub params: Vec<(String, Vec<Annotation>)>,
+}
+
+impl Inferred {
+    fn is_empty(&self) -> bool {
+        self.annotations.is_empty() && self.params.is_empty()
+    }
+}
+
+/// Infers annotations for every function, and every function parameter, that has no annotations of its own.
+///
+/// The rules are:
+/// * `char **err...` is an `error`, any other `char **` is a string `output`
+/// * `const char *` is a `string`
+/// * `Type_new(struct Type **, ...)` is a static `constructor` on `Type`, with the first parameter as the `this` `output`
+/// * `Type_free(struct Type *)` is a `destructor` on `Type`
+/// * any other `Type_fn(struct Type *, ...)` is a method on `Type`, with the first parameter as `this`
+/// * a `uint32_t` return alongside an `error` parameter `throws`
+pub fn infer(header: &HeaderFile) -> Vec<Inferred> {
+    header
+        .functions
+        .iter()
+        .map(infer_function)
+        .filter(|i| !i.is_empty())
+        .collect()
+}
+
+/// Adds the inferred annotations to the header, and recomputes the meta values of everything they touch
+pub fn apply(header: &mut HeaderFile, inferred: &[Inferred]) {
+    for i in inferred {
+        let f = match header.functions.iter_mut().find(|f| f.label == i.function) {
+            Some(f) => f,
+            None => continue,
+        };
+        if !i.annotations.is_empty() {
+            f.annotations = i.annotations.clone();
+            f.meta = MetaValue::from_annotations(&f.annotations);
+        }
+        for (label, annotations) in &i.params {
+            if let Some(p) = f.parameters.iter_mut().find(|p| p.label == *label) {
+                p.annotations = annotations.clone();
+                p.meta = MetaValue::from_annotations(&p.annotations);
+            }
+        }
+    }
+}
+
+fn infer_function(f: &CFunction) -> Inferred {
+    let mut annotations = vec![];
+    let mut params: Vec<(String, Vec<Annotation>)> = vec![];
+
+    /* Methods take the struct they belong to as their first parameter */
+    let owner = f.parameters.first().and_then(|p| {
+        let name = struct_name(p)?;
+        let method = f.label.strip_prefix(name)?.strip_prefix('_')?;
+        Some((name, method, p.variable_type.pointer_count))
+    });
+    let mut this_param = None;
+    match owner {
+        Some((_, "new", 2)) => {
+            annotations.extend(flags(&["for_struct", "static", "constructor"]));
+            this_param = Some(flags(&["this", "output"]));
+        }
+        Some((_, "free", 1)) if f.parameters.len() == 1 => {
+            annotations.extend(flags(&["for_struct", "destructor"]));
+            this_param = Some(flags(&["this"]));
+        }
+        Some((_, _, 1)) => {
+            annotations.push(Annotation::flag("for_struct"));
+            this_param = Some(flags(&["this"]));
+        }
+        _ => {}
+    }
+
+    let mut has_error = false;
+    for (i, p) in f.parameters.iter().enumerate() {
+        let inferred = match &this_param {
+            Some(this) if i == 0 => this.clone(),
+            _ => infer_parameter(p),
+        };
+        has_error |= inferred.iter().any(|a| a.name == "error");
+        /* Annotations from the header always win */
+        if p.annotations.is_empty() && !inferred.is_empty() {
+            params.push((p.label.to_owned(), inferred));
+        }
+    }
+
+    if has_error && matches!(f.return_type.kind, CType::UInt32T(_)) {
+        annotations.push(Annotation::flag("throws"));
+    }
+
+    Inferred {
+        function: f.label.to_owned(),
+        annotations: if f.annotations.is_empty() {
+            annotations
+        } else {
+            vec![]
+        },
+        params,
+    }
+}
+
+fn infer_parameter(p: &CVariableDeclaration) -> Vec<Annotation> {
+    let is_char = matches!(p.variable_type.kind, CType::Char(_));
+    match p.variable_type.pointer_count {
+        2 if is_char && p.label.starts_with("err") => flags(&["error"]),
+        2 if is_char => flags(&["output", "string"]),
+        2 if struct_name(p).is_some() => flags(&["output"]),
+        1 if is_char && p.is_const => flags(&["string"]),
+        _ => vec![],
+    }
+}
+
+/// Name of the struct `p` points to, if it is a struct pointer
+fn struct_name(p: &CVariableDeclaration) -> Option<&str> {
+    match &p.variable_type.kind {
+        CType::Struct(s) if p.variable_type.pointer_count > 0 => Some(&s.identifier.label),
+        _ => None,
+    }
+}
+
+fn flags(names: &[&str]) -> Vec<Annotation> {
+    names.iter().map(|n| Annotation::flag(n)).collect()
+}
+
+/// Inserts the inferred annotations into `source`, the text of the header they were inferred from, as `#meta` lines.
+///
+/// Lines are added to the end of a function's doc comment, or into a new doc comment if it has none
+/// or it is not a `/** */` block. Functions that are not declared in `source` itself, e.g., those of included headers, are skipped.
+///
+/// `file` is the name clang reported `source` under
+pub fn rewrite(source: &str, file: &str, header: &HeaderFile, inferred: &[Inferred]) -> String {
+    let mut lines: Vec<String> = source.lines().map(|l| l.to_owned()).collect();
+    /* Insert from the bottom up, so that the line numbers of the functions above stay valid */
+    let mut insertions: Vec<(InsertionPoint, Vec<String>)> = inferred
+        .iter()
+        .filter_map(|i| {
+            let f = header.functions.iter().find(|f| f.label == i.function)?;
+            let point = insertion_point(&lines, file, f)?;
+            let new_lines = point.lines(meta_lines(i));
+            Some((point, new_lines))
+        })
+        .collect();
+    insertions.sort_by_key(|(point, _)| std::cmp::Reverse(point.start));
+    for (point, new_lines) in insertions {
+        lines.splice(point.start..point.end, new_lines);
+    }
+
+    let mut result = lines.join("\n");
+    if source.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// The `#meta` and `#meta_param` lines for `inferred`, without any comment decoration
+fn meta_lines(inferred: &Inferred) -> Vec<String> {
+    let join = |annotations: &[Annotation]| {
+        annotations
+            .iter()
+            .map(|a| format!("{};", a))
+            .collect::<String>()
+    };
+    let mut lines = vec![];
+    if !inferred.annotations.is_empty() {
+        lines.push(format!("{} {}", META_TOKEN, join(&inferred.annotations)));
+    }
+    for (label, annotations) in &inferred.params {
+        lines.push(format!(
+            "{} {};{}",
+            META_PARAM_TOKEN,
+            label,
+            join(annotations)
+        ));
+    }
+    lines
+}
+
+/// Where the meta lines of a function go
+struct InsertionPoint {
+    /// Lines `start..end` of the source are replaced by the new lines
+    start: usize,
+    end: usize,
+    /// Leading whitespace of the comment's closing line, or of the line a new comment is put before
+    indent: String,
+    /// Text of a comment to turn into a new `/** */` block along with the meta lines.
+    /// `None` if they are added to an existing block instead
+    block_text: Option<Vec<String>>,
+}
+
+impl InsertionPoint {
+    fn lines(&self, meta_lines: Vec<String>) -> Vec<String> {
+        let indent = &self.indent;
+        let text = match &self.block_text {
+            Some(text) => text,
+            None => {
+                return meta_lines
+                    .iter()
+                    .map(|l| format!("{}* {}", indent, l))
+                    .collect()
+            }
+        };
+        let body = text.iter().chain(meta_lines.iter()).map(|l| {
+            if l.is_empty() {
+                format!("{} *", indent)
+            } else {
+                format!("{} * {}", indent, l)
+            }
+        });
+        std::iter::once(format!("{}/**", indent))
+            .chain(body)
+            .chain(std::iter::once(format!("{} */", indent)))
+            .collect()
+    }
+}
+
+fn insertion_point(lines: &[String], file: &str, f: &CFunction) -> Option<InsertionPoint> {
+    if let (Some(comment), Some(location)) = (&f.comment, &f.comment_location) {
+        if location.file != file {
+            return None;
+        }
+        let first = location.line - 1;
+        let last = first + comment.matches('\n').count();
+        let closing = lines.get(last)?;
+        /* Add to the end of a `/** */` block */
+        if comment.starts_with("/*") && closing.trim() == "*/" {
+            return Some(InsertionPoint {
+                start: last,
+                end: last,
+                indent: leading_whitespace(closing).to_owned(),
+                block_text: None,
+            });
+        }
+        /* Anything else becomes a block, the parser only picks up a single comment per declaration */
+        return Some(InsertionPoint {
+            start: first,
+            end: last + 1,
+            indent: leading_whitespace(&lines[first]).to_owned(),
+            block_text: Some(comment_text(comment)),
+        });
+    }
+
+    /* Without a comment, look for the declaration itself */
+    let declaration = Regex::new(&format!(r"^\s*[\w\s\*]*\b{}\s*\(", f.label)).ok()?;
+    let line = lines
+        .iter()
+        .position(|l| declaration.is_match(l).unwrap_or(false))?;
+    Some(InsertionPoint {
+        start: line,
+        end: line,
+        indent: leading_whitespace(&lines[line]).to_owned(),
+        block_text: Some(vec![]),
+    })
+}
+
+/// The text of a comment, without any of the `//`, `/*`, `*` or `*/` decoration
+fn comment_text(comment: &str) -> Vec<String> {
+    let text: Vec<String> = comment
+        .lines()
+        .map(|l| {
+            let l = l.trim();
+            let l = ["///", "//", "/**", "/*", "*"]
+                .iter()
+                .find_map(|p| l.strip_prefix(p))
+                .unwrap_or(l);
+            l.strip_suffix("*/").unwrap_or(l).trim().to_owned()
+        })
+        .collect();
+    let start = text
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(text.len());
+    let end = text
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |e| e + 1);
+    text[start..end].to_vec()
+}
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    const ENGINE: &str = "typedef struct Engine { int32_t x; } Engine;\n";
+
+    fn rewritten(source: &str) -> String {
+        let header = parse_source(source);
+        rewrite(source, "test.h", &header, &infer(&header))
+    }
+
+    #[test]
+    fn adds_a_comment_to_undocumented_functions() {
+        let source = format!(
+            "{}uint32_t Engine_new(struct Engine **out, const char *name, char **err);\n",
+            ENGINE
+        );
+        assert_eq!(
+            rewritten(&source),
+            format!(
+                "{}/**\n * #meta: for_struct;static;constructor;throws;\n * #meta_param: out;this;output;\n * #meta_param: name;string;\n * #meta_param: err;error;\n */\nuint32_t Engine_new(struct Engine **out, const char *name, char **err);\n",
+                ENGINE
+            )
+        );
+    }
+
+    #[test]
+    fn appends_to_existing_doc_comments() {
+        let source = format!(
+            "{}  /**\n   * Frees the engine\n   */\n  void Engine_free(struct Engine *this_);\n",
+            ENGINE
+        );
+        assert_eq!(
+            rewritten(&source),
+            format!(
+                "{}  /**\n   * Frees the engine\n   * #meta: for_struct;destructor;\n   * #meta_param: this_;this;\n   */\n  void Engine_free(struct Engine *this_);\n",
+                ENGINE
+            )
+        );
+    }
+
+    #[test]
+    fn turns_line_comments_into_doc_comments() {
+        let source = format!(
+            "{}// Reads the name\nvoid Engine_name(struct Engine *this_, char **out);\n",
+            ENGINE
+        );
+        assert_eq!(
+            rewritten(&source),
+            format!(
+                "{}/**\n * Reads the name\n * #meta: for_struct;\n * #meta_param: this_;this;\n * #meta_param: out;output;string;\n */\nvoid Engine_name(struct Engine *this_, char **out);\n",
+                ENGINE
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_annotations_from_the_header() {
+        let source = format!(
+            "{}/**\n * #meta: for_struct;\n * #meta_param: out;output;nullable;\n */\nvoid Engine_name(struct Engine *this_, char **out, char **err);\n",
+            ENGINE
+        );
+        assert_eq!(
+            rewritten(&source),
+            format!(
+                "{}/**\n * #meta: for_struct;\n * #meta_param: out;output;nullable;\n * #meta_param: this_;this;\n * #meta_param: err;error;\n */\nvoid Engine_name(struct Engine *this_, char **out, char **err);\n",
+                ENGINE
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_functions_of_other_files_alone() {
+        let source = format!(
+            "{}/** Frees it */\nvoid Engine_free(struct Engine *this_);\n",
+            ENGINE
+        );
+        let header = parse_source(&source);
+        assert_eq!(
+            rewrite(&source, "other.h", &header, &infer(&header)),
+            source
+        );
+    }
+
+    #[test]
+    fn leaves_annotated_functions_out() {
+        let header = parse_source("/**\n * #meta_param: name;string;\n */\nvoid greet(const char *name);\nvoid wave(int32_t times);");
+        assert!(infer(&header).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use crate::lexer::{CVariableDeclaration, HeaderFile};
+use crate::meta::MetaValue;
+
+/// Renders the parsed header as a readable tree, along with the meta values resolved for each item.
+///
+/// If `filter` is set, only enums, structs and functions whose name contains it are listed
+pub fn inspect(header: &HeaderFile, filter: Option<&str>) -> String {
+    let matches = |label: &str| filter.is_none_or(|f| label.contains(f));
+    let mut s = String::new();
+
+    for e in header.enums.iter().filter(|e| matches(&e.identifier.label)) {
+        writeln!(s, "enum {}{}", e.identifier.label, format_meta(&e.meta)).unwrap();
+        /* Members whose value cannot be worked out are listed with their initializer as written, if any */
+        let values = e.values().ok();
+        for (i, d) in e.declarations.iter().enumerate() {
+            let value = match (&values, &d.initializer) {
+                (Some(values), _) => format!(" = {}", values[i]),
+                (None, Some(initializer)) => format!(" = {}", initializer),
+                (None, None) => String::new(),
+            };
+            writeln!(
+                s,
+                "    {}{}{}",
+                d.label,
+                value,
+                format_deprecated(&d.deprecated)
+            )
+            .unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+
+    for st in header
+        .structs
+        .iter()
+        .filter(|st| matches(&st.identifier.label))
+    {
+        writeln!(
+            s,
+            "struct {}{}{}",
+            st.identifier.label,
+            format_meta(&st.meta),
+            format_deprecated(&st.deprecated)
+        )
+        .unwrap();
+        for d in &st.declarations {
+            writeln!(s, "    {}", format_declaration(d)).unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+
+    for f in header.functions.iter().filter(|f| matches(&f.label)) {
+        writeln!(
+            s,
+            "fn {} -> {} (pointers: {}){}{}",
+            f.label,
+            f.return_type,
+            f.return_type.pointer_count,
+            format_meta(&f.meta),
+            format_deprecated(&f.deprecated)
+        )
+        .unwrap();
+        for p in &f.parameters {
+            writeln!(s, "    {}", format_declaration(p)).unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+
+    s
+}
+
+fn format_declaration(d: &CVariableDeclaration) -> String {
+    let str_const = if d.is_const { "const " } else { "" };
+    format!(
+        "{}: {}{} (pointers: {}){}{}",
+        d.label,
+        str_const,
+        d.variable_type,
+        d.variable_type.pointer_count,
+        format_meta(&d.meta),
+        format_deprecated(&d.deprecated)
+    )
+}
+
+/// The deprecation the lexer resolved for an item, from its attribute or its `#meta`
+fn format_deprecated(deprecated: &Option<String>) -> String {
+    match deprecated.as_deref() {
+        Some("") => " (deprecated)".to_owned(),
+        Some(reason) => format!(" (deprecated: {})", reason),
+        None => String::new(),
+    }
+}
+
+fn format_meta(meta: &Option<MetaValue>) -> String {
+    match meta {
+        Some(m) if !m.is_empty() => format!(" [{}]", m.keywords().join(", ")),
+        _ => String::new(),
+    }
+}
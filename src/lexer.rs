@@ -4,6 +4,7 @@ use fancy_regex::Regex;
 use lang_c::ast::Identifier;
 use serde::Serialize;
 
+use crate::annotation::{item_annotations, param_annotations, Annotation};
 use crate::meta::MetaValue;
 
 const KEYWORD_STRUCT: &str = "struct";
@@ -24,6 +25,7 @@ const KEYWORD_PREPOCESSOR_ERROR: &str = "error";
 const KEYWORD_PREPOCESSOR_IFDEF: &str = "ifdef";
 const KEYWORD_PREPOCESSOR_IFNDEF: &str = "ifndef";
 
+#[derive(Clone)]
 pub struct HeaderFile {
     pub includes: Vec<String>,
     pub defines: Vec<CVariableDeclaration>,
@@ -48,13 +50,16 @@ impl From<Vec<CType>> for HeaderFile {
                 CType::Define(label, decl) => hf.defines.push(CVariableDeclaration {
                     label: label.to_owned(),
                     comment: None,
+                    comment_location: None,
                     meta: None,
+                    annotations: vec![],
                     is_const: true,
                     variable_type: CVariableType {
                         kind: *decl,
                         is_struct: false,
                         pointer_count: 0,
                     },
+                    deprecated: None,
                 }),
                 CType::Enum(e) => hf.enums.push(e),
                 CType::Struct(s) => hf.structs.push(s),
@@ -69,7 +74,10 @@ impl From<Vec<CType>> for HeaderFile {
 
 pub fn tokenize(token_str: &str) -> Vec<ClangTokenType> {
     let mut tokens: Vec<ClangTokenType> = vec![];
-    let r = Regex::new(r"([a-z_]+) '((//[^\n]*$|/(?!\\)\*[\s\S]*?\*(?!\\)/)|.*[\s]*)'").unwrap();
+    let r = Regex::new(
+        r"([a-z_]+) '((//[^\n]*$|/(?!\\)\*[\s\S]*?\*(?!\\)/)|.*[\s]*)'(?:[^\n]*?Loc=<(.+):(\d+):(\d+)>)?",
+    )
+    .unwrap();
     let captures = r.captures_iter(token_str);
     for capture in captures.into_iter() {
         match capture {
@@ -93,7 +101,17 @@ pub fn tokenize(token_str: &str) -> Vec<ClangTokenType> {
                     "l_square" => tokens.push(ClangTokenType::LSquare),
                     "r_square" => tokens.push(ClangTokenType::RSquare),
                     "comma" => tokens.push(ClangTokenType::Comma),
-                    "comment" => tokens.push(ClangTokenType::Comment(val)),
+                    "comment" => {
+                        let location = match (c.get(4), c.get(5), c.get(6)) {
+                            (Some(file), Some(line), Some(column)) => Some(SourceLocation {
+                                file: file.as_str().to_owned(),
+                                line: line.as_str().parse().unwrap_or_default(),
+                                column: column.as_str().parse().unwrap_or_default(),
+                            }),
+                            _ => None,
+                        };
+                        tokens.push(ClangTokenType::Comment(val, location))
+                    }
                     "numeric_constant" => {
                         let num = val.parse::<f64>().unwrap();
                         tokens.push(ClangTokenType::NumericConstant(num));
@@ -108,7 +126,12 @@ pub fn tokenize(token_str: &str) -> Vec<ClangTokenType> {
 }
 
 pub fn parse(tokens: Vec<ClangTokenType>) -> Result<HeaderFile, String> {
+    let tokens = fold_attributes(tokens);
     let mut ctypes: Vec<CType> = vec![];
+    /* Doc comments of `#define`s, which are not carried by `CType::Define` */
+    let mut define_comments: Vec<(String, String, Option<SourceLocation>)> = vec![];
+    /* The reason given by a deprecation attribute in front of the next declaration */
+    let mut deprecated: Option<String> = None;
 
     let mut iter = &mut tokens.iter().peekable();
     while let Some(token) = iter.peek() {
@@ -116,10 +139,13 @@ pub fn parse(tokens: Vec<ClangTokenType>) -> Result<HeaderFile, String> {
             ClangTokenType::Unknown(_) => {
                 iter.next();
             }
-            ClangTokenType::Comment(cmt) => {
-                if let ClangTokenType::Comment(_) = consume_whitespace(iter).unwrap() {}
-                consume_token(iter, ClangTokenType::Comment("".to_owned()), false);
+            ClangTokenType::Comment(cmt, location) => {
+                if let ClangTokenType::Comment(..) = consume_whitespace(iter).unwrap() {}
+                consume_token(iter, ClangTokenType::Comment("".to_owned(), None), false);
                 consume_whitespace(iter);
+                if let Some(reason) = take_deprecation(iter) {
+                    deprecated = Some(reason);
+                }
                 let peek = iter.peek().unwrap();
                 match peek {
                     ClangTokenType::RawIdentifier(val) => {
@@ -130,12 +156,26 @@ pub fn parse(tokens: Vec<ClangTokenType>) -> Result<HeaderFile, String> {
                                 false,
                             );
                             iter.next();
-                            ctypes.push(parse_type(iter, Some(cmt.to_owned()))?);
+                            ctypes.push(parse_type(iter, Some(cmt.to_owned()), location.clone())?);
                         } else {
-                            ctypes.push(parse_function(iter, Some(cmt.to_owned()))?);
+                            ctypes.push(parse_function(
+                                iter,
+                                Some(cmt.to_owned()),
+                                location.clone(),
+                            )?);
+                        }
+                        deprecate(ctypes.last_mut(), deprecated.take());
+                    }
+                    ClangTokenType::Hash => {
+                        let (cmt, location) = (cmt.to_owned(), location.clone());
+                        let before = ctypes.len();
+                        parse_preprocessor(iter, &mut ctypes)?;
+                        if let (Some(CType::Define(label, _)), true) =
+                            (ctypes.last(), ctypes.len() > before)
+                        {
+                            define_comments.push((label.to_owned(), cmt, location));
                         }
                     }
-                    ClangTokenType::Hash => continue,
                     _ => {
                         iter.next();
                     }
@@ -149,11 +189,16 @@ pub fn parse(tokens: Vec<ClangTokenType>) -> Result<HeaderFile, String> {
                         false,
                     );
                     consume_whitespace(iter);
-                    ctypes.push(parse_type(iter, None)?);
+                    ctypes.push(parse_type(iter, None, None)?);
                 } else {
-                    ctypes.push(parse_function(iter, None)?);
+                    ctypes.push(parse_function(iter, None, None)?);
                     // println!("{}", ctypes[ctypes.len() - 1]);
                 }
+                deprecate(ctypes.last_mut(), deprecated.take());
+            }
+            ClangTokenType::Deprecated(reason) => {
+                deprecated = Some(reason.to_owned());
+                iter.next();
             }
             ClangTokenType::Hash => parse_preprocessor(&mut iter, &mut ctypes)?,
             ClangTokenType::NumericConstant(_) => todo!(),
@@ -174,7 +219,213 @@ pub fn parse(tokens: Vec<ClangTokenType>) -> Result<HeaderFile, String> {
             ClangTokenType::Plus => todo!(),
         }
     }
-    return Ok(HeaderFile::from(ctypes));
+    let mut hf = HeaderFile::from(ctypes);
+    for (label, comment, location) in define_comments {
+        if let Some(d) = hf.defines.iter_mut().find(|d| d.label == label) {
+            d.comment = Some(comment);
+            d.comment_location = location;
+            d.annotations = item_annotations(&d.comment);
+            d.meta = MetaValue::from_annotations(&d.annotations);
+        }
+    }
+    return Ok(hf);
+}
+
+/// The names of the macros that stand for a deprecation attribute when no `#define` in the header says so,
+/// e.g., those of cbindgen's `deprecated` and `deprecated_with_note` settings
+const DEPRECATION_MACROS: [&str; 2] = ["DEPRECATED", "DEPRECATED_WITH_NOTE"];
+
+/// Folds each attribute of a declaration into a single `ClangTokenType::Deprecated` token if it deprecates it,
+/// and drops it otherwise, so that the parsers never see the likes of `__attribute__((warn_unused_result))`.
+///
+/// e.g.,
+/// ```c
+/// #define DEPRECATED_WITH_NOTE(note) __attribute__((deprecated(note)))
+///
+/// DEPRECATED_WITH_NOTE("use OAuth2PKCE_get_token instead")
+/// uint32_t OAuth2PKCE_get_token_automatic(struct OAuth2PKCE *this_, char **err_ptr);
+///
+/// typedef struct [[deprecated]] LegacyToken {
+///   int64_t expires_at __attribute__((deprecated("always 0")));
+/// } LegacyToken;
+/// ```
+///
+/// Besides `DEPRECATION_MACROS`, any macro the header defines as a deprecation attribute counts as one.
+/// Such `#define`s are dropped along with the attributes, as they mean nothing to the bindings
+fn fold_attributes(tokens: Vec<ClangTokenType>) -> Vec<ClangTokenType> {
+    let macros = deprecation_macros(&tokens);
+    let mut folded: Vec<ClangTokenType> = vec![];
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            ClangTokenType::Hash => {
+                let mut directive = vec![ClangTokenType::Hash];
+                while let Some(t) = iter.next_if(|t| !is_end_of_line(t)) {
+                    directive.push(t);
+                }
+                let defined = directive.iter().filter_map(|t| match t {
+                    ClangTokenType::RawIdentifier(val) => Some(val.as_str()),
+                    _ => None,
+                });
+                if let [KEYWORD_PREPOCESSOR_DEFINE, name, ..] = defined.collect::<Vec<_>>()[..] {
+                    if macros.iter().any(|m| m == name) {
+                        continue;
+                    }
+                }
+                folded.extend(directive);
+            }
+            ClangTokenType::RawIdentifier(val)
+                if val == "__attribute__" || macros.iter().any(|m| *m == val) =>
+            {
+                /* a macro may take the reason in parentheses, or nothing at all */
+                let mut whitespace = vec![];
+                while let Some(t) =
+                    iter.next_if(|t| matches!(t, ClangTokenType::Unknown(s) if s.trim().is_empty()))
+                {
+                    whitespace.push(t);
+                }
+                let arguments = match iter.peek() {
+                    Some(ClangTokenType::LParen) => take_parenthesized(&mut iter),
+                    _ => vec![],
+                };
+                if val != "__attribute__" || is_deprecation(&arguments) {
+                    folded.push(ClangTokenType::Deprecated(reason_among(&arguments)));
+                }
+                if arguments.is_empty() {
+                    folded.extend(whitespace);
+                }
+            }
+            /* `[[deprecated("reason")]]` */
+            ClangTokenType::LSquare if iter.peek() == Some(&ClangTokenType::LSquare) => {
+                let mut attribute = vec![];
+                let mut depth = 1;
+                for t in iter.by_ref() {
+                    match t {
+                        ClangTokenType::LSquare => depth += 1,
+                        ClangTokenType::RSquare => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    attribute.push(t);
+                }
+                if is_deprecation(&attribute) {
+                    folded.push(ClangTokenType::Deprecated(reason_among(&attribute)));
+                }
+            }
+            token => folded.push(token),
+        }
+    }
+    folded
+}
+
+/// The names of the macros that stand for a deprecation attribute in `tokens`, which are `DEPRECATION_MACROS`
+/// along with those defined as one, e.g., `#define MY_DEPRECATED(note) __attribute__((deprecated(note)))`
+fn deprecation_macros(tokens: &[ClangTokenType]) -> Vec<String> {
+    let mut macros: Vec<String> = DEPRECATION_MACROS.iter().map(|m| m.to_string()).collect();
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        if *token != ClangTokenType::Hash {
+            continue;
+        }
+        let mut directive = vec![];
+        while let Some(t) = iter.next_if(|t| !is_end_of_line(t)) {
+            if let ClangTokenType::RawIdentifier(val) = t {
+                directive.push(val.as_str());
+            }
+        }
+        if let [KEYWORD_PREPOCESSOR_DEFINE, name, ..] = directive[..] {
+            if directive[2..].iter().any(|t| is_deprecated_keyword(t)) {
+                macros.push(name.to_owned());
+            }
+        }
+    }
+    macros
+}
+
+/// Reads everything from an opening parenthesis up to the one closing it, and returns what is between them
+fn take_parenthesized(
+    iter: &mut Peekable<std::vec::IntoIter<ClangTokenType>>,
+) -> Vec<ClangTokenType> {
+    let mut inner = vec![];
+    let mut depth = 0;
+    for t in iter.by_ref() {
+        match t {
+            ClangTokenType::LParen => depth += 1,
+            ClangTokenType::RParen => depth -= 1,
+            _ => {}
+        }
+        match depth {
+            0 => break,
+            1 if t == ClangTokenType::LParen => continue,
+            _ => inner.push(t),
+        }
+    }
+    inner
+}
+
+fn is_end_of_line(token: &ClangTokenType) -> bool {
+    matches!(token, ClangTokenType::Unknown(s) if s.contains('\n'))
+}
+
+fn is_deprecated_keyword(val: &str) -> bool {
+    val == "deprecated" || val == "__deprecated__"
+}
+
+/// Whether the attribute made of `tokens` deprecates what it is attached to, e.g., `(deprecated("reason"))`
+fn is_deprecation(tokens: &[ClangTokenType]) -> bool {
+    tokens
+        .iter()
+        .any(|t| matches!(t, ClangTokenType::RawIdentifier(val) if is_deprecated_keyword(val)))
+}
+
+/// The first string in `tokens`, without its quotes, which is the reason given by a deprecation attribute
+fn reason_among(tokens: &[ClangTokenType]) -> String {
+    tokens
+        .iter()
+        .find_map(|t| match t {
+            ClangTokenType::Unknown(s)
+                if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') =>
+            {
+                Some(
+                    s[1..s.len() - 1]
+                        .replace("\\\"", "\"")
+                        .replace("\\\\", "\\"),
+                )
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Marks the declaration `ctype` deprecated for `reason`, as given by an attribute in front of it
+fn deprecate(ctype: Option<&mut CType>, reason: Option<String>) {
+    if reason.is_none() {
+        return;
+    }
+    match ctype {
+        Some(CType::Struct(s)) => s.deprecated = reason,
+        Some(CType::Function(f)) => f.deprecated = reason,
+        _ => {}
+    }
+}
+
+/// Reads the deprecation attribute the iterator is at, if it is at one, along with the whitespace after it
+fn take_deprecation(iter: &mut Peekable<Iter<ClangTokenType>>) -> Option<String> {
+    match iter.peek() {
+        Some(ClangTokenType::Deprecated(reason)) => {
+            iter.next();
+            consume_whitespace(iter);
+            Some(reason.to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Why the item with the meta value `meta` is deprecated, from the attribute it carries, or else its `#meta: deprecated`
+fn deprecation(attribute: Option<String>, meta: &Option<MetaValue>) -> Option<String> {
+    attribute.or_else(|| meta.as_ref().and_then(|m| m.deprecated.to_owned()))
 }
 
 fn parse_preprocessor(
@@ -246,6 +497,7 @@ fn parse_preprocessor(
 fn parse_type(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: Option<String>,
+    comment_location: Option<SourceLocation>,
 ) -> Result<CType, String> {
     let peek = iter.peek();
     if let None = peek {
@@ -255,11 +507,11 @@ fn parse_type(
     match kind {
         ClangTokenType::RawIdentifier(val) => {
             if val == KEYWORD_ENUM {
-                parse_enum(iter, comment)
+                parse_enum(iter, comment, comment_location)
             } else if val == KEYWORD_STRUCT {
-                parse_struct(iter, comment)
+                parse_struct(iter, comment, comment_location)
             } else {
-                parse_function(iter, comment)
+                parse_function(iter, comment, comment_location)
             }
         }
         _ => Err("Invalid token: nothing after typedef".to_owned()),
@@ -269,9 +521,13 @@ fn parse_type(
 fn parse_enum(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: Option<String>,
+    comment_location: Option<SourceLocation>,
 ) -> Result<CType, String> {
+    let annotations = item_annotations(&comment);
     iter.next(); // Consume `enum` token
     consume_whitespace(iter);
+    /* only the members of an enum can be deprecated, not the enum itself */
+    take_deprecation(iter);
     let label: String;
     let mut declarations: Vec<CIdentifier> = vec![];
     if let ClangTokenType::RawIdentifier(val) = iter.next().unwrap() {
@@ -283,6 +539,11 @@ fn parse_enum(
     consume_whitespace(iter);
 
     let mut current_comment: Option<String> = None;
+    /* the attribute of a member comes after its name, e.g., `Fast DEPRECATED,`, though it may come before it */
+    let mut is_member_open = false;
+    /* the tokens after `=` make up the initializer of the open member, e.g., `A_FAIL = 10,` */
+    let mut is_initializer = false;
+    let mut deprecated: Option<String> = None;
 
     while let Some(token) = iter.next() {
         match token {
@@ -292,20 +553,48 @@ fn parse_enum(
                     identifier: CIdentifier {
                         label: label.to_owned(),
                         comment: None,
+                        deprecated: None,
+                        initializer: None,
                     },
-                    meta: MetaValue::from_meta_comment_dontcare(&comment),
+                    meta: MetaValue::from_annotations(&annotations),
+                    annotations,
                     comment,
+                    comment_location,
                     declarations,
                 }));
             }
-            ClangTokenType::Comma => continue,
-            ClangTokenType::Comment(cmt) => current_comment = Some(cmt.to_owned()),
-            ClangTokenType::RawIdentifier(identifier) => {
+            ClangTokenType::Comma => {
+                is_member_open = false;
+                is_initializer = false;
+            }
+            ClangTokenType::Comment(cmt, _) => current_comment = Some(cmt.to_owned()),
+            ClangTokenType::Deprecated(reason) => match declarations.last_mut() {
+                Some(member) if is_member_open => member.deprecated = Some(reason.to_owned()),
+                _ => deprecated = Some(reason.to_owned()),
+            },
+            ClangTokenType::RawIdentifier(identifier) if !is_initializer => {
+                let meta = MetaValue::from_annotations(&item_annotations(&current_comment));
                 declarations.push(CIdentifier {
                     label: identifier.to_owned(),
                     comment: current_comment,
+                    deprecated: deprecation(deprecated.take(), &meta),
+                    initializer: None,
                 });
                 current_comment = None;
+                is_member_open = true;
+            }
+            ClangTokenType::Unknown(val) if val == "=" && is_member_open => is_initializer = true,
+            ClangTokenType::Unknown(val) if val.trim().is_empty() => continue,
+            token if is_initializer => {
+                if let Some(member) = declarations.last_mut() {
+                    let initializer = member.initializer.get_or_insert_with(String::new);
+                    /* `l_paren` is tokenized as `LParen`, which displays as `)` */
+                    match token {
+                        ClangTokenType::LParen => initializer.push('('),
+                        ClangTokenType::RParen => initializer.push(')'),
+                        _ => initializer.push_str(&token.to_string()),
+                    }
+                }
             }
             _ => continue,
         }
@@ -316,9 +605,15 @@ fn parse_enum(
 fn parse_struct(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: Option<String>,
+    comment_location: Option<SourceLocation>,
 ) -> Result<CType, String> {
+    let annotations = item_annotations(&comment);
     iter.next(); // Consume `struct` token
     consume_whitespace(iter);
+    let deprecated = deprecation(
+        take_deprecation(iter),
+        &MetaValue::from_annotations(&annotations),
+    );
     let label: String;
     let mut members: Vec<CVariableDeclaration> = vec![];
     if let ClangTokenType::RawIdentifier(val) = iter.next().unwrap() {
@@ -336,10 +631,15 @@ fn parse_struct(
             identifier: CIdentifier {
                 label: label.to_owned(),
                 comment: None,
+                deprecated: None,
+                initializer: None,
             },
-            meta: MetaValue::from_meta_comment_dontcare(&comment),
+            meta: MetaValue::from_annotations(&annotations),
+            annotations,
             comment,
+            comment_location,
             declarations: members,
+            deprecated,
         }));
     }
 
@@ -348,6 +648,7 @@ fn parse_struct(
     consume_whitespace(iter);
 
     let mut current_comment: Option<String> = None;
+    let mut current_location: Option<SourceLocation> = None;
     while let Some(token) = iter.peek() {
         match token {
             ClangTokenType::RBrace => {
@@ -357,19 +658,29 @@ fn parse_struct(
                     identifier: CIdentifier {
                         label: label.to_owned(),
                         comment: None,
+                        deprecated: None,
+                        initializer: None,
                     },
-                    meta: MetaValue::from_meta_comment_dontcare(&comment),
+                    meta: MetaValue::from_annotations(&annotations),
+                    annotations,
 
                     comment,
+                    comment_location,
                     declarations: members,
+                    deprecated,
                 }));
             }
-            ClangTokenType::Comment(cmt) => {
+            ClangTokenType::Comment(cmt, location) => {
                 current_comment = Some(cmt.to_owned());
+                current_location = location.clone();
                 iter.next();
             }
             _ => {
-                members.push(parse_struct_member(iter, current_comment)?);
+                members.push(parse_struct_member(
+                    iter,
+                    current_comment,
+                    current_location.take(),
+                )?);
                 current_comment = None;
             }
         }
@@ -380,10 +691,15 @@ fn parse_struct(
         identifier: CIdentifier {
             label: label.to_owned(),
             comment: None,
+            deprecated: None,
+            initializer: None,
         },
-        meta: MetaValue::from_meta_comment_dontcare(&comment),
+        meta: MetaValue::from_annotations(&annotations),
+        annotations,
         comment,
+        comment_location,
         declarations: members,
+        deprecated,
     };
     Ok(CType::Struct(s))
 }
@@ -391,13 +707,16 @@ fn parse_struct(
 fn parse_struct_member(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: Option<String>,
+    comment_location: Option<SourceLocation>,
 ) -> Result<CVariableDeclaration, String> {
+    let annotations = item_annotations(&comment);
     consume_whitespace(iter);
     let mut label: &str = "";
     let mut pointer_count = 0;
     let mut is_const: bool = false;
     let mut is_struct: bool = false;
     let mut is_enum: bool = false;
+    let mut deprecated: Option<String> = None;
 
     let mut signature: Vec<&str> = vec![];
     while let Some(token) = iter.next() {
@@ -417,15 +736,25 @@ fn parse_struct_member(
                 }
             }
             ClangTokenType::Star => pointer_count += 1,
+            /* either in front of the field or after its name, e.g., `int64_t expires_at DEPRECATED;` */
+            ClangTokenType::Deprecated(reason) => deprecated = Some(reason.to_owned()),
             ClangTokenType::Semi => {
+                if label.is_empty() {
+                    /* the name was not right in front of the semicolon, so it is the last word of the signature */
+                    label = signature.pop().unwrap_or_default();
+                }
                 let variable_type =
                     match_variable_signature(signature, is_struct, is_enum, pointer_count)?;
+                let meta = MetaValue::from_annotations(&annotations);
                 let variable_decl = CVariableDeclaration {
-                    meta: MetaValue::from_meta_comment_dontcare(&comment),
+                    deprecated: deprecation(deprecated, &meta),
+                    meta,
+                    annotations,
                     comment: match &comment {
                         Some(cmt) => Some(cmt.to_owned()),
                         None => None,
                     },
+                    comment_location,
                     is_const,
                     label: label.to_owned(),
                     variable_type,
@@ -481,10 +810,15 @@ fn match_variable_signature(
                         identifier: CIdentifier {
                             label: signature.first().unwrap().to_string(),
                             comment: None,
+                            deprecated: None,
+                            initializer: None,
                         },
                         meta: None,
+                        annotations: vec![],
                         comment: None,
+                        comment_location: None,
                         declarations: vec![],
+                        deprecated: None,
                     })
                 } else if is_enum {
                     // FYI(nf): dummy enum
@@ -492,9 +826,13 @@ fn match_variable_signature(
                         identifier: CIdentifier {
                             label: signature.first().unwrap().to_string(),
                             comment: None,
+                            deprecated: None,
+                            initializer: None,
                         },
                         meta: None,
+                        annotations: vec![],
                         comment: None,
+                        comment_location: None,
                         declarations: vec![],
                     })
                 } else {
@@ -533,7 +871,9 @@ fn end_struct_with_name(
 fn parse_function(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: Option<String>,
+    comment_location: Option<SourceLocation>,
 ) -> Result<CType, String> {
+    let annotations = item_annotations(&comment);
     let mut label: &str = "";
 
     /* Get the Return Value */
@@ -541,6 +881,7 @@ fn parse_function(
     let mut return_pointer_count: u8 = 0;
     let mut return_is_struct: bool = false;
     let mut return_is_enum: bool = false;
+    let mut deprecated: Option<String> = None;
 
     while let Some(token) = iter.peek() {
         match token {
@@ -562,6 +903,7 @@ fn parse_function(
                     return_signature.push(val);
                 }
             }
+            ClangTokenType::Deprecated(reason) => deprecated = Some(reason.to_owned()),
             /* ignore whitespace */
             ClangTokenType::Unknown(_) => (),
             _ => (),
@@ -580,6 +922,7 @@ fn parse_function(
     iter.next(); /* consume l-paren */
 
     let mut parameters: Vec<CVariableDeclaration> = vec![];
+    let meta = MetaValue::from_annotations(&annotations);
 
     let mut current_comment: Option<String> = None;
     while let Some(token) = iter.peek() {
@@ -590,12 +933,20 @@ fn parse_function(
                 return Ok(CType::Function(CFunction {
                     return_type: Box::new(return_type),
                     label: label.to_string(),
-                    meta: MetaValue::from_meta_comment_dontcare(&comment),
+                    deprecated: deprecation(deprecated, &meta),
+                    meta,
+                    annotations,
                     comment,
+                    comment_location,
                     parameters,
                 }));
             }
-            ClangTokenType::Comment(cmt) => {
+            /* after the parameters, e.g., `void OAuth2PKCE_free(struct OAuth2PKCE *this_) DEPRECATED;` */
+            ClangTokenType::Deprecated(reason) => {
+                deprecated = Some(reason.to_owned());
+                iter.next();
+            }
+            ClangTokenType::Comment(cmt, _) => {
                 current_comment = Some(cmt.to_owned());
                 iter.next();
             }
@@ -604,8 +955,10 @@ fn parse_function(
                 break;
             }
             _ => {
-                let param = parse_function_parameter(iter, &comment)?;
-                if let CType::Void = param.variable_type.kind {
+                let param = parse_function_parameter(iter, &comment, &comment_location)?;
+                if let (CType::Void, 0) =
+                    (&param.variable_type.kind, param.variable_type.pointer_count)
+                {
                     parameters.clear();
                 } else {
                     parameters.push(param);
@@ -620,8 +973,11 @@ fn parse_function(
     let func: CFunction = CFunction {
         return_type: Box::new(return_type),
         label: label.to_string(),
-        meta: MetaValue::from_meta_comment_dontcare(&comment),
+        deprecated: deprecation(deprecated, &meta),
+        meta,
+        annotations,
         comment,
+        comment_location,
         parameters,
     };
 
@@ -631,6 +987,7 @@ fn parse_function(
 fn parse_function_parameter(
     iter: &mut Peekable<Iter<ClangTokenType>>,
     comment: &Option<String>,
+    comment_location: &Option<SourceLocation>,
 ) -> Result<CVariableDeclaration, String> {
     consume_whitespace(iter);
     let mut label: &str = "";
@@ -638,18 +995,22 @@ fn parse_function_parameter(
     let mut is_const: bool = false;
     let mut is_struct: bool = false;
     let mut is_enum: bool = false;
+    /* set once a function pointer is read, e.g., `void (*on_done)(void *user_data)` */
+    let mut function: Option<CFunction> = None;
 
     let mut signature: Vec<&str> = vec![];
     while let Some(token) = iter.next() {
         match token {
             ClangTokenType::RawIdentifier(val) => {
-                if val == KEYWORD_VOID {
+                if val == KEYWORD_VOID && matches!(iter.peek(), Some(ClangTokenType::RParen)) {
                     /* skip parsing parameters, this is a void function */
                     consume_until(iter, ClangTokenType::RParen);
                     /* fyi(nf): dummy variable for void parameter functions */
                     return Ok(CVariableDeclaration {
                         comment: None,
+                        comment_location: None,
                         meta: None,
+                        annotations: vec![],
                         is_const: false,
                         label: "".to_owned(),
                         variable_type: CVariableType {
@@ -657,6 +1018,7 @@ fn parse_function_parameter(
                             is_struct: false,
                             pointer_count: 0,
                         },
+                        deprecated: None,
                     });
                 }
                 let peek = iter.peek().unwrap();
@@ -677,18 +1039,41 @@ fn parse_function_parameter(
                 }
             }
             ClangTokenType::Star => pointer_count += 1,
+            ClangTokenType::LParen if function.is_none() => {
+                let return_type = match_variable_signature(
+                    std::mem::take(&mut signature),
+                    is_struct,
+                    is_enum,
+                    pointer_count,
+                )?;
+                let (name, f) =
+                    parse_function_pointer(iter, return_type, comment, comment_location)?;
+                label = name;
+                function = Some(f);
+            }
             ClangTokenType::Comma | ClangTokenType::RParen => {
-                let variable_type =
-                    match_variable_signature(signature, is_struct, is_enum, pointer_count)?;
+                let variable_type = match function.take() {
+                    /* the pointer is part of the function pointer's own syntax, `(*on_done)` */
+                    Some(f) => CVariableType {
+                        kind: CType::Function(f),
+                        is_struct: false,
+                        pointer_count: 1,
+                    },
+                    None => match_variable_signature(signature, is_struct, is_enum, pointer_count)?,
+                };
+                let annotations = param_annotations(comment, label);
                 let variable_decl = CVariableDeclaration {
-                    meta: MetaValue::from_meta_comment_for_param(&comment, label),
+                    meta: MetaValue::from_annotations(&annotations),
+                    annotations,
                     comment: match &comment {
                         Some(cmt) => Some(cmt.to_owned()),
                         None => None,
                     },
+                    comment_location: comment_location.clone(),
                     is_const,
                     label: label.to_owned(),
                     variable_type,
+                    deprecated: None,
                 };
 
                 return Ok(variable_decl);
@@ -700,6 +1085,69 @@ fn parse_function_parameter(
     Err("function parameter: Failed to parse variable member".to_owned())
 }
 
+/// Reads the rest of a function pointer parameter once its return type is known, from its name to its parameters,
+/// e.g., `*on_done)(void *user_data, char *error)`. The parameters take their `#meta_param` lines from `comment`,
+/// the same way the parameters of the function do.
+///
+/// Returns the name of the parameter along with the signature of the function it points to, labelled with the same name
+fn parse_function_pointer<'a>(
+    iter: &mut Peekable<Iter<'a, ClangTokenType>>,
+    return_type: CVariableType,
+    comment: &Option<String>,
+    comment_location: &Option<SourceLocation>,
+) -> Result<(&'a str, CFunction), String> {
+    let invalid =
+        || "Invalid function pointer parameter: expected `(*name)(parameters)`".to_owned();
+    consume_whitespace(iter);
+    if !matches!(iter.next(), Some(ClangTokenType::Star)) {
+        return Err(invalid());
+    }
+    consume_whitespace(iter);
+    let label = match iter.next() {
+        Some(ClangTokenType::RawIdentifier(label)) => label,
+        _ => return Err(invalid()),
+    };
+    consume_whitespace(iter);
+    if !matches!(iter.next(), Some(ClangTokenType::RParen)) {
+        return Err(invalid());
+    }
+    consume_whitespace(iter);
+    if !matches!(iter.next(), Some(ClangTokenType::LParen)) {
+        return Err(invalid());
+    }
+
+    let mut parameters: Vec<CVariableDeclaration> = vec![];
+    loop {
+        let param = parse_function_parameter(iter, comment, comment_location)?;
+        /* `(void)` takes nothing */
+        if !matches!(param.variable_type.kind, CType::Void) || param.variable_type.pointer_count > 0
+        {
+            parameters.push(param);
+        }
+        /* each parameter eats the comma after it, the last one the closing parenthesis */
+        if matches!(
+            consume_whitespace(iter),
+            Some(ClangTokenType::Comma | ClangTokenType::RParen) | None
+        ) {
+            break;
+        }
+    }
+
+    Ok((
+        label,
+        CFunction {
+            return_type: Box::new(return_type),
+            label: label.to_owned(),
+            comment: None,
+            comment_location: None,
+            meta: None,
+            annotations: vec![],
+            parameters,
+            deprecated: None,
+        },
+    ))
+}
+
 fn parse_preprocessor_define(iter: &mut Peekable<Iter<ClangTokenType>>) -> Result<CType, String> {
     let mut label: &str = "";
     let mut is_negative: bool = false;
@@ -828,8 +1276,8 @@ fn consume_token<'a>(
                             }
                         }
                     }
-                    ClangTokenType::Comment(val) => {
-                        if let ClangTokenType::Comment(inner) = token {
+                    ClangTokenType::Comment(val, _) => {
+                        if let ClangTokenType::Comment(inner, _) = token {
                             if &inner == val {
                                 return t;
                             }
@@ -910,8 +1358,8 @@ fn check_ifndef(ctypes: &Vec<CType>, identifier: &str) -> bool {
 pub enum ClangTokenType {
     /// Usually just whitespace
     Unknown(String),
-    /// Either // or /* [...] */
-    Comment(String),
+    /// Either // or /* [...] */, along with where it starts in the header
+    Comment(String, Option<SourceLocation>),
     /// Anything else
     RawIdentifier(String),
     /// Numbers (all ints are doubles)
@@ -948,12 +1396,15 @@ pub enum ClangTokenType {
     Plus,
     /// !
     Bang,
+    /// `__attribute__((deprecated))`, `[[deprecated]]` or a macro for either, along with the reason it gives,
+    /// which is empty if it gives none, see `fold_attributes`
+    Deprecated(String),
 }
 impl fmt::Display for ClangTokenType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match &self {
             ClangTokenType::Unknown(val) => format!("{}", val),
-            ClangTokenType::Comment(val) => format!("{}", val),
+            ClangTokenType::Comment(val, _) => format!("{}", val),
             ClangTokenType::RawIdentifier(val) => format!("{}", val),
             ClangTokenType::NumericConstant(val) => format!("{}", val),
             ClangTokenType::Comma => String::from(","),
@@ -972,11 +1423,27 @@ impl fmt::Display for ClangTokenType {
             ClangTokenType::Minus => String::from("-"),
             ClangTokenType::Plus => String::from("+"),
             ClangTokenType::Bang => String::from("!"),
+            ClangTokenType::Deprecated(reason) => {
+                format!("__attribute__((deprecated({:?})))", reason)
+            }
         };
         write!(f, "{}", s)
     }
 }
 
+/// A position in the header file, as reported by clang
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CType {
     Enum(CEnum),
@@ -1021,6 +1488,30 @@ pub enum CType {
     Void,
     UNINITIALIZED,
 }
+impl CType {
+    /// The C spelling of this type, without any value attached, e.g., `uint32_t` or `OAuthManager`
+    pub fn type_name(&self) -> String {
+        match &self {
+            CType::Enum(v) => v.identifier.label.to_owned(),
+            CType::Struct(v) => v.identifier.label.to_owned(),
+            CType::Function(v) => v.label.to_owned(),
+            CType::Include(_) => String::from("include"),
+            CType::Define(_, value) => value.type_name(),
+            CType::SignedShort(_) => String::from("signed short"),
+            CType::UnsignedShort(_) => String::from("unsigned short"),
+            CType::SignedInteger(_) => String::from("signed int"),
+            CType::UnsignedInteger(_) => String::from("unsigned int"),
+            CType::SignedLong(_) => String::from("signed long"),
+            CType::UnsignedLong(_) => String::from("unsigned long"),
+            CType::Int64T(_) => String::from("int64_t"),
+            CType::Float(_) => String::from("float"),
+            CType::Double(_) => String::from("double"),
+            CType::DoubleDouble(_) => String::from("long double"),
+            CType::Char(_) => String::from("char"),
+            _ => self.to_string(),
+        }
+    }
+}
 impl fmt::Display for CType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match &self {
@@ -1061,12 +1552,52 @@ impl fmt::Display for CType {
 pub struct CEnum {
     pub identifier: CIdentifier,
     pub comment: Option<String>,
+    /// Where the doc comment of this enum starts, if it has one
+    pub comment_location: Option<SourceLocation>,
     pub meta: Option<MetaValue>,
+    /// The `#meta` annotations `meta` was derived from
+    pub annotations: Vec<Annotation>,
     pub declarations: Vec<CIdentifier>,
 }
+impl CEnum {
+    /// The value of each member, which is its initializer, or one more than the member before it, starting at 0.
+    /// Only integer initializers are understood, e.g., `A_FAIL = 10` or `A_NONE = -1`
+    pub fn values(&self) -> Result<Vec<i64>, String> {
+        let mut next = 0;
+        self.declarations
+            .iter()
+            .map(|d| {
+                let value = match &d.initializer {
+                    Some(initializer) => initializer.parse::<i64>().map_err(|_| {
+                        format!(
+                            "enum member `{}` is initialized with `{}`, which is not an integer",
+                            d.label, initializer
+                        )
+                    })?,
+                    None => next,
+                };
+                next = value + 1;
+                Ok(value)
+            })
+            .collect()
+    }
+}
 impl fmt::Display for CEnum {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "enum {}{{{}}};", self.identifier.label, "TODO_MEMBERS")
+        let members: Vec<String> = self
+            .declarations
+            .iter()
+            .map(|d| match &d.initializer {
+                Some(initializer) => format!("{} = {}", d.label, initializer),
+                None => d.label.to_owned(),
+            })
+            .collect();
+        write!(
+            f,
+            "enum {}{{{}}};",
+            self.identifier.label,
+            members.join(", ")
+        )
     }
 }
 
@@ -1074,8 +1605,14 @@ impl fmt::Display for CEnum {
 pub struct CStruct {
     pub identifier: CIdentifier,
     pub comment: Option<String>,
+    /// Where the doc comment of this struct starts, if it has one
+    pub comment_location: Option<SourceLocation>,
     pub meta: Option<MetaValue>,
+    /// The `#meta` annotations `meta` was derived from
+    pub annotations: Vec<Annotation>,
     pub declarations: Vec<CVariableDeclaration>,
+    /// Why this struct is deprecated, if it is, see `CFunction::deprecated`
+    pub deprecated: Option<String>,
 }
 impl fmt::Display for CStruct {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -1087,12 +1624,20 @@ impl fmt::Display for CStruct {
 pub struct CIdentifier {
     pub label: String,
     pub comment: Option<String>,
+    /// Why this enum member is deprecated, if it is, see `CFunction::deprecated`.
+    /// Never set on the identifier of a struct or enum
+    pub deprecated: Option<String>,
+    /// The value this enum member is explicitly given, as written, e.g., `10` for `A_FAIL = 10`.
+    /// Never set on the identifier of a struct or enum
+    pub initializer: Option<String>,
 }
 impl CIdentifier {
     pub fn new(label: &str, comment: Option<&str>) -> Self {
         return CIdentifier {
             label: label.to_owned(),
             comment: comment.map(|f| f.to_owned()),
+            deprecated: None,
+            initializer: None,
         };
     }
 }
@@ -1119,18 +1664,27 @@ pub struct CFunction {
     pub return_type: Box<CVariableType>,
     pub label: String,
     pub comment: Option<String>,
+    /// Where the doc comment of this function starts, if it has one
+    pub comment_location: Option<SourceLocation>,
     pub meta: Option<MetaValue>,
+    /// The `#meta` annotations `meta` was derived from
+    pub annotations: Vec<Annotation>,
     pub parameters: Vec<CVariableDeclaration>,
+    /// Why this function is deprecated, if it is. This is the reason given by `__attribute__((deprecated))`,
+    /// `[[deprecated]]` or a macro for either, see `fold_attributes`, or else by `#meta: deprecated(#reason);`.
+    /// Empty if neither gives one
+    pub deprecated: Option<String>,
 }
 
 impl fmt::Display for CFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
         write!(
             f,
             "{} {}({});",
             self.return_type.to_string(),
             self.label,
-            "TODO_PARAM",
+            params.join(", "),
         )
     }
 }
@@ -1139,9 +1693,15 @@ impl fmt::Display for CFunction {
 pub struct CVariableDeclaration {
     pub label: String,
     pub comment: Option<String>,
+    /// Where `comment` starts. For function parameters, this is the comment of the function
+    pub comment_location: Option<SourceLocation>,
     pub is_const: bool,
     pub variable_type: CVariableType,
     pub meta: Option<MetaValue>,
+    /// The `#meta` annotations `meta` was derived from. For function parameters, those of its `#meta_param` lines
+    pub annotations: Vec<Annotation>,
+    /// Why this struct field is deprecated, if it is, see `CFunction::deprecated`. Never set on other declarations
+    pub deprecated: Option<String>,
 }
 
 impl fmt::Display for CVariableDeclaration {
@@ -1149,7 +1709,7 @@ impl fmt::Display for CVariableDeclaration {
         let str_const = if self.is_const { "const " } else { "" };
         write!(
             f,
-            "{}{}{}",
+            "{}{} {}",
             str_const,
             self.variable_type.to_string(),
             self.label
@@ -1165,18 +1725,66 @@ pub struct CVariableType {
 }
 impl fmt::Display for CVariableType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.kind)
-        // let str_struct = if self.is_struct { "struct " } else { "" };
-        // let str_ptr = "TODO*";
-
-        // let ptrcnt: usize = 0;
-        // while ptrcnt < self.pointer_count {
+        if let CType::Function(function) = &self.kind {
+            let params: Vec<String> = function.parameters.iter().map(|p| p.to_string()).collect();
+            return write!(f, "{} (*)({})", function.return_type, params.join(", "));
+        }
+        let str_struct = if self.is_struct { "struct " } else { "" };
+        let str_ptr = "*".repeat(self.pointer_count.into());
+        write!(f, "{}{}{}", str_struct, self.kind.type_name(), str_ptr)
+    }
+}
 
-        // }
-        // for let i = 0; i < self.pointer_count; i++ {
+/// Parses `source` as if it had been tokenized by clang
+#[cfg(test)]
+pub fn parse_source(source: &str) -> HeaderFile {
+    parse(tokenize(&dump_tokens(source))).unwrap()
+}
 
-        // }
-        // let str_ptr = "*".repeat(self.pointer_count.into::<usize>());
-        // write!(f, "{}{}{}", str_struct, str_ptr, self.kind.to_string())
+/// Dumps `source` the way `clang -dump-raw-tokens` does
+#[cfg(test)]
+fn dump_tokens(source: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::new();
+    let (mut line, mut column) = (1, 1);
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("/*") {
+            rest.find("*/").unwrap() + 2
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if is_word(c) {
+            rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+        } else if c.is_whitespace() {
+            rest.find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        let text = &rest[..len];
+        let kind = match c {
+            _ if text.starts_with("/*") || text.starts_with("//") => "comment",
+            _ if c.is_ascii_digit() => "numeric_constant",
+            _ if is_word(c) => "raw_identifier",
+            '(' => "l_paren",
+            ')' => "r_paren",
+            '{' => "l_brace",
+            '}' => "r_brace",
+            ';' => "semi",
+            ',' => "comma",
+            '*' => "star",
+            '#' => "hash",
+            _ => "unknown",
+        };
+        out += &format!("{} '{}'\t\tLoc=<test.h:{}:{}>\n", kind, text, line, column);
+        match text.rfind('\n') {
+            Some(i) => {
+                line += text.matches('\n').count();
+                column = len - i;
+            }
+            None => column += len,
+        }
+        rest = &rest[len..];
     }
+    out + &format!("eof ''\t\tLoc=<test.h:{}:{}>\n", line, column)
 }
@@ -0,0 +1,161 @@
+use crate::lexer::{CEnum, CStruct, CType, CVariableDeclaration, HeaderFile};
+
+/// An array of elements, which C sees as a pointer to the first one along with their number.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta_param: out;output;list;
+///  * #meta_param: out_len;length(out);
+///  */
+/// uint32_t Engine_tokens(const struct Engine *this_, struct TokenResponse **out, uintptr_t *out_len, char **err);
+/// ```
+///
+/// What the list is made of follows from the pointer type, see `Element`,
+/// or is named with `list(TokenResponse)` when the pointer type does not say, e.g., a `void *`.
+/// An `output` list takes one more pointer, as does its length, which is not marked `output` itself.
+/// The caller owns an `output` list, unless it is `borrowed`; one marked `free_with(f)` is handed back to `f`
+/// with its length, and its capacity if it has one, so that the library can rebuild what it gave out.
+/// The same annotations describe a list stored in the fields of a struct, e.g., `FFIArray`
+pub struct List<'a> {
+    /// The pointer marked `list`, which names the list in the generated code
+    pub items: &'a CVariableDeclaration,
+    /// Number of elements, marked `length(items)`
+    pub length: Option<&'a CVariableDeclaration>,
+    /// Number of elements the array has room for, marked `capacity(items)`
+    pub capacity: Option<&'a CVariableDeclaration>,
+}
+
+/// What the elements of a list are
+pub enum Element<'a> {
+    /// `char *`, copied into the language's own strings
+    String,
+    /// A number, e.g., `uint32_t`
+    Primitive(&'a CType),
+    Enum(&'a CEnum),
+    /// Structs laid out one after the other
+    Struct(&'a CStruct),
+    /// Pointers to structs, e.g., the handles of `persistent` structs
+    StructPointer(&'a CStruct),
+}
+
+impl<'a> List<'a> {
+    /// Every list among `declarations`, the parameters of a function or the fields of a struct
+    pub fn all(declarations: &'a [CVariableDeclaration]) -> Vec<Self> {
+        declarations
+            .iter()
+            .filter(|d| d.meta.as_ref().is_some_and(|m| m.is_list))
+            .map(|items| List {
+                items,
+                length: declarations.iter().find(|d| {
+                    d.meta
+                        .as_ref()
+                        .is_some_and(|m| m.length_for.as_ref() == Some(&items.label))
+                }),
+                capacity: declarations.iter().find(|d| {
+                    d.meta
+                        .as_ref()
+                        .is_some_and(|m| m.capacity_for.as_ref() == Some(&items.label))
+                }),
+            })
+            .collect()
+    }
+
+    /// Whether `d` is the length or capacity of one of the lists among `declarations`.
+    /// It is marshalled along with the list, and is left out of generated signatures
+    pub fn is_part(declarations: &[CVariableDeclaration], d: &CVariableDeclaration) -> bool {
+        List::all(declarations).iter().any(|l| {
+            l.length.is_some_and(|len| len.label == d.label)
+                || l.capacity.is_some_and(|cap| cap.label == d.label)
+        })
+    }
+
+    /// Whether the library hands this list back, rather than reading it
+    pub fn is_output(&self) -> bool {
+        self.items.meta.as_ref().is_some_and(|m| m.is_output)
+    }
+
+    /// What the elements of this list are, see `Element::of`
+    pub fn element(&self) -> Option<Element<'a>> {
+        Element::of(self.items)
+    }
+}
+
+impl<'a> Element<'a> {
+    /// What the list `items` is made of, going by its pointer type once `output` is taken off:
+    /// * `char **` is a list of strings
+    /// * `struct T *` is a list of structs, `struct T **` a list of pointers to them
+    /// * `enum T *` is a list of enums, and a pointer to any number a list of numbers
+    ///
+    /// Returns `None` for anything else, e.g., a `void *` whose elements were not named with `list(T)`
+    pub fn of(items: &'a CVariableDeclaration) -> Option<Self> {
+        let is_output = items.meta.as_ref().is_some_and(|m| m.is_output);
+        let depth = items
+            .variable_type
+            .pointer_count
+            .checked_sub(is_output as u8)?;
+        match (&items.variable_type.kind, depth) {
+            (CType::Char(_), 2) => Some(Element::String),
+            (CType::Struct(s), 1) => Some(Element::Struct(s)),
+            (CType::Struct(s), 2) => Some(Element::StructPointer(s)),
+            (CType::Enum(e), 1) => Some(Element::Enum(e)),
+            (
+                CType::Char(_)
+                | CType::Include(_)
+                | CType::Function(_)
+                | CType::Define(_, _)
+                | CType::VoidStar
+                | CType::Void
+                | CType::UNINITIALIZED,
+                _,
+            ) => None,
+            (kind, 1) => Some(Element::Primitive(kind)),
+            _ => None,
+        }
+    }
+
+    /// Whether the bindings can make a C array of these elements from a list of their own.
+    /// Structs are only ever read out of C, save for the handles of `persistent` ones
+    pub fn can_be_input(&self) -> bool {
+        match self {
+            Element::Struct(_) => false,
+            Element::StructPointer(s) => s.meta.as_ref().is_some_and(|m| m.is_persistent),
+            _ => true,
+        }
+    }
+}
+
+/// Replaces the type of every list marked `list(T)` with the struct or enum `T`,
+/// and fills in the struct or enum of every other list with its declaration in `header`,
+/// so that the generators know what the elements are made of.
+///
+/// Names that are not declared in `header` are left alone, and are reported by `validate`
+pub fn resolve(header: &mut HeaderFile) {
+    let structs = header.structs.clone();
+    let enums = header.enums.clone();
+    let resolve_declaration = |d: &mut CVariableDeclaration| {
+        let meta = match &d.meta {
+            Some(m) if m.is_list => m,
+            _ => return,
+        };
+        let name = match (&meta.list_of, &d.variable_type.kind) {
+            (Some(name), _) => name.to_owned(),
+            (None, CType::Struct(s)) => s.identifier.label.to_owned(),
+            (None, CType::Enum(e)) => e.identifier.label.to_owned(),
+            _ => return,
+        };
+        if let Some(s) = structs.iter().find(|s| s.identifier.label == name) {
+            d.variable_type.kind = CType::Struct(s.clone());
+            d.variable_type.is_struct = true;
+        } else if let Some(e) = enums.iter().find(|e| e.identifier.label == name) {
+            d.variable_type.kind = CType::Enum(e.clone());
+            d.variable_type.is_struct = false;
+        }
+    };
+    for s in header.structs.iter_mut() {
+        s.declarations.iter_mut().for_each(&resolve_declaration);
+    }
+    for f in header.functions.iter_mut() {
+        f.parameters.iter_mut().for_each(&resolve_declaration);
+    }
+}
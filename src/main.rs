@@ -1,219 +1,498 @@
+pub mod annotation;
+pub mod bytes;
+pub mod cancel;
+pub mod clang;
+pub mod completion;
+pub mod config;
+pub mod cursor;
+pub mod default_value;
+pub mod error_codes;
+pub mod formatter;
 pub mod generator_csharp;
 pub mod generator_dart;
 pub mod generator_go2;
+pub mod hashmap;
+pub mod infer;
+pub mod inspect;
 pub mod lexer;
+pub mod list;
 pub mod meta;
+pub mod naming;
+pub mod property;
+pub mod sidecar;
+pub mod validate;
+pub mod value_type;
+pub mod watch;
 use clap::{Parser, Subcommand};
-use std::fs::File;
-use std::io::prelude::*;
+use similar::TextDiff;
 use std::path::PathBuf;
 use std::{path::Path, process::exit};
-use uuid::Uuid;
 
-use crate::lexer::{parse, tokenize};
+use crate::clang::Clang;
+use crate::config::{CSharpOptions, Config, DartOptions, GoOptions, Target, DEFAULT_CONFIG_FILE};
+use crate::lexer::{parse, tokenize, HeaderFile};
+use crate::sidecar::Sidecar;
+use crate::validate::{Diagnostic, Severity};
 
 fn main() {
     let args = Arguments::parse();
 
-    let token_str = dump_clang_tokes(Path::new(&args.header_file)).unwrap();
-    let tokens = tokenize(&token_str);
-    let header = parse(tokens).unwrap();
-
-    match args.cmd {
-        SubCommand::CSharp {
-            namespace,
-            dll_location,
-        } => {
-            generator_csharp::generate(header, &namespace, &dll_location);
-        }
-        SubCommand::Go {
-            package_name,
-            ld_flags,
-            header_file_location,
-        } => {
-            let res =
-                generator_go2::generate(header, &package_name, &ld_flags, &header_file_location);
-            if check_program_exists("gofmt") {
-                if let Ok(val) = std::process::Command::new("echo")
-                    .args(vec![res.to_owned(), "|".to_owned(), "gofmt".to_owned()])
-                    .stdout(std::process::Stdio::piped())
-                    .output()
-                {
-                    println!("{}", String::from_utf8(val.stdout).unwrap());
-                    return;
-                };
-            }
-            println!("{}", &res);
-        }
-
-        SubCommand::Dart {
-            library_path,
-            library_name,
-        } => {
-            let res = generator_dart::generate(header, &library_path, &library_name);
-            if check_program_exists("dart") {
-                let fpath = match save_temporary_output(&res, ".dart") {
-                    Ok(p) => Some(p),
-                    Err(e) => {
-                        eprintln!("Failed to write temporary output for dart formatting {}", e);
-                        None
-                    }
-                };
-
-                if let Some(p) = fpath {
-                    match std::process::Command::new("dart")
-                        .args(vec![
-                            "format",
-                            "--fix",
-                            // "-o",
-                            // "show",
-                            (p.as_os_str().to_str()).unwrap(),
-                        ])
-                        .stdout(std::process::Stdio::piped())
-                        .output()
-                    {
-                        Ok(_) => match std::fs::read_to_string(&p) {
-                            Ok(s) => {
-                                println!("{}", s);
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "Failed to run dart format: File could not be read: {}",
-                                    e
-                                );
-                            }
-                        },
-                        Err(e) => {
-                            eprintln!("Failed to run dart format: {}", e);
-                        }
-                    };
-
-                    if let Err(e) = delete_temporary_output(&p) {
-                        eprintln!("Failed to delete temporary file {}", e);
-                    }
+    if let Some(SubCommand::Watch) = args.cmd {
+        if let Err(e) = watch::watch(|| regenerate(&args)) {
+            eprintln!("{}", e);
+        }
+        exit(-1);
+    }
+
+    let cmd = args.cmd.clone();
+    let invocation = match Invocation::from_arguments(args) {
+        Ok(i) => i,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(-1);
+        }
+    };
+
+    let (header, diagnostics) = match parse_header(&invocation) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(-1);
+        }
+    };
+
+    let diagnostics = report_diagnostics(&header, diagnostics);
+    if let Some(SubCommand::Lint { .. }) = cmd {
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        eprintln!("{} errors, {} warnings", errors, diagnostics.len() - errors);
+        if errors > 0 {
+            exit(EXIT_LINT_ERRORS);
+        }
+        return;
+    }
+
+    if let Some(SubCommand::Infer { write, .. }) = cmd {
+        if let Err(e) = rewrite_header(&header, &invocation, write) {
+            eprintln!("{}", e);
+            exit(-1);
+        }
+        return;
+    }
+
+    if let Some(SubCommand::Inspect { filter, .. }) = cmd {
+        print!("{}", inspect::inspect(&header, filter.as_deref()));
+        return;
+    }
+
+    if invocation.check {
+        let mut stale = false;
+        for target in &invocation.targets {
+            let res = render_target(&header, target, invocation.format);
+            match check_target(target, &res) {
+                Ok(up_to_date) => stale |= !up_to_date,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(-1);
                 }
             }
-            // println!("{}", &res);
         }
+        if stale {
+            eprintln!("Generated bindings are out of date. Rerun clang2src to regenerate them");
+            exit(EXIT_STALE_BINDINGS);
+        }
+        return;
     }
+
+    if let Err(e) = write_targets(&header, &invocation) {
+        eprintln!("{}", e);
+        exit(-1);
+    }
+}
+
+/// A single run of `watch` mode. Errors are reported rather than exiting, so that the next change can fix them.
+///
+/// Returns the files to watch for the next run
+fn regenerate(args: &Arguments) -> Vec<PathBuf> {
+    let mut watched = vec![args.config_file()];
+    let invocation = match Invocation::from_arguments(args.clone()) {
+        Ok(i) => i,
+        Err(e) => {
+            eprintln!("{}", e);
+            return watched;
+        }
+    };
+    if clang::is_stdin(&invocation.header_file) {
+        eprintln!("Cannot watch a header read from stdin");
+        return watched;
+    }
+    watched.extend(invocation.clang.included_headers(&invocation.header_file));
+    watched.push(invocation.header_file.clone());
+    watched.extend(invocation.annotations.clone());
+
+    match parse_header(&invocation) {
+        Ok((header, diagnostics)) => {
+            report_diagnostics(&header, diagnostics);
+            if let Err(e) = write_targets(&header, &invocation) {
+                eprintln!("{}", e);
+            }
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+    watched
+}
+
+/// Parses the header and merges the sidecar annotations into it, if there are any.
+/// With `--infer-meta`, whatever is still unannotated after that is inferred.
+/// Lists are then resolved to the structs and enums they are made of.
+///
+/// Returns the header along with any problems found in the sidecar
+fn parse_header(invocation: &Invocation) -> Result<(HeaderFile, Vec<Diagnostic>), String> {
+    let token_str = invocation.clang.dump_clang_tokes(&invocation.header_file)?;
+    let tokens = tokenize(&token_str);
+    let mut header = parse(tokens)?;
+    let diagnostics = match &invocation.annotations {
+        Some(path) => Sidecar::load(path)?.apply(&mut header),
+        None => vec![],
+    };
+    if invocation.infer_meta {
+        let inferred = infer::infer(&header);
+        infer::apply(&mut header, &inferred);
+    }
+    list::resolve(&mut header);
+    Ok((header, diagnostics))
 }
 
-fn save_temporary_output(output: &str, extension: &str) -> Result<PathBuf, std::io::Error> {
-    let file_name = &format!("{}.{}", Uuid::new_v4().to_string(), extension);
-    let mut file = File::create(file_name)?;
-    file.write(output.as_bytes())?;
-    Ok(Path::new(file_name).to_path_buf())
+/// Inserts the inferred `#meta` lines into the header, printing the result or writing it back to the header file
+fn rewrite_header(header: &HeaderFile, invocation: &Invocation, write: bool) -> Result<(), String> {
+    let path = &invocation.header_file;
+    if clang::is_stdin(path) {
+        return Err("Cannot infer annotations for a header read from stdin".to_owned());
+    }
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let inferred = infer::infer(header);
+    let rewritten = infer::rewrite(&source, &path.display().to_string(), header, &inferred);
+    if !write {
+        print!("{}", rewritten);
+        return Ok(());
+    }
+    std::fs::write(path, rewritten)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    eprintln!(
+        "Added inferred annotations for {} functions to {}",
+        inferred.len(),
+        path.display()
+    );
+    Ok(())
 }
 
-fn delete_temporary_output(path: &PathBuf) -> Result<(), std::io::Error> {
-    std::fs::remove_file(path)
+/// Validates the meta annotations of the header, printing every problem found along with those already in `diagnostics`
+fn report_diagnostics(header: &HeaderFile, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.extend(validate::validate(header));
+    for d in &diagnostics {
+        eprintln!("{}", d);
+    }
+    diagnostics
 }
 
-fn check_program_exists(program_name: &str) -> bool {
-    #[cfg(target_os = "windows")]
-    return match std::process::Command::new("where")
-        .args(vec![program_name])
-        .stdout(std::process::Stdio::piped())
-        .output()
-    {
-        Ok(val) => {
-            if val.stdout.starts_with("INFO:".as_bytes()) {
-                false
-            } else {
-                true
+/// Generates every target, writing it to its output file or stdout
+fn write_targets(header: &HeaderFile, invocation: &Invocation) -> Result<(), String> {
+    for target in &invocation.targets {
+        let res = render_target(header, target, invocation.format);
+        match target.output() {
+            Some(p) => {
+                write_output(p, &res).map_err(|e| {
+                    format!(
+                        "Failed to write {} bindings to {}: {}",
+                        target.language(),
+                        p.display(),
+                        e
+                    )
+                })?;
+                eprintln!("Wrote {} bindings to {}", target.language(), p.display());
             }
+            None => println!("{}", &res),
         }
-        Err(_) => false,
+    }
+    Ok(())
+}
+
+/// Exit code of `lint` when the meta annotations contain errors
+const EXIT_LINT_ERRORS: i32 = 1;
+
+/// Exit code of `--check` when at least one output file differs from what would be generated
+const EXIT_STALE_BINDINGS: i32 = 2;
+
+/// Compares freshly generated bindings against the target's output file, printing a unified diff if they differ.
+///
+/// Returns whether the file on disk is up to date
+fn check_target(target: &Target, generated: &str) -> Result<bool, String> {
+    let path = target.output().ok_or_else(|| {
+        format!(
+            "Cannot check {} bindings: target has no output file",
+            target.language()
+        )
+    })?;
+    let on_disk = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("{} bindings missing: {}", target.language(), path.display());
+            String::new()
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
     };
-    #[cfg(target_os = "macos")]
-    return false;
-    #[cfg(target_os = "linux")]
-    return match std::process::Command::new("which")
-        .args(vec![program_name])
-        .stdout(std::process::Stdio::piped())
-        .output()
-    {
-        Ok(val) => {
-            if val.stdout.len() == 0 {
-                false
-            } else {
-                true
+    if on_disk == generated {
+        return Ok(true);
+    }
+
+    let name = path.display().to_string();
+    let diff = TextDiff::from_lines(on_disk.as_str(), generated);
+    print!(
+        "{}",
+        diff.unified_diff()
+            .header(&name, &format!("{} (generated)", name))
+    );
+    Ok(false)
+}
+
+/// Everything needed for a single run: which header to parse, how to invoke clang, and what to generate
+struct Invocation {
+    header_file: PathBuf,
+    clang: Clang,
+    /// Sidecar file to merge into the header's annotations
+    annotations: Option<PathBuf>,
+    /// Infer annotations for unannotated functions and parameters
+    infer_meta: bool,
+    targets: Vec<Target>,
+    /// Compare against the output files instead of writing them
+    check: bool,
+    /// Run the generated bindings through the language's formatter
+    format: bool,
+}
+
+impl Invocation {
+    /// Combines the command line with the project config file.
+    ///
+    /// A subcommand generates just that one target, falling back to the config file only for the header and clang flags.
+    /// Without a subcommand, every target listed in the config file is generated.
+    fn from_arguments(args: Arguments) -> Result<Self, String> {
+        let config_file = args.config_file();
+        let config = if args.config.is_some() || config_file.exists() {
+            Some(Config::load(&config_file)?)
+        } else {
+            None
+        };
+
+        let generates_code = args.cmd.as_ref().is_none_or(SubCommand::generates_code);
+        let cli_header = match &args.cmd {
+            Some(SubCommand::Inspect { header, .. })
+            | Some(SubCommand::Lint { header })
+            | Some(SubCommand::Infer { header, .. }) => header.clone(),
+            _ => None,
+        };
+        /* `infer` shows what would be inferred, so nothing may be inferred up front */
+        let infer_meta = args.infer_meta && !matches!(args.cmd, Some(SubCommand::Infer { .. }));
+
+        let targets = match (args.cmd.and_then(SubCommand::into_target), &config) {
+            (Some(target), _) => vec![target],
+            (None, Some(c)) => c.targets(),
+            (None, None) if generates_code => {
+                return Err(format!(
+                    "No target given and no {} found. Specify a target or pass --config",
+                    DEFAULT_CONFIG_FILE
+                ))
             }
+            (None, None) => vec![],
+        };
+        if generates_code && targets.is_empty() {
+            return Err("Config file does not list any [target.*] sections".to_owned());
         }
-        Err(_) => false,
-    };
+
+        let header_file = match (cli_header.or(args.header_file), &config) {
+            (Some(h), _) => PathBuf::from(h),
+            (None, Some(c)) => c.header.clone(),
+            (None, None) => return Err("No header file given".to_owned()),
+        };
+
+        let annotations = args
+            .annotations
+            .or_else(|| config.as_ref().and_then(|c| c.annotations.clone()));
+
+        /* The command line wins over the config file, extra arguments from both are combined */
+        let mut clang = Clang::default();
+        if let Some(c) = config {
+            clang.program = c.clang.unwrap_or(clang.program);
+            clang.args = c.clang_args;
+        }
+        if let Some(program) = args.clang {
+            clang.program = program;
+        }
+        clang.args.extend(args.clang_args);
+
+        Ok(Invocation {
+            header_file,
+            clang,
+            annotations,
+            infer_meta,
+            targets,
+            check: args.check,
+            format: !args.no_format,
+        })
+    }
 }
 
-fn dump_clang_tokes(p: &Path) -> Result<String, ()> {
-    if !p.exists() {
-        eprintln!("No file found: {}", p.to_str().unwrap());
-        exit(-1);
+/// Generates the bindings for a single target, running them through the language's formatter if `format` is set
+fn render_target(header: &HeaderFile, target: &Target, format: bool) -> String {
+    let res = match target {
+        Target::CSharp(o) => generator_csharp::generate(header, &o.namespace, &o.dll_location),
+        Target::Go(o) => generator_go2::generate(
+            header,
+            &o.package_name,
+            &o.ld_flags,
+            &o.header_file_location,
+        ),
+        Target::Dart(o) => generator_dart::generate(header, &o.library_path, &o.library_name),
+    };
+    if !format {
+        return res;
     }
+    formatter::format_or_warn(&*formatter::for_target(target), res)
+}
 
-    let c = std::process::Command::new("clang")
-        .arg("-fsyntax-only")
-        .arg("-Xclang")
-        .arg("-dump-raw-tokens")
-        .arg(p.to_owned())
-        .output();
-    match c {
-        Err(e) => {
-            eprintln!("Failed to generate clang tokens: {}", e);
-            return Err(());
-        }
-        Ok(output) => {
-            /* FYI(nf): Clang dumps output to stderr for some reason */
-            // let stdout = String::from_utf8(output.stdout).unwrap();
-            let stderr = String::from_utf8(output.stderr).unwrap();
-            return Ok(stderr);
+/// Writes generated bindings to `path`, creating any missing parent directories
+fn write_output(path: &Path, contents: &str) -> Result<(), std::io::Error> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
         }
     }
+    std::fs::write(path, contents)
 }
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 pub struct Arguments {
     /// Path to generated C header file (.h) to parse.
     ///
     /// Header file must not contain any function bodies or variable assignemnts.
     ///
     /// Is assumed to be generated solely by `Bindcgen`
-    header_file: String,
+    ///
+    /// May be omitted if a config file provides the `header` key. Pass `-` to read the header from stdin
+    header_file: Option<String>,
+
+    /// Project config file listing the header, clang flags and targets to generate.
+    ///
+    /// Defaults to `clang2src.toml` in the working directory, if present.
+    /// When no subcommand is given, every target in the config file is generated
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// clang executable used to tokenize the header. Defaults to `clang` on the PATH
+    #[arg(long, env = "CLANG_PATH", global = true)]
+    clang: Option<PathBuf>,
+
+    /// Extra argument to hand to clang, e.g., `--clang-arg=-std=c11`. May be given multiple times
+    #[arg(
+        long = "clang-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        global = true
+    )]
+    clang_args: Vec<String>,
+
+    /// Sidecar file (`.toml` or `.json`) with annotations to merge with, or override, the header's `#meta` comments
+    #[arg(long, global = true)]
+    annotations: Option<PathBuf>,
+
+    /// Infer meta annotations for functions and parameters that have none, from their signatures and names.
+    ///
+    /// See the `infer` subcommand to review what would be inferred
+    #[arg(long, global = true)]
+    infer_meta: bool,
+
+    /// Generate in memory and compare against the output files on disk instead of writing them.
+    ///
+    /// Prints a unified diff and exits with code 2 if any of them are out of date
+    #[arg(long, global = true)]
+    check: bool,
+
+    /// Skip running gofmt, `dart format` or `dotnet format` over the generated bindings
+    #[arg(long, global = true)]
+    no_format: bool,
 
     #[clap(subcommand)]
-    pub cmd: SubCommand,
+    pub cmd: Option<SubCommand>,
+}
+
+impl Arguments {
+    /// The config file given by `--config`, or the default one in the working directory
+    fn config_file(&self) -> PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE))
+    }
 }
 
-#[derive(Subcommand, Debug)]
+#[derive(Subcommand, Debug, Clone)]
 pub enum SubCommand {
-    CSharp {
-        /// C# Namespace of the generated file to use
-        ///
-        /// e.g., `namespace FFI {
-        ///     public struct OAuthManager{}
-        ///  [...]
-        /// }`
-        namespace: String,
-
-        /// Location, relative to the runtime location of the final libary, where the dll
-        /// will be loaded from. Should include the extension, e.g.,
-        ///
-        /// `some/relative/folder/liboauthtool.dll`
-        dll_location: String,
+    CSharp(CSharpOptions),
+    Go(GoOptions),
+    Dart(DartOptions),
+
+    /// Watch the header, its includes and the config file, regenerating all configured targets on every change
+    Watch,
+
+    /// Print the parsed enums, structs and functions along with their resolved meta values, without generating anything
+    Inspect {
+        /// Header file to inspect. Takes the place of the top level header argument
+        header: Option<String>,
+
+        /// Only list items whose name contains this string
+        #[arg(short, long)]
+        filter: Option<String>,
     },
 
-    Go {
-        /// Name of the Go Package to place the generated bindings under
-        package_name: String,
-        /// Where to load the lib files relative to the go binary
-        ld_flags: String,
-        /// header file location relative to the building location of the go binary
-        header_file_location: String,
+    /// Check the meta annotations of the header for mistakes, without generating anything.
+    ///
+    /// Exits with code 1 if any errors are found
+    Lint {
+        /// Header file to lint. Takes the place of the top level header argument
+        header: Option<String>,
     },
 
-    Dart {
-        /// Location of the folder containing DLLs/SO files
-        library_path: String,
-        /// Name of the lib file, without the extension
-        library_name: String,
+    /// Print the header with `#meta` lines inserted for every function and parameter whose annotations can be inferred
+    Infer {
+        /// Header file to infer annotations for. Takes the place of the top level header argument
+        header: Option<String>,
+
+        /// Rewrite the header file in place instead of printing it
+        #[arg(short, long)]
+        write: bool,
     },
 }
+
+impl SubCommand {
+    /// The single target this subcommand generates, if any
+    fn into_target(self) -> Option<Target> {
+        match self {
+            SubCommand::CSharp(o) => Some(Target::CSharp(o)),
+            SubCommand::Go(o) => Some(Target::Go(o)),
+            SubCommand::Dart(o) => Some(Target::Dart(o)),
+            SubCommand::Watch
+            | SubCommand::Inspect { .. }
+            | SubCommand::Lint { .. }
+            | SubCommand::Infer { .. } => None,
+        }
+    }
+
+    /// Whether this subcommand writes bindings, and thus needs at least one target
+    fn generates_code(&self) -> bool {
+        !matches!(
+            self,
+            SubCommand::Inspect { .. } | SubCommand::Lint { .. } | SubCommand::Infer { .. }
+        )
+    }
+}
@@ -1,8 +1,8 @@
-use std::str::FromStr;
-
-use fancy_regex::Regex;
 use serde::Serialize;
 
+use crate::annotation::{Annotation, Argument, Value};
+use crate::naming::Language;
+
 pub const META_TOKEN: &str = "#meta:";
 pub const META_PARAM_TOKEN: &str = "#meta_param:";
 
@@ -34,6 +34,10 @@ pub struct MetaValue {
     /// See also: `length_for` and `capacity_for`
     pub is_list: bool,
 
+    /// If `list` is set, the struct or enum its elements are, when the pointer type does not say, see `List`
+    /// #meta_param: value_name;list(#type);
+    pub list_of: Option<String>,
+
     /// Whether this value is the pointer to the object that the method this parameter is for should attach to
     pub is_this: bool,
 
@@ -57,10 +61,15 @@ pub struct MetaValue {
     /// #meta_param: value_name;string;
     pub is_string: bool,
 
-    /// Whether this value is a representing a Map or Dictionary
+    /// Whether this value is a representing a Map or Dictionary.
+    /// The value is the `char **` array of keys, see `values_for` and `length_for` for the rest of the map
     /// #meta_param: value_name;hashmap;
     pub is_hashmap: bool,
 
+    /// Whether this `uint8_t *` is a buffer of raw bytes, whose size is given by a companion marked `length(#param)`
+    /// #meta_param: value_name;bytes;
+    pub is_bytes: bool,
+
     /// Whether this value is a pointer to a string that will handle the Error Message of this function
     /// #meta_param: value_name;error;
     pub is_error: bool,
@@ -83,13 +92,106 @@ pub struct MetaValue {
     /// #meta_param: value_name;timestamp;
     pub is_timestamp: bool,
 
-    /// If `list` is set, this value is the `len` of the array
+    /// If `list` is set, this value is the `len` of the array, or, for `bytes`, the size of the buffer
     /// #meta_param: value_name;length_for(#param);
     pub length_for: Option<String>,
 
     /// If `list` is set, this value is the `cap` of the array
     /// #meta_param: value_name;capacity_for(#param);
     pub capacity_for: Option<String>,
+
+    /// If `hashmap` is set on `#param`, this value is the `char **` array of its values, in the same order as the keys
+    /// #meta_param: value_name;values(#param);
+    pub values_for: Option<String>,
+
+    /// Whether the caller takes ownership of this output or error value and must release it.
+    /// This is already assumed for outputs and errors, the keyword only makes it explicit
+    /// #meta_param: value_name;owned;
+    pub is_owned: bool,
+
+    /// Whether this output or error value still belongs to the library, and must not be released by the caller
+    /// #meta_param: value_name;borrowed;
+    pub is_borrowed: bool,
+
+    /// The exported function that releases this output or error value, in place of the language's own `free`
+    /// #meta_param: value_name;free_with(#function);
+    pub free_with: Option<String>,
+
+    /// If `for_struct` is set, this method reads the property `#property` of the struct, see `Property`
+    /// #meta: getter(#property);
+    pub getter_for: Option<String>,
+
+    /// If `for_struct` is set, this method writes the property `#property` of the struct, see `Property`
+    /// #meta: setter(#property);
+    pub setter_for: Option<String>,
+
+    /// If `persistent` is set, this struct is a cursor handing out the struct `#struct` one at a time, see `Cursor`
+    /// #meta: iterator(#struct);
+    pub iterator_of: Option<String>,
+
+    /// If `async` is set, the function that cancels this one while it runs, handed the value of its `cancel_handle`, see `Cancellation`
+    /// #meta: cancellable(#function);
+    pub cancel_with: Option<String>,
+
+    /// Whether this value is the handle a `cancellable` function is cancelled by, which the bindings make up for each call
+    /// #meta_param: value_name;cancel_handle;
+    pub is_cancel_handle: bool,
+
+    /// The function pointer parameter this function hands its result to once it is done, rather than blocking until then,
+    /// see `Completion`
+    /// #meta: async_callback(#param);
+    pub completes_with: Option<String>,
+
+    /// The value this parameter takes when the caller leaves it out, a string, a number or `null`, see `DefaultValue`
+    /// #meta_param: value_name;default(#value);
+    pub default_value: Option<Value>,
+
+    /// The name this struct, field, enum member or function goes by in the bindings of every language, see `Language`
+    /// #meta: rename(#name);
+    pub rename: Option<String>,
+
+    /// The name this item goes by in the Dart bindings, over `rename`
+    /// #meta: rename_dart(#name);
+    pub rename_dart: Option<String>,
+
+    /// The name this item goes by in the Go bindings, over `rename`
+    /// #meta: rename_go(#name);
+    pub rename_go: Option<String>,
+
+    /// The name this item goes by in the C# bindings, over `rename`
+    /// #meta: rename_csharp(#name);
+    pub rename_csharp: Option<String>,
+
+    /// Whether this struct or function is left out of the bindings of every language
+    /// #meta: skip;
+    pub is_skipped: bool,
+
+    /// Whether this struct or function is left out of the Dart bindings
+    /// #meta: skip_dart;
+    pub skip_dart: bool,
+
+    /// Whether this struct or function is left out of the Go bindings
+    /// #meta: skip_go;
+    pub skip_go: bool,
+
+    /// Whether this struct or function is left out of the C# bindings
+    /// #meta: skip_csharp;
+    pub skip_csharp: bool,
+
+    /// Why this struct, field, enum member or function is deprecated, empty if no reason is given.
+    /// C headers usually say so with an attribute instead, see `CFunction::deprecated`
+    /// #meta: deprecated(#reason);
+    pub deprecated: Option<String>,
+
+    /// Whether this struct, which must not be `persistent`, is compared, hashed, printed and copied by the values
+    /// of its fields in the bindings, see `ValueType`
+    /// #meta: value;
+    pub is_value: bool,
+
+    /// Whether this group of `#define`s, or this enum, lists the failure codes returned by `throws` functions,
+    /// each of which gets its own error type
+    /// #meta: error_codes;
+    pub is_error_codes: bool,
 }
 
 impl MetaValue {
@@ -106,6 +208,7 @@ impl MetaValue {
             && !self.is_constructor
             && !self.is_string
             && !self.is_hashmap
+            && !self.is_bytes
             && !self.is_error
             && !self.is_duration
             && !self.is_datetime
@@ -115,8 +218,31 @@ impl MetaValue {
             && !self.as_ptr
             && !self.is_void
             && !self.is_async
+            && self.list_of.is_none()
             && matches!(self.length_for, None)
-            && matches!(self.capacity_for, None);
+            && matches!(self.capacity_for, None)
+            && self.values_for.is_none()
+            && !self.is_owned
+            && !self.is_borrowed
+            && self.free_with.is_none()
+            && self.getter_for.is_none()
+            && self.setter_for.is_none()
+            && self.iterator_of.is_none()
+            && self.cancel_with.is_none()
+            && !self.is_cancel_handle
+            && self.completes_with.is_none()
+            && self.default_value.is_none()
+            && self.rename.is_none()
+            && self.rename_dart.is_none()
+            && self.rename_go.is_none()
+            && self.rename_csharp.is_none()
+            && !self.is_skipped
+            && !self.skip_dart
+            && !self.skip_go
+            && !self.skip_csharp
+            && self.deprecated.is_none()
+            && !self.is_value
+            && !self.is_error_codes;
     }
     pub fn new() -> Self {
         MetaValue {
@@ -125,6 +251,7 @@ impl MetaValue {
             is_persistent: false,
             for_struct: false,
             is_list: false,
+            list_of: None,
             is_nullable: false,
             is_static: false,
             throws: false,
@@ -132,6 +259,7 @@ impl MetaValue {
             is_constructor: false,
             is_error: false,
             is_hashmap: false,
+            is_bytes: false,
             is_string: false,
             is_duration: false,
             is_datetime: false,
@@ -142,125 +270,129 @@ impl MetaValue {
             as_ptr: false,
             length_for: None,
             capacity_for: None,
+            values_for: None,
+            is_owned: false,
+            is_borrowed: false,
+            free_with: None,
+            getter_for: None,
+            setter_for: None,
+            iterator_of: None,
+            cancel_with: None,
+            is_cancel_handle: false,
+            completes_with: None,
+            default_value: None,
+            rename: None,
+            rename_dart: None,
+            rename_go: None,
+            rename_csharp: None,
+            is_skipped: false,
+            skip_dart: false,
+            skip_go: false,
+            skip_csharp: false,
+            deprecated: None,
+            is_value: false,
+            is_error_codes: false,
         }
     }
 
-    pub fn from_meta_comment_for_param(cmt: &Option<String>, param_name: &str) -> Option<Self> {
-        if let Some(c) = cmt {
-            // This matcher is of the form `#meta_param: <name>;`, e..g,  #meta_param: encrypted;`
-            let match_param_str = format!("{} {};", META_PARAM_TOKEN, param_name);
-            let ls: Vec<&str> = c
-                .split("\n")
-                .filter(|l| l.contains(&match_param_str))
-                .collect();
-
-            let pseudo_comment = ls.join("\n");
-
-            let meta_matcher = Regex::new(r"(\w+(?:\(\w+\))?);").unwrap();
-            let mut meta = MetaValue::new();
-            let mut mm = meta_matcher.captures_iter(&pseudo_comment);
-            while let Some(cap) = mm.next() {
-                if let Ok(capture) = cap {
-                    let mut citer = capture.iter();
-                    while let Some(cc) = citer.next() {
-                        if let Some(match_value) = cc {
-                            let m = match_value.as_str().trim_end_matches(';');
-                            meta.modify_from_keyword(m);
-                        }
-                    }
-                }
-            }
-            if meta.is_empty() {
-                None
-            } else {
-                Some(meta)
-            }
-        } else {
-            None
+    /// The keywords that are set on this meta value, spelled the way they are written in a `#meta` comment
+    pub fn keywords(&self) -> Vec<String> {
+        let flags = [
+            (self.is_async, "async"),
+            (self.is_void, "void"),
+            (self.is_persistent, "persistent"),
+            (self.is_this, "this"),
+            (self.for_struct, "for_struct"),
+            (self.is_list && self.list_of.is_none(), "list"),
+            (self.is_nullable, "nullable"),
+            (self.is_static, "static"),
+            (self.throws, "throws"),
+            (self.is_destructor, "destructor"),
+            (self.is_constructor, "constructor"),
+            (self.is_string, "string"),
+            (self.is_hashmap, "hashmap"),
+            (self.is_bytes, "bytes"),
+            (self.is_error, "error"),
+            (self.is_duration, "duration"),
+            (self.is_datetime, "datetime"),
+            (self.is_output, "output"),
+            (self.is_url, "url"),
+            (self.as_ptr, "as_ptr"),
+            (self.is_timestamp, "timestamp"),
+            (self.is_owned, "owned"),
+            (self.is_borrowed, "borrowed"),
+            (self.is_error_codes, "error_codes"),
+            (self.is_cancel_handle, "cancel_handle"),
+            (self.is_skipped, "skip"),
+            (self.skip_dart, "skip_dart"),
+            (self.skip_go, "skip_go"),
+            (self.skip_csharp, "skip_csharp"),
+            (self.is_value, "value"),
+        ];
+        let mut keywords: Vec<String> = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, kw)| kw.to_string())
+            .collect();
+        if let Some(t) = &self.list_of {
+            keywords.push(format!("list({})", t));
         }
-    }
-
-    /// Takes a meta value, and a meta_keyword and assigns the appropriate meta tag based on the keyword
-    fn modify_from_keyword(&mut self, m: &str) {
-        match m {
-            "async" => self.is_async = true,
-            "void" => self.is_void = true,
-            "persistent" => self.is_persistent = true,
-            "this" => self.is_this = true,
-            "for_struct" => self.for_struct = true,
-            "list" => self.is_list = true,
-            "nullable" => self.is_nullable = true,
-            "static" => self.is_static = true,
-            "throws" => self.throws = true,
-            "destructor" => self.is_destructor = true,
-            "constructor" => self.is_constructor = true,
-            "string" => self.is_string = true,
-            "hashmap" => self.is_hashmap = true,
-            "error" => self.is_error = true,
-            "duration" => self.is_duration = true,
-            "datetime" => self.is_datetime = true,
-            "output" => self.is_output = true,
-            "url" => self.is_url = true,
-            "as_ptr" => self.as_ptr = true,
-            "timestamp" => self.is_timestamp = true,
-            _ => {
-                let compound_matcher = Regex::new(r"(\w+)\((\w+)\)").unwrap();
-                let c = compound_matcher.captures(m);
-                match c {
-                    Ok(cap) => match cap {
-                        Some(ccc) => {
-                            if ccc.len() == 3 {
-                                match ccc.get(1) {
-                                    Some(ccc_inner) => match ccc_inner.as_str() {
-                                        "length" => {
-                                            self.length_for =
-                                                Some(ccc.get(2).unwrap().as_str().to_string())
-                                        }
-                                        "capacity" => {
-                                            self.capacity_for =
-                                                Some(ccc.get(2).unwrap().as_str().to_string())
-                                        }
-                                        _ => (),
-                                    },
-                                    None => {
-                                        panic!("invalid inner(item) format in meta")
-                                    }
-                                }
-                            }
-                        }
-                        None => {}
-                    },
-                    Err(_) => {}
-                };
-            }
+        if let Some(l) = &self.length_for {
+            keywords.push(format!("length({})", l));
         }
-    }
-
-    pub fn from_meta_comment_dontcare(cmt: &Option<String>) -> Option<Self> {
-        if let Some(c) = cmt {
-            for s in c.split('\n') {
-                if s.contains(META_TOKEN) || s.contains(META_PARAM_TOKEN) {
-                    return MetaValue::from_meta_comment(s);
-                }
+        if let Some(c) = &self.capacity_for {
+            keywords.push(format!("capacity({})", c));
+        }
+        if let Some(v) = &self.values_for {
+            keywords.push(format!("values({})", v));
+        }
+        if let Some(f) = &self.free_with {
+            keywords.push(format!("free_with({})", f));
+        }
+        if let Some(g) = &self.getter_for {
+            keywords.push(format!("getter({})", g));
+        }
+        if let Some(s) = &self.setter_for {
+            keywords.push(format!("setter({})", s));
+        }
+        if let Some(i) = &self.iterator_of {
+            keywords.push(format!("iterator({})", i));
+        }
+        if let Some(c) = &self.cancel_with {
+            keywords.push(format!("cancellable({})", c));
+        }
+        if let Some(c) = &self.completes_with {
+            keywords.push(format!("async_callback({})", c));
+        }
+        if let Some(d) = &self.default_value {
+            keywords.push(format!("default({})", d));
+        }
+        let renames = [
+            (&self.rename, "rename"),
+            (&self.rename_dart, "rename_dart"),
+            (&self.rename_go, "rename_go"),
+            (&self.rename_csharp, "rename_csharp"),
+        ];
+        for (name, kw) in renames {
+            if let Some(n) = name {
+                keywords.push(format!("{}({})", kw, n));
             }
         }
-        None
+        match self.deprecated.as_deref() {
+            Some("") => keywords.push("deprecated".to_owned()),
+            Some(reason) => keywords.push(format!("deprecated({:?})", reason)),
+            None => {}
+        }
+        keywords
     }
 
-    pub fn from_meta_comment(cmt: &str) -> Option<Self> {
-        let meta_matcher = Regex::new(r"(\w+(?:\(\w+\))?);").unwrap();
+    /// Builds the meta value described by `annotations`, skipping any that are not understood.
+    ///
+    /// Returns `None` if none of them set anything
+    pub fn from_annotations(annotations: &[Annotation]) -> Option<Self> {
         let mut meta = MetaValue::new();
-        let mut mm = meta_matcher.captures_iter(cmt);
-        while let Some(cap) = mm.next() {
-            if let Ok(capture) = cap {
-                let mut citer = capture.iter();
-                while let Some(cc) = citer.next() {
-                    if let Some(match_value) = cc {
-                        let m = match_value.as_str().trim_end_matches(';');
-                        meta.modify_from_keyword(m);
-                    }
-                }
-            }
+        for a in annotations {
+            let _ = meta.apply(a);
         }
         if meta.is_empty() {
             None
@@ -268,4 +400,250 @@ impl MetaValue {
             Some(meta)
         }
     }
+
+    /// Sets whatever `annotation` describes on this meta value, or explains why it is not understood
+    pub fn apply(&mut self, annotation: &Annotation) -> Result<(), String> {
+        let name = annotation.name.as_str();
+        let flag = match name {
+            "async" => &mut self.is_async,
+            "void" => &mut self.is_void,
+            "persistent" => &mut self.is_persistent,
+            "this" => &mut self.is_this,
+            "for_struct" => &mut self.for_struct,
+            "nullable" => &mut self.is_nullable,
+            "static" => &mut self.is_static,
+            "throws" => &mut self.throws,
+            "destructor" => &mut self.is_destructor,
+            "constructor" => &mut self.is_constructor,
+            "string" => &mut self.is_string,
+            "hashmap" => &mut self.is_hashmap,
+            "bytes" => &mut self.is_bytes,
+            "error" => &mut self.is_error,
+            "duration" => &mut self.is_duration,
+            "datetime" => &mut self.is_datetime,
+            "output" => &mut self.is_output,
+            "url" => &mut self.is_url,
+            "as_ptr" => &mut self.as_ptr,
+            "timestamp" => &mut self.is_timestamp,
+            "owned" => &mut self.is_owned,
+            "borrowed" => &mut self.is_borrowed,
+            "error_codes" => &mut self.is_error_codes,
+            "cancel_handle" => &mut self.is_cancel_handle,
+            "skip" => &mut self.is_skipped,
+            "skip_dart" => &mut self.skip_dart,
+            "skip_go" => &mut self.skip_go,
+            "skip_csharp" => &mut self.skip_csharp,
+            "value" => &mut self.is_value,
+            "list" => {
+                self.list_of = match annotation.args.as_slice() {
+                    [] => None,
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(element),
+                        ..
+                    }] => Some(element.to_owned()),
+                    _ => {
+                        return Err(
+                            "`list` takes at most the name of a single struct or enum, e.g., `list(TokenResponse)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                self.is_list = true;
+                return Ok(());
+            }
+            "iterator" => {
+                self.iterator_of = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(item),
+                        ..
+                    }] => Some(item.to_owned()),
+                    _ => {
+                        return Err(
+                            "`iterator` takes the name of the struct it hands out, e.g., `iterator(TokenResponse)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "cancellable" => {
+                self.cancel_with = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(function),
+                        ..
+                    }] => Some(function.to_owned()),
+                    _ => {
+                        return Err(
+                            "`cancellable` takes the name of the function that cancels the call, e.g., `cancellable(OAuth2PKCE_cancel)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "async_callback" => {
+                self.completes_with = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(callback),
+                        ..
+                    }] => Some(callback.to_owned()),
+                    _ => {
+                        return Err(
+                            "`async_callback` takes the name of the parameter the result is handed to, e.g., `async_callback(on_done)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "default" => {
+                self.default_value = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None, value, ..
+                    }] => Some(value.to_owned()),
+                    _ => {
+                        return Err(
+                            "`default` takes a single value, e.g., `default(\"openid\")`, `default(30000)` or `default(null)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "rename" | "rename_dart" | "rename_go" | "rename_csharp" => {
+                let new_name = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(new_name),
+                        ..
+                    }] => new_name.to_owned(),
+                    _ => {
+                        return Err(format!(
+                            "`{}` takes the single name to use instead, e.g., `{}(fetchToken)`",
+                            name, name
+                        ))
+                    }
+                };
+                match name {
+                    "rename" => self.rename = Some(new_name),
+                    "rename_dart" => self.rename_dart = Some(new_name),
+                    "rename_go" => self.rename_go = Some(new_name),
+                    _ => self.rename_csharp = Some(new_name),
+                }
+                return Ok(());
+            }
+            "deprecated" => {
+                self.deprecated = match annotation.args.as_slice() {
+                    [] => Some(String::new()),
+                    [Argument {
+                        key: None,
+                        value: Value::Str(reason),
+                        ..
+                    }] => Some(reason.to_owned()),
+                    _ => {
+                        return Err(
+                            "`deprecated` takes the reason as a single string, if any, e.g., `deprecated(\"use fetch_token instead\")`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "free_with" => {
+                self.free_with = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(function),
+                        ..
+                    }] => Some(function.to_owned()),
+                    _ => {
+                        return Err(
+                            "`free_with` takes the name of a single function, e.g., `free_with(lib_free_string)`"
+                                .to_owned(),
+                        )
+                    }
+                };
+                return Ok(());
+            }
+            "getter" | "setter" => {
+                let property = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(property),
+                        ..
+                    }] => property.to_owned(),
+                    _ => {
+                        return Err(format!(
+                            "`{}` takes the name of a single property, e.g., `{}(timeout)`",
+                            name, name
+                        ))
+                    }
+                };
+                match name {
+                    "getter" => self.getter_for = Some(property),
+                    _ => self.setter_for = Some(property),
+                }
+                return Ok(());
+            }
+            "length" | "capacity" | "values" => {
+                let target = match annotation.args.as_slice() {
+                    [Argument {
+                        key: None,
+                        value: Value::Ident(target),
+                        ..
+                    }] => target.to_owned(),
+                    _ => {
+                        return Err(format!(
+                            "`{}` takes the name of a single parameter or field, e.g., `{}(len)`",
+                            name, name
+                        ))
+                    }
+                };
+                match name {
+                    "length" => self.length_for = Some(target),
+                    "capacity" => self.capacity_for = Some(target),
+                    _ => self.values_for = Some(target),
+                }
+                return Ok(());
+            }
+            _ => return Err(format!("unknown meta keyword `{}`", name)),
+        };
+        if !annotation.args.is_empty() {
+            return Err(format!("`{}` does not take any arguments", name));
+        }
+        *flag = true;
+        Ok(())
+    }
+
+    /// The name this item goes by in `language`, if it is renamed, before it is spelled the way the language spells such names
+    pub fn rename_for(&self, language: Language) -> Option<&str> {
+        let renamed = match language {
+            Language::Dart => &self.rename_dart,
+            Language::Go => &self.rename_go,
+            Language::CSharp => &self.rename_csharp,
+        };
+        renamed
+            .as_ref()
+            .or(self.rename.as_ref())
+            .map(|n| n.as_str())
+    }
+
+    /// Whether this item is renamed for any of the languages
+    pub fn is_renamed(&self) -> bool {
+        Language::ALL.iter().any(|l| self.rename_for(*l).is_some())
+    }
+
+    /// Whether this item is left out of the bindings for `language`
+    pub fn is_skipped_for(&self, language: Language) -> bool {
+        self.is_skipped
+            || match language {
+                Language::Dart => self.skip_dart,
+                Language::Go => self.skip_go,
+                Language::CSharp => self.skip_csharp,
+            }
+    }
 }
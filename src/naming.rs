@@ -0,0 +1,183 @@
+use crate::annotation::item_annotations;
+use crate::lexer::{CFunction, CIdentifier, CStruct, CType, CVariableDeclaration, HeaderFile};
+use crate::meta::MetaValue;
+
+/// A language the bindings are generated for, which `rename` and `skip` can single out.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: for_struct;rename(fetch_token);rename_csharp(OAuth2PKCE_fetch_token);
+///  * #meta_param: this_;this;
+///  */
+/// uint32_t OAuth2PKCE_get_token_automatic(struct OAuth2PKCE *this_, ...);
+///
+/// /**
+///  * Only meant for the tests of the Dart package
+///  * #meta: skip_go;skip_csharp;
+///  */
+/// void reset_engine(void);
+/// ```
+///
+/// The new name is still spelled the way the language spells such names, e.g., `fetchToken` in Dart and
+/// `FetchToken` in Go. Structs, fields, enum members and functions can be renamed, structs and functions skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Dart,
+    Go,
+    CSharp,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::Dart, Language::Go, Language::CSharp];
+
+    /// As written at the end of `rename_*` and `skip_*`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Dart => "dart",
+            Language::Go => "go",
+            Language::CSharp => "csharp",
+        }
+    }
+
+    /// What the item called `label` in C, with the meta value `meta`, is called in this language,
+    /// before it is spelled the way the language spells such names
+    pub fn name_of<'a>(&self, label: &'a str, meta: &'a Option<MetaValue>) -> &'a str {
+        meta.as_ref()
+            .and_then(|m| m.rename_for(*self))
+            .unwrap_or(label)
+    }
+
+    /// What the struct `s` is called in this language, see `name_of`
+    pub fn struct_name<'a>(&self, s: &'a CStruct) -> &'a str {
+        self.name_of(&s.identifier.label, &s.meta)
+    }
+
+    /// What the struct called `label` in C is called in this language, see `name_of`
+    pub fn struct_label<'a>(&self, header: &'a HeaderFile, label: &'a str) -> &'a str {
+        match header.structs.iter().find(|s| s.identifier.label == label) {
+            Some(s) => self.struct_name(s),
+            None => label,
+        }
+    }
+
+    /// What the function called `label` in C is called in this language, see `name_of`
+    pub fn function_label<'a>(&self, header: &'a HeaderFile, label: &'a str) -> &'a str {
+        match header.functions.iter().find(|f| f.label == label) {
+            Some(f) => self.name_of(&f.label, &f.meta),
+            None => label,
+        }
+    }
+
+    /// What the enum member `member` is called in this language, see `name_of`
+    pub fn member_name(&self, member: &CIdentifier) -> String {
+        self.name_of(&member.label, &member_meta(member)).to_owned()
+    }
+
+    /// What the `for_struct` function `f`, e.g., `OAuth2PKCE_get_token`, is called as a method in this language,
+    /// which is its label without the name of the struct, e.g., `_get_token`, unless it is renamed
+    pub fn method_name<'a>(&self, f: &'a CFunction) -> &'a str {
+        match f.meta.as_ref().and_then(|m| m.rename_for(*self)) {
+            Some(name) => name,
+            None => &f.label[f.label.find('_').unwrap_or(0)..],
+        }
+    }
+
+    /// Whether the item with the meta value `meta` is left out of the bindings for this language
+    pub fn skips(&self, meta: &Option<MetaValue>) -> bool {
+        meta.as_ref().is_some_and(|m| m.is_skipped_for(*self))
+    }
+
+    /// `header` as the bindings for this language see it: without the structs and functions it skips,
+    /// and with every struct it refers to carrying the meta value of its definition, so that a renamed struct
+    /// goes by its new name wherever it is used
+    pub fn header(&self, header: &HeaderFile) -> HeaderFile {
+        let mut filtered = header.clone();
+        filtered.structs.retain(|s| !self.skips(&s.meta));
+        filtered.functions.retain(|f| !self.skips(&f.meta));
+
+        let definitions = header.structs.clone();
+        let resolve = |v: &mut CVariableDeclaration| resolve_declaration(v, &definitions);
+        for s in filtered.structs.iter_mut() {
+            s.declarations.iter_mut().for_each(resolve);
+        }
+        for f in filtered.functions.iter_mut() {
+            resolve_function(f, &definitions);
+        }
+        filtered
+    }
+}
+
+/// The meta value of the enum member `member`, which is only ever given by its comment
+pub fn member_meta(member: &CIdentifier) -> Option<MetaValue> {
+    MetaValue::from_annotations(&item_annotations(&member.comment))
+}
+
+/// The structs skipped for `language` that `f` takes or hands back
+pub fn skipped_structs<'a>(
+    f: &CFunction,
+    header: &'a HeaderFile,
+    language: Language,
+) -> Vec<&'a CStruct> {
+    let mut kinds = vec![&f.return_type.kind];
+    kinds.extend(f.parameters.iter().map(|p| &p.variable_type.kind));
+    skipped_among(kinds, header, language)
+}
+
+/// The structs skipped for `language` that the fields of `s` are, or point to
+pub fn skipped_fields<'a>(
+    s: &CStruct,
+    header: &'a HeaderFile,
+    language: Language,
+) -> Vec<&'a CStruct> {
+    let kinds = s
+        .declarations
+        .iter()
+        .map(|d| &d.variable_type.kind)
+        .collect();
+    skipped_among(kinds, header, language)
+}
+
+fn skipped_among<'a>(
+    kinds: Vec<&CType>,
+    header: &'a HeaderFile,
+    language: Language,
+) -> Vec<&'a CStruct> {
+    header
+        .structs
+        .iter()
+        .filter(|s| language.skips(&s.meta))
+        .filter(|s| {
+            kinds
+                .iter()
+                .any(|k| matches!(k, CType::Struct(r) if r.identifier.label == s.identifier.label))
+        })
+        .collect()
+}
+
+fn resolve_function(f: &mut CFunction, definitions: &[CStruct]) {
+    resolve_kind(&mut f.return_type.kind, definitions);
+    for p in f.parameters.iter_mut() {
+        resolve_declaration(p, definitions);
+    }
+}
+
+fn resolve_declaration(v: &mut CVariableDeclaration, definitions: &[CStruct]) {
+    resolve_kind(&mut v.variable_type.kind, definitions);
+}
+
+fn resolve_kind(kind: &mut CType, definitions: &[CStruct]) {
+    match kind {
+        CType::Struct(s) => {
+            if let Some(d) = definitions
+                .iter()
+                .find(|d| d.identifier.label == s.identifier.label)
+            {
+                s.meta = d.meta.clone();
+            }
+        }
+        /* the parameters of the callback of an `async_callback` function */
+        CType::Function(callback) => resolve_function(callback, definitions),
+        _ => {}
+    }
+}
@@ -0,0 +1,106 @@
+use crate::bytes::Bytes;
+use crate::hashmap::Hashmap;
+use crate::lexer::{CFunction, CVariableDeclaration, HeaderFile};
+use crate::list::List;
+
+/// A property of a struct, which C sees as a pair of methods that read and write it.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: for_struct;getter(timeout);throws;
+///  * #meta_param: out;output;duration;
+///  */
+/// uint32_t Engine_get_timeout(const struct Engine *this_, uint64_t *out, char **err);
+///
+/// /**
+///  * #meta: for_struct;setter(timeout);throws;
+///  * #meta_param: timeout;duration;
+///  */
+/// uint32_t Engine_set_timeout(struct Engine *this_, uint64_t timeout, char **err);
+/// ```
+///
+/// The getter hands the value back through its one `output`, the setter takes it as its one input.
+/// Either may be left out, for a property that is only read or only written
+pub struct Property<'a> {
+    /// Name of the struct the property belongs to, the text leading up to the first underscore of its methods
+    pub owner: &'a str,
+    /// Name of the property, as given to `getter(#property)` and `setter(#property)`
+    pub name: &'a str,
+    pub getter: Option<&'a CFunction>,
+    pub setter: Option<&'a CFunction>,
+}
+
+impl<'a> Property<'a> {
+    /// Every property among the methods of `header`, in the order they are first declared
+    pub fn all(header: &'a HeaderFile) -> Vec<Self> {
+        let mut properties: Vec<Property> = vec![];
+        for f in &header.functions {
+            let (name, is_getter) = match Property::of(f) {
+                Some(p) => p,
+                None => continue,
+            };
+            let owner = owner(f);
+            let property = match properties
+                .iter_mut()
+                .find(|p| p.owner == owner && p.name == name)
+            {
+                Some(p) => p,
+                None => {
+                    properties.push(Property {
+                        owner,
+                        name,
+                        getter: None,
+                        setter: None,
+                    });
+                    properties.last_mut().unwrap()
+                }
+            };
+            if is_getter {
+                property.getter = property.getter.or(Some(f));
+            } else {
+                property.setter = property.setter.or(Some(f));
+            }
+        }
+        properties
+    }
+
+    /// The name of the property `f` reads or writes, and whether it reads it.
+    ///
+    /// Returns `None` if `f` is neither a getter nor a setter
+    pub fn of(f: &CFunction) -> Option<(&str, bool)> {
+        let meta = f.meta.as_ref()?;
+        match (&meta.getter_for, &meta.setter_for) {
+            (Some(name), _) => Some((name, true)),
+            (None, Some(name)) => Some((name, false)),
+            (None, None) => None,
+        }
+    }
+
+    /// The parameter carrying the value of the property: the `output` of a getter, or the input of a setter.
+    ///
+    /// Returns `None` unless there is exactly one, see `validate`
+    pub fn value(f: &CFunction) -> Option<&CVariableDeclaration> {
+        let is_getter = Property::of(f)?.1;
+        let mut values = f.parameters.iter().filter(|p| {
+            let (is_output, is_other) = p
+                .meta
+                .as_ref()
+                .map_or((false, false), |m| (m.is_output, m.is_this || m.is_error));
+            !is_other
+                && is_output == is_getter
+                && !List::is_part(&f.parameters, p)
+                && !Bytes::is_part(&f.parameters, p)
+                && !Hashmap::is_part(&f.parameters, p)
+        });
+        match (values.next(), values.next()) {
+            (Some(value), None) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The struct `f` is a method of, going by the text leading up to the first underscore of its name
+fn owner(f: &CFunction) -> &str {
+    f.label.split('_').next().unwrap_or_default()
+}
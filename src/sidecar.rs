@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::annotation::{parse_annotation, Annotation};
+use crate::lexer::{CVariableDeclaration, HeaderFile};
+use crate::meta::MetaValue;
+use crate::validate::Diagnostic;
+
+/// Annotations kept next to the header rather than in its `#meta` comments, usually read from `annotations.toml`.
+///
+/// Items are keyed by their C name, every `meta` entry is written the same way as in a `#meta` comment
+///
+/// e.g.,
+/// ```toml
+/// [functions.Engine_new]
+/// meta = ["for_struct", "constructor"]
+///
+/// [functions.Engine_new.params.error]
+/// meta = ["error"]
+///
+/// [structs.Engine]
+/// meta = ["persistent"]
+///
+/// [structs.Engine.fields.scopes]
+/// meta = ["list", "length(scopes_len)"]
+///
+/// [defines.OAUTHTOOL_PASS]
+/// meta = ["error_codes"]
+/// ```
+///
+/// A `.json` file with the same structure is accepted as well.
+///
+/// Sidecar annotations are merged with those of the header, replacing any with the same name.
+/// Setting `replace = true` on an item ignores its `#meta` comment altogether
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Sidecar {
+    #[serde(default)]
+    pub functions: BTreeMap<String, FunctionAnnotations>,
+    #[serde(default)]
+    pub structs: BTreeMap<String, StructAnnotations>,
+    #[serde(default)]
+    pub enums: BTreeMap<String, ItemAnnotations>,
+    #[serde(default)]
+    pub defines: BTreeMap<String, ItemAnnotations>,
+
+    /// Where the sidecar was read from, used when reporting problems
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Annotations for an enum, a `#define`, a struct field or a function parameter
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ItemAnnotations {
+    #[serde(default)]
+    pub meta: Vec<String>,
+    /// Ignore the item's annotations from the header
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionAnnotations {
+    #[serde(default)]
+    pub meta: Vec<String>,
+    /// Ignore the function's `#meta` line from the header. Its `#meta_param` lines are kept
+    #[serde(default)]
+    pub replace: bool,
+    #[serde(default)]
+    pub params: BTreeMap<String, ItemAnnotations>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StructAnnotations {
+    #[serde(default)]
+    pub meta: Vec<String>,
+    /// Ignore the struct's `#meta` line from the header. Those of its fields are kept
+    #[serde(default)]
+    pub replace: bool,
+    #[serde(default)]
+    pub fields: BTreeMap<String, ItemAnnotations>,
+}
+
+impl Sidecar {
+    /// Reads and parses the sidecar at `path`, as JSON if it ends in `.json` and as TOML otherwise
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read annotations {}: {}", path.display(), e))?;
+        let invalid = |e: &dyn std::fmt::Display| {
+            format!("Invalid annotations file {}: {}", path.display(), e)
+        };
+        let mut sidecar: Sidecar = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&contents).map_err(|e| invalid(&e))?
+        } else {
+            toml::from_str(&contents).map_err(|e| invalid(&e))?
+        };
+        sidecar.path = path.to_owned();
+        Ok(sidecar)
+    }
+
+    /// Merges the sidecar into the annotations of `header`, and recomputes the meta values of everything it touches.
+    ///
+    /// Returns every problem found, e.g., entries for items that are not in the header, or that conflict with a `#meta` comment
+    pub fn apply(&self, header: &mut HeaderFile) -> Vec<Diagnostic> {
+        let mut merge = Merge {
+            sidecar: &self.path,
+            diagnostics: vec![],
+        };
+
+        for (name, annotations) in &self.enums {
+            let what = format!("enum `{}`", name);
+            match header
+                .enums
+                .iter_mut()
+                .find(|e| e.identifier.label == *name)
+            {
+                Some(e) => {
+                    merge.annotations(
+                        &mut e.annotations,
+                        &annotations.meta,
+                        annotations.replace,
+                        &[],
+                        &what,
+                    );
+                    e.meta = MetaValue::from_annotations(&e.annotations);
+                }
+                None => merge.missing(&what),
+            }
+        }
+
+        for (name, annotations) in &self.defines {
+            let what = format!("#define `{}`", name);
+            match header.defines.iter_mut().find(|d| d.label == *name) {
+                Some(d) => {
+                    merge.annotations(
+                        &mut d.annotations,
+                        &annotations.meta,
+                        annotations.replace,
+                        &[],
+                        &what,
+                    );
+                    d.meta = MetaValue::from_annotations(&d.annotations);
+                }
+                None => merge.missing(&what),
+            }
+        }
+
+        for (name, annotations) in &self.structs {
+            let what = format!("struct `{}`", name);
+            let s = match header
+                .structs
+                .iter_mut()
+                .find(|s| s.identifier.label == *name)
+            {
+                Some(s) => s,
+                None => {
+                    merge.missing(&what);
+                    continue;
+                }
+            };
+            let fields = labels(&s.declarations);
+            merge.annotations(
+                &mut s.annotations,
+                &annotations.meta,
+                annotations.replace,
+                &fields,
+                &what,
+            );
+            s.meta = MetaValue::from_annotations(&s.annotations);
+            for (field, annotations) in &annotations.fields {
+                let what = format!("field `{}` of {}", field, what);
+                merge.declaration(&mut s.declarations, field, annotations, &what);
+            }
+        }
+
+        for (name, annotations) in &self.functions {
+            let what = format!("function `{}`", name);
+            let f = match header.functions.iter_mut().find(|f| f.label == *name) {
+                Some(f) => f,
+                None => {
+                    merge.missing(&what);
+                    continue;
+                }
+            };
+            let params = labels(&f.parameters);
+            merge.annotations(
+                &mut f.annotations,
+                &annotations.meta,
+                annotations.replace,
+                &params,
+                &what,
+            );
+            f.meta = MetaValue::from_annotations(&f.annotations);
+            for (param, annotations) in &annotations.params {
+                let what = format!("parameter `{}` of {}", param, what);
+                merge.declaration(&mut f.parameters, param, annotations, &what);
+            }
+        }
+
+        merge.diagnostics
+    }
+}
+
+/// Merges sidecar entries into parsed items, collecting problems along the way
+struct Merge<'a> {
+    sidecar: &'a Path,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Merge<'_> {
+    fn missing(&mut self, what: &str) {
+        self.diagnostics.push(Diagnostic::error(
+            None,
+            format!(
+                "{} annotates {}, which does not exist in the header",
+                self.sidecar.display(),
+                what
+            ),
+        ));
+    }
+
+    /// Merges into the struct field or function parameter called `label`
+    fn declaration(
+        &mut self,
+        declarations: &mut [CVariableDeclaration],
+        label: &str,
+        annotations: &ItemAnnotations,
+        what: &str,
+    ) {
+        let siblings = labels(declarations);
+        match declarations.iter_mut().find(|d| d.label == label) {
+            Some(d) => {
+                self.annotations(
+                    &mut d.annotations,
+                    &annotations.meta,
+                    annotations.replace,
+                    &siblings,
+                    what,
+                );
+                d.meta = MetaValue::from_annotations(&d.annotations);
+            }
+            None => self.missing(what),
+        }
+    }
+
+    /// Adds each of `entries` to `existing`. An entry replaces an existing annotation of the same name,
+    /// which is reported if their arguments differ.
+    ///
+    /// `siblings` are the names `length(x)`, `capacity(x)` and `values(x)` may refer to
+    fn annotations(
+        &mut self,
+        existing: &mut Vec<Annotation>,
+        entries: &[String],
+        replace: bool,
+        siblings: &[String],
+        what: &str,
+    ) {
+        if replace {
+            existing.clear();
+        }
+        for entry in entries {
+            let annotation = match parse_annotation(entry) {
+                Ok(a) => a,
+                Err(e) => {
+                    self.diagnostics.push(Diagnostic::error(
+                        None,
+                        format!(
+                            "{}: `{}` on {}: {}",
+                            self.sidecar.display(),
+                            entry,
+                            what,
+                            e.message
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            let mut meta = MetaValue::new();
+            if let Err(e) = meta.apply(&annotation) {
+                self.diagnostics.push(Diagnostic::error(
+                    None,
+                    format!("{}: {} on {}", self.sidecar.display(), e, what),
+                ));
+                continue;
+            }
+            if let Some(target) = meta.length_for.or(meta.capacity_for).or(meta.values_for) {
+                if !siblings.contains(&target) {
+                    self.diagnostics.push(Diagnostic::error(
+                        None,
+                        format!(
+                            "{}: `{}` on {} refers to `{}`, which does not exist",
+                            self.sidecar.display(),
+                            annotation,
+                            what,
+                            target
+                        ),
+                    ));
+                }
+            }
+
+            match existing.iter_mut().find(|a| a.name == annotation.name) {
+                Some(a) => {
+                    if a.to_string() != annotation.to_string() {
+                        self.diagnostics.push(Diagnostic::warning(
+                            None,
+                            format!(
+                                "{}: `{}` on {} overrides `{}` from the header",
+                                self.sidecar.display(),
+                                annotation,
+                                what,
+                                a
+                            ),
+                        ));
+                    }
+                    *a = annotation;
+                }
+                None => existing.push(annotation),
+            }
+        }
+    }
+}
+
+fn labels(declarations: &[CVariableDeclaration]) -> Vec<String> {
+    declarations.iter().map(|d| d.label.to_owned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    const HEADER: &str = "/**\n * #meta_param: values;length(ports);\n */\nint32_t listen(const uint32_t *ports, const uint32_t *other, uintptr_t values);\ntypedef struct Engine { int32_t x; } Engine;";
+
+    fn sidecar(toml: &str) -> Sidecar {
+        let mut sidecar: Sidecar = toml::from_str(toml).unwrap();
+        sidecar.path = PathBuf::from("annotations.toml");
+        sidecar
+    }
+
+    fn apply(toml: &str) -> (HeaderFile, Vec<String>) {
+        let mut header = parse_source(HEADER);
+        let diagnostics = sidecar(toml).apply(&mut header);
+        (header, diagnostics.iter().map(|d| d.to_string()).collect())
+    }
+
+    fn param_meta<'a>(header: &'a HeaderFile, name: &str) -> &'a MetaValue {
+        let f = &header.functions[0];
+        let p = f.parameters.iter().find(|p| p.label == name).unwrap();
+        p.meta.as_ref().unwrap()
+    }
+
+    #[test]
+    fn merges_into_items_and_their_members() {
+        let (header, diagnostics) = apply(
+            r#"
+[functions.listen]
+meta = ["throws"]
+
+[functions.listen.params.ports]
+meta = ["list"]
+
+[structs.Engine]
+meta = ["persistent"]
+
+[structs.Engine.fields.x]
+meta = ["rename=y"]
+"#,
+        );
+        assert_eq!(diagnostics, Vec::<String>::new());
+        assert!(header.functions[0].meta.as_ref().unwrap().throws);
+        assert!(param_meta(&header, "ports").is_list);
+        /* The header's own annotations are kept */
+        assert_eq!(
+            param_meta(&header, "values").length_for.as_deref(),
+            Some("ports")
+        );
+        let engine = &header.structs[0];
+        assert!(engine.meta.as_ref().unwrap().is_persistent);
+        let x = engine.declarations[0].meta.as_ref().unwrap();
+        assert_eq!(x.rename.as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn overrides_conflicting_header_annotations() {
+        let (header, diagnostics) =
+            apply("[functions.listen.params.values]\nmeta = [\"length(other)\"]\n");
+        assert_eq!(
+            diagnostics,
+            ["warning: annotations.toml: `length(other)` on parameter `values` of function `listen` overrides `length(ports)` from the header"]
+        );
+        assert_eq!(
+            param_meta(&header, "values").length_for.as_deref(),
+            Some("other")
+        );
+
+        let (_, diagnostics) =
+            apply("[functions.listen.params.values]\nmeta = [\"length(ports)\"]\n");
+        assert_eq!(diagnostics, Vec::<String>::new());
+    }
+
+    #[test]
+    fn replaces_header_annotations() {
+        let (header, _) =
+            apply("[functions.listen.params.values]\nmeta = [\"nullable\"]\nreplace = true\n");
+        let values = param_meta(&header, "values");
+        assert!(values.is_nullable);
+        assert_eq!(values.length_for, None);
+    }
+
+    #[test]
+    fn reports_unknown_items_and_malformed_entries() {
+        let (_, diagnostics) = apply(
+            r#"
+[functions.missing]
+meta = ["throws"]
+
+[functions.listen]
+meta = ["frobnicate", "length(", "length(nothing)"]
+
+[enums.Scope]
+meta = ["error_codes"]
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            [
+                "error: annotations.toml annotates enum `Scope`, which does not exist in the header",
+                "error: annotations.toml: unknown meta keyword `frobnicate` on function `listen`",
+                "error: annotations.toml: `length(` on function `listen`: expected a name, found end of line",
+                "error: annotations.toml: `length(nothing)` on function `listen` refers to `nothing`, which does not exist",
+                "error: annotations.toml annotates function `missing`, which does not exist in the header",
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_toml_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml = dir.path().join("annotations.toml");
+        std::fs::write(&toml, "[structs.Engine]\nmeta = [\"persistent\"]\n").unwrap();
+        let json = dir.path().join("annotations.json");
+        std::fs::write(
+            &json,
+            r#"{"structs": {"Engine": {"meta": ["persistent"]}}}"#,
+        )
+        .unwrap();
+        for path in [toml, json] {
+            let sidecar = Sidecar::load(&path).unwrap();
+            assert_eq!(sidecar.path, path);
+            assert_eq!(sidecar.structs["Engine"].meta, ["persistent"]);
+        }
+
+        let broken = dir.path().join("broken.toml");
+        std::fs::write(&broken, "[structs.Engine]\nmetas = []\n").unwrap();
+        let error = Sidecar::load(&broken).unwrap_err();
+        assert!(error.starts_with("Invalid annotations file"), "{}", error);
+    }
+}
@@ -0,0 +1,1208 @@
+use std::fmt;
+
+use crate::annotation::{
+    parse_comment, AnnotationLine, AnnotationTarget, Argument, CommentAnnotations, Value,
+};
+use crate::bytes::Bytes;
+use crate::cancel::Cancellation;
+use crate::completion::Completion;
+use crate::cursor::Cursor;
+use crate::default_value::DefaultValue;
+use crate::error_codes::define_groups;
+use crate::hashmap::Hashmap;
+use crate::lexer::{CFunction, CStruct, CType, CVariableDeclaration, HeaderFile, SourceLocation};
+use crate::list::List;
+use crate::meta::MetaValue;
+use crate::naming::{member_meta, skipped_fields, skipped_structs, Language};
+use crate::property::Property;
+use crate::value_type::ValueType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found with the meta annotations of a header
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where in the header the problem is, if known
+    pub location: Option<SourceLocation>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(location: Option<SourceLocation>, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            location,
+            message,
+        }
+    }
+
+    pub fn warning(location: Option<SourceLocation>, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            location,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.location {
+            Some(loc) => write!(f, "{}: {}: {}", loc, severity, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Checks the meta annotations of every item in the header for mistakes that would otherwise be silently ignored
+pub fn validate(header: &HeaderFile) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for d in &header.defines {
+        let what = format!("#define `{}`", d.label);
+        check_comment(
+            &mut diagnostics,
+            &d.comment,
+            &d.comment_location,
+            &what,
+            false,
+        );
+        check_naming(
+            &mut diagnostics,
+            &d.meta,
+            &what,
+            &d.comment_location,
+            false,
+            false,
+        );
+        check_deprecation(&mut diagnostics, &d.meta, &what, &d.comment_location);
+        check_value(&mut diagnostics, &d.meta, &what, &d.comment_location);
+    }
+    validate_error_codes(&mut diagnostics, header);
+    for e in &header.enums {
+        let what = format!("enum `{}`", e.identifier.label);
+        check_comment(
+            &mut diagnostics,
+            &e.comment,
+            &e.comment_location,
+            &what,
+            false,
+        );
+        check_naming(
+            &mut diagnostics,
+            &e.meta,
+            &what,
+            &e.comment_location,
+            false,
+            false,
+        );
+        check_deprecation(&mut diagnostics, &e.meta, &what, &e.comment_location);
+        check_value(&mut diagnostics, &e.meta, &what, &e.comment_location);
+        for member in &e.declarations {
+            let what = format!("member `{}` of {}", member.label, what);
+            check_comment(&mut diagnostics, &member.comment, &None, &what, false);
+            check_naming(
+                &mut diagnostics,
+                &member_meta(member),
+                &what,
+                &None,
+                true,
+                false,
+            );
+        }
+    }
+    for s in &header.structs {
+        validate_struct(&mut diagnostics, s, header);
+    }
+    for f in &header.functions {
+        validate_function(&mut diagnostics, f, header);
+    }
+    validate_properties(&mut diagnostics, header);
+    diagnostics
+}
+
+fn validate_struct(diagnostics: &mut Vec<Diagnostic>, s: &CStruct, header: &HeaderFile) {
+    let what = format!("struct `{}`", s.identifier.label);
+    check_comment(diagnostics, &s.comment, &s.comment_location, &what, false);
+    if s.meta.as_ref().is_some_and(|m| m.is_error_codes) {
+        diagnostics.push(Diagnostic::warning(
+            s.comment_location.clone(),
+            format!(
+                "error_codes on {} is ignored, it only applies to #defines and enums",
+                what
+            ),
+        ));
+    }
+
+    if let Err(message) = Cursor::of(s, header) {
+        diagnostics.push(Diagnostic::error(s.comment_location.clone(), message));
+    }
+    if let Err(message) = ValueType::of(s, header) {
+        diagnostics.push(Diagnostic::error(s.comment_location.clone(), message));
+    }
+    check_naming(diagnostics, &s.meta, &what, &s.comment_location, true, true);
+    for language in Language::ALL.iter().filter(|l| !l.skips(&s.meta)) {
+        for skipped in skipped_fields(s, header, *language) {
+            diagnostics.push(Diagnostic::error(
+                s.comment_location.clone(),
+                format!(
+                    "{} has a field of struct `{}`, which is skipped for {}",
+                    what,
+                    skipped.identifier.label,
+                    language.name()
+                ),
+            ));
+        }
+    }
+
+    for field in &s.declarations {
+        let what = format!("field `{}` of {}", field.label, what);
+        if let Some(comment) = check_comment(
+            diagnostics,
+            &field.comment,
+            &field.comment_location,
+            &what,
+            false,
+        ) {
+            for line in &comment.parsed.lines {
+                check_list_targets(diagnostics, &comment, line, &s.declarations, &what);
+            }
+        }
+        check_naming(
+            diagnostics,
+            &field.meta,
+            &what,
+            &field.comment_location,
+            true,
+            false,
+        );
+        check_value(diagnostics, &field.meta, &what, &field.comment_location);
+        let companion = field.meta.as_ref().and_then(|m| {
+            m.length_for
+                .as_ref()
+                .or(m.capacity_for.as_ref())
+                .or(m.values_for.as_ref())
+        });
+        let is_renamed = field.meta.as_ref().is_some_and(|m| m.is_renamed());
+        if let (Some(companion), true) = (companion, is_renamed) {
+            diagnostics.push(Diagnostic::error(
+                field.comment_location.clone(),
+                format!(
+                    "{} is renamed, but is read along with `{}` rather than on its own",
+                    what, companion
+                ),
+            ));
+        }
+    }
+    check_hashmaps(
+        diagnostics,
+        &s.declarations,
+        &what,
+        &s.comment_location,
+        false,
+    );
+    check_bytes(
+        diagnostics,
+        &s.declarations,
+        &what,
+        &s.comment_location,
+        false,
+        header,
+    );
+    check_lists(
+        diagnostics,
+        &s.declarations,
+        &what,
+        &s.comment_location,
+        false,
+        header,
+    );
+}
+
+fn validate_function(diagnostics: &mut Vec<Diagnostic>, f: &CFunction, header: &HeaderFile) {
+    let what = format!("function `{}`", f.label);
+    let comment = check_comment(diagnostics, &f.comment, &f.comment_location, &what, true);
+    let lines = comment
+        .as_ref()
+        .map_or(&[][..], |c| c.parsed.lines.as_slice());
+
+    for line in lines {
+        let comment = comment.as_ref().unwrap();
+        check_list_targets(
+            diagnostics,
+            comment,
+            line,
+            &f.parameters,
+            &subject(line, &what),
+        );
+
+        if let AnnotationTarget::Param { name, span } = &line.target {
+            /* The parameters of a callback are annotated alongside those of the function */
+            let is_callback_param = |p: &CVariableDeclaration| match &p.variable_type.kind {
+                CType::Function(callback) => callback.parameters.iter().any(|c| c.label == *name),
+                _ => false,
+            };
+            if !f
+                .parameters
+                .iter()
+                .any(|p| p.label == *name || is_callback_param(p))
+            {
+                diagnostics.push(Diagnostic::error(
+                    comment.at(span.start),
+                    format!(
+                        "#meta_param names `{}`, which is not a parameter of {}",
+                        name, what
+                    ),
+                ));
+            }
+        }
+    }
+
+    /* Function level problems are reported at the `#meta:` line, if there is one */
+    let location = match (
+        &comment,
+        lines.iter().find(|l| l.target == AnnotationTarget::Item),
+    ) {
+        (Some(c), Some(l)) => c.at(l.span.start),
+        _ => f.comment_location.clone(),
+    };
+    let meta = f.meta.clone().unwrap_or_else(MetaValue::new);
+    if meta.is_constructor && meta.is_destructor {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            format!("{} is marked as both constructor and destructor", what),
+        ));
+    }
+    if meta.is_error_codes {
+        diagnostics.push(Diagnostic::warning(
+            location.clone(),
+            format!(
+                "error_codes on {} is ignored, it only applies to #defines and enums",
+                what
+            ),
+        ));
+    }
+    if meta.is_static && !meta.for_struct {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            format!("{} is marked static, but not for_struct", what),
+        ));
+    }
+    if meta.getter_for.is_some() && meta.setter_for.is_some() {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            format!("{} is marked as both getter and setter", what),
+        ));
+    } else if let Some((name, is_getter)) = Property::of(f) {
+        check_accessor(diagnostics, f, &meta, name, is_getter, &what, &location);
+    }
+    if let Err(message) = Cancellation::of(f, header) {
+        diagnostics.push(Diagnostic::error(location.clone(), message));
+    }
+    if let Err(message) = Completion::of(f, header) {
+        diagnostics.push(Diagnostic::error(location.clone(), message));
+    }
+    check_naming(diagnostics, &f.meta, &what, &location, true, true);
+    check_value(diagnostics, &f.meta, &what, &location);
+    if Property::of(f).is_some() && meta.is_renamed() {
+        diagnostics.push(Diagnostic::warning(
+            location.clone(),
+            format!(
+                "rename on {} is ignored, a getter or setter goes by the name of its property",
+                what
+            ),
+        ));
+    }
+    for language in Language::ALL.iter().filter(|l| !l.skips(&f.meta)) {
+        for skipped in skipped_structs(f, header, *language) {
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!(
+                    "{} takes or hands back struct `{}`, which is skipped for {}",
+                    what,
+                    skipped.identifier.label,
+                    language.name()
+                ),
+            ));
+        }
+    }
+    for p in &f.parameters {
+        if matches!(p.variable_type.kind, CType::Function(_))
+            && meta.completes_with.as_ref() != Some(&p.label)
+        {
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!(
+                    "parameter `{}` of {} is a function pointer, which is only supported as the async_callback of the function",
+                    p.label, what
+                ),
+            ));
+        }
+    }
+    let has_error_param = f
+        .parameters
+        .iter()
+        .any(|p| p.meta.as_ref().is_some_and(|m| m.is_error));
+    if meta.throws && !has_error_param {
+        diagnostics.push(Diagnostic::error(
+            location,
+            format!(
+                "{} is marked throws, but none of its parameters is marked error",
+                what
+            ),
+        ));
+    }
+
+    check_hashmaps(diagnostics, &f.parameters, &what, &f.comment_location, true);
+    check_bytes(
+        diagnostics,
+        &f.parameters,
+        &what,
+        &f.comment_location,
+        true,
+        header,
+    );
+    check_lists(
+        diagnostics,
+        &f.parameters,
+        &what,
+        &f.comment_location,
+        true,
+        header,
+    );
+    for p in &f.parameters {
+        let meta = match &p.meta {
+            Some(m) => m,
+            None => continue,
+        };
+        let location = lines
+            .iter()
+            .find(|l| matches!(&l.target, AnnotationTarget::Param { name, .. } if *name == p.label))
+            .and_then(|l| comment.as_ref().and_then(|c| c.at(l.span.start)));
+        let what = format!("parameter `{}` of {}", p.label, what);
+
+        if let Err(message) = DefaultValue::of(p, f) {
+            diagnostics.push(Diagnostic::error(location.clone(), message));
+        }
+        check_naming(diagnostics, &p.meta, &what, &location, false, false);
+        check_deprecation(diagnostics, &p.meta, &what, &location);
+        check_value(diagnostics, &p.meta, &what, &location);
+
+        if meta.is_cancel_handle && f.meta.as_ref().is_none_or(|m| m.cancel_with.is_none()) {
+            diagnostics.push(Diagnostic::warning(
+                location.clone(),
+                format!(
+                    "cancel_handle on {} is ignored, the function is not cancellable",
+                    what
+                ),
+            ));
+        }
+
+        let is_struct_ptr = p.variable_type.is_struct && p.variable_type.pointer_count > 0;
+        if meta.is_this && !is_struct_ptr {
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!("{} is marked this, but is not a struct pointer", what),
+            ));
+        }
+
+        if meta.is_borrowed && (meta.is_owned || meta.free_with.is_some()) {
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!(
+                    "{} is marked borrowed, but also owned or free_with, which release it",
+                    what
+                ),
+            ));
+        }
+        let has_ownership = meta.is_owned || meta.is_borrowed || meta.free_with.is_some();
+        if has_ownership && !(meta.is_output || meta.is_error) {
+            diagnostics.push(Diagnostic::warning(
+                location.clone(),
+                format!(
+                    "ownership of {} is ignored, it is neither an output nor an error",
+                    what
+                ),
+            ));
+        }
+        if let Some(free_with) = &meta.free_with {
+            if !header.functions.iter().any(|f| f.label == *free_with) {
+                diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "{} is released with `{}`, which is not a function of the header",
+                        what, free_with
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Reports a getter or setter that cannot be turned into a property: it must be a plain method,
+/// a getter handing back one `output` and taking nothing, a setter taking one value and handing back nothing
+fn check_accessor(
+    diagnostics: &mut Vec<Diagnostic>,
+    f: &CFunction,
+    meta: &MetaValue,
+    name: &str,
+    is_getter: bool,
+    what: &str,
+    location: &Option<SourceLocation>,
+) {
+    let kind = if is_getter { "getter" } else { "setter" };
+    if !meta.for_struct || meta.is_static || meta.is_constructor || meta.is_destructor {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            format!(
+                "{} is the {} of `{}`, but is not a for_struct method of an instance",
+                what, kind, name
+            ),
+        ));
+    }
+    if !is_getter && meta.is_async {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            format!(
+                "{} is the setter of `{}`, which cannot be async",
+                what, name
+            ),
+        ));
+    }
+    /* the other side of the property must not show up at all */
+    let strays = f.parameters.iter().filter(|p| {
+        let is_output = p.meta.as_ref().is_some_and(|m| m.is_output);
+        let is_other = p.meta.as_ref().is_some_and(|m| m.is_this || m.is_error);
+        !is_other
+            && is_output != is_getter
+            && !List::is_part(&f.parameters, p)
+            && !Bytes::is_part(&f.parameters, p)
+            && !Hashmap::is_part(&f.parameters, p)
+    });
+    if Property::value(f).is_none() || strays.count() > 0 {
+        diagnostics.push(Diagnostic::error(
+            location.clone(),
+            if is_getter {
+                format!(
+                    "{} is the getter of `{}`, which must hand back one output and take no other parameters",
+                    what, name
+                )
+            } else {
+                format!(
+                    "{} is the setter of `{}`, which must take one value and hand back no outputs",
+                    what, name
+                )
+            },
+        ));
+    }
+}
+
+/// Reports properties that are read or written by more than one method
+fn validate_properties(diagnostics: &mut Vec<Diagnostic>, header: &HeaderFile) {
+    for property in Property::all(header) {
+        for f in &header.functions {
+            let is_other = |accessor: Option<&CFunction>, is_getter: bool| {
+                Property::of(f) == Some((property.name, is_getter))
+                    && f.label.split('_').next() == Some(property.owner)
+                    && accessor.is_some_and(|a| a.label != f.label)
+            };
+            for (accessor, kind, is_getter) in [
+                (property.getter, "getter", true),
+                (property.setter, "setter", false),
+            ] {
+                if is_other(accessor, is_getter) {
+                    diagnostics.push(Diagnostic::error(
+                        f.comment_location.clone(),
+                        format!(
+                            "function `{}` is a second {} of `{}` on struct `{}`, which already has `{}`",
+                            f.label,
+                            kind,
+                            property.name,
+                            property.owner,
+                            accessor.unwrap().label
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Reports maps of `declarations` that are missing their values or length, or cannot be marshalled.
+///
+/// Function parameters are passed along together, so they must follow each other in the order keys, values, length
+fn check_hashmaps(
+    diagnostics: &mut Vec<Diagnostic>,
+    declarations: &[CVariableDeclaration],
+    what: &str,
+    item_location: &Option<SourceLocation>,
+    are_parameters: bool,
+) {
+    for h in Hashmap::all(declarations) {
+        let location = h
+            .keys
+            .comment_location
+            .clone()
+            .or_else(|| item_location.clone());
+        let kind = if are_parameters { "parameter" } else { "field" };
+        let what = format!("hashmap {} `{}` of {}", kind, h.keys.label, what);
+        let (values, length) = match (h.values, h.length) {
+            (Some(v), Some(l)) => (v, l),
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "{} needs a {} marked `values({})` and one marked `length({})`",
+                        what, kind, h.keys.label, h.keys.label
+                    ),
+                ));
+                continue;
+            }
+        };
+        if !are_parameters {
+            continue;
+        }
+        if h.keys.meta.as_ref().is_some_and(|m| m.is_output) {
+            diagnostics.push(Diagnostic::error(
+                location,
+                format!(
+                    "{} is an output, maps can only be returned as the fields of a struct",
+                    what
+                ),
+            ));
+            continue;
+        }
+        let position =
+            |d: &CVariableDeclaration| declarations.iter().position(|p| p.label == d.label);
+        let keys = position(h.keys).unwrap_or_default();
+        if position(values) != Some(keys + 1) || position(length) != Some(keys + 2) {
+            diagnostics.push(Diagnostic::error(
+                location,
+                format!(
+                    "{} must be directly followed by `{}` and then `{}`",
+                    what, values.label, length.label
+                ),
+            ));
+        }
+    }
+}
+
+/// Reports byte buffers of `declarations` that are missing their length, are not made of bytes,
+/// or are released with a function that does not take them back along with their length.
+///
+/// Inputs and fields are a `uint8_t *` with a plain length, outputs a `uint8_t **` with a pointer the length is written to
+fn check_bytes(
+    diagnostics: &mut Vec<Diagnostic>,
+    declarations: &[CVariableDeclaration],
+    what: &str,
+    item_location: &Option<SourceLocation>,
+    are_parameters: bool,
+    header: &HeaderFile,
+) {
+    for b in Bytes::all(declarations) {
+        let location = b
+            .data
+            .comment_location
+            .clone()
+            .or_else(|| item_location.clone());
+        let kind = if are_parameters { "parameter" } else { "field" };
+        let what = format!("bytes {} `{}` of {}", kind, b.data.label, what);
+        if !Bytes::is_byte_pointer(b.data) {
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!("{} is not a pointer to `uint8_t`", what),
+            ));
+        }
+        let length = match b.length {
+            Some(l) => l,
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "{} needs a {} marked `length({})`",
+                        what, kind, b.data.label
+                    ),
+                ));
+                continue;
+            }
+        };
+        /* The library gets back what it handed out along with its size, the same as for lists */
+        let free_with = b.data.meta.as_ref().and_then(|m| m.free_with.as_ref());
+        if let Some(f) =
+            free_with.and_then(|name| header.functions.iter().find(|f| f.label == *name))
+        {
+            if b.is_output() && f.parameters.len() != 2 {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    format!(
+                        "{} is released with `{}`, which must take its data and length",
+                        what, f.label
+                    ),
+                ));
+            }
+        }
+        let (data_pointers, length_pointers) = if b.is_output() { (2, 1) } else { (1, 0) };
+        if b.data.variable_type.pointer_count != data_pointers
+            || length.variable_type.pointer_count != length_pointers
+        {
+            diagnostics.push(Diagnostic::error(
+                location,
+                if b.is_output() {
+                    format!(
+                        "{} is an output, it must be a `uint8_t **` and `{}` a pointer the size is written to",
+                        what, length.label
+                    )
+                } else {
+                    format!(
+                        "{} must be a `uint8_t *` and `{}` its size",
+                        what, length.label
+                    )
+                },
+            ));
+        }
+    }
+}
+
+/// Reports lists whose elements are unknown, that have no length,
+/// or that are handed to the library while being made of something the bindings cannot build
+fn check_lists(
+    diagnostics: &mut Vec<Diagnostic>,
+    declarations: &[CVariableDeclaration],
+    what: &str,
+    item_location: &Option<SourceLocation>,
+    are_parameters: bool,
+    header: &HeaderFile,
+) {
+    for l in List::all(declarations) {
+        let location = l
+            .items
+            .comment_location
+            .clone()
+            .or_else(|| item_location.clone());
+        let kind = if are_parameters { "parameter" } else { "field" };
+        let what = format!("list {} `{}` of {}", kind, l.items.label, what);
+        let element_name = match &l.items.variable_type.kind {
+            CType::Struct(s) => Some(&s.identifier.label),
+            CType::Enum(e) => Some(&e.identifier.label),
+            _ => l.items.meta.as_ref().and_then(|m| m.list_of.as_ref()),
+        };
+        if let Some(name) = element_name {
+            if !header.structs.iter().any(|s| s.identifier.label == *name)
+                && !header.enums.iter().any(|e| e.identifier.label == *name)
+            {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    format!(
+                        "{} is made of `{}`, which is not a struct or enum of this header",
+                        what, name
+                    ),
+                ));
+                continue;
+            }
+        }
+        match l.element() {
+            None => diagnostics.push(Diagnostic::error(
+                location.clone(),
+                format!(
+                    "cannot tell what the elements of {} are from `{}`, name them with `list(T)`",
+                    what, l.items.variable_type
+                ),
+            )),
+            Some(e) if are_parameters && !l.is_output() && !e.can_be_input() => {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    format!(
+                        "{} can only be handed back by the library, only lists of strings, numbers, enums and `persistent` struct pointers can be handed to it",
+                        what
+                    ),
+                ))
+            }
+            Some(_) => {}
+        }
+        let length = match l.length {
+            Some(len) => len,
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "{} needs a {} marked `length({})`",
+                        what, kind, l.items.label
+                    ),
+                ));
+                continue;
+            }
+        };
+        let size_pointers = if l.is_output() { 1 } else { 0 };
+        for (size, name) in [(Some(length), "length"), (l.capacity, "capacity")] {
+            let size = match size {
+                Some(s) if s.variable_type.pointer_count != size_pointers => s,
+                _ => continue,
+            };
+            diagnostics.push(Diagnostic::error(
+                location.clone(),
+                if l.is_output() {
+                    format!(
+                        "{} is an output, `{}` must be a pointer its {} is written to",
+                        what, size.label, name
+                    )
+                } else {
+                    format!("`{}` must be the {} of {}", size.label, name, what)
+                },
+            ));
+        }
+        /* The library gets back what it handed out, to rebuild the array and release it */
+        let free_with = l.items.meta.as_ref().and_then(|m| m.free_with.as_ref());
+        if let Some(f) =
+            free_with.and_then(|name| header.functions.iter().find(|f| f.label == *name))
+        {
+            let (expected, parts) = match l.capacity {
+                Some(_) => (3, "its items, length and capacity"),
+                None => (2, "its items and length"),
+            };
+            if f.parameters.len() != expected {
+                diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "{} is released with `{}`, which must take {}",
+                        what, f.label, parts
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Reports a `deprecated` keyword on `what`, which has no effect, as only structs, fields, enum members
+/// and functions can be deprecated
+fn check_deprecation(
+    diagnostics: &mut Vec<Diagnostic>,
+    meta: &Option<MetaValue>,
+    what: &str,
+    location: &Option<SourceLocation>,
+) {
+    if meta.as_ref().is_some_and(|m| m.deprecated.is_some()) {
+        diagnostics.push(Diagnostic::warning(
+            location.clone(),
+            format!(
+                "deprecated on {} is ignored, only structs, fields, enum members and functions can be deprecated",
+                what
+            ),
+        ));
+    }
+}
+
+/// Reports a `value` keyword on `what`, which has no effect on anything but a struct, see `ValueType`
+fn check_value(
+    diagnostics: &mut Vec<Diagnostic>,
+    meta: &Option<MetaValue>,
+    what: &str,
+    location: &Option<SourceLocation>,
+) {
+    if meta.as_ref().is_some_and(|m| m.is_value) {
+        diagnostics.push(Diagnostic::warning(
+            location.clone(),
+            format!("value on {} is ignored, only structs can be values", what),
+        ));
+    }
+}
+
+/// Reports `rename` and `skip` keywords on `what` that have no effect, as it cannot be renamed or skipped
+fn check_naming(
+    diagnostics: &mut Vec<Diagnostic>,
+    meta: &Option<MetaValue>,
+    what: &str,
+    location: &Option<SourceLocation>,
+    can_rename: bool,
+    can_skip: bool,
+) {
+    let meta = match meta {
+        Some(m) => m,
+        None => return,
+    };
+    let renames = [
+        (&meta.rename, "rename"),
+        (&meta.rename_dart, "rename_dart"),
+        (&meta.rename_go, "rename_go"),
+        (&meta.rename_csharp, "rename_csharp"),
+    ];
+    let skips = [
+        (meta.is_skipped, "skip"),
+        (meta.skip_dart, "skip_dart"),
+        (meta.skip_go, "skip_go"),
+        (meta.skip_csharp, "skip_csharp"),
+    ];
+    let ignored = renames
+        .iter()
+        .filter(|(name, _)| !can_rename && name.is_some())
+        .map(|(_, kw)| {
+            (
+                *kw,
+                "structs, fields, enum members and functions can be renamed",
+            )
+        })
+        .chain(
+            skips
+                .iter()
+                .filter(|(set, _)| !can_skip && *set)
+                .map(|(_, kw)| (*kw, "structs and functions can be skipped")),
+        );
+    for (kw, reason) in ignored {
+        diagnostics.push(Diagnostic::warning(
+            location.clone(),
+            format!("{} on {} is ignored, only {}", kw, what, reason),
+        ));
+    }
+}
+
+/// Reports error codes that cannot become error types: `#define`s that are not integers, enum members whose value
+/// cannot be worked out, and codes sharing a value
+fn validate_error_codes(diagnostics: &mut Vec<Diagnostic>, header: &HeaderFile) {
+    let mut seen: Vec<(String, i32)> = vec![];
+    let mut check = |diagnostics: &mut Vec<Diagnostic>,
+                     label: &str,
+                     value: i32,
+                     location: &Option<SourceLocation>| {
+        if value == 0 {
+            return;
+        }
+        match seen.iter().find(|(_, v)| *v == value) {
+            Some((first, _)) => diagnostics.push(Diagnostic::warning(
+                location.clone(),
+                format!(
+                    "error code `{}` has the same value as `{}`, and is reported with its error type",
+                    label, first
+                ),
+            )),
+            None => seen.push((label.to_owned(), value)),
+        }
+    };
+
+    for group in define_groups(&header.defines) {
+        /* Members of a group have no comment of their own, the first one's is the closest */
+        let location = &group[0].comment_location;
+        for d in group {
+            match d.variable_type.kind {
+                CType::SignedInteger(value) => check(diagnostics, &d.label, value, location),
+                _ => diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    format!(
+                        "#define `{}` is part of an error_codes group, but is not an integer",
+                        d.label
+                    ),
+                )),
+            }
+        }
+    }
+    for e in header
+        .enums
+        .iter()
+        .filter(|e| e.meta.as_ref().is_some_and(|m| m.is_error_codes))
+    {
+        match e.values() {
+            Ok(values) => {
+                for (member, value) in e.declarations.iter().zip(values) {
+                    check(
+                        diagnostics,
+                        &member.label,
+                        value as i32,
+                        &e.comment_location,
+                    );
+                }
+            }
+            Err(err) => diagnostics.push(Diagnostic::error(
+                e.comment_location.clone(),
+                format!(
+                    "enum `{}` is marked error_codes, but {}",
+                    e.identifier.label, err
+                ),
+            )),
+        }
+    }
+}
+
+/// A doc comment along with its parsed meta lines
+struct MetaComment<'a> {
+    text: &'a str,
+    /// Where the first character of `text` is in the header
+    location: &'a Option<SourceLocation>,
+    parsed: CommentAnnotations,
+}
+
+impl MetaComment<'_> {
+    /// Location of the byte at `offset` within the comment
+    fn at(&self, offset: usize) -> Option<SourceLocation> {
+        let loc = self.location.as_ref()?;
+        let before = &self.text[..offset];
+        /* Only the first line of a comment starts where the comment does, the others start at their first column */
+        let (line, column) = match before.rfind('\n') {
+            Some(newline) => (loc.line + before.matches('\n').count(), offset - newline),
+            None => (loc.line, loc.column + offset),
+        };
+        Some(SourceLocation {
+            file: loc.file.to_owned(),
+            line,
+            column,
+        })
+    }
+}
+
+/// What the annotations of `line` apply to, given the item the comment belongs to
+fn subject(line: &AnnotationLine, what: &str) -> String {
+    match &line.target {
+        AnnotationTarget::Param { name, .. } => format!("parameter `{}` of {}", name, what),
+        AnnotationTarget::Item => what.to_owned(),
+    }
+}
+
+/// Reports meta lines of `comment` that cannot be parsed, and annotations that are not understood.
+///
+/// Returns the parsed comment for further checks
+fn check_comment<'a>(
+    diagnostics: &mut Vec<Diagnostic>,
+    comment: &'a Option<String>,
+    comment_location: &'a Option<SourceLocation>,
+    what: &str,
+    has_parameters: bool,
+) -> Option<MetaComment<'a>> {
+    let text = comment.as_deref()?;
+    let comment = MetaComment {
+        text,
+        location: comment_location,
+        parsed: parse_comment(text),
+    };
+
+    for e in &comment.parsed.errors {
+        diagnostics.push(Diagnostic::error(
+            comment.at(e.span.start),
+            format!("{} in the meta annotations of {}", e.message, what),
+        ));
+    }
+    for line in &comment.parsed.lines {
+        if !has_parameters && line.target != AnnotationTarget::Item {
+            diagnostics.push(Diagnostic::warning(
+                comment.at(line.span.start),
+                format!("#meta_param on {} is ignored, it has no parameters", what),
+            ));
+            continue;
+        }
+        for a in &line.annotations {
+            if let Err(e) = MetaValue::new().apply(a) {
+                diagnostics.push(Diagnostic::error(
+                    comment.at(a.span.start),
+                    format!("{} on {}", e, subject(line, what)),
+                ));
+            }
+        }
+    }
+    Some(comment)
+}
+
+/// Reports `length(x)`, `capacity(x)` and `values(x)` annotations whose `x` is not one of `siblings`
+fn check_list_targets(
+    diagnostics: &mut Vec<Diagnostic>,
+    comment: &MetaComment,
+    line: &AnnotationLine,
+    siblings: &[CVariableDeclaration],
+    what: &str,
+) {
+    for a in &line.annotations {
+        if !["length", "capacity", "values"].contains(&a.name.as_str()) {
+            continue;
+        }
+        if let [Argument {
+            key: None,
+            value: Value::Ident(target),
+            ..
+        }] = a.args.as_slice()
+        {
+            if !siblings.iter().any(|s| s.label == *target) {
+                diagnostics.push(Diagnostic::error(
+                    comment.at(a.span.start),
+                    format!(
+                        "`{}` on {} refers to `{}`, which does not exist",
+                        a, what, target
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        validate(&parse_source(source))
+    }
+
+    fn messages(source: &str) -> Vec<String> {
+        diagnostics(source).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn check_comment_reports_unknown_keywords_and_malformed_lines() {
+        assert_eq!(
+            messages("/**\n * #meta: nullable;\n */\nvoid f(void);"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages("/**\n * #meta: frobnicate;length(;\n */\nvoid f(void);"),
+            [
+                "expected a name, found `;` in the meta annotations of function `f`",
+                "unknown meta keyword `frobnicate` on function `f`",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_comment_points_at_the_annotation() {
+        let found = diagnostics("/**\n * #meta: frobnicate;\n */\nvoid f(void);");
+        let location = found[0].location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (2, 11));
+    }
+
+    #[test]
+    fn check_comment_reports_meta_param_without_parameters() {
+        assert_eq!(
+            messages("/**\n * #meta_param: a;nullable;\n */\ntypedef struct S { int32_t a; } S;"),
+            ["#meta_param on struct `S` is ignored, it has no parameters"]
+        );
+    }
+
+    #[test]
+    fn check_list_targets_reports_missing_siblings() {
+        let source = |target: &str| {
+            format!(
+                "/**\n * #meta_param: ports_len;length({});\n */\nvoid g(const uint32_t *ports, uintptr_t ports_len);",
+                target
+            )
+        };
+        assert_eq!(messages(&source("ports")), Vec::<String>::new());
+        assert!(messages(&source("port")).contains(
+            &"`length(port)` on parameter `ports_len` of function `g` refers to `port`, which does not exist".to_owned()
+        ));
+    }
+
+    #[test]
+    fn check_hashmaps_reports_maps_without_values() {
+        let source = |values: &str| {
+            format!(
+                "typedef struct R {{\n  /**\n   * #meta: hashmap;\n   */\n  const char *const *keys;\n  /**\n   * #meta: {};\n   */\n  const char *const *vals;\n  /**\n   * #meta: length(keys);\n   */\n  uintptr_t keys_len;\n}} R;",
+                values
+            )
+        };
+        assert_eq!(messages(&source("values(keys)")), Vec::<String>::new());
+        assert_eq!(
+            messages(&source("string")),
+            ["hashmap field `keys` of struct `R` needs a field marked `values(keys)` and one marked `length(keys)`"]
+        );
+    }
+
+    #[test]
+    fn check_lists_reports_lists_without_a_length() {
+        let source = |length: &str| {
+            format!(
+                "typedef struct S {{\n  /**\n   * #meta: list;\n   */\n  const uint32_t *ports;\n  {}\n  uintptr_t ports_len;\n}} S;",
+                length
+            )
+        };
+        assert_eq!(
+            messages(&source("/** #meta: length(ports); */")),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages(&source("")),
+            ["list field `ports` of struct `S` needs a field marked `length(ports)`"]
+        );
+    }
+
+    #[test]
+    fn check_accessor_reports_getters_taking_inputs() {
+        let source = |params: &str| {
+            format!(
+                "/**\n * #meta: persistent;\n */\ntypedef struct Engine {{ int32_t x; }} Engine;\n/**\n * #meta: for_struct;getter(retries);\n * #meta_param: this_;this;\n * #meta_param: out;output;\n */\nvoid Engine_get_retries(const struct Engine *this_{}, int32_t *out);",
+                params
+            )
+        };
+        assert_eq!(messages(&source("")), Vec::<String>::new());
+        assert_eq!(
+            messages(&source(", int32_t extra")),
+            ["function `Engine_get_retries` is the getter of `retries`, which must hand back one output and take no other parameters"]
+        );
+    }
+
+    #[test]
+    fn check_naming_reports_ignored_renames() {
+        assert_eq!(
+            messages("/**\n * #meta: rename=Other;\n */\ntypedef struct S { int32_t a; } S;"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages("/**\n * #meta: rename=Other;\n */\ntypedef enum E { A, B } E;"),
+            ["rename on enum `E` is ignored, only structs, fields, enum members and functions can be renamed"]
+        );
+    }
+
+    #[test]
+    fn check_deprecation_reports_deprecated_enums() {
+        assert_eq!(
+            messages("/**\n * #meta: deprecated;\n */\nvoid f(void);"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages("/**\n * #meta: deprecated;\n */\ntypedef enum E { A, B } E;"),
+            ["deprecated on enum `E` is ignored, only structs, fields, enum members and functions can be deprecated"]
+        );
+    }
+
+    #[test]
+    fn check_value_reports_values_that_are_not_structs() {
+        assert_eq!(
+            messages("/**\n * #meta: value;\n */\ntypedef struct S { int32_t a; } S;"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages("/**\n * #meta: value;\n */\ntypedef enum E { A, B } E;"),
+            ["value on enum `E` is ignored, only structs can be values"]
+        );
+    }
+
+    #[test]
+    fn validate_error_codes_reports_values_that_cannot_be_worked_out() {
+        assert_eq!(
+            messages(
+                "/**\n * #meta: error_codes;\n */\ntypedef enum E { OK, FAIL = 10, OTHER } E;"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages("/**\n * #meta: error_codes;\n */\ntypedef enum E { OK, FAIL = OK } E;"),
+            ["enum `E` is marked error_codes, but enum member `FAIL` is initialized with `OK`, which is not an integer"]
+        );
+    }
+
+    #[test]
+    fn check_bytes_reports_release_functions_without_a_length() {
+        let source = |free: &str| {
+            format!(
+                "void free_bytes(uint8_t *data, uintptr_t len);\nvoid free_one(uint8_t *data);\n/**\n * #meta_param: out;output;bytes;free_with({});\n * #meta_param: out_len;length(out);\n */\nvoid read(uint8_t **out, uintptr_t *out_len);",
+                free
+            )
+        };
+        assert_eq!(messages(&source("free_bytes")), Vec::<String>::new());
+        assert_eq!(
+            messages(&source("free_one")),
+            ["bytes parameter `out` of function `read` is released with `free_one`, which must take its data and length"]
+        );
+    }
+}
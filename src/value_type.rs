@@ -0,0 +1,87 @@
+use crate::lexer::{CStruct, CType, HeaderFile};
+use crate::list::Element;
+use crate::meta::MetaValue;
+
+/// A struct marked `value`, which the bindings compare, hash and print by the values of its fields rather than
+/// by reference, and which can be copied with some of its fields replaced.
+///
+/// e.g.,
+/// ```c
+/// /**
+///  * #meta: value;
+///  */
+/// typedef struct TokenResponse {
+///   /**
+///    * #meta: string;nullable;
+///    */
+///   const char *refresh_token;
+///   int64_t expires_at;
+///   struct FFIArray *scopes;
+/// } TokenResponse;
+/// ```
+///
+/// `nullable` fields are equal when both are null, and lists and maps are equal when their elements are.
+/// A field holding another struct, or a list of them, compares them as values too, so those structs must be marked `value` as well.
+/// A `persistent` struct is the pointer it keeps, and cannot be a value
+pub struct ValueType<'a> {
+    /// The struct marked `value`
+    pub owner: &'a CStruct,
+}
+
+impl<'a> ValueType<'a> {
+    /// Every value type of `header`, leaving out those that `ValueType::of` rejects
+    pub fn all(header: &'a HeaderFile) -> Vec<Self> {
+        header
+            .structs
+            .iter()
+            .filter_map(|s| ValueType::of(s, header).ok().flatten())
+            .collect()
+    }
+
+    /// The value type `s` describes, if it is marked `value`, or what is wrong with it
+    pub fn of(s: &'a CStruct, header: &'a HeaderFile) -> Result<Option<Self>, String> {
+        if !is_value(s) {
+            return Ok(None);
+        }
+        let owner = &s.identifier.label;
+        if s.meta.as_ref().is_some_and(|m| m.is_persistent) {
+            return Err(format!(
+                "struct `{}` is a value, but is persistent, so it is compared by the pointer it keeps",
+                owner
+            ));
+        }
+        for d in &s.declarations {
+            let inner = match (Element::of(d), &d.variable_type.kind) {
+                (Some(Element::Struct(inner) | Element::StructPointer(inner)), _)
+                    if d.meta.as_ref().is_some_and(|m| m.is_list) =>
+                {
+                    inner
+                }
+                (_, CType::Struct(inner)) => inner,
+                _ => continue,
+            };
+            let is_inner_value = header
+                .structs
+                .iter()
+                .find(|s| s.identifier.label == inner.identifier.label)
+                .is_some_and(is_value);
+            if !is_inner_value {
+                return Err(format!(
+                    "struct `{}` is a value, but its field `{}` holds struct `{}`, which is not",
+                    owner, d.label, inner.identifier.label
+                ));
+            }
+        }
+        Ok(Some(ValueType { owner: s }))
+    }
+
+    /// Whether a value is compared and hashed by the field with `meta`, which leaves out the capacity of its lists,
+    /// as it says nothing about their elements. Every field is printed and copied all the same
+    pub fn compares(meta: Option<&MetaValue>) -> bool {
+        meta.is_none_or(|m| m.capacity_for.is_none())
+    }
+}
+
+fn is_value(s: &CStruct) -> bool {
+    s.meta.as_ref().is_some_and(|m| m.is_value)
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the watched files have to stay quiet before regenerating.
+///
+/// Editors and cbindgen tend to write a file in several steps, which would otherwise trigger several runs
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Calls `regenerate` once, then again every time one of the files it returned changes. Never returns on success.
+///
+/// `regenerate` returns the files to watch for the next run, so that changes to the config file or the includes
+/// of the header are picked up.
+pub fn watch<F>(mut regenerate: F) -> Result<(), String>
+where
+    F: FnMut() -> Vec<PathBuf>,
+{
+    let (tx, rx) = channel();
+    let mut watcher: RecommendedWatcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("Failed to start watcher: {}", e))?;
+    let mut watched_dirs: HashSet<PathBuf> = HashSet::new();
+
+    loop {
+        let files: HashSet<PathBuf> = regenerate().iter().map(|p| absolute(p)).collect();
+
+        /* Watch the parent directories rather than the files themselves. Editors usually save by
+         * writing a new file and renaming it over the old one, after which a watch on the file is gone */
+        let dirs: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|f| f.parent().map(Path::to_path_buf))
+            .collect();
+        for dir in watched_dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&watched_dirs) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Failed to watch {}: {}", dir.display(), e);
+            }
+        }
+        watched_dirs = dirs;
+
+        eprintln!("Watching {} files for changes...", files.len());
+        wait_for_change(&rx, &files)?;
+    }
+}
+
+/// Blocks until one of `files` changes and no further events have arrived for [DEBOUNCE]
+fn wait_for_change(
+    rx: &Receiver<notify::Result<Event>>,
+    files: &HashSet<PathBuf>,
+) -> Result<(), String> {
+    let disconnected = || "File watcher stopped unexpectedly".to_owned();
+    loop {
+        match rx.recv().map_err(|_| disconnected())? {
+            Ok(event) if is_relevant(&event, files) => break,
+            Ok(_) => {}
+            Err(e) => eprintln!("Watch error: {}", e),
+        }
+    }
+    while rx.recv_timeout(DEBOUNCE).is_ok() {}
+    Ok(())
+}
+
+fn is_relevant(event: &Event, files: &HashSet<PathBuf>) -> bool {
+    let kind_matters = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any
+    );
+    kind_matters && event.paths.iter().any(|p| files.contains(&absolute(p)))
+}
+
+/// Canonicalizes `p` if it exists, so that paths reported by the watcher can be compared against it
+fn absolute(p: &Path) -> PathBuf {
+    if let Ok(c) = p.canonicalize() {
+        return c;
+    }
+    /* The file may be in the middle of being replaced, but its directory should still be there */
+    match (p.parent(), p.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            match parent.canonicalize() {
+                Ok(dir) => dir.join(name),
+                Err(_) => p.to_owned(),
+            }
+        }
+        _ => p.to_owned(),
+    }
+}
@@ -0,0 +1,243 @@
+#![cfg(unix)]
+
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// Stands in for clang: records its arguments and stdin, and dumps the tokens of `tokens.txt` to stderr like clang does
+const FAKE_CLANG: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+echo "$@" > "$dir/clang_args.txt"
+for last; do :; done
+if [ "$1" = "-MM" ]; then
+    echo "header.o: $last"
+    exit 0
+fi
+if [ "$last" = "-" ]; then
+    cat > "$dir/stdin.h"
+fi
+cat "$dir/tokens.txt" >&2
+"#;
+
+/// A temporary directory with a header, and a fake clang that tokenizes it as declaring a single function
+struct Project {
+    dir: tempfile::TempDir,
+}
+
+impl Project {
+    fn new() -> Self {
+        let project = Project {
+            dir: tempfile::tempdir().unwrap(),
+        };
+        let clang = project.path("clang");
+        std::fs::write(&clang, FAKE_CLANG).unwrap();
+        std::fs::set_permissions(&clang, std::fs::Permissions::from_mode(0o755)).unwrap();
+        project.declare("add");
+        project
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    fn read(&self, name: &str) -> String {
+        std::fs::read_to_string(self.path(name)).unwrap()
+    }
+
+    /// Makes the header declare `int32_t <function>(int32_t a);`
+    fn declare(&self, function: &str) {
+        let tokens = [
+            "raw_identifier 'int32_t'".to_owned(),
+            "unknown ' '".to_owned(),
+            format!("raw_identifier '{}'", function),
+            "l_paren '('".to_owned(),
+            "raw_identifier 'int32_t'".to_owned(),
+            "unknown ' '".to_owned(),
+            "raw_identifier 'a'".to_owned(),
+            "r_paren ')'".to_owned(),
+            "semi ';'".to_owned(),
+            "eof ''".to_owned(),
+        ];
+        std::fs::write(self.path("tokens.txt"), tokens.join("\n") + "\n").unwrap();
+        /* Written last, watch mode picks up the change to the header */
+        let source = format!("int32_t {}(int32_t a);\n", function);
+        std::fs::write(self.path("header.h"), source).unwrap();
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_clang2src"));
+        cmd.current_dir(self.dir.path())
+            .env("CLANG_PATH", self.path("clang"))
+            .arg("--no-format");
+        cmd
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.command().args(args).output().unwrap()
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+const GO_TARGET: [&str; 6] = ["go", "lib", "lib", "header.h", "-o", "bindings.go"];
+
+#[test]
+fn check_passes_when_bindings_are_up_to_date() {
+    let project = Project::new();
+    let written = project.run(&[&["header.h"][..], &GO_TARGET].concat());
+    assert!(written.status.success());
+    assert!(project.read("bindings.go").contains("func Add("));
+
+    let checked = project.run(&[&["--check", "header.h"][..], &GO_TARGET].concat());
+    assert_eq!(checked.status.code(), Some(0));
+    assert_eq!(stdout(&checked), "");
+}
+
+#[test]
+fn check_diffs_stale_bindings_and_exits_with_2() {
+    let project = Project::new();
+    project.run(&[&["header.h"][..], &GO_TARGET].concat());
+    project.declare("subtract");
+
+    let checked = project.run(&[&["--check", "header.h"][..], &GO_TARGET].concat());
+    assert_eq!(checked.status.code(), Some(2));
+    let diff = stdout(&checked);
+    assert!(diff.contains("--- bindings.go"), "{}", diff);
+    assert!(diff.contains("+++ bindings.go (generated)"), "{}", diff);
+    assert!(diff.contains("+func Subtract("), "{}", diff);
+    assert!(diff.contains("-func Add("), "{}", diff);
+    /* Nothing is written in check mode */
+    assert!(!project.read("bindings.go").contains("Subtract"));
+}
+
+#[test]
+fn check_reports_missing_bindings_as_stale() {
+    let project = Project::new();
+    let checked = project.run(&[&["--check", "header.h"][..], &GO_TARGET].concat());
+    assert_eq!(checked.status.code(), Some(2));
+    assert!(!project.path("bindings.go").exists());
+}
+
+/// Kills the child process once the test is done with it, even if the test fails
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Polls until `file` exists and contains `needle`, giving up after a few seconds
+fn wait_for(project: &Project, file: &str, needle: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if std::fs::read_to_string(project.path(file)).is_ok_and(|s| s.contains(needle)) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn watch_regenerates_targets_when_the_header_changes() {
+    let project = Project::new();
+    std::fs::write(
+        project.path("clang2src.toml"),
+        r#"
+header = "header.h"
+
+[target.go]
+package_name = "lib"
+ld_flags = "lib"
+header_file_location = "header.h"
+output = "bindings.go"
+"#,
+    )
+    .unwrap();
+    let _watch = KillOnDrop(
+        project
+            .command()
+            .arg("watch")
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    assert!(wait_for(&project, "bindings.go", "func Add("));
+
+    project.declare("subtract");
+    assert!(wait_for(&project, "bindings.go", "func Subtract("));
+}
+
+#[test]
+fn reads_the_header_from_stdin() {
+    let project = Project::new();
+    let mut child = project
+        .command()
+        .args(["-", "go", "lib", "lib", "header.h"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"int32_t add(int32_t a);\n").unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("func Add("));
+    assert_eq!(project.read("stdin.h"), "int32_t add(int32_t a);\n");
+    assert!(project.read("clang_args.txt").ends_with("-x c -\n"));
+}
+
+#[test]
+fn hands_extra_arguments_to_clang() {
+    let project = Project::new();
+    let output = project.run(&[
+        "--clang-arg=-std=c11",
+        "--clang-arg",
+        "-DLIB",
+        "header.h",
+        "go",
+        "lib",
+        "lib",
+        "header.h",
+    ]);
+    assert!(output.status.success());
+    let args = project.read("clang_args.txt");
+    assert!(
+        args.starts_with("-fsyntax-only -Xclang -dump-raw-tokens -std=c11 -DLIB "),
+        "{}",
+        args
+    );
+}
+
+#[test]
+fn prefers_the_clang_option_over_clang_path() {
+    let project = Project::new();
+    let output = project
+        .command()
+        .env("CLANG_PATH", project.path("missing-clang"))
+        .arg("--clang")
+        .arg(project.path("clang"))
+        .args(["header.h", "go", "lib", "lib", "header.h"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = project
+        .command()
+        .env("CLANG_PATH", project.path("missing-clang"))
+        .args(["header.h", "go", "lib", "lib", "header.h"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to run"), "{}", stderr);
+    assert!(stderr.contains("missing-clang"), "{}", stderr);
+}